@@ -0,0 +1,124 @@
+//! Differentiated System Description Table
+//!
+//! The DSDT is AML bytecode, which would need a full interpreter to evaluate properly. Until there is one, the few
+//! objects the kernel needs are found by scanning for simple definitions of them.
+
+use crate::tables::header::Header;
+
+/// AML opcode defining a named object
+const NAME_OP: u8 = 0x08;
+/// AML opcode defining a package
+const PACKAGE_OP: u8 = 0x12;
+/// AML prefix of a name relative to the root of the namespace
+const ROOT_CHAR: u8 = b'\\';
+/// AML constant 0
+const ZERO_OP: u8 = 0x00;
+/// AML constant 1
+const ONE_OP: u8 = 0x01;
+/// AML prefix of a byte constant
+const BYTE_PREFIX: u8 = 0x0A;
+/// AML prefix of a word constant
+const WORD_PREFIX: u8 = 0x0B;
+/// AML prefix of a dword constant
+const DWORD_PREFIX: u8 = 0x0C;
+
+/// Differentiated System Description Table
+#[derive(Debug)]
+pub struct Dsdt {
+    /// DSDT header
+    pub header: &'static Header,
+    /// AML definition block
+    aml: &'static [u8],
+}
+
+/// Values to write to the SLP_TYP field of the PM1 control blocks to enter a sleep state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SleepType {
+    /// SLP_TYP for the PM1a control block
+    pub pm1a: u8,
+    /// SLP_TYP for the PM1b control block
+    pub pm1b: u8,
+}
+
+impl Dsdt {
+    /// Signature of DSDT: "DSDT"
+    pub const SIGNATURE: [u8; 4] = *b"DSDT";
+
+    /// Constructs a DSDT, assuming it is at the given address
+    ///
+    /// ## Safety
+    /// `addr` must point to a valid DSDT.
+    /// This function _does_ check it contains a DSDT signature, but only **after** already reading
+    /// the header, so if the pointer is invalid then it will still be UB.
+    pub unsafe fn from_addr(addr: usize) -> Option<Self> {
+        unsafe {
+            let (header, aml) = Header::from_addr(addr)?;
+
+            if header.signature != Self::SIGNATURE {
+                return None;
+            }
+
+            Some(Self { header, aml })
+        }
+    }
+
+    /// Finds the SLP_TYP values for sleep state `state` (0-5), from the `\_Sx_` package.
+    ///
+    /// Only packages defined directly with `Name`, with constant elements, are found.
+    pub fn sleep_type(&self, state: u8) -> Option<SleepType> {
+        if state > 5 {
+            return None;
+        }
+        let name = [b'_', b'S', b'0' + state, b'_'];
+
+        let aml = self.aml;
+        (1..aml.len().saturating_sub(4))
+            .filter(|&i| aml[i..i + 4] == name)
+            .find_map(|i| {
+                let is_definition = match aml[i - 1] {
+                    NAME_OP => true,
+                    ROOT_CHAR => i >= 2 && aml[i - 2] == NAME_OP,
+                    _ => false,
+                };
+                if !is_definition {
+                    return None;
+                }
+
+                let mut package = aml.get(i + 4..)?;
+                if *package.first()? != PACKAGE_OP {
+                    return None;
+                }
+
+                // the top two bits of the first PkgLength byte give the number of bytes which follow it, then
+                // NumElements follows that
+                let length_bytes = (*package.get(1)? >> 6) as usize + 1;
+                package = package.get(1 + length_bytes + 1..)?;
+
+                let pm1a = read_integer(&mut package)?;
+                let pm1b = read_integer(&mut package)?;
+
+                Some(SleepType {
+                    pm1a: pm1a as u8 & 0b111,
+                    pm1b: pm1b as u8 & 0b111,
+                })
+            })
+    }
+}
+
+/// Reads a constant integer from the start of `aml`, advancing past it
+fn read_integer(aml: &mut &[u8]) -> Option<u32> {
+    let (value, length) = match *aml.first()? {
+        ZERO_OP => (0, 1),
+        ONE_OP => (1, 1),
+        BYTE_PREFIX => (*aml.get(1)? as u32, 2),
+        WORD_PREFIX => (
+            u16::from_le_bytes(aml.get(1..3)?.try_into().ok()?) as u32,
+            3,
+        ),
+        DWORD_PREFIX => (u32::from_le_bytes(aml.get(1..5)?.try_into().ok()?), 5),
+        _ => return None,
+    };
+
+    *aml = &aml[length..];
+    Some(value)
+}
@@ -0,0 +1,118 @@
+//! Fixed ACPI Description Table
+
+use std::cursor::CursorR;
+
+use crate::tables::{AcpiAddress, header::Header};
+
+/// Fixed ACPI Description Table, describing the fixed hardware used for power management
+#[derive(Debug)]
+pub struct Fadt {
+    /// FADT header
+    pub header: &'static Header,
+    /// Physical address of the DSDT, preferring the 64 bit field if set
+    pub dsdt: u64,
+    /// Legacy interrupt the SCI is wired to
+    pub sci_interrupt: u16,
+    /// Port to write `acpi_enable` or `acpi_disable` to, or 0 if the system is always in ACPI mode
+    pub smi_command: u32,
+    /// Value to write to `smi_command` to hand control of the fixed hardware from firmware to the OS
+    pub acpi_enable: u8,
+    /// Value to write to `smi_command` to hand control of the fixed hardware back to firmware
+    pub acpi_disable: u8,
+    /// PM1a control block, which is absent on hardware reduced systems
+    pub pm1a_control_block: Option<AcpiAddress>,
+    /// PM1b control block, which most systems don't have
+    pub pm1b_control_block: Option<AcpiAddress>,
+    /// Fixed feature flags, see the `FLAG_*` constants
+    pub flags: u32,
+    /// Register to write `reset_value` to to reset the system, if supported
+    pub reset_register: Option<AcpiAddress>,
+    /// Value to write to `reset_register`
+    pub reset_value: u8,
+}
+
+impl Fadt {
+    /// Signature of FADT: "FACP"
+    pub const SIGNATURE: [u8; 4] = *b"FACP";
+
+    /// Flag set if `reset_register` is supported
+    pub const FLAG_RESET_REG_SUPPORTED: u32 = 1 << 10;
+    /// Flag set if the system is hardware reduced, so has no PM1 blocks
+    pub const FLAG_HW_REDUCED_ACPI: u32 = 1 << 20;
+
+    /// Constructs a FADT, assuming it is at the given address
+    ///
+    /// ## Safety
+    /// `addr` must point to a valid FADT.
+    /// This function _does_ check it contains a FADT signature, but only **after** already reading
+    /// the header, so if the pointer is invalid then it will still be UB.
+    pub unsafe fn from_addr(addr: usize) -> Option<Self> {
+        unsafe {
+            let (header, remaining) = Header::from_addr(addr)?;
+
+            if header.signature != Self::SIGNATURE {
+                return None;
+            }
+
+            let mut cursor = CursorR::from(remaining);
+
+            let _firmware_ctrl = cursor.read_u32()?;
+            let dsdt = cursor.read_u32()?;
+            let _reserved = cursor.read_u8()?;
+            let _preferred_pm_profile = cursor.read_u8()?;
+            let sci_interrupt = cursor.read_u16()?;
+            let smi_command = cursor.read_u32()?;
+            let acpi_enable = cursor.read_u8()?;
+            let acpi_disable = cursor.read_u8()?;
+
+            // S4BIOS_REQ, PSTATE_CNT and the PM1 event blocks
+            cursor.increment_offset(10);
+            let pm1a_control_port = cursor.read_u32()?;
+            let pm1b_control_port = cursor.read_u32()?;
+
+            // PM2 control, PM timer and GPE blocks, and the PM1 event length
+            cursor.increment_offset(17);
+            let pm1_control_length = cursor.read_u8()?;
+
+            // remaining lengths, C state and duty cycle fields, RTC alarms and boot architecture flags
+            cursor.increment_offset(22);
+            let flags = cursor.read_u32()?;
+
+            // everything from here was added in ACPI 2.0, so may be missing
+            let reset_register = AcpiAddress::from_cursor(&mut cursor);
+            let reset_value = cursor.read_u8().unwrap_or(0);
+
+            // ARM boot architecture flags, minor version and X_FIRMWARE_CTRL
+            cursor.increment_offset(11);
+            let x_dsdt = cursor.read_u64().filter(|&addr| addr != 0);
+
+            // extended PM1 event blocks
+            cursor.increment_offset(24);
+            let x_pm1a_control_block = AcpiAddress::from_cursor(&mut cursor);
+            let x_pm1b_control_block = AcpiAddress::from_cursor(&mut cursor);
+
+            // extended blocks take priority if set, otherwise fall back to the legacy port
+            let control_block = |extended: Option<AcpiAddress>, port: u32| {
+                extended.filter(|block| block.address != 0).or_else(|| {
+                    (port != 0)
+                        .then(|| AcpiAddress::io(port as u16, pm1_control_length.saturating_mul(8)))
+                })
+            };
+
+            Some(Self {
+                header,
+                dsdt: x_dsdt.unwrap_or(dsdt as u64),
+                sci_interrupt,
+                smi_command,
+                acpi_enable,
+                acpi_disable,
+                pm1a_control_block: control_block(x_pm1a_control_block, pm1a_control_port),
+                pm1b_control_block: control_block(x_pm1b_control_block, pm1b_control_port),
+                flags,
+                reset_register: reset_register
+                    .filter(|_| flags & Self::FLAG_RESET_REG_SUPPORTED != 0),
+                reset_value,
+            })
+        }
+    }
+}
@@ -44,21 +44,7 @@ impl Hpet {
             let mut cursor = CursorR::from(remaining);
 
             let block_id = cursor.read_u32()?;
-            let address = {
-                let address_space_id = cursor.read_u8()?;
-                let register_bit_width = cursor.read_u8()?;
-                let register_bit_offset = cursor.read_u8()?;
-                let _reserved = cursor.read_u8()?;
-                let address = cursor.read_u64()?;
-
-                AcpiAddress {
-                    address_space_id,
-                    register_bit_width,
-                    register_bit_offset,
-                    _reserved,
-                    address,
-                }
-            };
+            let address = AcpiAddress::from_cursor(&mut cursor)?;
             let hpet_number = cursor.read_u8()?;
             let minimum_clock_tick = cursor.read_u16()?;
             let page_protection = cursor.read_u8()?;
@@ -1,5 +1,7 @@
 //! Code for parsing fixed ACPI tables
 
+pub mod fadt;
 pub mod hpet;
 pub mod madt;
 pub mod rsdt;
+pub mod xsdt;
@@ -4,7 +4,10 @@ use core::marker::PhantomData;
 
 use crate::tables::header::Header;
 
-/// Root System Description Table
+/// Root System Description Table, with table pointers of type `PTR`.
+///
+/// The RSDT uses 32-bit pointers, and the XSDT has the same layout with 64-bit pointers - see
+/// [`Xsdt`](crate::tables::fixed::xsdt::Xsdt).
 #[derive(Debug)]
 pub struct Rsdt<PTR> {
     /// RSDT header
@@ -17,6 +20,11 @@ pub struct Rsdt<PTR> {
     _phantom: PhantomData<PTR>,
 }
 
+impl Rsdt<u32> {
+    /// Signature of the RSDT
+    pub const SIGNATURE: [u8; 4] = *b"RSDT";
+}
+
 impl<PTR> Rsdt<PTR>
 where
     PTR: TryInto<usize> + Copy,
@@ -0,0 +1,12 @@
+//! Extended System Description Table
+
+use crate::tables::fixed::rsdt::Rsdt;
+
+/// Extended System Description Table, provided by ACPI 2.0 and later. It has the same layout as the RSDT but with
+/// 64-bit table pointers, so can list tables above 4 GiB.
+pub type Xsdt = Rsdt<u64>;
+
+impl Xsdt {
+    /// Signature of the XSDT
+    pub const SIGNATURE: [u8; 4] = *b"XSDT";
+}
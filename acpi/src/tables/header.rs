@@ -1,6 +1,6 @@
 //! Shared header for all tables
 
-use std::cursor::Cursor;
+use std::{assert_eq_size, assert_offset, cursor::Cursor};
 
 /// ACPI table header
 #[derive(Debug)]
@@ -26,6 +26,14 @@ pub struct Header {
     pub creator_revision: u32,
 }
 
+assert_eq_size!(Header, 36);
+assert_offset!(Header, length, 4);
+assert_offset!(Header, checksum, 9);
+assert_offset!(Header, oem_id, 10);
+assert_offset!(Header, oem_table_id, 16);
+assert_offset!(Header, oem_revision, 24);
+assert_offset!(Header, creator_revision, 32);
+
 impl Header {
     /// Constructs a header from the given cursor, **without** any checks
     ///
@@ -57,6 +65,18 @@ impl Header {
         }
     }
 
+    /// Returns if every byte of the table, including the header, sums to zero
+    ///
+    /// ## Safety
+    /// The header must be followed by the rest of its table, for `self.length` bytes in total
+    pub unsafe fn checksum_valid(&self) -> bool {
+        let bytes = unsafe {
+            core::slice::from_raw_parts(self as *const Self as *const u8, self.length as usize)
+        };
+
+        bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+    }
+
     /// Returns `self.signature` as a string
     pub const fn signature(&self) -> &str {
         // safety: we assume that self was constructed from an actual header, in which case self.signature is a valid
@@ -1,7 +1,11 @@
 //! Code for parsing ACPI tables
 
+use std::{assert_eq_size, assert_offset, cursor::CursorR};
+
+pub mod dsdt;
 pub mod fixed;
 pub mod header;
+pub mod registry;
 
 /// An ACPI address struct
 #[repr(C, packed)]
@@ -20,3 +24,41 @@ pub struct AcpiAddress {
     /// Address
     pub address: u64,
 }
+
+assert_eq_size!(AcpiAddress, 12);
+assert_offset!(AcpiAddress, address, 4);
+
+impl AcpiAddress {
+    /// Address space ID of system memory
+    pub const SYSTEM_MEMORY: u8 = 0;
+    /// Address space ID of system I/O ports
+    pub const SYSTEM_IO: u8 = 1;
+
+    /// Constructs an address for an I/O port register of the given width in bits
+    pub const fn io(port: u16, register_bit_width: u8) -> Self {
+        Self {
+            address_space_id: Self::SYSTEM_IO,
+            register_bit_width,
+            register_bit_offset: 0,
+            _reserved: 0,
+            address: port as u64,
+        }
+    }
+
+    /// Reads an address from the cursor, advancing it by 12 bytes
+    pub(crate) fn from_cursor(cursor: &mut CursorR) -> Option<Self> {
+        let address_space_id = cursor.read_u8()?;
+        let register_bit_width = cursor.read_u8()?;
+        let register_bit_offset = cursor.read_u8()?;
+        let _reserved = cursor.read_u8()?;
+        let address = cursor.read_u64()?;
+
+        Some(Self {
+            address_space_id,
+            register_bit_width,
+            register_bit_offset,
+            _reserved,
+            address,
+        })
+    }
+}
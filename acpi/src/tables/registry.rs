@@ -0,0 +1,85 @@
+//! Registry of every table listed in the RSDT or XSDT, built in one pass so tables can be looked up without rescanning
+
+use crate::tables::{
+    fixed::{fadt::Fadt, hpet::Hpet, madt::Madt, rsdt::Rsdt},
+    header::Header,
+};
+
+/// Maximum number of tables which can be registered
+const MAX_TABLES: usize = 32;
+
+/// Signature and address of every table listed in the RSDT or XSDT
+#[derive(Debug)]
+pub struct AcpiTables {
+    /// Signature and address of each table
+    entries: [([u8; 4], usize); MAX_TABLES],
+    /// Number of valid entries
+    count: usize,
+}
+
+impl AcpiTables {
+    /// Reads the header of every table in the RSDT or XSDT, recording its signature and address.
+    /// `mem_mask` is ORed into each physical address to get an address which can be accessed.
+    ///
+    /// Any tables past [`MAX_TABLES`] are ignored.
+    ///
+    /// ## Safety
+    /// Every address in the table must point to a valid ACPI table once masked.
+    pub unsafe fn new<PTR>(rsdt: &Rsdt<PTR>, mem_mask: usize) -> Self
+    where
+        PTR: TryInto<usize> + Copy,
+    {
+        let mut tables = Self {
+            entries: [([0; 4], 0); MAX_TABLES],
+            count: 0,
+        };
+
+        for i in 0..rsdt.num_addresses {
+            let Some(table_addr) = rsdt.table(i).and_then(|addr| addr.try_into().ok()) else {
+                continue;
+            };
+            let table_addr = table_addr | mem_mask;
+
+            let Some((header, _)) = (unsafe { Header::from_addr(table_addr) }) else {
+                continue;
+            };
+
+            if tables.count == MAX_TABLES {
+                log::warn!("too many ACPI tables, ignoring {}", header.signature());
+                continue;
+            }
+
+            tables.entries[tables.count] = (header.signature, table_addr);
+            tables.count += 1;
+        }
+
+        tables
+    }
+
+    /// Returns the signature and address of every registered table
+    pub fn iter(&self) -> impl Iterator<Item = ([u8; 4], usize)> + '_ {
+        self.entries[..self.count].iter().copied()
+    }
+
+    /// Returns the address of the table with the given signature, if present
+    pub fn find(&self, signature: &[u8; 4]) -> Option<usize> {
+        self.iter()
+            .find(|(table_signature, _)| table_signature == signature)
+            .map(|(_, addr)| addr)
+    }
+
+    /// Parses the MADT, returning None if it is not present or invalid
+    pub fn madt(&self) -> Option<Madt> {
+        unsafe { Madt::from_addr(self.find(&Madt::SIGNATURE)?) }
+    }
+
+    /// Parses the HPET table, returning None if it is not present or invalid
+    pub fn hpet(&self) -> Option<Hpet> {
+        unsafe { Hpet::from_addr(self.find(&Hpet::SIGNATURE)?) }
+    }
+
+    /// Parses the FADT, returning None if it is not present or invalid
+    pub fn fadt(&self) -> Option<Fadt> {
+        unsafe { Fadt::from_addr(self.find(&Fadt::SIGNATURE)?) }
+    }
+}
@@ -0,0 +1,108 @@
+//! Copy of the boot information, kept on the heap so it outlives the bootloader's memory once that is reclaimed
+
+use alloc::{string::String, vec::Vec};
+use core::cell::OnceCell;
+use std::mutex::Mutex;
+
+use kernel_shared::{
+    boot::{AcpiRoot, BootModule, BootProtocol, Framebuffer, MemoryRegion},
+    mem::addr::PhysAddr,
+    x86::without_interrupts,
+};
+
+/// Boot information saved by [`save`], unset until init has finished
+pub static BOOT_INFO: Mutex<OnceCell<SavedBootInfo>> = Mutex::new(OnceCell::new());
+
+/// Owned copy of everything a [`BootProtocol`] provides
+#[derive(Debug)]
+pub struct SavedBootInfo {
+    /// Name of the protocol the kernel was booted with
+    name: &'static str,
+    /// Command line the kernel was booted with, if any
+    command_line: Option<String>,
+    /// Regions of physical memory described by the firmware
+    memory_regions: Vec<MemoryRegion>,
+    /// Modules loaded alongside the kernel
+    modules: Vec<SavedModule>,
+    /// Root ACPI tables, most preferred first
+    acpi_roots: Vec<AcpiRoot>,
+    /// Framebuffer set up by the bootloader, if any
+    framebuffer: Option<Framebuffer>,
+}
+
+/// Owned copy of a [`BootModule`]. The module's contents aren't part of the boot information, so stay where they are
+#[derive(Debug)]
+struct SavedModule {
+    /// Physical address of the start of the module
+    start: PhysAddr,
+    /// Length of the module in bytes
+    length: usize,
+    /// Name of the module
+    name: String,
+    /// Anything on the module's command line after its name
+    arguments: String,
+}
+
+impl SavedBootInfo {
+    /// Copies everything out of `bootinfo`
+    pub fn copy_from(bootinfo: &impl BootProtocol) -> Self {
+        Self {
+            name: bootinfo.name(),
+            command_line: bootinfo.command_line().map(String::from),
+            memory_regions: bootinfo.memory_regions().collect(),
+            modules: bootinfo
+                .modules()
+                .map(|module| SavedModule {
+                    start: module.start,
+                    length: module.length,
+                    name: String::from(module.name),
+                    arguments: String::from(module.arguments),
+                })
+                .collect(),
+            acpi_roots: bootinfo.acpi_roots().collect(),
+            framebuffer: bootinfo.framebuffer(),
+        }
+    }
+}
+
+impl BootProtocol for SavedBootInfo {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn command_line(&self) -> Option<&str> {
+        self.command_line.as_deref()
+    }
+
+    fn memory_regions(&self) -> impl Iterator<Item = MemoryRegion> + '_ {
+        self.memory_regions.iter().copied()
+    }
+
+    fn modules(&self) -> impl Iterator<Item = BootModule<'_>> + '_ {
+        self.modules.iter().map(|module| BootModule {
+            start: module.start,
+            length: module.length,
+            name: &module.name,
+            arguments: &module.arguments,
+        })
+    }
+
+    fn acpi_roots(&self) -> impl Iterator<Item = AcpiRoot> + '_ {
+        self.acpi_roots.iter().copied()
+    }
+
+    fn framebuffer(&self) -> Option<Framebuffer> {
+        self.framebuffer
+    }
+}
+
+/// Copies `bootinfo` onto the heap, so it can still be read once the memory it lives in is reclaimed.
+///
+/// Must be called after the heap is initialised. Only the first call has any effect.
+pub fn save(bootinfo: &impl BootProtocol) {
+    let saved = SavedBootInfo::copy_from(bootinfo);
+
+    if without_interrupts(|| BOOT_INFO.lock().set(saved).is_err()) {
+        log::warn!("boot information already saved");
+    }
+}
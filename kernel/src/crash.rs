@@ -0,0 +1,213 @@
+//! Machine-readable dump of kernel state, written to serial on panic
+//!
+//! The dump is a block of lines between [`BEGIN_MARKER`] and [`END_MARKER`]. Each line is a JSON object, tagged
+//! with a short record kind, so a dump cut off partway still parses up to where it stopped:
+//!
+//! ```text
+//! {"kind":"msg","message":<panic message>,"location":<file:line:column or null>}
+//! {"kind":"cmdline","command_line":<kernel command line>}
+//! {"kind":"reg","name":<name>,"value":<hex string>}
+//! {"kind":"bt","depth":<depth>,"address":<hex return address>}
+//! {"kind":"mem","used":<used frames>,"total":<total frames>}
+//! {"kind":"step","index":<index>,"address":<hex instruction address>}
+//! {"kind":"lock","acquisitions":<n>,"contended":<n>,"spins":<n>,"max_hold_cycles":<n>,"site":<call site>}
+//! {"kind":"log","line":<log line>}
+//! ```
+//!
+//! `scripts/crashdump.py` in the repository root extracts dumps from a serial log and pretty-prints them,
+//! resolving backtrace addresses against the `target/rustyos.debug` file split out of the kernel at build time.
+
+use core::{
+    arch::asm,
+    fmt::{Result, Write},
+    panic::PanicInfo,
+};
+use std::json::JsonWriter;
+
+use kernel_shared::{
+    boot::BootProtocol,
+    io::serial::SerialPort,
+    x86::{
+        backtrace::Backtrace,
+        registers::{CR0, CR2, CR3, CR4, CpuFlags},
+    },
+};
+
+use crate::{LOGGER, boot::BOOT_INFO, interrupts::single_step};
+
+/// Line starting a crash dump, including the format version
+const BEGIN_MARKER: &str = "@@CRASHDUMP-BEGIN 2";
+
+/// Line ending a crash dump
+const END_MARKER: &str = "@@CRASHDUMP-END";
+
+/// Maximum number of frames to walk, in case the stack is corrupt in a way that loops
+const MAX_BACKTRACE_DEPTH: usize = 64;
+
+/// Writes a crash dump to COM1.
+///
+/// This bypasses the COM1 lock and only try-locks the log history, so the dump is written even if the panic
+/// happened while either was held.
+pub fn dump(info: &PanicInfo) {
+    // capture general purpose registers before anything else clobbers them
+    let registers = GeneralRegisters::capture();
+
+    // the port only holds its drop count, so a fresh one can be used without waiting on the lock. this also gives
+    // a stalled port another chance, with the full timeout
+    let mut serial = SerialPort::<0x3F8>::new();
+
+    let _ = write_dump(&mut serial, info, &registers);
+}
+
+/// Writes every record of the dump
+fn write_dump<W: Write>(out: &mut W, info: &PanicInfo, registers: &GeneralRegisters) -> Result {
+    write!(out, "\n\r{BEGIN_MARKER}\n\r")?;
+
+    record(out, "msg", |json| {
+        json.key("message")?;
+        json.display(&info.message())?;
+        json.key("location")?;
+        match info.location() {
+            Some(location) => json.display(location),
+            None => json.null(),
+        }
+    })?;
+
+    // only present once init has saved the boot information, and skipped if the panic happened while reading it
+    if let Some(bootinfo) = BOOT_INFO.try_lock()
+        && let Some(command_line) = bootinfo.get().and_then(|bootinfo| bootinfo.command_line())
+    {
+        record(out, "cmdline", |json| {
+            json.field("command_line", command_line)
+        })?;
+    }
+
+    let control_registers = [
+        ("rflags", CpuFlags::read().bits() as usize),
+        ("cr0", CR0::read().bits() as usize),
+        ("cr2", CR2::read()),
+        ("cr3", CR3::read().0.start_address().as_usize()),
+        ("cr4", CR4::read()),
+    ];
+    for (name, value) in registers.iter().chain(control_registers) {
+        record(out, "reg", |json| {
+            json.field("name", name)?;
+            json.key("value")?;
+            json.display(&format_args!("{value:#018x}"))
+        })?;
+    }
+
+    for (depth, address) in Backtrace::capture().take(MAX_BACKTRACE_DEPTH).enumerate() {
+        record(out, "bt", |json| {
+            json.field("depth", &depth)?;
+            json.key("address")?;
+            json.display(&format_args!("{address:#018x}"))
+        })?;
+    }
+
+    if let Some((used, total)) = crate::mem::frame_counts() {
+        record(out, "mem", |json| {
+            json.field("used", &used)?;
+            json.field("total", &total)
+        })?;
+    }
+
+    // only present if something was single step traced
+    for (index, address) in single_step::steps().enumerate() {
+        record(out, "step", |json| {
+            json.field("index", &index)?;
+            json.key("address")?;
+            json.display(&format_args!("{address:#018x}"))
+        })?;
+    }
+
+    // only recorded when std is built with LOCK_STATS
+    for site in std::lock_stats::sites() {
+        record(out, "lock", |json| {
+            json.field("acquisitions", &site.acquisitions)?;
+            json.field("contended", &site.contended)?;
+            json.field("spins", &site.spins)?;
+            json.field("max_hold_cycles", &site.max_hold_cycles)?;
+            json.key("site")?;
+            json.display(site.location)
+        })?;
+    }
+
+    match LOGGER.history() {
+        Some(history) => {
+            for line in history.iter() {
+                record(out, "log", |json| {
+                    json.key("line")?;
+                    json.display(&line)
+                })?;
+            }
+        }
+        None => record(out, "log", |json| {
+            json.field("line", "<history unavailable, panicked while logging>")
+        })?,
+    }
+
+    write!(out, "{END_MARKER}\n\r")
+}
+
+/// Writes a single record as a JSON object on its own line, with `fields` writing everything after its kind
+fn record<W: Write>(
+    out: &mut W,
+    kind: &str,
+    fields: impl FnOnce(&mut JsonWriter<&mut W>) -> Result,
+) -> Result {
+    let mut json = JsonWriter::new(&mut *out);
+    json.begin_object()?;
+    json.field("kind", kind)?;
+    fields(&mut json)?;
+    json.end_object()?;
+
+    out.write_str("\n\r")
+}
+
+/// General purpose registers at the point the dump began
+struct GeneralRegisters([usize; 16]);
+
+impl GeneralRegisters {
+    /// Names of the registers, in the order they are stored
+    const NAMES: [&str; 16] = [
+        "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12",
+        "r13", "r14", "r15",
+    ];
+
+    /// Captures the current register values
+    #[inline(always)]
+    fn capture() -> Self {
+        let mut values = [0; 16];
+
+        unsafe {
+            asm!(
+                "mov [{0}], rax",
+                "mov [{0} + 8], rbx",
+                "mov [{0} + 16], rcx",
+                "mov [{0} + 24], rdx",
+                "mov [{0} + 32], rsi",
+                "mov [{0} + 40], rdi",
+                "mov [{0} + 48], rbp",
+                "mov [{0} + 56], rsp",
+                "mov [{0} + 64], r8",
+                "mov [{0} + 72], r9",
+                "mov [{0} + 80], r10",
+                "mov [{0} + 88], r11",
+                "mov [{0} + 96], r12",
+                "mov [{0} + 104], r13",
+                "mov [{0} + 112], r14",
+                "mov [{0} + 120], r15",
+                in(reg) values.as_mut_ptr(),
+                options(nostack, preserves_flags),
+            );
+        }
+
+        Self(values)
+    }
+
+    /// Iterates over each register's name and value
+    fn iter(&self) -> impl Iterator<Item = (&'static str, usize)> {
+        Self::NAMES.into_iter().zip(self.0)
+    }
+}
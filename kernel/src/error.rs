@@ -0,0 +1,174 @@
+//! Errors which can occur while initialising the kernel and its drivers
+
+use core::fmt::{Display, Formatter};
+
+use kernel_shared::mem::heap::HeapError;
+use multiboot::boot::BootInfoError;
+
+/// An error encountered by the kernel, grouped by the subsystem it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelError {
+    /// Multiboot boot information could not be parsed
+    BadBootInfo(BootInfoError),
+    /// Initialisation was attempted more than once
+    AlreadyInitialised,
+    /// Error finding or parsing ACPI tables
+    Acpi(AcpiError),
+    /// Error programming interrupt controllers or timers
+    Interrupts(InterruptError),
+    /// Init stages could not be ordered
+    Init(InitError),
+    /// Heap could not be set up
+    Heap(HeapError),
+}
+
+/// An error finding or parsing ACPI tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcpiError {
+    /// Bootloader did not provide an RSDP
+    MissingRsdp,
+    /// A table could not be found in the RSDT
+    MissingTable(&'static str),
+    /// A table was found, but its header or checksum is invalid
+    BadTable(&'static str),
+}
+
+/// An error programming interrupt controllers or timers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptError {
+    /// No MADT was found, so the APICs can't be used
+    NoMadt,
+    /// MADT does not describe an IOAPIC
+    MissingIoApic,
+    /// HPET does not have the requested timer
+    MissingHpetTimer(u8),
+    /// Interrupts were already initialised
+    AlreadyInitialised,
+}
+
+/// An error rebooting or powering off through ACPI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerError {
+    /// No FADT was found, so the fixed hardware is unknown
+    NoFadt,
+    /// FADT does not have a reset register
+    ResetUnsupported,
+    /// FADT does not have a PM1a control block
+    NoPm1Control,
+    /// DSDT does not define the SLP_TYP values for the given sleep state
+    NoSleepState(u8),
+    /// Register is in an address space, or has a width, which can't be accessed. Contains the address space and
+    /// width in bits.
+    UnsupportedRegister(u8, u8),
+    /// Firmware did not hand over control of the fixed hardware in time
+    AcpiEnableTimeout,
+    /// Register was written, but the machine kept running
+    NoEffect,
+}
+
+/// An error ordering init stages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitError {
+    /// A stage depends on a stage which doesn't exist
+    UnknownDependency {
+        /// Stage with the dependency
+        stage: &'static str,
+        /// Name of the missing stage
+        dependency: &'static str,
+    },
+    /// Stages depend on each other, so none of them can run. Contains the first stage which couldn't run.
+    DependencyCycle(&'static str),
+    /// More stages were given than can be tracked
+    TooManyStages(usize),
+}
+
+impl From<AcpiError> for KernelError {
+    fn from(error: AcpiError) -> Self {
+        Self::Acpi(error)
+    }
+}
+
+impl From<InterruptError> for KernelError {
+    fn from(error: InterruptError) -> Self {
+        Self::Interrupts(error)
+    }
+}
+
+impl From<HeapError> for KernelError {
+    fn from(error: HeapError) -> Self {
+        Self::Heap(error)
+    }
+}
+
+impl From<InitError> for KernelError {
+    fn from(error: InitError) -> Self {
+        Self::Init(error)
+    }
+}
+
+impl Display for KernelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadBootInfo(err) => {
+                write!(f, "failed to parse multiboot boot information: {err}")
+            }
+            Self::AlreadyInitialised => write!(f, "kernel was already initialised"),
+            Self::Acpi(error) => write!(f, "ACPI: {error}"),
+            Self::Interrupts(error) => write!(f, "interrupts: {error}"),
+            Self::Init(error) => write!(f, "init: {error}"),
+            Self::Heap(error) => write!(f, "heap: {error}"),
+        }
+    }
+}
+
+impl Display for AcpiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MissingRsdp => write!(f, "no RSDP provided by bootloader"),
+            Self::MissingTable(table) => write!(f, "no {table} table found"),
+            Self::BadTable(table) => write!(f, "{table} table is invalid"),
+        }
+    }
+}
+
+impl Display for InterruptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoMadt => write!(f, "no MADT to configure APICs from"),
+            Self::MissingIoApic => write!(f, "no IOAPIC described in MADT"),
+            Self::MissingHpetTimer(timer) => write!(f, "HPET has no timer {timer}"),
+            Self::AlreadyInitialised => write!(f, "interrupts were already initialised"),
+        }
+    }
+}
+
+impl Display for PowerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoFadt => write!(f, "no FADT describing power management hardware"),
+            Self::ResetUnsupported => write!(f, "FADT has no reset register"),
+            Self::NoPm1Control => write!(f, "FADT has no PM1a control block"),
+            Self::NoSleepState(state) => write!(f, "DSDT does not define sleep state S{state}"),
+            Self::UnsupportedRegister(space, width) => write!(
+                f,
+                "can't access {width} bit register in address space {space}"
+            ),
+            Self::AcpiEnableTimeout => write!(f, "timed out waiting for firmware to enable ACPI"),
+            Self::NoEffect => write!(f, "register was written but the machine is still running"),
+        }
+    }
+}
+
+impl Display for InitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownDependency { stage, dependency } => {
+                write!(f, "stage `{stage}` depends on unknown stage `{dependency}`")
+            }
+            Self::DependencyCycle(stage) => {
+                write!(f, "dependency cycle involving stage `{stage}`")
+            }
+            Self::TooManyStages(count) => write!(f, "too many init stages ({count})"),
+        }
+    }
+}
@@ -1,26 +1,50 @@
-use core::ptr::addr_of;
+use core::{ops::Range, ptr::addr_of};
 
 use kernel_shared::x86::{
     gdt::{Descriptor, GlobalDescriptorTable},
     segment_selector::SegmentSelector,
-    tss::TaskStateSegment,
+    tss::TssWithIoBitmap,
 };
 use lazy_static::lazy_static;
 
+/// IST entry used for double faults, so stack overflows can still be reported
 pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
 
-lazy_static! {
-    static ref TSS: TaskStateSegment = {
-        let mut tss = TaskStateSegment::default();
+/// IST entry used for NMIs, which can arrive at any point, even while the stack pointer is invalid
+pub const NMI_IST_INDEX: u16 = 1;
+
+/// IST entry used for page faults, if the `PAGE_FAULT_IST` feature is enabled
+pub const PAGE_FAULT_IST_INDEX: u16 = 2;
+
+/// Number of IST entries in use
+const IST_STACK_COUNT: usize = 3;
+
+/// Size of each IST stack in bytes
+const IST_STACK_SIZE: usize = 4096 * 5;
+
+/// A stack switched to by an interrupt, aligned as the ABI expects
+#[repr(C, align(16))]
+struct IstStack([u8; IST_STACK_SIZE]);
 
-        tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = {
-            const STACK_SIZE: usize = 4096 * 5;
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+/// Stacks for each IST entry, indexed by IST index
+static mut IST_STACKS: [IstStack; IST_STACK_COUNT] =
+    [const { IstStack([0; IST_STACK_SIZE]) }; IST_STACK_COUNT];
 
-            let stack_start = addr_of!(STACK) as usize;
+/// Returns the range of addresses covered by the stack for the given IST index
+pub fn ist_stack(index: u16) -> Range<usize> {
+    let start = addr_of!(IST_STACKS) as usize + index as usize * IST_STACK_SIZE;
+
+    start..start + IST_STACK_SIZE
+}
+
+lazy_static! {
+    static ref TSS: TssWithIoBitmap = {
+        let mut tss = TssWithIoBitmap::default();
 
-            stack_start + STACK_SIZE
-        };
+        // stacks grow downwards, so each entry points at the end of its stack
+        for index in 0..IST_STACK_COUNT {
+            tss.tss.interrupt_stack_table[index] = ist_stack(index as u16).end;
+        }
 
         tss
     };
@@ -29,7 +53,7 @@ lazy_static! {
 
         let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
         let data_selector = gdt.add_entry(Descriptor::kernel_data_segment());
-        let tss_selector = gdt.add_entry(Descriptor::tss_segment(&TSS));
+        let tss_selector = gdt.add_entry(Descriptor::tss_segment_with_io_bitmap(&TSS));
 
         (
             gdt,
@@ -0,0 +1,104 @@
+//! Running each subsystem's initialisation in dependency order, timing every stage
+
+use kernel_shared::{
+    kassert,
+    x86::{cpuid, rdtsc},
+};
+
+use crate::error::{InitError, KernelError};
+
+/// Maximum number of stages which can be run
+const MAX_STAGES: usize = 32;
+
+/// Initialisation of a single subsystem
+pub struct Stage<C> {
+    /// Name other stages refer to this stage by
+    pub name: &'static str,
+    /// Names of stages which must finish before this stage runs
+    pub dependencies: &'static [&'static str],
+    /// Initialises the subsystem, given state shared between stages
+    pub run: fn(&mut C) -> Result<(), KernelError>,
+}
+
+/// Runs every stage once all of its dependencies have run, preferring the order stages are listed in
+pub fn run<C>(stages: &[Stage<C>], context: &mut C) -> Result<(), KernelError> {
+    kassert!(
+        stages.len() <= MAX_STAGES,
+        InitError::TooManyStages(stages.len()),
+        "{} init stages, at most {MAX_STAGES} are supported",
+        stages.len()
+    );
+
+    for stage in stages {
+        if let Some(dependency) = stage
+            .dependencies
+            .iter()
+            .find(|dependency| position(stages, dependency).is_none())
+        {
+            return Err(InitError::UnknownDependency {
+                stage: stage.name,
+                dependency,
+            }
+            .into());
+        }
+    }
+
+    let tsc_hz = cpuid::tsc_frequency_hz();
+    let mut done = [false; MAX_STAGES];
+    let mut total_cycles = 0;
+
+    for _ in 0..stages.len() {
+        let ready = |(index, stage): &(usize, &Stage<C>)| {
+            !done[*index]
+                && stage
+                    .dependencies
+                    .iter()
+                    .all(|dependency| position(stages, dependency).is_some_and(|i| done[i]))
+        };
+
+        // if nothing is ready but stages remain, they must depend on each other
+        let Some((index, stage)) = stages.iter().enumerate().find(ready) else {
+            let (_, stuck) = stages
+                .iter()
+                .enumerate()
+                .find(|(index, _)| !done[*index])
+                .unwrap();
+
+            return Err(InitError::DependencyCycle(stuck.name).into());
+        };
+
+        let start = rdtsc();
+        (stage.run)(context)?;
+        let cycles = rdtsc() - start;
+
+        done[index] = true;
+        total_cycles += cycles;
+
+        log::debug!(
+            "init stage `{}` took {}",
+            stage.name,
+            Elapsed(cycles, tsc_hz)
+        );
+    }
+
+    log::info!("initialisation took {}", Elapsed(total_cycles, tsc_hz));
+
+    Ok(())
+}
+
+/// Returns the index of the stage with the given name
+fn position<C>(stages: &[Stage<C>], name: &str) -> Option<usize> {
+    stages.iter().position(|stage| stage.name == name)
+}
+
+/// Number of TSC cycles, displayed as a duration if the TSC frequency is known
+struct Elapsed(u64, Option<u64>);
+
+impl core::fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.1 {
+            Some(hz) => write!(f, "{}μs", self.0 as u128 * 1_000_000 / hz as u128),
+            None => write!(f, "{} cycles", self.0),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Choosing which processor each device interrupt is delivered to
+
+use std::mutex::Mutex;
+
+use acpi::tables::fixed::madt::{Madt, MadtField};
+use kernel_shared::x86::hardware::msi::MsiMessage;
+
+use crate::interrupts::{ioapic::IO_APIC, lapic};
+
+/// Maximum number of processors interrupts can be spread across
+const MAX_CPUS: usize = 64;
+
+/// Processors which are online and able to receive device interrupts
+static ONLINE_CPUS: Mutex<CpuSet> = Mutex::new(CpuSet::new());
+
+/// Set of processors, handing them out in turn
+struct CpuSet {
+    /// APIC ids of processors in the set
+    apic_ids: [u8; MAX_CPUS],
+    /// Number of valid entries in `apic_ids`
+    count: usize,
+    /// Index of processor to hand out next
+    next: usize,
+}
+
+impl CpuSet {
+    /// Constructs an empty set
+    const fn new() -> Self {
+        Self {
+            apic_ids: [0; MAX_CPUS],
+            count: 0,
+            next: 0,
+        }
+    }
+
+    /// Adds a processor to the set, if not already present
+    fn insert(&mut self, apic_id: u8) {
+        if self.apic_ids[..self.count].contains(&apic_id) || self.count == MAX_CPUS {
+            return;
+        }
+
+        self.apic_ids[self.count] = apic_id;
+        self.count += 1;
+    }
+
+    /// Returns the next processor in round-robin order
+    fn next(&mut self) -> Option<u8> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let apic_id = self.apic_ids[self.next % self.count];
+        self.next = (self.next + 1) % self.count;
+
+        Some(apic_id)
+    }
+}
+
+/// Marks the bootstrap processor as online.
+///
+/// Application processors are listed in the MADT, but are only added with [`mark_online`] once started.
+pub fn init(madt_table: &Madt) {
+    let bsp_id = lapic::local().unwrap().id();
+    ONLINE_CPUS.lock().insert(bsp_id);
+
+    let mut processors = 0;
+    let mut table_idx = 0;
+
+    while let Some(table) = madt_table.get_table_entry(table_idx) {
+        if let MadtField::ProcessorLocalAPIC { flags, .. } = table
+            && flags & 1 == 1
+        {
+            processors += 1;
+        }
+
+        table_idx += 1;
+    }
+
+    log::trace!("\t\t* {processors} processor(s) in MADT, BSP has APIC id {bsp_id}");
+}
+
+/// Adds a started processor to those device interrupts are spread across, called by application processor bring-up
+#[allow(unused)]
+pub fn mark_online(apic_id: u8) {
+    ONLINE_CPUS.lock().insert(apic_id);
+}
+
+/// Returns the processor the next device interrupt should be routed to
+pub fn next_cpu() -> u8 {
+    ONLINE_CPUS
+        .lock()
+        .next()
+        .expect("no processors online to route interrupts to")
+}
+
+/// Routes the given global system interrupt to the processor with the given APIC id
+pub fn set_gsi_affinity(gsi: u8, apic_id: u8) -> Option<()> {
+    IO_APIC.lock().get_mut()?.set_destination_cpu(gsi, apic_id)
+}
+
+/// Routes the given MSI to the processor with the given APIC id.
+///
+/// The updated message must then be written back to the device's MSI capability.
+pub fn set_msi_affinity(message: &mut MsiMessage, apic_id: u8) {
+    message.set_destination(apic_id);
+}
+
+/// Spreads the given global system interrupts across online processors in round-robin order
+pub fn spread(gsis: &[u8]) {
+    for &gsi in gsis {
+        let apic_id = next_cpu();
+
+        if set_gsi_affinity(gsi, apic_id).is_some() {
+            log::trace!("\t\t* routing GSI {gsi} to APIC id {apic_id}");
+        }
+    }
+}
@@ -1,14 +1,29 @@
+use core::cell::OnceCell;
+use std::mutex::Mutex;
+
 use acpi::tables::fixed::madt::{Madt, MadtField};
 use kernel_shared::{
-    mem::PHYS_MEM_OFFSET,
+    mem::addr::PhysAddr,
     x86::hardware::io_apic::{DeliveryMode, DestinationMode, IoApic, RedirectionEntry},
 };
 
-pub fn init(madt_table: &Madt) {
-    let mut io_apic = find_ioapic(madt_table).expect("no IOAPIC detected!");
+use crate::{
+    error::InterruptError,
+    interrupts::{affinity, vectors::IRQ_BASE},
+};
+
+pub static IO_APIC: Mutex<OnceCell<IoApic>> = Mutex::new(OnceCell::new());
+
+/// ISA IRQ of the PIT
+const TIMER_IRQ: u8 = 0;
+
+/// ISA IRQ of COM1
+const COM1_IRQ: u8 = 4;
+
+pub fn init(madt_table: &Madt) -> Result<(), InterruptError> {
+    let mut io_apic = find_ioapic(madt_table).ok_or(InterruptError::MissingIoApic)?;
     log::trace!("\t* IO APIC found");
 
-    let mut timer_idx = 0;
     let mut table_idx = 0;
 
     while let Some(table) = madt_table.get_table_entry(table_idx) {
@@ -23,7 +38,7 @@ pub fn init(madt_table: &Madt) {
             // set up each redirection entry from interrupt source override tables, but without enabling yet
             let mut redirection_entry = RedirectionEntry::default();
             redirection_entry
-                .set_interrupt_vector(source + 32)
+                .set_interrupt_vector(source + IRQ_BASE)
                 .set_delivery_mode(DeliveryMode::Fixed)
                 .set_destination_mode(DestinationMode::Physical)
                 .set_irq_relaxed(true)
@@ -32,11 +47,6 @@ pub fn init(madt_table: &Madt) {
                 .set_mask(true)
                 .set_destination(0);
 
-            // IRQ source 0 is timer, so we've found it
-            if source == 0 {
-                timer_idx = global_system_interrupt;
-            }
-
             log::trace!("\t\t* setting IO APIC redirect {global_system_interrupt} -> {source}");
             io_apic.set_redirection_entry(global_system_interrupt as u8, redirection_entry);
         }
@@ -44,20 +54,54 @@ pub fn init(madt_table: &Madt) {
         table_idx += 1;
     }
 
-    // also configure keyboard
-    io_apic.modify_redirection_entry(1, |entry| {
+    // enable COM1 so serial input is received. the keyboard IRQ stays masked until a driver handles it.
+    // an overridden IRQ already has its polarity and trigger mode from the loop above, otherwise it uses the ISA default
+    let com1_override = isa_override(madt_table, COM1_IRQ);
+    let com1_gsi = com1_override.unwrap_or(COM1_IRQ);
+    io_apic.modify_redirection_entry(com1_gsi, |entry| {
         entry
-            .set_interrupt_vector(1 + 32)
+            .set_interrupt_vector(COM1_IRQ + IRQ_BASE)
             .set_irq_relaxed(true)
-            .set_mask(false)
-            .set_active_high(true)
-            .set_edge_triggered(true);
+            .set_mask(false);
+
+        if com1_override.is_none() {
+            entry.set_active_high(true).set_edge_triggered(true);
+        }
     });
-    log::trace!("\t\t* setting IO APIC keyboard redirect");
+    log::trace!("\t\t* setting IO APIC COM1 redirect on GSI {com1_gsi}");
 
     // and enable timer
-    io_apic.mask_redirection_entry(timer_idx as u8, false);
+    let timer_gsi = isa_override(madt_table, TIMER_IRQ).unwrap_or(TIMER_IRQ);
+    io_apic.mask_redirection_entry(timer_gsi, false);
     log::trace!("\t\t* enabling IO APIC timer redirect");
+
+    IO_APIC.lock().set(io_apic).unwrap();
+
+    // only the BSP is online until application processors are started, so for now this routes everything to it
+    affinity::spread(&[com1_gsi, timer_gsi]);
+
+    Ok(())
+}
+
+/// Returns the global system interrupt an interrupt source override maps the given ISA IRQ to, if there is one
+fn isa_override(madt_table: &Madt, irq: u8) -> Option<u8> {
+    let mut table_idx = 0;
+
+    while let Some(table) = madt_table.get_table_entry(table_idx) {
+        if let MadtField::InterruptSourceOverride {
+            source,
+            global_system_interrupt,
+            ..
+        } = table
+            && source == irq
+        {
+            return Some(global_system_interrupt as u8);
+        }
+
+        table_idx += 1;
+    }
+
+    None
 }
 
 fn find_ioapic(madt_table: &Madt) -> Option<IoApic> {
@@ -74,7 +118,9 @@ fn find_ioapic(madt_table: &Madt) -> Option<IoApic> {
             global_system_interrupt_base: _,
         } = table
         {
-            io_apic = Some(unsafe { IoApic::new(apic_addr as usize | PHYS_MEM_OFFSET) });
+            io_apic = Some(unsafe {
+                IoApic::new(PhysAddr::new(apic_addr as usize).to_virt().as_usize())
+            });
             break;
         }
 
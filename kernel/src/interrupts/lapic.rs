@@ -1,20 +1,40 @@
-use core::cell::OnceCell;
+use core::{
+    cell::OnceCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 use std::mutex::Mutex;
 
 use acpi::tables::fixed::madt::Madt;
-use kernel_shared::{mem::PHYS_MEM_OFFSET, x86::hardware::local_apic::LocalApic};
+use kernel_shared::{mem::addr::PhysAddr, x86::hardware::local_apic::LocalApic};
 
+use crate::interrupts::vectors::SPURIOUS_VECTOR;
+
+/// Local APIC used for configuration, which must be synchronised
 pub static LAPIC: Mutex<OnceCell<LocalApic>> = Mutex::new(OnceCell::new());
 
+/// Address every processor sees its own local APIC at, or 0 before initialisation
+static LAPIC_BASE: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a handle to the current processor's local APIC without taking any locks, so it can be used from
+/// interrupt handlers.
+///
+/// Only per-processor registers which need no synchronisation (such as EOI and the APIC id) should be accessed
+/// through this handle, anything else goes through [`LAPIC`].
+pub fn local() -> Option<LocalApic> {
+    let base_addr = LAPIC_BASE.load(Ordering::Acquire);
+
+    (base_addr != 0).then(|| unsafe { LocalApic::new(base_addr) })
+}
+
 pub fn init(madt_table: &Madt) {
+    let base_addr = PhysAddr::new(madt_table.lapic_addr as usize)
+        .to_virt()
+        .as_usize();
+
     unsafe {
         // set static LAPIC based on address in madt
-        LAPIC
-            .lock()
-            .set(LocalApic::new(
-                madt_table.lapic_addr as usize | PHYS_MEM_OFFSET,
-            ))
-            .unwrap();
+        LAPIC.lock().set(LocalApic::new(base_addr)).unwrap();
+        LAPIC_BASE.store(base_addr, Ordering::Release);
 
         // and then actually enable
         LAPIC
@@ -22,7 +42,7 @@ pub fn init(madt_table: &Madt) {
             .get()
             .unwrap()
             .spurious_interrupt_vector_register()
-            .set_spurious_vector(0xFF)
+            .set_spurious_vector(SPURIOUS_VECTOR)
             .set_enabled(true);
     }
 }
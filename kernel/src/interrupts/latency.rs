@@ -0,0 +1,101 @@
+//! Measuring how long interrupt handlers take, in TSC cycles from handler entry to completion
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use kernel_shared::x86::rdtsc;
+
+/// Latency of a single vector, updated by its handler
+struct VectorLatency {
+    /// Number of interrupts measured
+    count: AtomicU64,
+    /// Sum of every measurement, for the average
+    total: AtomicU64,
+    /// Fastest measurement
+    min: AtomicU64,
+    /// Slowest measurement
+    max: AtomicU64,
+}
+
+impl VectorLatency {
+    /// Constructs empty statistics
+    const fn new() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Latency statistics of every vector
+static LATENCIES: [VectorLatency; 256] = [const { VectorLatency::new() }; 256];
+
+/// Snapshot of the latency statistics of a vector, in TSC cycles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latency {
+    /// Number of interrupts measured
+    pub count: u64,
+    /// Fastest handler
+    pub min: u64,
+    /// Mean handler time
+    pub avg: u64,
+    /// Slowest handler
+    pub max: u64,
+}
+
+/// Runs an interrupt handler body, recording how long it took against the given vector
+#[inline(always)]
+pub fn measure<R>(vector: u8, handler: impl FnOnce() -> R) -> R {
+    let start = rdtsc();
+    let result = handler();
+    record(vector, rdtsc().wrapping_sub(start));
+
+    result
+}
+
+/// Records a single measurement for the given vector
+pub fn record(vector: u8, cycles: u64) {
+    let latency = &LATENCIES[vector as usize];
+
+    latency.count.fetch_add(1, Ordering::Relaxed);
+    latency.total.fetch_add(cycles, Ordering::Relaxed);
+    latency.min.fetch_min(cycles, Ordering::Relaxed);
+    latency.max.fetch_max(cycles, Ordering::Relaxed);
+}
+
+/// Returns the latency statistics of the given vector, if any interrupts on it have been measured
+pub fn get(vector: u8) -> Option<Latency> {
+    let latency = &LATENCIES[vector as usize];
+    let count = latency.count.load(Ordering::Relaxed);
+
+    (count > 0).then(|| Latency {
+        count,
+        min: latency.min.load(Ordering::Relaxed),
+        avg: latency.total.load(Ordering::Relaxed) / count,
+        max: latency.max.load(Ordering::Relaxed),
+    })
+}
+
+/// Returns the latency statistics of every vector which has been measured
+pub fn measured() -> impl Iterator<Item = (u8, Latency)> {
+    (0..=u8::MAX).filter_map(|vector| get(vector).map(|latency| (vector, latency)))
+}
+
+/// Logs the latency statistics of every vector which has been measured
+pub fn dump() {
+    log::info!("interrupt latency (TSC cycles):");
+
+    for (
+        vector,
+        Latency {
+            count,
+            min,
+            avg,
+            max,
+        },
+    ) in measured()
+    {
+        log::info!("\t{vector:#04X}: {count} interrupts, min {min}, avg {avg}, max {max}");
+    }
+}
@@ -1,68 +1,172 @@
+mod affinity;
 mod ioapic;
 mod lapic;
+pub mod latency;
 mod pic_8259;
+pub mod selftest;
+pub mod single_step;
+pub mod snapshot;
+pub mod stats;
 mod timers;
+mod vectors;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::init_once::{InitOnce, InitToken};
 
 use acpi::tables::fixed::{hpet::Hpet, madt::Madt};
 use bitflags::bitflags;
-use kernel_shared::x86::{
-    enable_interrupts, exception::ExceptionStackFrame, halt, idt::InterruptDescriptorTable,
-    registers::CR2,
+use kernel_shared::{
+    config, fault,
+    io::serial_demux,
+    timer,
+    x86::{
+        debug::{self, DR6, Dr6Flags},
+        enable_interrupts,
+        exception::ExceptionStackFrame,
+        halt,
+        idt::InterruptDescriptorTable,
+        registers::{CR2, CpuFlags},
+    },
 };
 use lazy_static::lazy_static;
 
 use crate::{
+    error::{InterruptError, KernelError},
     gdt,
-    interrupts::{lapic::LAPIC, pic_8259::PICS},
+    interrupts::{pic_8259::PICS, vectors::IRQ_BASE},
 };
 
+/// Guards against interrupts being initialised twice
+static INIT: InitOnce = InitOnce::new();
+
+/// Whether the legacy 8259 PICs are handling interrupts, because the APICs could not be set up
+static USING_PIC: AtomicBool = AtomicBool::new(false);
+
 lazy_static! {
     static ref IDT: InterruptDescriptorTable = {
         let mut idt = InterruptDescriptorTable::default();
 
         idt.divide_error.set(divide_by_zero_handler);
+        idt.debug.set(debug_handler);
         idt.breakpoint.set(breakpoint_handler);
         idt.invalid_opcode.set(invalid_opcode_handler);
-        idt.page_fault.set(page_fault_handler);
         idt.general_protection_fault
             .set(general_protection_fault_handler);
         unsafe {
+            idt.non_maskable_interrupt
+                .set(nmi_handler)
+                .set_ist_index(gdt::NMI_IST_INDEX);
+
+            let page_fault = idt.page_fault.set(page_fault_handler);
+            if config::PAGE_FAULT_IST {
+                page_fault.set_ist_index(gdt::PAGE_FAULT_IST_INDEX);
+            }
+
             idt.double_fault
                 .set(double_fault)
                 .set_ist_index(gdt::DOUBLE_FAULT_IST_INDEX);
         }
 
         idt[0x20].set(timer_interrupt_handler);
+        idt[IRQ_BASE + 4].set(com1_interrupt_handler);
+        idt[IRQ_BASE + 7].set(irq7_handler);
+        idt[IRQ_BASE + 15].set(irq15_handler);
 
         idt
     };
 }
 
-extern "x86-interrupt" fn divide_by_zero_handler(stack_frame: ExceptionStackFrame) {
-    log::error!("EXCEPTION: DIVIDE BY ZERO\n{stack_frame}");
+extern "x86-interrupt" fn divide_by_zero_handler(mut stack_frame: ExceptionStackFrame) {
+    let should_log = stats::record(0);
+    if selftest::recover(&mut stack_frame) {
+        return;
+    }
+
+    if should_log {
+        log::error!("EXCEPTION: DIVIDE BY ZERO\n{stack_frame}");
+    }
 
     halt();
 }
 
-extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: ExceptionStackFrame) {
-    log::error!(
-        "EXCEPTION: INVALID OPCODE at {:#X}\n{}",
-        stack_frame.instruction_pointer,
-        stack_frame
-    );
+extern "x86-interrupt" fn invalid_opcode_handler(mut stack_frame: ExceptionStackFrame) {
+    let should_log = stats::record(6);
+    if selftest::recover(&mut stack_frame) {
+        return;
+    }
+
+    if should_log {
+        log::error!(
+            "EXCEPTION: INVALID OPCODE at {:#X}\n{}",
+            stack_frame.instruction_pointer,
+            stack_frame
+        );
+    }
 
     halt();
 }
 
+extern "x86-interrupt" fn debug_handler(mut stack_frame: ExceptionStackFrame) {
+    // the status flags are never cleared by the CPU, so would be reported again by the next debug exception
+    let status = DR6::read();
+    DR6::clear();
+
+    // steps while tracing are far too frequent to count or log, and nothing else needs doing for them
+    let stepped = status.contains(Dr6Flags::SINGLE_STEP);
+    let traced = stepped && single_step::step(&mut stack_frame);
+    if traced && status.breakpoints_hit().next().is_none() {
+        return;
+    }
+
+    if stats::record(1) {
+        if stepped && !traced {
+            log::warn!(
+                "EXCEPTION: DEBUG, unexpected single step at {:#X}",
+                stack_frame.instruction_pointer
+            );
+        }
+        for index in status.breakpoints_hit() {
+            match debug::breakpoint(index) {
+                Some(breakpoint) => log::warn!(
+                    "EXCEPTION: DEBUG, hit {breakpoint} at {:#X}\n{}",
+                    stack_frame.instruction_pointer,
+                    stack_frame
+                ),
+                None => log::warn!("EXCEPTION: DEBUG, hit cleared hardware breakpoint {index}"),
+            }
+        }
+    }
+
+    // execute breakpoints fault before the instruction runs, so it has to be skipped once or it would trigger again
+    if status.breakpoints_hit().next().is_some() {
+        // safety: this is the handler's own frame, and the resume flag only suppresses instruction breakpoints
+        let flags = stack_frame.cpu_flags | CpuFlags::RESUME_FLAG;
+        unsafe { stack_frame.as_mut_view() }.set_cpu_flags(flags);
+    }
+}
+
 extern "x86-interrupt" fn breakpoint_handler(stack_frame: ExceptionStackFrame) {
-    log::warn!(
-        "EXCEPTION: BREAKPOINT at {:#X}\n{}",
-        stack_frame.instruction_pointer,
-        stack_frame
-    );
+    if stats::record(3) {
+        log::warn!(
+            "EXCEPTION: BREAKPOINT at {:#X}\n{}",
+            stack_frame.instruction_pointer,
+            stack_frame
+        );
+    }
+}
+
+extern "x86-interrupt" fn nmi_handler(stack_frame: ExceptionStackFrame) {
+    // an NMI can arrive while the log locks are held, even with interrupts disabled, so it's only counted
+    selftest::note_stack(&stack_frame);
+    stats::record_quietly(2);
 }
 
 extern "x86-interrupt" fn double_fault(stack_frame: ExceptionStackFrame, err: u64) -> ! {
+    stats::record(8);
+    if selftest::expecting_stack_overflow() {
+        selftest::finish_stack_overflow("double fault", gdt::DOUBLE_FAULT_IST_INDEX, &stack_frame);
+    }
+
     log::error!("DOUBLE FAULT with err {err}\n{stack_frame}");
     panic!("\nDOUBLE FAULT with err {}\n{}", err, stack_frame);
 }
@@ -78,40 +182,125 @@ bitflags! {
     }
 }
 
-extern "x86-interrupt" fn page_fault_handler(stack_frame: ExceptionStackFrame, error_code: u64) {
-    log::error!(
-        "EXCEPTION: PAGE FAULT while accessing {:#X}\
-        \nerror code: {:?}\n{}",
-        CR2::read(),
-        PageFaultErrorCode::from_bits(error_code).unwrap(),
-        stack_frame
-    );
+extern "x86-interrupt" fn page_fault_handler(
+    mut stack_frame: ExceptionStackFrame,
+    error_code: u64,
+) {
+    selftest::note_stack(&stack_frame);
+    let should_log = stats::record(14);
+    if selftest::recover(&mut stack_frame) {
+        return;
+    }
+
+    // without its own stack, a page fault from overflowing the stack can't be delivered, so becomes a double fault
+    if config::PAGE_FAULT_IST && selftest::expecting_stack_overflow() {
+        selftest::finish_stack_overflow("page fault", gdt::PAGE_FAULT_IST_INDEX, &stack_frame);
+    }
+
+    if should_log {
+        log::error!(
+            "EXCEPTION: PAGE FAULT while accessing {:#X}\
+            \nerror code: {:?}\n{}",
+            CR2::read(),
+            PageFaultErrorCode::from_bits(error_code).unwrap(),
+            stack_frame
+        );
+    }
 
     halt();
 }
 
 extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: ExceptionStackFrame) {
-    log::trace!("timer interrupt.");
+    latency::measure(0x20, || {
+        log::trace!("timer interrupt.");
+        timer::run_expired();
+
+        end_of_interrupt(0x20);
+    });
+}
+
+extern "x86-interrupt" fn com1_interrupt_handler(_stack_frame: ExceptionStackFrame) {
+    latency::measure(IRQ_BASE + 4, || {
+        serial_demux::receive_com1();
 
-    LAPIC.lock().get_mut().unwrap().end_of_interrupt();
+        end_of_interrupt(IRQ_BASE + 4);
+    });
+}
+
+extern "x86-interrupt" fn irq7_handler(_stack_frame: ExceptionStackFrame) {
+    latency::measure(IRQ_BASE + 7, || possibly_spurious_irq(IRQ_BASE + 7));
+}
+
+extern "x86-interrupt" fn irq15_handler(_stack_frame: ExceptionStackFrame) {
+    latency::measure(IRQ_BASE + 15, || possibly_spurious_irq(IRQ_BASE + 15));
+}
+
+/// Handles IRQ7 or IRQ15, which the 8259 PICs also raise for spurious interrupts
+fn possibly_spurious_irq(vector: u8) {
+    // when using the APICs this vector may be a real IOAPIC interrupt, and the PIC in-service register says nothing
+    // about it, so only check when the PICs are in use
+    if USING_PIC.load(Ordering::Relaxed) && unsafe { PICS.lock().handle_spurious(vector) } {
+        log::debug!(
+            "spurious 8259 interrupt on vector {vector:#X}, seen {:?} so far",
+            pic_8259::spurious_counts()
+        );
+        return;
+    }
+
+    log::warn!("unhandled interrupt on vector {vector:#X}");
+    end_of_interrupt(vector);
+}
+
+/// Signals that an interrupt has been handled to whichever interrupt controller raised it
+fn end_of_interrupt(vector: u8) {
+    if USING_PIC.load(Ordering::Relaxed) {
+        unsafe { PICS.lock().notify_end_of_interrupt(vector) };
+    } else {
+        // interrupt handlers can't take the LAPIC lock, as it may already be held by the code they interrupted
+        lapic::local()
+            .expect("LAPIC interrupt without LAPIC")
+            .end_of_interrupt();
+    }
 }
 
 extern "x86-interrupt" fn general_protection_fault_handler(
-    stack_frame: ExceptionStackFrame,
+    mut stack_frame: ExceptionStackFrame,
     error_code: u64,
 ) {
-    log::error!(
-        "EXCEPTION: GENERAL PROTECTION FAULT while accessing {:#X}\
-        \nerror code: {:?}\n{}",
-        CR2::read(),
-        error_code,
-        stack_frame
-    );
+    let should_log = stats::record(13);
+    if selftest::recover(&mut stack_frame) {
+        return;
+    }
+
+    if should_log {
+        log::error!(
+            "EXCEPTION: GENERAL PROTECTION FAULT while accessing {:#X}\
+            \nerror code: {:?}\n{}",
+            CR2::read(),
+            error_code,
+            stack_frame
+        );
+    }
 
     halt();
 }
 
-pub fn init(madt_table: &Madt, hpet_table: &Hpet) {
+/// Sets up interrupts, using the APICs if described by the MADT and the 8259 PICs otherwise.
+///
+/// Returns an error without touching any interrupt state if called more than once.
+pub fn init(
+    madt_table: Option<&Madt>,
+    hpet_table: Option<&Hpet>,
+) -> Result<InitToken, InterruptError> {
+    let ((), token) = INIT
+        .call_once(|| init_controllers(madt_table, hpet_table))
+        .ok_or(InterruptError::AlreadyInitialised)?;
+
+    Ok(token)
+}
+
+/// Loads the IDT, programs the interrupt controllers and timers, then enables interrupts
+fn init_controllers(madt_table: Option<&Madt>, hpet_table: Option<&Hpet>) {
     log::trace!("initialising interrupts");
 
     IDT.load();
@@ -131,16 +320,51 @@ pub fn init(madt_table: &Madt, hpet_table: &Hpet) {
     }
     log::trace!("\t* 8259 PICs disabled");
 
-    lapic::init(madt_table);
-    log::trace!("\t* LAPIC enabled");
+    let apic_result = match madt_table {
+        Some(madt_table) => init_apic(madt_table),
+        None => Err(InterruptError::NoMadt),
+    };
 
-    ioapic::init(madt_table);
-    log::trace!("\t* IOAPIC programmed");
+    let hpet_table = match apic_result {
+        Ok(()) => hpet_table,
+        Err(err) => {
+            log::warn!("{}", KernelError::from(err));
+            log::warn!("\t* falling back to 8259 PICs");
+
+            // HPET is routed through the IOAPIC, so the PIT must be used instead
+            USING_PIC.store(true, Ordering::Relaxed);
+            unsafe { PICS.lock().write_masks(0xEE, 0xFF) };
+            log::trace!("\t* 8259 PIC timer and COM1 IRQs unmasked");
+
+            None
+        }
+    };
 
     timers::init(hpet_table);
     log::trace!("\t* timers programmed");
 
+    // input received before now never raised an interrupt, and an edge triggered IRQ won't be raised again until it
+    // is read
+    serial_demux::receive_com1();
+
     enable_interrupts();
     log::trace!("\t* enabled interrupts");
     log::trace!("interrupts initialised");
 }
+
+fn init_apic(madt_table: &Madt) -> Result<(), InterruptError> {
+    if fault::APIC.should_fail() {
+        return Err(InterruptError::MissingIoApic);
+    }
+
+    lapic::init(madt_table);
+    log::trace!("\t* LAPIC enabled");
+
+    affinity::init(madt_table);
+    log::trace!("\t* interrupt affinity initialised");
+
+    ioapic::init(madt_table)?;
+    log::trace!("\t* IOAPIC programmed");
+
+    Ok(())
+}
@@ -1,8 +1,9 @@
 #![allow(unused)]
 
+use core::sync::atomic::{AtomicUsize, Ordering};
 use std::mutex::Mutex;
 
-use kernel_shared::io::port::Port;
+use kernel_shared::{io::port::Port, x86::delay::delay_us};
 
 macro_rules! intersperse {
     (
@@ -25,6 +26,14 @@ pub static PICS: Mutex<ChainedPics> = Mutex::new(unsafe { ChainedPics::new(32, 4
 
 const CMD_INIT: u8 = 0x11;
 const CMD_END_OF_INTERRUPT: u8 = 0x20;
+const CMD_READ_IRR: u8 = 0x0A;
+const CMD_READ_ISR: u8 = 0x0B;
+
+/// IRQ line each PIC raises spurious interrupts on
+const SPURIOUS_LINE: u8 = 7;
+
+/// Number of spurious interrupts seen from each PIC
+static SPURIOUS_COUNTS: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
 
 const MODE_8086: u8 = 0x01;
 
@@ -46,6 +55,22 @@ impl Pic {
         unsafe { self.command.write(CMD_END_OF_INTERRUPT) };
     }
 
+    /// Reads the in-service register, which has a bit set for each IRQ currently being handled
+    unsafe fn read_isr(&mut self) -> u8 {
+        unsafe {
+            self.command.write(CMD_READ_ISR);
+            self.command.read()
+        }
+    }
+
+    /// Reads the interrupt request register, which has a bit set for each IRQ waiting to be handled
+    unsafe fn read_irr(&mut self) -> u8 {
+        unsafe {
+            self.command.write(CMD_READ_IRR);
+            self.command.read()
+        }
+    }
+
     /// Reads the current mask
     unsafe fn read_mask(&mut self) -> u8 {
         unsafe { self.data.read() }
@@ -85,14 +110,12 @@ impl ChainedPics {
 
     /// Initialises the PICs so they're ready to start handling interrupts
     pub unsafe fn init(&mut self) {
-        // need to add artificial delay, so write garbage data to this port between each write
-        let mut wait_port: Port<u8> = Port::new(0x80);
-
         unsafe {
             let saved_masks = self.read_masks();
 
+            // older PICs need a short delay between each command
             intersperse!(
-                wait_port.write(0);
+                delay_us(1);
                 {
                     // start init process
                     self.pics[0].command.write(CMD_INIT);
@@ -135,6 +158,50 @@ impl ChainedPics {
         self.pics.iter().any(|p| p.handles_interrupt(interrupt_id))
     }
 
+    /// Reads the in-service registers of both PICs, with the secondary PIC in the high byte
+    pub unsafe fn read_isr(&mut self) -> u16 {
+        unsafe { u16::from_le_bytes([self.pics[0].read_isr(), self.pics[1].read_isr()]) }
+    }
+
+    /// Reads the interrupt request registers of both PICs, with the secondary PIC in the high byte
+    pub unsafe fn read_irr(&mut self) -> u16 {
+        unsafe { u16::from_le_bytes([self.pics[0].read_irr(), self.pics[1].read_irr()]) }
+    }
+
+    /// Returns the vectors spurious interrupts can arrive on, which are IRQ7 and IRQ15
+    pub fn spurious_vectors(&self) -> [u8; 2] {
+        [
+            self.pics[0].offset + SPURIOUS_LINE,
+            self.pics[1].offset + SPURIOUS_LINE,
+        ]
+    }
+
+    /// Checks if an interrupt on IRQ7 or IRQ15 was spurious, by whether the PIC actually has it in service.
+    ///
+    /// No EOI must be sent to the PIC that raised a spurious interrupt, but if it came from the secondary PIC then
+    /// the primary PIC did see a real interrupt on the cascade line, so it is sent an EOI here.
+    pub unsafe fn handle_spurious(&mut self, interrupt: u8) -> bool {
+        let Some(index) = self
+            .pics
+            .iter()
+            .position(|pic| pic.offset + SPURIOUS_LINE == interrupt)
+        else {
+            return false;
+        };
+
+        let in_service = unsafe { self.pics[index].read_isr() } & (1 << SPURIOUS_LINE) != 0;
+        if in_service {
+            return false;
+        }
+
+        SPURIOUS_COUNTS[index].fetch_add(1, Ordering::Relaxed);
+        if index == 1 {
+            unsafe { self.pics[0].end_of_interrupt() };
+        }
+
+        true
+    }
+
     /// Writes an end of interrupt command
     pub unsafe fn notify_end_of_interrupt(&mut self, interrupt: u8) {
         if self.handles_interrupt(interrupt) {
@@ -146,3 +213,10 @@ impl ChainedPics {
         }
     }
 }
+
+/// Returns the number of spurious interrupts seen from the primary and secondary PIC
+pub fn spurious_counts() -> [usize; 2] {
+    SPURIOUS_COUNTS
+        .each_ref()
+        .map(|count| count.load(Ordering::Relaxed))
+}
@@ -0,0 +1,261 @@
+//! Deliberately triggers each exception to check the IDT, TSS and IST are configured correctly.
+//!
+//! Only run when the `EXCEPTION_SELFTEST` feature is enabled, as it finishes by overflowing the stack and halting.
+//! Handlers with an IST entry are also checked to have run on their own stack, going by where the CPU pushed the
+//! exception stack frame. Saving and restoring the interrupt controllers is checked along the way, as nothing else
+//! restores them yet.
+
+use core::{
+    arch::asm,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use kernel_shared::{
+    config,
+    mem::addr::VirtAddr,
+    x86::{
+        debug::{self, Breakpoint, BreakpointCondition, BreakpointSize},
+        exception::ExceptionStackFrame,
+        halt, without_interrupts,
+    },
+};
+
+use crate::{
+    gdt,
+    interrupts::{single_step, snapshot, stats},
+};
+
+/// Address to resume at after the expected exception, or 0 if no exception is expected
+static RECOVERY_ADDR: AtomicU64 = AtomicU64::new(0);
+
+/// Whether the stack is being deliberately overflowed, so the next double fault (or page fault, if page faults have
+/// their own stack) is expected
+static EXPECTING_STACK_OVERFLOW: AtomicBool = AtomicBool::new(false);
+
+/// Address of the exception stack frame most recently passed to [`note_stack`]
+static LAST_FRAME_ADDR: AtomicU64 = AtomicU64::new(0);
+
+/// Variable for the hardware watchpoint check to write to
+static WATCHED: AtomicU64 = AtomicU64::new(0);
+
+/// Address which is never mapped, just past the end of the heap region
+const UNMAPPED_ADDR: u64 = 0xFFFFFFFF30000000;
+
+/// Address which is not canonical, so causes a general protection fault on access
+const NON_CANONICAL_ADDR: u64 = 0x8000000000000000;
+
+/// Executes the given instruction(s), which are expected to fault, then resumes just after them
+macro_rules! trigger {
+    ($($instruction:literal),+ $(, $($operands:tt)*)?) => {
+        unsafe {
+            asm!(
+                "lea {tmp}, [rip + 2f]",
+                "mov [{recovery}], {tmp}",
+                $($instruction,)+
+                "2:",
+                tmp = out(reg) _,
+                recovery = in(reg) RECOVERY_ADDR.as_ptr(),
+                $($($operands)*)?
+            )
+        }
+    };
+}
+
+/// Triggers each exception in turn, logging whether the expected handler ran, and then overflows the stack to check
+/// double faults are handled on a separate stack. This never returns.
+pub fn run() -> ! {
+    log::info!("running exception self-test");
+
+    let mut failures = 0;
+
+    failures += check("breakpoint", 3, || unsafe { asm!("int3") });
+    failures += check("divide error", 0, || {
+        trigger!("div ecx", inout("eax") 0 => _, inout("edx") 0 => _, in("ecx") 0);
+    });
+    failures += check("invalid opcode", 6, || trigger!("ud2"));
+    failures += check("page fault", 14, || {
+        trigger!("mov {value}, [{addr}]", addr = in(reg) UNMAPPED_ADDR, value = out(reg) _);
+    });
+    if config::PAGE_FAULT_IST {
+        failures += check_stack("page fault", gdt::PAGE_FAULT_IST_INDEX);
+    }
+    failures += check("general protection fault", 13, || {
+        trigger!("mov {value}, [{addr}]", addr = in(reg) NON_CANONICAL_ADDR, value = out(reg) _);
+    });
+    failures += check("hardware watchpoint", 1, || {
+        watch(
+            BreakpointCondition::Write,
+            WATCHED.as_ptr() as usize,
+            || WATCHED.store(1, Ordering::Relaxed),
+        );
+    });
+    // returning from the handler would trigger an execute breakpoint again unless it sets the resume flag
+    failures += check("hardware breakpoint", 1, || {
+        watch(
+            BreakpointCondition::Execute,
+            watched_function as *const () as usize,
+            || {
+                core::hint::black_box(watched_function)();
+            },
+        );
+    });
+    failures += check_single_step();
+    failures += check_snapshot();
+    // a software `int 2` doesn't block NMIs like a real one, but goes through the same gate so switches stacks the same
+    failures += check("non-maskable interrupt", 2, || unsafe { asm!("int 2") });
+    failures += check_stack("non-maskable interrupt", gdt::NMI_IST_INDEX);
+
+    if failures == 0 {
+        log::info!("all exceptions handled, overflowing stack to test IST");
+    } else {
+        log::error!("{failures} exception(s) not handled, overflowing stack anyway");
+    }
+
+    EXPECTING_STACK_OVERFLOW.store(true, Ordering::Relaxed);
+    overflow_stack(0);
+
+    unreachable!("stack overflow did not fault");
+}
+
+/// Runs the closure, checking the given exception was raised exactly once. Returns the number of failures.
+fn check<F: FnOnce()>(name: &str, vector: u8, trigger: F) -> usize {
+    let before = stats::count(vector);
+    trigger();
+    let after = stats::count(vector);
+
+    // make sure a handler which didn't recover doesn't leave a stale address around
+    RECOVERY_ADDR.store(0, Ordering::Relaxed);
+
+    if after == before + 1 {
+        log::info!("\t* {name}: ok");
+        0
+    } else {
+        log::error!(
+            "\t* {name}: handler ran {} times, expected once",
+            after - before
+        );
+        1
+    }
+}
+
+/// Runs the closure with a hardware breakpoint set on `addr`, clearing it afterwards
+fn watch<F: FnOnce()>(condition: BreakpointCondition, addr: usize, trigger: F) {
+    let size = match condition {
+        BreakpointCondition::Execute => BreakpointSize::One,
+        _ => BreakpointSize::Eight,
+    };
+    let breakpoint = Breakpoint {
+        addr: VirtAddr::new(addr),
+        condition,
+        size,
+    };
+
+    // safety: neither the watched variable nor function are touched by the debug exception handler
+    if let Err(err) = unsafe { debug::set_breakpoint(0, breakpoint) } {
+        log::error!("failed to set {breakpoint}: {err}");
+        return;
+    }
+
+    trigger();
+    debug::clear_breakpoint(0);
+}
+
+/// Single step traces a call to [`watched_function`], checking its first instruction was stepped. Returns the number
+/// of failures.
+fn check_single_step() -> usize {
+    let entry = watched_function as *const () as usize;
+    single_step::trace(|| core::hint::black_box(watched_function)());
+
+    if single_step::steps().any(|address| address == entry) {
+        log::info!("\t* single step: ok");
+        0
+    } else {
+        log::error!(
+            "\t* single step: {entry:#X} not among {} steps traced",
+            single_step::steps().count()
+        );
+        1
+    }
+}
+
+/// Saves the interrupt controller state and restores it straight away, checking nothing changed. Returns the number
+/// of failures.
+fn check_snapshot() -> usize {
+    // interrupts stay off throughout, so delivery status bits don't change between saves
+    let (saved, restored) = without_interrupts(|| {
+        let saved = snapshot::save();
+        // safety: saved on this processor just now, so every vector it refers to still has its handler
+        unsafe { snapshot::restore(&saved) };
+
+        (saved, snapshot::save())
+    });
+
+    if restored == saved {
+        log::info!("\t* interrupt controller save/restore: ok");
+        0
+    } else {
+        log::error!("\t* interrupt controller save/restore: state changed after restoring");
+        1
+    }
+}
+
+/// Function for the hardware breakpoint and single step checks to break on
+#[inline(never)]
+extern "C" fn watched_function() {}
+
+/// Checks the most recent exception stack frame was pushed to the stack of the given IST index. Returns the number of
+/// failures.
+fn check_stack(name: &str, ist_index: u16) -> usize {
+    let frame_addr = LAST_FRAME_ADDR.swap(0, Ordering::Relaxed) as usize;
+
+    if gdt::ist_stack(ist_index).contains(&frame_addr) {
+        log::info!("\t* {name}: ok, handled on IST stack {ist_index}");
+        0
+    } else {
+        log::error!("\t* {name}: handled at {frame_addr:#X}, not on IST stack {ist_index}");
+        1
+    }
+}
+
+/// Recurses until the stack runs into the unmapped memory below it
+#[inline(never)]
+#[allow(unconditional_recursion)]
+fn overflow_stack(depth: usize) -> usize {
+    let buffer = core::hint::black_box([depth as u8; 4096]);
+
+    overflow_stack(depth + 1) + buffer[0] as usize
+}
+
+/// Records where the CPU pushed the exception stack frame, so [`check_stack`] can tell which stack a handler ran on
+pub fn note_stack(stack_frame: &ExceptionStackFrame) {
+    LAST_FRAME_ADDR.store(stack_frame as *const _ as u64, Ordering::Relaxed);
+}
+
+/// Resumes execution at the recovery address if an exception is expected, returning whether it did so
+pub fn recover(stack_frame: &mut ExceptionStackFrame) -> bool {
+    let addr = RECOVERY_ADDR.swap(0, Ordering::Relaxed);
+    if addr == 0 {
+        return false;
+    }
+
+    // safety: only called by exception handlers with their own frame, and the recovery address is set by the
+    // self-test to somewhere safe to resume
+    unsafe { stack_frame.as_mut_view() }.set_instruction_pointer(addr);
+
+    true
+}
+
+/// Returns whether the self-test is deliberately overflowing the stack
+pub fn expecting_stack_overflow() -> bool {
+    EXPECTING_STACK_OVERFLOW.load(Ordering::Relaxed)
+}
+
+/// Reports the fault caused by overflowing the stack, checking it was handled on the given IST stack, then halts
+pub fn finish_stack_overflow(name: &str, ist_index: u16, stack_frame: &ExceptionStackFrame) -> ! {
+    note_stack(stack_frame);
+    check_stack(name, ist_index);
+    log::info!("stack overflow reported as {name}\n{stack_frame}");
+    log::info!("exception self-test complete");
+
+    halt();
+}
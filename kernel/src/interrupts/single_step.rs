@@ -0,0 +1,79 @@
+//! Single stepping a window of kernel code, recording the address of every instruction executed.
+//!
+//! This is a last resort for code which corrupts state before anything can be logged: the trace is kept in plain
+//! atomics, so it can be read back after the fact and is included in crash dumps. Interrupt and exception handlers
+//! run with the trap flag cleared, so they aren't traced.
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use kernel_shared::x86::{
+    debug::{clear_trap_flag, set_trap_flag},
+    exception::ExceptionStackFrame,
+    registers::CpuFlags,
+};
+
+/// Number of instruction addresses kept, the most recent overwriting the oldest
+pub const TRACE_LENGTH: usize = 1024;
+
+/// Address of each instruction stepped, indexed by step number modulo [`TRACE_LENGTH`]
+static TRACE: [AtomicUsize; TRACE_LENGTH] = [const { AtomicUsize::new(0) }; TRACE_LENGTH];
+
+/// Number of steps recorded since tracing last started
+static STEPS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether a window is being traced
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+/// Runs the closure one instruction at a time, recording the address of each instruction in the trace. Any previous
+/// trace is discarded.
+///
+/// Every step takes a debug exception, so this is very slow. Panics if already tracing.
+#[inline(never)]
+pub fn trace<R, F: FnOnce() -> R>(f: F) -> R {
+    assert!(
+        !TRACING.swap(true, Ordering::Relaxed),
+        "single step tracing can't be nested"
+    );
+    STEPS.store(0, Ordering::Relaxed);
+
+    // safety: the debug exception handler records steps while tracing
+    unsafe { set_trap_flag() };
+    let result = f();
+    stop();
+
+    result
+}
+
+/// Stops tracing, keeping what has been recorded so far. Does nothing if not tracing.
+pub fn stop() {
+    // the trap flag goes first, so the last few steps are still expected
+    clear_trap_flag();
+    TRACING.store(false, Ordering::Relaxed);
+}
+
+/// Records a single step from the debug exception handler, returning whether it was expected. An unexpected step
+/// has the trap flag cleared, so execution carries on normally.
+pub fn step(stack_frame: &mut ExceptionStackFrame) -> bool {
+    if TRACING.load(Ordering::Relaxed) {
+        let step = STEPS.fetch_add(1, Ordering::Relaxed);
+        TRACE[step % TRACE_LENGTH]
+            .store(stack_frame.instruction_pointer as usize, Ordering::Relaxed);
+
+        return true;
+    }
+
+    // safety: only called by the debug handler with its own frame, and clearing the trap flag just stops stepping
+    let flags = stack_frame.cpu_flags - CpuFlags::TRAP_FLAG;
+    unsafe { stack_frame.as_mut_view() }.set_cpu_flags(flags);
+
+    false
+}
+
+/// Returns the address of each instruction in the most recent trace, oldest first. Only the last [`TRACE_LENGTH`]
+/// are kept.
+pub fn steps() -> impl Iterator<Item = usize> {
+    let count = STEPS.load(Ordering::Relaxed);
+    let start = count.saturating_sub(TRACE_LENGTH);
+
+    (start..count).map(|step| TRACE[step % TRACE_LENGTH].load(Ordering::Relaxed))
+}
@@ -0,0 +1,44 @@
+//! Saving and restoring interrupt controller state, for suspend-style flows and handing the controllers over to
+//! another kernel
+
+use kernel_shared::x86::{
+    hardware::{io_apic::IoApicState, local_apic::LocalApicState},
+    without_interrupts,
+};
+
+use crate::interrupts::{ioapic::IO_APIC, lapic::LAPIC};
+
+/// State of the IOAPIC and this processor's local APIC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterruptControllerState {
+    /// IOAPIC redirection entries, if an IOAPIC is in use
+    io_apic: Option<IoApicState>,
+    /// Local APIC configuration, if the local APIC is in use
+    local_apic: Option<LocalApicState>,
+}
+
+/// Saves the state of the interrupt controllers
+pub fn save() -> InterruptControllerState {
+    without_interrupts(|| InterruptControllerState {
+        io_apic: IO_APIC.lock().get_mut().map(|io_apic| io_apic.save()),
+        local_apic: LAPIC.lock().get().map(|lapic| lapic.save()),
+    })
+}
+
+/// Restores the state of the interrupt controllers saved with [`save`]
+///
+/// ## Safety
+/// The state must have been saved on this processor, and every vector it refers to must still have a handler
+/// installed.
+pub unsafe fn restore(state: &InterruptControllerState) {
+    without_interrupts(|| {
+        // restore the local APIC first, so it is ready to accept whatever the IOAPIC starts delivering
+        if let (Some(lapic), Some(saved)) = (LAPIC.lock().get(), &state.local_apic) {
+            unsafe { lapic.restore(saved) };
+        }
+
+        if let (Some(io_apic), Some(saved)) = (IO_APIC.lock().get_mut(), &state.io_apic) {
+            io_apic.restore(saved);
+        }
+    });
+}
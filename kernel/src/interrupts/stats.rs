@@ -0,0 +1,96 @@
+//! Counting of CPU exceptions, and rate limiting of their logs so a fault storm can't flood the console
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use kernel_shared::config;
+
+/// Name of each exception, indexed by vector
+const EXCEPTION_NAMES: [&str; 32] = [
+    "divide error",
+    "debug",
+    "non-maskable interrupt",
+    "breakpoint",
+    "overflow",
+    "bound range exceeded",
+    "invalid opcode",
+    "device not available",
+    "double fault",
+    "coprocessor segment overrun",
+    "invalid TSS",
+    "segment not present",
+    "stack segment fault",
+    "general protection fault",
+    "page fault",
+    "reserved",
+    "x87 floating point",
+    "alignment check",
+    "machine check",
+    "SIMD floating point",
+    "virtualization",
+    "control protection",
+    "reserved",
+    "reserved",
+    "reserved",
+    "reserved",
+    "reserved",
+    "reserved",
+    "hypervisor injection",
+    "VMM communication",
+    "security",
+    "reserved",
+];
+
+/// Number of times each exception has been raised
+static EXCEPTION_COUNTS: [AtomicUsize; 32] = [const { AtomicUsize::new(0) }; 32];
+
+/// Records that the given exception was raised, returning whether it should be logged in full.
+///
+/// Only the first `exception_log_limit` occurrences of each exception are logged in full, after which a summary is
+/// logged each time the count doubles.
+pub fn record(vector: u8) -> bool {
+    let count = record_quietly(vector);
+    let limit = config::EXCEPTION_LOG_LIMIT.get();
+
+    if count <= limit {
+        return true;
+    }
+
+    if count == limit + 1 || count.is_power_of_two() {
+        log::warn!(
+            "EXCEPTION: {} raised {count} times, suppressing further logs",
+            EXCEPTION_NAMES[vector as usize]
+        );
+    }
+
+    false
+}
+
+/// Records that the given exception was raised without logging anything, returning the new count.
+///
+/// For handlers like NMI which can interrupt code holding the log locks.
+pub fn record_quietly(vector: u8) -> usize {
+    EXCEPTION_COUNTS[vector as usize].fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Returns the number of times the given exception has been raised
+pub fn count(vector: u8) -> usize {
+    EXCEPTION_COUNTS[vector as usize].load(Ordering::Relaxed)
+}
+
+/// Returns the name and count of every exception which has been raised at least once
+pub fn raised() -> impl Iterator<Item = (&'static str, usize)> {
+    EXCEPTION_NAMES
+        .iter()
+        .zip(EXCEPTION_COUNTS.iter())
+        .map(|(&name, count)| (name, count.load(Ordering::Relaxed)))
+        .filter(|&(_, count)| count > 0)
+}
+
+/// Logs the count of every exception which has been raised
+pub fn dump() {
+    log::info!("exception counts:");
+
+    for (name, count) in raised() {
+        log::info!("\t{name}: {count}");
+    }
+}
@@ -2,27 +2,73 @@ use std::duration::Duration;
 
 use acpi::tables::fixed::hpet::Hpet as HpetTable;
 use kernel_shared::{
-    mem::PHYS_MEM_OFFSET,
-    x86::hardware::{hpet::Hpet, pit::ProgrammableIntervalTimer},
+    config,
+    mem::addr::PhysAddr,
+    x86::hardware::{
+        hpet::Hpet,
+        msi::MsiMessage,
+        pit::{PIT_FREQUENCY_HZ, ProgrammableIntervalTimer},
+    },
 };
 
-const DESIRED_TIME: Duration = Duration::from_milliseconds(500);
+use crate::{
+    error::{InterruptError, KernelError},
+    interrupts::{affinity, lapic},
+};
+
+/// Vector the timer interrupt is raised on
+const TIMER_VECTOR: u8 = 0x20;
 
-pub fn init(hpet_table: &HpetTable) {
+/// Programs the HPET as the periodic timer if available, falling back to the PIT otherwise
+pub fn init(hpet_table: Option<&HpetTable>) {
     log::trace!("\t* programming timers");
 
+    let desired_time = Duration::from_milliseconds(config::TIMER_INTERVAL_MS.get());
     let mut pit = ProgrammableIntervalTimer::default();
-    pit.disable_irq();
-    log::trace!("\t\t* PIT disabled");
 
-    let hpet = unsafe { Hpet::new(hpet_table.address.address as usize | PHYS_MEM_OFFSET) };
-    let mut timer = hpet.timer(0).unwrap();
+    if let Some(hpet_table) = hpet_table {
+        match init_hpet(hpet_table, &desired_time) {
+            Ok(()) => {
+                pit.disable_irq();
+                log::trace!("\t\t* PIT disabled");
+
+                return;
+            }
+            Err(err) => log::warn!("{}", KernelError::from(err)),
+        }
+    }
+
+    log::warn!("\t\t* no usable HPET, falling back to PIT");
+    init_pit(&mut pit, &desired_time);
+}
+
+fn init_hpet(hpet_table: &HpetTable, desired_time: &Duration) -> Result<(), InterruptError> {
+    let hpet = unsafe {
+        Hpet::new(
+            PhysAddr::new(hpet_table.address.address as usize)
+                .to_virt()
+                .as_usize(),
+        )
+    };
+    let mut timer = hpet.timer(0).ok_or(InterruptError::MissingHpetTimer(0))?;
 
     let clock_period_fs = hpet.capabilities().clock_period() as u64;
-    let ticks_required = DESIRED_TIME.as_femtoseconds() as u64 / clock_period_fs;
+    let ticks_required = desired_time.as_femtoseconds() as u64 / clock_period_fs;
+
+    // deliver straight to the local APIC where possible, as the IO APIC doesn't need to be involved at all
+    if timer.supports_fsb_delivery()
+        && let Some(lapic) = lapic::local()
+    {
+        let mut message = MsiMessage::new(TIMER_VECTOR, lapic.id());
+        affinity::set_msi_affinity(&mut message, affinity::next_cpu());
+
+        timer.set_fsb_message(&message).set_fsb_enabled(true);
+        log::trace!("\t\t* HPET timer 0 delivering over FSB");
+    } else {
+        timer.set_fsb_enabled(false).set_interrupt_routing(2);
+    }
 
     timer
-        .set_interrupt_routing(2)
         .allow_accumulator_write()
         .set_timer_periodic(true)
         .set_interrupt_enabled(true);
@@ -32,9 +78,30 @@ pub fn init(hpet_table: &HpetTable) {
     timer.set_comparator_value(ticks_required);
     log::trace!(
         "\t\t* HPET timer 0 programmed with interval of {}μs",
-        DESIRED_TIME.as_microseconds()
+        desired_time.as_microseconds()
     );
 
     hpet.configuration().set_enabled(true);
     log::trace!("\t\t* HPET enabled");
+
+    Ok(())
+}
+
+fn init_pit(pit: &mut ProgrammableIntervalTimer, desired_time: &Duration) {
+    // the PIT counter is only 16 bits, so can't wait more than ~55ms between interrupts
+    let ticks_required = desired_time.as_microseconds() * PIT_FREQUENCY_HZ / 1_000_000;
+    let reload = ticks_required.clamp(1, u16::MAX as usize) as u16;
+
+    if reload as usize != ticks_required {
+        log::warn!(
+            "\t\t* PIT cannot wait {}μs, clamping",
+            desired_time.as_microseconds()
+        );
+    }
+
+    pit.set_periodic(reload);
+    log::trace!(
+        "\t\t* PIT programmed with interval of {}μs",
+        reload as usize * 1_000_000 / PIT_FREQUENCY_HZ
+    );
 }
@@ -0,0 +1,7 @@
+//! Interrupt vectors with fixed meanings
+
+/// First vector which legacy IRQs are remapped to
+pub const IRQ_BASE: u8 = 32;
+
+/// Vector used for spurious LAPIC interrupts
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
@@ -1,81 +1,428 @@
 #![feature(abi_x86_interrupt)]
 #![no_std]
 
+extern crate alloc;
+
+mod boot;
+mod crash;
+mod error;
 mod gdt;
+mod init;
 mod interrupts;
 mod mem;
+mod power;
+mod pstore;
 
-use core::{
-    panic::PanicInfo,
-    sync::atomic::{AtomicBool, Ordering},
-};
+use core::{cell::OnceCell, panic::PanicInfo};
+use std::{duration::Duration, init_once::InitOnce, mutex::Mutex};
 
-use acpi::tables::fixed::{hpet::Hpet as HpetTable, madt::Madt, rsdt::Rsdt};
+use acpi::tables::{
+    fixed::{fadt::Fadt, hpet::Hpet as HpetTable, madt::Madt, rsdt::Rsdt},
+    registry::AcpiTables,
+};
 use kernel_shared::{
+    boot::{AcpiRoot, BootProtocol, handoff::BootHandoff},
+    config, fault,
+    io::{
+        console::Console,
+        ega::EgaBuffer,
+        serial,
+        sinks::{CONSOLES, SERIAL_SINK, TextConsoleSink},
+    },
     logger::Logger,
     mem::{
-        PHYS_MEM_OFFSET, frame_alloc::bitmap::BitmapFrameAlloc,
-        paging::active_table::ActivePageTable,
+        addr::PhysAddr, paging::active_table::ActivePageTable, phys_mem_offset, set_phys_mem_offset,
+    },
+    nvram::{self, BootStatus},
+    x86::{
+        hardware::{
+            ps2::PS2_CONTROLLER,
+            speaker::{PcSpeaker, SPEAKER},
+        },
+        without_interrupts,
     },
 };
 use multiboot::prelude::BootInfo;
 
-static LOGGER: Logger = Logger::new(log::LevelFilter::Trace);
+use crate::{
+    error::{AcpiError, KernelError},
+    init::Stage,
+};
+
+/// Frequency of the beep played on panic, in Hz
+const PANIC_BEEP_FREQUENCY: usize = 880;
+
+/// Every ACPI table listed in the RSDT, unset if ACPI is unavailable
+pub static ACPI_TABLES: Mutex<OnceCell<AcpiTables>> = Mutex::new(OnceCell::new());
+
+static LOGGER: Logger = Logger::new(config::DEFAULT_LOG_LEVEL);
+
+/// Log output drawn to the EGA text console, alongside serial
+static EGA_SINK: TextConsoleSink<EgaBuffer, { config::CONSOLE_SCROLLBACK_LINES }> =
+    TextConsoleSink::new("ega", Console::new(EgaBuffer::new()));
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    // stop any single step trace before the panic handler pushes the steps leading up to the panic out of it
+    interrupts::single_step::stop();
+    // dump first, so the crash record is complete even if something below faults again
+    crash::dump(info);
     log::error!("{info}");
+    // a fault storm leading up to the panic only has its first few faults logged, so give the totals
+    interrupts::stats::dump();
+    interrupts::latency::dump();
+    LOGGER.flush_persistent();
+    nvram::update(|settings| settings.boot_status = BootStatus::Panicked);
+
+    // let anyone without a serial cable know something went wrong.
+    // whatever panicked may hold the speaker, but it has no state worth waiting for so a fresh handle works as well
+    let beep_duration = Duration::from_milliseconds(500);
+    match SPEAKER.try_lock() {
+        Some(mut speaker) => speaker.beep_busy(PANIC_BEEP_FREQUENCY, beep_duration),
+        None => PcSpeaker::new().beep_busy(PANIC_BEEP_FREQUENCY, beep_duration),
+    }
+
+    let result = match config::PANIC_ACTION.choice() {
+        Some("reboot") => power::reboot(),
+        Some("poweroff") => power::shutdown(),
+        _ => kernel_shared::x86::halt(),
+    };
+    let Err(err) = result;
+    log::error!("{err}, halting instead");
+
     kernel_shared::x86::halt()
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn kernel_main(bootinfo_addr: usize, loader_start: usize, loader_end: usize) {
-    // bootinfo is only valid for this scope
-    let (_frame_alloc, _active_page_table) = {
-        // it is not mapped at lower address anymore, so must mask to access from physical memory mapping
-        let bootinfo_addr = bootinfo_addr | PHYS_MEM_OFFSET;
-        let bootinfo = unsafe { BootInfo::new(bootinfo_addr as *const u32) }.unwrap();
-
-        init(&bootinfo, loader_start, loader_end).unwrap()
+pub extern "C" fn kernel_main(handoff: &BootHandoff) {
+    // the handoff is in loader memory, which is reclaimed later, so copy it out first
+    let BootHandoff {
+        bootinfo_addr,
+        loader_start,
+        loader_end,
+        phys_mem_offset,
+    } = *handoff;
+
+    // installed first so nothing logged before the logger stage is lost, it is written once serial is attached
+    LOGGER.init().expect("failed to init logger");
+
+    // the loader maps all physical memory at this offset before jumping here, and nothing has used the old one
+    unsafe { set_phys_mem_offset(phys_mem_offset) };
+
+    // bootinfo is only valid for this scope, as its memory is reclaimed afterwards
+    let (mut frame_alloc, mut active_table, bootinfo_end) = {
+        // it is not mapped at lower address anymore, so must be accessed through the physical memory mapping
+        let bootinfo = unsafe { BootInfo::new(PhysAddr::new(bootinfo_addr).as_hhdm_ptr()) };
+
+        match bootinfo
+            .map_err(KernelError::BadBootInfo)
+            .and_then(|bootinfo| {
+                let memory = init(&bootinfo)?;
+                // the heap is up now, so keep a copy of anything that might be wanted later
+                boot::save(&bootinfo);
+
+                Ok((memory, bootinfo_addr + bootinfo.size))
+            }) {
+            Ok(((frame_alloc, active_table), bootinfo_end)) => {
+                (frame_alloc, active_table, bootinfo_end)
+            }
+            Err(err) => panic!("failed to initialise kernel: {err}"),
+        }
     };
 
+    // bootinfo was copied onto the heap above, so nothing refers to its memory or the loader's any more
+    mem::reclaim_boot_memory(
+        &mut active_table,
+        &mut frame_alloc,
+        &[
+            (
+                "bootinfo",
+                PhysAddr::new(bootinfo_addr),
+                PhysAddr::new(bootinfo_end),
+            ),
+            (
+                "loader",
+                PhysAddr::new(loader_start),
+                PhysAddr::new(loader_end),
+            ),
+        ],
+    );
+
+    let dropped = without_interrupts(|| serial::COM1.lock().dropped());
+    if dropped > 0 {
+        log::warn!("dropped {dropped} bytes of serial output");
+    }
+
+    nvram::update(|settings| settings.boot_status = BootStatus::Running);
+
     kernel_shared::x86::halt()
 }
 
-fn init(
-    bootinfo: &BootInfo,
-    loader_start: usize,
-    loader_end: usize,
-) -> Option<(&'static mut BitmapFrameAlloc, ActivePageTable)> {
+/// State shared between init stages
+struct InitContext<'a, B: BootProtocol> {
+    /// Boot information passed by the loader
+    bootinfo: &'a B,
+    /// Frame allocator and page table, set by the `memory` stage
+    memory: Option<(mem::KernelFrameAlloc, ActivePageTable)>,
+    /// MADT, set by the `acpi` stage if present
+    madt: Option<Madt>,
+    /// HPET table, set by the `acpi` stage if present
+    hpet: Option<HpetTable>,
+}
+
+fn init<B: BootProtocol>(
+    bootinfo: &B,
+) -> Result<(mem::KernelFrameAlloc, ActivePageTable), KernelError> {
     // prevents being called twice
-    static INIT_CALLED: AtomicBool = AtomicBool::new(false);
+    static INIT: InitOnce = InitOnce::new();
 
-    if INIT_CALLED.swap(true, Ordering::Relaxed) {
-        panic!("init must only be called once")
-    }
+    let (result, _) = INIT
+        .call_once(|| run_stages(bootinfo))
+        .ok_or(KernelError::AlreadyInitialised)?;
 
-    LOGGER.init().expect("failed to init logger");
+    result
+}
+
+/// Runs every init stage, returning the frame allocator and page table set up by the `memory` stage
+fn run_stages<B: BootProtocol>(
+    bootinfo: &B,
+) -> Result<(mem::KernelFrameAlloc, ActivePageTable), KernelError> {
+    let stages: &[Stage<InitContext<B>>] = &[
+        Stage {
+            name: "logger",
+            dependencies: &[],
+            run: init_logger,
+        },
+        Stage {
+            name: "memory",
+            dependencies: &["logger"],
+            run: |ctx| {
+                ctx.memory = Some(mem::init());
+                Ok(())
+            },
+        },
+        Stage {
+            name: "heap",
+            dependencies: &["memory"],
+            run: |_| Ok(mem::init_heap()?),
+        },
+        Stage {
+            name: "pstore",
+            dependencies: &["memory"],
+            run: |ctx| {
+                let (frame_alloc, _) = ctx.memory.as_mut().unwrap();
+                pstore::init(ctx.bootinfo, frame_alloc);
+                Ok(())
+            },
+        },
+        Stage {
+            name: "acpi",
+            dependencies: &["memory"],
+            run: init_acpi,
+        },
+        Stage {
+            name: "gdt",
+            dependencies: &["logger"],
+            run: |_| {
+                gdt::init();
+                Ok(())
+            },
+        },
+        Stage {
+            name: "ps2",
+            dependencies: &["logger"],
+            run: |_| {
+                // failing to find PS/2 devices isn't fatal
+                if let Err(err) = PS2_CONTROLLER.lock().init(true) {
+                    log::warn!("{err}, PS/2 devices will be unavailable");
+                }
+                Ok(())
+            },
+        },
+        Stage {
+            name: "interrupts",
+            dependencies: &["gdt", "acpi"],
+            run: |ctx| {
+                interrupts::init(ctx.madt.as_ref(), ctx.hpet.as_ref())?;
+                Ok(())
+            },
+        },
+        Stage {
+            name: "random",
+            dependencies: &["logger"],
+            run: |_| {
+                kernel_shared::random::init();
+                Ok(())
+            },
+        },
+        Stage {
+            name: "clock",
+            dependencies: &["logger"],
+            run: |_| {
+                kernel_shared::time::init();
+                Ok(())
+            },
+        },
+        Stage {
+            name: "selftest",
+            dependencies: &["interrupts"],
+            run: |_| {
+                if config::EXCEPTION_SELFTEST {
+                    interrupts::selftest::run();
+                }
+                Ok(())
+            },
+        },
+    ];
+
+    let mut ctx = InitContext {
+        bootinfo,
+        memory: None,
+        madt: None,
+        hpet: None,
+    };
+    log::debug!("booted via {}", bootinfo.name());
+    init::run(stages, &mut ctx)?;
+
+    Ok(ctx.memory.expect("memory stage did not run"))
+}
+
+fn init_logger<B: BootProtocol>(ctx: &mut InitContext<B>) -> Result<(), KernelError> {
+    CONSOLES.attach(&SERIAL_SINK, &config::CONSOLE_SERIAL_LEVEL);
+    CONSOLES.attach(&EGA_SINK, &config::CONSOLE_EGA_LEVEL);
     log::info!("entered kernel_main");
 
-    // initialise memory
-    let (frame_alloc, page_table) = mem::init(loader_start, loader_end);
+    // saved settings are applied first, so the command line can override them
+    let settings = nvram::load().unwrap_or_else(|err| {
+        log::debug!("{err}, using defaults");
+        Default::default()
+    });
+    if let Some(level) = settings.log_level {
+        let _ = config::LOG_LEVEL.set(level.as_str());
+    }
+    match settings.boot_status {
+        BootStatus::Booting => log::warn!("previous boot did not finish initialising"),
+        BootStatus::Panicked => log::warn!("previous boot panicked"),
+        BootStatus::Unknown | BootStatus::Running => {}
+    }
+    nvram::store(&nvram::Settings {
+        boot_status: BootStatus::Booting,
+        ..settings
+    });
+
+    if let Some(command) = ctx.bootinfo.command_line() {
+        config::parse_command_line(command);
+    }
+    config::dump();
+
+    Ok(())
+}
+
+fn init_acpi<B: BootProtocol>(ctx: &mut InitContext<B>) -> Result<(), KernelError> {
+    // missing acpi tables aren't fatal, we just fall back to legacy hardware
+    match find_acpi_tables(ctx.bootinfo.acpi_roots()) {
+        Ok(tables) => {
+            ctx.madt = find_madt(&tables)
+                .inspect_err(|&err| log::warn!("{}", KernelError::from(err)))
+                .ok();
+            ctx.hpet = find_hpet(&tables)
+                .inspect_err(|&err| log::warn!("{}", KernelError::from(err)))
+                .ok();
+            match find_fadt(&tables) {
+                Ok(fadt) => power::init(fadt),
+                Err(err) => log::warn!(
+                    "{}, ACPI reboot and power off unavailable",
+                    KernelError::from(err)
+                ),
+            }
+
+            ACPI_TABLES.lock().set(tables).unwrap();
+        }
+        Err(err) => log::warn!("{}", KernelError::from(err)),
+    }
+
+    Ok(())
+}
+
+fn find_acpi_tables(
+    mut acpi_roots: impl Iterator<Item = AcpiRoot>,
+) -> Result<AcpiTables, AcpiError> {
+    if fault::ACPI.should_fail() {
+        return Err(AcpiError::MissingRsdp);
+    }
+
+    // fall back to the next root if one is corrupt, reporting the last error if none work
+    let mut result = Err(AcpiError::MissingRsdp);
+    let tables = loop {
+        let Some(root) = acpi_roots.next() else {
+            return result;
+        };
+
+        result = read_root_table(root);
+        match result {
+            Ok(tables) => break tables,
+            Err(err) => log::warn!("{}", KernelError::from(err)),
+        }
+    };
+
+    for (signature, addr) in tables.iter() {
+        log::trace!(
+            "\t* {} table at {addr:#X}",
+            core::str::from_utf8(&signature).unwrap_or("????")
+        );
+    }
+
+    Ok(tables)
+}
+
+/// Reads every table listed in an RSDT or XSDT
+fn read_root_table(root: AcpiRoot) -> Result<AcpiTables, AcpiError> {
+    match root {
+        AcpiRoot::Xsdt(addr) => read_description_table::<u64>("XSDT", addr),
+        AcpiRoot::Rsdt(addr) => read_description_table::<u32>("RSDT", addr),
+    }
+}
+
+/// Reads every table listed in a system description table with pointers of type `PTR`, checking it has the given
+/// signature and a valid checksum
+fn read_description_table<PTR>(
+    signature: &'static str,
+    addr: PhysAddr,
+) -> Result<AcpiTables, AcpiError>
+where
+    PTR: TryInto<usize> + Copy,
+{
+    log::trace!("ACPI {signature} table at {addr}");
+
+    let table = unsafe { Rsdt::<PTR>::from_addr(addr.to_virt().as_usize()) }
+        .filter(|table| {
+            table.header.signature() == signature && unsafe { table.header.checksum_valid() }
+        })
+        .ok_or(AcpiError::BadTable(signature))?;
 
-    // now find acpi root table
-    let rsdt_addr = bootinfo.rsdpv1.as_ref()?.rsdt_addr as usize | PHYS_MEM_OFFSET;
-    log::trace!("ACPI RSDT table at {rsdt_addr:#X}");
+    Ok(unsafe { AcpiTables::new(&table, phys_mem_offset()) })
+}
+
+fn find_madt(tables: &AcpiTables) -> Result<Madt, AcpiError> {
+    tables
+        .find(&Madt::SIGNATURE)
+        .ok_or(AcpiError::MissingTable("MADT"))?;
 
-    let rsdt_table = unsafe { Rsdt::<u32>::from_addr(rsdt_addr) }?;
+    tables.madt().ok_or(AcpiError::BadTable("MADT"))
+}
 
-    let madt_table = rsdt_table.find_table(&Madt::SIGNATURE, PHYS_MEM_OFFSET)?;
-    log::trace!("ACPI MADT table at {madt_table:#X}");
-    let madt = unsafe { Madt::from_addr(madt_table)? };
+fn find_hpet(tables: &AcpiTables) -> Result<HpetTable, AcpiError> {
+    tables
+        .find(&HpetTable::SIGNATURE)
+        .ok_or(AcpiError::MissingTable("HPET"))?;
 
-    let hpet_table = rsdt_table.find_table(&HpetTable::SIGNATURE, PHYS_MEM_OFFSET)?;
-    log::trace!("ACPI HPET table at {hpet_table:#X}");
-    let hpet = unsafe { HpetTable::from_addr(hpet_table)? };
+    tables.hpet().ok_or(AcpiError::BadTable("HPET"))
+}
 
-    gdt::init();
-    interrupts::init(&madt, &hpet);
+fn find_fadt(tables: &AcpiTables) -> Result<Fadt, AcpiError> {
+    tables
+        .find(&Fadt::SIGNATURE)
+        .ok_or(AcpiError::MissingTable("FADT"))?;
 
-    Some((frame_alloc, page_table))
+    tables.fadt().ok_or(AcpiError::BadTable("FADT"))
 }
@@ -1,37 +1,209 @@
-use kernel_shared::mem::{
-    frame_alloc::bitmap::BitmapFrameAlloc, page::Page, paging::active_table::ActivePageTable,
+mod protect;
+
+use core::{
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+use kernel_shared::{
+    config,
+    mem::{
+        addr::{PhysAddr, VirtAddr},
+        frame::Frame,
+        frame_alloc::{FrameAllocator, bitmap::BitmapFrameAlloc, buddy::BuddyFrameAlloc},
+        heap::{HEAP_START, HeapError, KernelHeap},
+        page::Page,
+        paging::active_table::ActivePageTable,
+    },
 };
 
+/// Address the loader constructs the frame allocator at
+const FRAME_ALLOC_ADDR: usize = 0xFFFFFFFF00000000;
+
+/// Set once the frame allocator is known to be valid
+static FRAME_ALLOC_READY: AtomicBool = AtomicBool::new(false);
+
+/// Buddy allocator which free frames were moved into, or null if the bitmap allocator is used
+static BUDDY_FRAME_ALLOC: AtomicPtr<BuddyFrameAlloc> = AtomicPtr::new(ptr::null_mut());
+
+/// Frame allocator used by the kernel. This is the bitmap allocator set up by the loader, unless
+/// [`config::BUDDY_FRAME_ALLOC`] is set and free frames were moved into a buddy allocator.
+pub struct KernelFrameAlloc {
+    /// Allocator set up by the loader, which has nothing free if `buddy` is set
+    bitmap: &'static mut BitmapFrameAlloc,
+    /// Allocator holding every free frame, if used
+    buddy: Option<&'static mut BuddyFrameAlloc>,
+}
+
+impl KernelFrameAlloc {
+    /// Returns the number of frames in use and the total number of usable frames
+    pub fn frame_counts(&self) -> (usize, usize) {
+        match &self.buddy {
+            Some(buddy) => buddy.frame_counts(),
+            None => self.bitmap.frame_counts(),
+        }
+    }
+
+    /// Returns if the frame is tracked by the allocator and marked as in use
+    pub fn is_frame_used(&mut self, frame: Frame) -> bool {
+        match &self.buddy {
+            Some(buddy) => self.bitmap.is_frame_tracked(frame) && buddy.is_frame_used(frame),
+            None => self.bitmap.is_frame_used(frame),
+        }
+    }
+}
+
+impl FrameAllocator for KernelFrameAlloc {
+    fn allocate_frame(&mut self) -> Option<Frame> {
+        match &mut self.buddy {
+            Some(buddy) => buddy.allocate_frame(),
+            None => self.bitmap.allocate_frame(),
+        }
+    }
+
+    fn deallocate_frame(&mut self, frame: Frame) {
+        match &mut self.buddy {
+            Some(buddy) => buddy.deallocate_frame(frame),
+            None => self.bitmap.deallocate_frame(frame),
+        }
+    }
+
+    fn allocate_frames(&mut self, count: usize) -> Option<Frame> {
+        match &mut self.buddy {
+            Some(buddy) => buddy.allocate_frames(count),
+            None => self.bitmap.allocate_frames(count),
+        }
+    }
+
+    fn deallocate_frames(&mut self, frame: Frame, count: usize) {
+        match &mut self.buddy {
+            Some(buddy) => buddy.deallocate_frames(frame, count),
+            None => self.bitmap.deallocate_frames(frame, count),
+        }
+    }
+}
+
+/// Kernel heap, backing `alloc` collections
+#[global_allocator]
+static HEAP: KernelHeap = KernelHeap::new();
+
 /// Initialises memory for kernel
-pub fn init(
-    loader_start: usize,
-    loader_end: usize,
-) -> (&'static mut BitmapFrameAlloc, ActivePageTable) {
+pub fn init() -> (KernelFrameAlloc, ActivePageTable) {
     log::info!("initialising memory");
 
-    let frame_alloc = unsafe { BitmapFrameAlloc::from_address(0xFFFFFFFF00000000) };
-    let mut active_table = unsafe { ActivePageTable::new() };
+    let bitmap = unsafe { BitmapFrameAlloc::from_address(FRAME_ALLOC_ADDR) };
+    let mut buddy = config::BUDDY_FRAME_ALLOC
+        .then(|| {
+            // safety: the loader maps all physical memory, and the bitmap allocator isn't used directly again
+            let buddy = unsafe { BuddyFrameAlloc::from_bitmap(bitmap) };
+            match &buddy {
+                Some(buddy) => {
+                    let (used, total) = buddy.frame_counts();
+                    log::trace!(
+                        "\t* moved {} free frames into buddy allocator",
+                        total - used
+                    );
+                }
+                None => log::warn!("no room for buddy allocator, using bitmap allocator"),
+            }
+
+            buddy
+        })
+        .flatten();
 
-    unsafe {
-        free_region(&mut active_table, frame_alloc, loader_start, loader_end);
+    if let Some(buddy) = buddy.as_deref_mut() {
+        BUDDY_FRAME_ALLOC.store(buddy, Ordering::Release);
     }
+    FRAME_ALLOC_READY.store(true, Ordering::Release);
+    let frame_alloc = KernelFrameAlloc { bitmap, buddy };
+    let mut active_table = unsafe { ActivePageTable::new() };
 
-    log::trace!("\t* loader memory freed");
+    protect::protect_kernel(&mut active_table);
+    log::trace!("\t* kernel sections protected");
     log::info!("memory initialised");
 
     (frame_alloc, active_table)
 }
 
+/// Initialises the heap over the region mapped by the loader
+pub fn init_heap() -> Result<(), HeapError> {
+    unsafe { HEAP.init(VirtAddr::new(HEAP_START), config::HEAP_SIZE)? };
+    log::info!("heap initialised with {} KiB", config::HEAP_SIZE / 1024);
+
+    Ok(())
+}
+
+/// Hands back memory the loader left behind: each region is identity mapped by the loader, so its pages are
+/// unmapped and their frames freed, along with any page tables which become empty.
+///
+/// Must only be called once nothing refers to the regions any more - in particular, bootinfo must not be read
+/// afterwards.
+pub fn reclaim_boot_memory(
+    active_table: &mut ActivePageTable,
+    frame_alloc: &mut KernelFrameAlloc,
+    regions: &[(&str, PhysAddr, PhysAddr)],
+) {
+    log::trace!("reclaiming boot memory");
+    let (used_before, _) = frame_alloc.frame_counts();
+
+    for &(name, start, end) in regions {
+        unsafe {
+            free_region(
+                active_table,
+                frame_alloc,
+                VirtAddr::new(start.as_usize()),
+                VirtAddr::new(end.as_usize()),
+            );
+        }
+
+        log::trace!("\t* {name} memory at {start}-{end} freed");
+    }
+
+    let (used_after, _) = frame_alloc.frame_counts();
+    log::info!(
+        "reclaimed {} frames of boot memory",
+        used_before.saturating_sub(used_after)
+    );
+}
+
+/// Returns the number of frames in use and the total number of usable frames, if memory has been initialised.
+///
+/// This only reads the frame allocator, so is safe to call while panicking even if the allocator is borrowed.
+pub fn frame_counts() -> Option<(usize, usize)> {
+    if !FRAME_ALLOC_READY.load(Ordering::Acquire) {
+        return None;
+    }
+
+    let buddy = BUDDY_FRAME_ALLOC.load(Ordering::Acquire);
+    Some(if buddy.is_null() {
+        unsafe { &*(FRAME_ALLOC_ADDR as *const BitmapFrameAlloc) }.frame_counts()
+    } else {
+        unsafe { &*buddy }.frame_counts()
+    })
+}
+
+/// Unmaps every page overlapping `addr_start..addr_end`, freeing their frames. The end is exclusive, so a region
+/// ending on a page boundary leaves the following page alone.
+///
+/// ## Safety
+/// Nothing may refer to memory in the region afterwards.
 pub unsafe fn free_region(
     active_table: &mut ActivePageTable,
-    frame_alloc: &mut BitmapFrameAlloc,
-    addr_start: usize,
-    addr_end: usize,
+    frame_alloc: &mut KernelFrameAlloc,
+    addr_start: VirtAddr,
+    addr_end: VirtAddr,
 ) {
+    if addr_start >= addr_end {
+        return;
+    }
+
     let start_page = Page::containing_address(addr_start);
-    let end_page = Page::containing_address(addr_end);
+    let end_page = Page::containing_address(addr_end - 1);
 
     for page in start_page..=end_page {
-        active_table.unmap(page, frame_alloc, true);
+        // regions may share a page at their boundary, which is only mapped and freed once
+        if active_table.translate_page(page).is_some() {
+            active_table.unmap(page, frame_alloc, true);
+        }
     }
 }
@@ -0,0 +1,79 @@
+//! Re-applying strict permissions to the kernel image once it is running
+
+use kernel_shared::{
+    mem::{
+        addr::VirtAddr,
+        page::Page,
+        paging::{active_table::ActivePageTable, entry::EntryFlags, mapper},
+    },
+    x86::registers::{CR0, Cr0Flags},
+};
+
+unsafe extern "C" {
+    static __text_start: u8;
+    static __text_end: u8;
+    static __rodata_start: u8;
+    static __rodata_end: u8;
+    static __data_start: u8;
+    static __data_end: u8;
+}
+
+/// Remaps kernel sections with the strictest flags they can have, and then locks text and rodata so they can't
+/// be remapped
+pub fn protect_kernel(active_table: &mut ActivePageTable) {
+    // without write protect, ring 0 ignores read only pages entirely
+    let cr0 = CR0::read();
+    if !cr0.contains(Cr0Flags::WRITE_PROTECT) {
+        log::warn!("\t* CR0.WP was not set, enabling");
+        unsafe { CR0::write(cr0 | Cr0Flags::WRITE_PROTECT) };
+    }
+
+    let text = (
+        VirtAddr::from_ptr(&raw const __text_start),
+        VirtAddr::from_ptr(&raw const __text_end),
+    );
+    let rodata = (
+        VirtAddr::from_ptr(&raw const __rodata_start),
+        VirtAddr::from_ptr(&raw const __rodata_end),
+    );
+    let data = (
+        VirtAddr::from_ptr(&raw const __data_start),
+        VirtAddr::from_ptr(&raw const __data_end),
+    );
+
+    protect_section(active_table, ".text", text, EntryFlags::empty());
+    protect_section(active_table, ".rodata", rodata, EntryFlags::NO_EXECUTE);
+    protect_section(
+        active_table,
+        ".data",
+        data,
+        EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE,
+    );
+
+    mapper::lock_range(text.0, rodata.1);
+    log::trace!("\t* locked kernel text and rodata");
+}
+
+/// Sets the flags of every page within a section
+fn protect_section(
+    active_table: &mut ActivePageTable,
+    name: &str,
+    (start, end): (VirtAddr, VirtAddr),
+    flags: EntryFlags,
+) {
+    if start >= end {
+        return;
+    }
+
+    // text and rodata are locked afterwards, so a page left with the loader's flags would keep them for good
+    for page in Page::containing_address(start)..=Page::containing_address(end - 1) {
+        if active_table.update_flags(page, flags).is_none() {
+            panic!(
+                "kernel {name} page at {} is unmapped or huge, so can't be protected",
+                page.start_address()
+            );
+        }
+    }
+
+    log::trace!("\t* kernel {name} at {start}-{end} set to `{flags}`");
+}
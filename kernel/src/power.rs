@@ -0,0 +1,238 @@
+//! Rebooting and powering off through the ACPI fixed hardware described by the FADT
+
+use core::{cell::OnceCell, convert::Infallible};
+use std::mutex::Mutex;
+
+use acpi::tables::{
+    AcpiAddress,
+    dsdt::{Dsdt, SleepType},
+    fixed::fadt::Fadt,
+};
+use kernel_shared::{
+    io::port::Port,
+    mem::{
+        addr::PhysAddr,
+        phys::{phys_read_volatile, phys_write_volatile},
+    },
+    x86::{disable_interrupts, without_interrupts},
+};
+
+use crate::error::PowerError;
+
+/// Number of times to spin waiting for the hardware to act before giving up
+const TIMEOUT_ITERATIONS: usize = 10_000_000;
+
+/// PM1 control bit set once the OS owns the fixed hardware, so sleep requests aren't handled by SMM
+const SCI_EN: u64 = 1 << 0;
+/// Position of the SLP_TYP field in the PM1 control registers
+const SLP_TYP_SHIFT: u64 = 10;
+/// SLP_TYP field in the PM1 control registers
+const SLP_TYP_MASK: u64 = 0b111 << SLP_TYP_SHIFT;
+/// PM1 control bit which enters the sleep state in SLP_TYP when written
+const SLP_EN: u64 = 1 << 13;
+
+/// Sleep state which powers the machine off
+const SOFT_OFF_STATE: u8 = 5;
+
+/// Power management state read from the FADT and DSDT, unset if there is no FADT
+static POWER: Mutex<OnceCell<PowerControl>> = Mutex::new(OnceCell::new());
+
+/// Everything needed to reboot or power off
+#[derive(Debug)]
+struct PowerControl {
+    /// The FADT
+    fadt: Fadt,
+    /// SLP_TYP values for S5, if the DSDT defines them
+    soft_off: Option<SleepType>,
+}
+
+/// Records the FADT, and finds the S5 sleep type in the DSDT it points to
+pub fn init(fadt: Fadt) {
+    let dsdt = PhysAddr::new(fadt.dsdt as usize);
+    let soft_off = unsafe { Dsdt::from_addr(dsdt.to_virt().as_usize()) }
+        .and_then(|dsdt| dsdt.sleep_type(SOFT_OFF_STATE));
+
+    match fadt.reset_register {
+        Some(register) => {
+            let (space, address) = (register.address_space_id, register.address);
+            log::trace!("\t* reset register {address:#X} in address space {space}");
+        }
+        None => log::trace!("\t* no reset register"),
+    }
+    match soft_off {
+        Some(soft_off) => log::trace!("\t* S5 sleep type {}/{}", soft_off.pm1a, soft_off.pm1b),
+        None => log::trace!("\t* no S5 package in DSDT"),
+    }
+
+    without_interrupts(|| {
+        let _ = POWER.lock().set(PowerControl { fadt, soft_off });
+    });
+}
+
+/// Resets the machine through the FADT reset register. Only returns if the reset couldn't be attempted, or didn't
+/// take effect.
+pub fn reboot() -> Result<Infallible, PowerError> {
+    let (register, value) = without_interrupts(|| {
+        let power = POWER.lock();
+        let fadt = &power.get().ok_or(PowerError::NoFadt)?.fadt;
+
+        fadt.reset_register
+            .map(|register| (register, fadt.reset_value))
+            .ok_or(PowerError::ResetUnsupported)
+    })?;
+
+    log::info!("rebooting");
+    disable_interrupts();
+    unsafe { write_register(&register, value as u64)? };
+
+    wait();
+    Err(PowerError::NoEffect)
+}
+
+/// Powers the machine off by entering S5. Only returns if powering off couldn't be attempted, or didn't take effect.
+pub fn shutdown() -> Result<Infallible, PowerError> {
+    let (pm1a, pm1b, soft_off, smi_command, acpi_enable) = without_interrupts(|| {
+        let power = POWER.lock();
+        let power = power.get().ok_or(PowerError::NoFadt)?;
+        let fadt = &power.fadt;
+
+        Ok((
+            fadt.pm1a_control_block.ok_or(PowerError::NoPm1Control)?,
+            fadt.pm1b_control_block,
+            power
+                .soft_off
+                .ok_or(PowerError::NoSleepState(SOFT_OFF_STATE))?,
+            fadt.smi_command,
+            fadt.acpi_enable,
+        ))
+    })?;
+
+    unsafe {
+        // firmware handles sleep requests itself until it is told the OS is taking over
+        if read_register(&pm1a)? & SCI_EN == 0 && smi_command != 0 {
+            log::trace!("enabling ACPI mode");
+            Port::<u8>::new(smi_command as u16).write(acpi_enable);
+
+            let enabled = (0..TIMEOUT_ITERATIONS).any(|_| {
+                core::hint::spin_loop();
+                read_register(&pm1a).is_ok_and(|value| value & SCI_EN != 0)
+            });
+            if !enabled {
+                return Err(PowerError::AcpiEnableTimeout);
+            }
+        }
+
+        log::info!("powering off");
+        disable_interrupts();
+
+        // PM1b has to be written first, as the machine may power off as soon as PM1a is
+        if let Some(pm1b) = pm1b {
+            enter_sleep_state(&pm1b, soft_off.pm1b)?;
+        }
+        enter_sleep_state(&pm1a, soft_off.pm1a)?;
+    }
+
+    wait();
+    Err(PowerError::NoEffect)
+}
+
+/// Writes SLP_TYP and SLP_EN to a PM1 control register, keeping its other bits
+///
+/// ## Safety
+/// This enters the sleep state, so everything must be ready for the machine to stop.
+unsafe fn enter_sleep_state(register: &AcpiAddress, sleep_type: u8) -> Result<(), PowerError> {
+    unsafe {
+        let value = read_register(register)? & !SLP_TYP_MASK;
+        write_register(
+            register,
+            value | ((sleep_type as u64) << SLP_TYP_SHIFT) | SLP_EN,
+        )
+    }
+}
+
+/// Spins for a while to give the hardware time to act
+fn wait() {
+    for _ in 0..TIMEOUT_ITERATIONS {
+        core::hint::spin_loop();
+    }
+}
+
+/// Reads a register in system memory or I/O space
+///
+/// ## Safety
+/// Reading the register must not have side effects which break anything.
+unsafe fn read_register(register: &AcpiAddress) -> Result<u64, PowerError> {
+    let AcpiAddress {
+        address_space_id,
+        register_bit_width,
+        address,
+        ..
+    } = *register;
+
+    unsafe {
+        Ok(match (address_space_id, register_bit_width) {
+            (AcpiAddress::SYSTEM_IO, 8) => Port::<u8>::new(address as u16).read() as u64,
+            (AcpiAddress::SYSTEM_IO, 16) => Port::<u16>::new(address as u16).read() as u64,
+            (AcpiAddress::SYSTEM_IO, 32) => Port::<u32>::new(address as u16).read() as u64,
+            (AcpiAddress::SYSTEM_MEMORY, 8) => {
+                phys_read_volatile::<u8>(PhysAddr::new(address as usize)) as u64
+            }
+            (AcpiAddress::SYSTEM_MEMORY, 16) => {
+                phys_read_volatile::<u16>(PhysAddr::new(address as usize)) as u64
+            }
+            (AcpiAddress::SYSTEM_MEMORY, 32) => {
+                phys_read_volatile::<u32>(PhysAddr::new(address as usize)) as u64
+            }
+            (AcpiAddress::SYSTEM_MEMORY, 64) => {
+                phys_read_volatile::<u64>(PhysAddr::new(address as usize))
+            }
+            _ => {
+                return Err(PowerError::UnsupportedRegister(
+                    address_space_id,
+                    register_bit_width,
+                ));
+            }
+        })
+    }
+}
+
+/// Writes a register in system memory or I/O space, truncating `value` to its width
+///
+/// ## Safety
+/// Writing the register must not break anything, other than the intended effect on the machine.
+unsafe fn write_register(register: &AcpiAddress, value: u64) -> Result<(), PowerError> {
+    let AcpiAddress {
+        address_space_id,
+        register_bit_width,
+        address,
+        ..
+    } = *register;
+
+    unsafe {
+        match (address_space_id, register_bit_width) {
+            (AcpiAddress::SYSTEM_IO, 8) => Port::<u8>::new(address as u16).write(value as u8),
+            (AcpiAddress::SYSTEM_IO, 16) => Port::<u16>::new(address as u16).write(value as u16),
+            (AcpiAddress::SYSTEM_IO, 32) => Port::<u32>::new(address as u16).write(value as u32),
+            (AcpiAddress::SYSTEM_MEMORY, 8) => {
+                phys_write_volatile(PhysAddr::new(address as usize), value as u8)
+            }
+            (AcpiAddress::SYSTEM_MEMORY, 16) => {
+                phys_write_volatile(PhysAddr::new(address as usize), value as u16)
+            }
+            (AcpiAddress::SYSTEM_MEMORY, 32) => {
+                phys_write_volatile(PhysAddr::new(address as usize), value as u32)
+            }
+            (AcpiAddress::SYSTEM_MEMORY, 64) => {
+                phys_write_volatile(PhysAddr::new(address as usize), value)
+            }
+            _ => {
+                return Err(PowerError::UnsupportedRegister(
+                    address_space_id,
+                    register_bit_width,
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Dumping the previous session's persistent log, and attaching a fresh one for this session
+
+use kernel_shared::{
+    boot::BootProtocol,
+    config,
+    mem::frame::Frame,
+    pstore::{self, PersistentLog},
+    serial_print,
+};
+
+use crate::{LOGGER, mem::KernelFrameAlloc};
+
+/// Prints the log left by the previous session if there is one, then starts persisting this session's log
+pub fn init(bootinfo: &impl BootProtocol, frame_alloc: &mut KernelFrameAlloc) {
+    let Some(addr) = pstore::locate(bootinfo) else {
+        log::debug!("no memory suitable for persistent log");
+        return;
+    };
+
+    // the loader doesn't reserve the region if something was loaded over it
+    let first_frame = Frame::containing_address(addr);
+    let last_frame = Frame::containing_address(addr + (config::PSTORE_SIZE - 1));
+    if !(first_frame..=last_frame).all(|frame| frame_alloc.is_frame_used(frame)) {
+        log::warn!("persistent log region at {addr} was not reserved, logs will not persist");
+        return;
+    }
+
+    // safety: the region is reserved, and nothing else knows about it
+    let persistent = unsafe { PersistentLog::from_addr(addr.as_hhdm_ptr()) };
+
+    match persistent.contents() {
+        Some((older, newer)) => {
+            log::warn!("log from previous session:");
+            print_log(older);
+            print_log(newer);
+            log::warn!("end of log from previous session");
+        }
+        None => log::trace!("\t* no log from previous session"),
+    }
+
+    persistent.reset();
+    LOGGER.attach_persistent(persistent);
+    log::trace!("\t* persistent log attached at {addr}");
+}
+
+/// Prints raw log bytes to serial, converting line endings
+fn print_log(bytes: &[u8]) {
+    for line in bytes.split_inclusive(|&byte| byte == b'\n') {
+        for chunk in line.utf8_chunks() {
+            serial_print!("{}", chunk.valid());
+        }
+
+        if line.ends_with(b"\n") {
+            serial_print!("\r");
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Minimal EGA text mode output, so boot progress and errors are visible without serial
+
+use core::fmt::Write;
+use std::mutex::Mutex;
+
+use kernel_shared::{
+    config,
+    io::{console::Console, ega::EgaBuffer},
+    x86::without_interrupts,
+};
+
+/// Shared console drawing to the EGA text buffer
+pub static EGA: Mutex<Console<EgaBuffer, { config::CONSOLE_SCROLLBACK_LINES }>> =
+    Mutex::new(Console::new(EgaBuffer::new()));
+
+/// Colour attribute used for a character
+#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
+pub enum Colour {
+    /// Light grey on black
+    Normal = 0x07,
+    /// Light green on black
+    Success = 0x0A,
+    /// White on red
+    Error = 0x4F,
+}
+
+/// Writes a line to the screen in the given colour
+pub fn println(colour: Colour, args: core::fmt::Arguments) {
+    without_interrupts(|| {
+        let mut ega = EGA.lock();
+        let colour_before = ega.colour();
+
+        ega.set_colour(colour as u8);
+        let _ = ega.write_fmt(args);
+        ega.write_byte(b'\n');
+
+        ega.set_colour(colour_before);
+    });
+}
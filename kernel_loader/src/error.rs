@@ -0,0 +1,186 @@
+//! Errors and progress reporting for the loader
+
+use core::{
+    fmt::{Display, Formatter},
+    sync::atomic::{AtomicU8, Ordering},
+};
+use std::{compression::DecompressError, sha256::Digest};
+
+use kernel_shared::mem::paging::MapError;
+use multiboot::{boot::BootInfoError, prelude::BootInfo};
+
+use crate::ega::{self, Colour};
+
+/// An error encountered while loading the kernel
+#[derive(Debug)]
+pub enum LoaderError {
+    /// Multiboot2 information could not be parsed
+    BadBootInfo(BootInfoError),
+    /// A required multiboot2 tag was not present
+    MissingBootInfoTag(&'static str),
+    /// The module with the given name was not loaded by the bootloader
+    MissingModule(&'static str),
+    /// The boot command line contains an invalid option
+    BadCommandLine(&'static str),
+    /// The kernel module does not have the hash given on the command line
+    KernelHashMismatch {
+        /// Hash given on the command line
+        expected: Digest,
+        /// Hash of the loaded kernel module
+        actual: Digest,
+    },
+    /// The kernel module is compressed, but could not be decompressed
+    Decompression(DecompressError),
+    /// The kernel module is not a valid ELF file
+    BadKernelElf,
+    /// No frames were left to allocate
+    NotEnoughMemory,
+    /// A kernel section was not page aligned
+    UnalignedSection {
+        /// Virtual address of the section
+        addr: u64,
+    },
+}
+
+impl Display for LoaderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadBootInfo(err) => {
+                write!(f, "multiboot2 information could not be parsed: {err}")
+            }
+            Self::MissingBootInfoTag(tag) => write!(f, "multiboot2 information has no {tag} tag"),
+            Self::MissingModule(name) => write!(f, "no module named `{name}` was loaded"),
+            Self::BadCommandLine(option) => write!(f, "invalid command line option `{option}`"),
+            Self::KernelHashMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "kernel hash mismatch, expected {expected} but got {actual}"
+                )
+            }
+            Self::Decompression(err) => write!(f, "failed to decompress kernel: {err}"),
+            Self::BadKernelElf => write!(f, "kernel module is not a valid ELF file"),
+            Self::NotEnoughMemory => write!(f, "ran out of frames to allocate"),
+            Self::UnalignedSection { addr } => {
+                write!(f, "kernel section at {addr:#X} is not page aligned")
+            }
+        }
+    }
+}
+
+impl From<MapError> for LoaderError {
+    fn from(error: MapError) -> Self {
+        match error {
+            MapError::OutOfFrames => Self::NotEnoughMemory,
+        }
+    }
+}
+
+/// Each stage the loader goes through before jumping to the kernel
+#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
+pub enum Stage {
+    /// Parsing multiboot2 information
+    ParseBootInfo = 1,
+    /// Finding the kernel module
+    LocateKernel,
+    /// Hashing the kernel module and checking it against the expected hash
+    VerifyKernel,
+    /// Decompressing the kernel module, if compressed
+    DecompressKernel,
+    /// Constructing the frame allocator
+    FrameAllocator,
+    /// Mapping bootinfo, loader, frame allocator and stack
+    MapLoader,
+    /// Mapping sections of the kernel ELF
+    MapKernel,
+    /// Mapping heap and physical memory
+    MapMemory,
+    /// Switching page tables and jumping to kernel
+    Jump,
+}
+
+/// Stage the loader is currently in
+static CURRENT_STAGE: AtomicU8 = AtomicU8::new(0);
+
+impl Stage {
+    /// Number of stages
+    const COUNT: u8 = Self::Jump as u8;
+
+    /// Marks this stage as the current one, logging progress
+    pub fn enter(self) {
+        CURRENT_STAGE.store(self as u8, Ordering::Relaxed);
+
+        log::info!("[{}/{}] {}", self as u8, Self::COUNT, self);
+
+        // final stage means everything went well, so highlight it
+        let colour = match self {
+            Self::Jump => Colour::Success,
+            _ => Colour::Normal,
+        };
+        ega::println(
+            colour,
+            format_args!("[{}/{}] {}", self as u8, Self::COUNT, self),
+        );
+    }
+
+    /// Returns the stage the loader is currently in, if any has been entered
+    pub fn current() -> Option<Self> {
+        use Stage::*;
+
+        match CURRENT_STAGE.load(Ordering::Relaxed) {
+            1 => Some(ParseBootInfo),
+            2 => Some(LocateKernel),
+            3 => Some(VerifyKernel),
+            4 => Some(DecompressKernel),
+            5 => Some(FrameAllocator),
+            6 => Some(MapLoader),
+            7 => Some(MapKernel),
+            8 => Some(MapMemory),
+            9 => Some(Jump),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::ParseBootInfo => "parsing multiboot2 information",
+            Self::LocateKernel => "locating kernel module",
+            Self::VerifyKernel => "verifying kernel module",
+            Self::DecompressKernel => "decompressing kernel module",
+            Self::FrameAllocator => "constructing frame allocator",
+            Self::MapLoader => "mapping loader structures",
+            Self::MapKernel => "mapping kernel sections",
+            Self::MapMemory => "mapping heap and physical memory",
+            Self::Jump => "jumping to kernel",
+        })
+    }
+}
+
+/// Reports a fatal loader error, along with as much context as is available, and then halts
+pub fn report(error: &LoaderError, bootinfo: Option<&BootInfo>) -> ! {
+    match Stage::current() {
+        Some(stage) => {
+            log::error!("loader failed while {stage}: {error}");
+            ega::println(
+                Colour::Error,
+                format_args!("loader failed while {stage}: {error}"),
+            );
+        }
+        None => {
+            log::error!("loader failed: {error}");
+            ega::println(Colour::Error, format_args!("loader failed: {error}"));
+        }
+    }
+    ega::println(
+        Colour::Error,
+        format_args!("see serial output for more details"),
+    );
+
+    if let Some(memory_map) = bootinfo.and_then(|bootinfo| bootinfo.memory_map.as_ref()) {
+        log::error!("{memory_map}");
+    }
+
+    kernel_shared::x86::halt()
+}
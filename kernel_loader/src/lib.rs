@@ -1,31 +1,48 @@
 #![no_std]
 #![feature(const_trait_impl, used_with_arg)]
 
+mod ega;
+mod error;
+
 use core::{arch::asm, ops::DerefMut, panic::PanicInfo};
 use std::{
-    align_up,
+    checked_align_up,
+    compression::{DecompressError, gzip},
     elf::{
         file_header::FileHeader,
         section_header::{SectionHeader, SectionType},
     },
+    is_aligned,
+    sha256::{Digest, Sha256},
 };
 
 use kernel_shared::{
-    io::serial,
+    boot::handoff::BootHandoff,
+    config,
+    io::{
+        serial,
+        sinks::{CONSOLES, SERIAL_SINK},
+    },
     logger::Logger,
     mem::{
-        PHYS_MEM_OFFSET, align_down_to_page,
+        DEFAULT_PHYS_MEM_OFFSET,
+        addr::{PhysAddr, VirtAddr},
+        align_down_to_page,
         frame::{FRAME_SIZE, Frame},
-        frame_alloc::{FrameAllocator, bitmap::BitmapFrameAlloc},
+        frame_alloc::{FrameAllocator, bitmap::BitmapFrameAlloc, low_mem},
+        heap::{HEAP_MAX_SIZE, HEAP_START},
         page::{PAGE_SIZE, Page},
         paging::{
-            active_table::ActivePageTable, entry::EntryFlags, inactive_table::InactivePageTable,
-            mapper::Mapper,
+            MapError, active_table::ActivePageTable, entry::EntryFlags,
+            inactive_table::InactivePageTable, mapper::Mapper,
         },
     },
+    pstore,
 };
 use multiboot::{multiboot_header, prelude::*};
 
+use crate::error::{LoaderError, Stage};
+
 multiboot_header! {
     arch: 0,
     tags: [
@@ -40,7 +57,16 @@ multiboot_header! {
     ]
 }
 
-static LOGGER: Logger = Logger::new(log::LevelFilter::Trace);
+/// End of the memory identity mapped by the boot assembly
+const IDENTITY_MAPPED_END: usize = 0x40000000;
+
+/// Where physical memory is mapped in the kernel's page tables, passed to the kernel in the handoff
+const KERNEL_PHYS_MEM_OFFSET: usize = DEFAULT_PHYS_MEM_OFFSET;
+
+/// Size of the virtual region reserved for the kernel's physical memory mapping
+const PHYS_MEM_MAPPING_SIZE: usize = 0x400000000000; // 64 TiB
+
+static LOGGER: Logger = Logger::new(config::DEFAULT_LOG_LEVEL);
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
@@ -50,44 +76,106 @@ fn panic(info: &PanicInfo) -> ! {
 
 #[unsafe(no_mangle)]
 extern "C" fn loader_main(bootinfo_addr: usize) {
+    LOGGER.init().unwrap();
     unsafe {
         serial::COM1.lock().init();
     }
-    LOGGER.init().unwrap();
+    CONSOLES.attach(&SERIAL_SINK, &config::CONSOLE_SERIAL_LEVEL);
 
-    let bootinfo = unsafe { BootInfo::new((bootinfo_addr) as *const u32).unwrap() };
-    let memory_map = bootinfo.memory_map.as_ref().unwrap();
+    Stage::ParseBootInfo.enter();
+    let bootinfo = match unsafe { BootInfo::new((bootinfo_addr) as *const u32) } {
+        Ok(bootinfo) => bootinfo,
+        Err(err) => error::report(&LoaderError::BadBootInfo(err), None),
+    };
+
+    if let Some(command) = command_line(&bootinfo) {
+        config::parse_command_line(command);
+    }
+
+    if let Err(err) = load(bootinfo_addr, &bootinfo) {
+        error::report(&err, Some(&bootinfo))
+    }
+}
+
+/// Sets up page tables for the kernel and jumps to it, only returning if an error occurs
+fn load(bootinfo_addr: usize, bootinfo: &BootInfo) -> Result<(), LoaderError> {
+    let memory_map = bootinfo
+        .memory_map
+        .as_ref()
+        .ok_or(LoaderError::MissingBootInfoTag("memory map"))?;
 
     let (bootinfo_start, bootinfo_end) = (bootinfo.addr, bootinfo.addr + bootinfo.size);
     log::trace!("bootinfo start: 0x{bootinfo_start:X}, end: 0x{bootinfo_end:X}");
 
-    let (loader_start, loader_end) =
-        loader_range(bootinfo.elf_symbols.as_ref().unwrap().section_headers);
+    let elf_symbols = bootinfo
+        .elf_symbols
+        .as_ref()
+        .ok_or(LoaderError::MissingBootInfoTag("ELF symbols"))?;
+    let load_base = bootinfo
+        .load_base_addr
+        .as_ref()
+        .map(|tag| tag.load_base_addr as usize);
+    let (loader_start, loader_end) = loader_range(elf_symbols.section_headers(), load_base);
     log::trace!("loader start: 0x{loader_start:X}, end: 0x{loader_end:X}");
 
-    let kernel_module = bootinfo.module(c"kernel").unwrap();
+    Stage::LocateKernel.enter();
+    let kernel_module = bootinfo
+        .module_by_name("kernel")
+        .ok_or(LoaderError::MissingModule("kernel"))?;
     let (kernel_start, kernel_end) = (
         kernel_module.module_addr as usize,
         (kernel_module.module_addr + kernel_module.module_len) as usize,
     );
     log::trace!("kernel start: 0x{kernel_start:X}, end 0x{kernel_end:X}");
 
+    let kernel_module_data = unsafe {
+        core::slice::from_raw_parts(kernel_start as *const u8, kernel_end - kernel_start)
+    };
+
+    Stage::VerifyKernel.enter();
+    verify_kernel(kernel_module_data, bootinfo)?;
+
+    // a compressed kernel needs unpacking before it can be mapped, and from then on we only care about the
+    // decompressed copy
+    let (kernel_start, kernel_end) = if gzip::is_gzip(kernel_module_data) {
+        Stage::DecompressKernel.enter();
+
+        decompress_kernel(
+            kernel_module_data,
+            bootinfo_end.max(loader_end).max(kernel_end),
+            memory_map,
+        )?
+    } else {
+        (kernel_start, kernel_end)
+    };
+
+    // check the kernel is a valid ELF before we start building anything
+    let kernel_elf =
+        unsafe { FileHeader::from_addr(kernel_start) }.ok_or(LoaderError::BadKernelElf)?;
+
+    Stage::FrameAllocator.enter();
+
     // if we have extended memory at 0x0000000100000000, then we can simply start frame alloc there
     // otherwise we have to place it after everything multiboot2 loaded
     let frame_alloc_phys_addr = if memory_map.contains_extended_memory_three() {
         0x0000000100000000
     } else {
-        align_up(bootinfo_end.max(loader_end).max(kernel_end), FRAME_SIZE)
+        checked_align_up(bootinfo_end.max(loader_end).max(kernel_end), FRAME_SIZE)
+            .ok_or(LoaderError::NotEnoughMemory)?
     };
 
-    let frame_alloc_addr = frame_alloc_phys_addr | PHYS_MEM_OFFSET;
+    let frame_alloc_addr = PhysAddr::new(frame_alloc_phys_addr).to_virt().as_usize();
 
     let (frame_alloc, frame_alloc_size) = unsafe {
-        BitmapFrameAlloc::new(frame_alloc_phys_addr, frame_alloc_addr, memory_map.entries)
+        BitmapFrameAlloc::new(
+            PhysAddr::new(frame_alloc_phys_addr),
+            frame_alloc_addr,
+            memory_map.entries,
+        )
     };
 
-    let bootinfo_region =
-        Frame::containing_address(bootinfo_start)..=Frame::containing_address(bootinfo_end);
+    let bootinfo_region = Frame::containing_address(PhysAddr::new(bootinfo_start))
+        ..=Frame::containing_address(PhysAddr::new(bootinfo_end - 1));
     log::trace!(
         "blocking bootinfo region 0x{:X}-0x{:X}",
         bootinfo_region.start().start_address(),
@@ -95,8 +183,8 @@ extern "C" fn loader_main(bootinfo_addr: usize) {
     );
     frame_alloc.block_region(bootinfo_region);
 
-    let loader_region =
-        Frame::containing_address(loader_start)..=Frame::containing_address(loader_end);
+    let loader_region = Frame::containing_address(PhysAddr::new(loader_start))
+        ..=Frame::containing_address(PhysAddr::new(loader_end - 1));
     log::trace!(
         "blocking loader region 0x{:X}-0x{:X}",
         loader_region.start().start_address(),
@@ -104,8 +192,8 @@ extern "C" fn loader_main(bootinfo_addr: usize) {
     );
     frame_alloc.block_region(loader_region);
 
-    let kernel_region =
-        Frame::containing_address(kernel_start)..=Frame::containing_address(kernel_end);
+    let kernel_region = Frame::containing_address(PhysAddr::new(kernel_start))
+        ..=Frame::containing_address(PhysAddr::new(kernel_end - 1));
     log::trace!(
         "blocking kernel region 0x{:X}-0x{:X}",
         kernel_region.start().start_address(),
@@ -113,14 +201,33 @@ extern "C" fn loader_main(bootinfo_addr: usize) {
     );
     frame_alloc.block_region(kernel_region);
 
-    // if we place the L4 frame at physical address 0, then things break
-    // so make sure frame 0 cant be handed out
-    frame_alloc.block_frame(Frame::containing_address(0));
+    // firmware may report parts of low memory as RAM, and if we place the L4 frame at physical address 0 then things
+    // break, so low memory is only ever handed out when explicitly claimed
+    low_mem::reserve(frame_alloc);
+
+    reserve_pstore(
+        frame_alloc,
+        bootinfo,
+        &[
+            (bootinfo_start, bootinfo_end),
+            (loader_start, loader_end),
+            (
+                kernel_module.module_addr as usize,
+                (kernel_module.module_addr + kernel_module.module_len) as usize,
+            ),
+            (kernel_start, kernel_end),
+            (
+                frame_alloc_phys_addr,
+                frame_alloc_phys_addr + frame_alloc_size,
+            ),
+        ],
+    );
 
     // now we can start remapping
+    Stage::MapLoader.enter();
     let table_frame = frame_alloc
         .allocate_frame()
-        .expect("failed to allocate a frame for level 4 table");
+        .ok_or(LoaderError::NotEnoughMemory)?;
 
     let mut table = unsafe { InactivePageTable::new(table_frame) };
 
@@ -131,37 +238,37 @@ extern "C" fn loader_main(bootinfo_addr: usize) {
         &mut table,
         bootinfo_start,
         bootinfo_end,
-    );
-    identity_map("loader", frame_alloc, &mut table, loader_start, loader_end);
+    )?;
+    identity_map("loader", frame_alloc, &mut table, loader_start, loader_end)?;
 
     // also make sure to map allocator
     map_frame_allocator(
         frame_alloc,
         &mut table,
-        Frame::containing_address(frame_alloc_phys_addr),
-        Frame::containing_address(frame_alloc_phys_addr + frame_alloc_size),
-    );
+        Frame::containing_address(PhysAddr::new(frame_alloc_phys_addr)),
+        Frame::containing_address(PhysAddr::new(frame_alloc_phys_addr + frame_alloc_size)),
+    )?;
 
     // set up stack, descending from end of kernel space
     log::trace!("setting up stack at {:#X}", usize::MAX);
-    let start_page = Page::containing_address(usize::MAX - kernel_shared::STACK_SIZE + 1);
-    let end_page = Page::containing_address(usize::MAX);
+    let start_page = Page::containing_address(VirtAddr::new(usize::MAX - config::STACK_SIZE + 1));
+    let end_page = Page::containing_address(VirtAddr::new(usize::MAX));
 
     for page in start_page..=end_page {
-        table.map(page, EntryFlags::WRITABLE, frame_alloc);
+        table.map(page, EntryFlags::WRITABLE, frame_alloc)?;
     }
 
     // now map kernel sections
-    let kernel_elf = unsafe { FileHeader::from_addr(kernel_start) }.unwrap();
+    Stage::MapKernel.enter();
     let string_header = kernel_elf.string_header();
 
     for section_header in kernel_elf.section_headers() {
+        // SAFETY: the whole kernel file sits in memory as a module starting at kernel_start
+        let name = unsafe { section_header.name_in_file(string_header, kernel_start) };
+
         // only map sections that need allocating
         if !section_header.allocated() {
-            log::trace!(
-                "skipping mapping kernel section {:?}",
-                section_header.name(string_header, kernel_start)
-            );
+            log::trace!("skipping mapping kernel section {name:?}");
             continue;
         }
 
@@ -174,22 +281,21 @@ extern "C" fn loader_main(bootinfo_addr: usize) {
         let end_virt = (section_header.addr + section_header.size - 1) as usize;
 
         log::trace!(
-            "mapping kernel section {:?} at {:#X}-{:#X} with flags `{}`",
-            section_header.name(string_header, kernel_start),
+            "mapping kernel section {name:?} at {:#X}-{:#X} with flags `{}`",
             align_down_to_page(start_virt),
             align_down_to_page(end_virt),
             flags
         );
 
-        assert_eq!(
-            section_header.addr as usize % PAGE_SIZE,
-            0,
-            "sections need to be page aligned, addr {:#X}",
-            section_header.addr
-        );
+        // sections need to be page aligned
+        if !is_aligned(section_header.addr as usize, PAGE_SIZE) {
+            return Err(LoaderError::UnalignedSection {
+                addr: section_header.addr,
+            });
+        }
 
         // if SHT_NOBITS, we need to manually zero
-        if section_header.section_type == SectionType::Nobits {
+        if section_header.section_type() == SectionType::Nobits {
             unsafe {
                 core::ptr::write_bytes(
                     align_down_to_page(start_phys) as *mut u8,
@@ -199,22 +305,24 @@ extern "C" fn loader_main(bootinfo_addr: usize) {
             };
         }
 
-        // finally actually map
+        // finally actually map. huge pages aren't used, as the kernel re-protects its sections page by page
         table.map_range(
-            (start_phys, end_phys),
-            (start_virt, end_virt),
+            (PhysAddr::new(start_phys), PhysAddr::new(end_phys)),
+            (VirtAddr::new(start_virt), VirtAddr::new(end_virt)),
             flags,
             frame_alloc,
-            true,
-        );
+            false,
+        )?;
     }
 
     // and heap/phys memory
-    map_heap(frame_alloc, &mut table, kernel_shared::HEAP_SIZE);
-    map_phys_memory(frame_alloc, &mut table, memory_map);
+    Stage::MapMemory.enter();
+    map_heap(frame_alloc, &mut table, config::HEAP_SIZE)?;
+    map_phys_memory(frame_alloc, &mut table, memory_map)?;
 
     // now we're ready to hop to kernel!
     // first switch out active table, and then jump
+    Stage::Jump.enter();
 
     let entrypoint = kernel_elf.entry;
 
@@ -223,21 +331,136 @@ extern "C" fn loader_main(bootinfo_addr: usize) {
 
     log::trace!("switched active table!");
 
+    // this stays on the loader's stack, which is identity mapped until the kernel has copied it and reclaims the
+    // loader. bootinfo is given as a physical address, as the kernel reads it through the physical memory mapping
+    let handoff = BootHandoff {
+        bootinfo_addr,
+        loader_start,
+        loader_end,
+        phys_mem_offset: KERNEL_PHYS_MEM_OFFSET,
+    };
+
     log::trace!("jumping to kernel at {entrypoint:#X}");
     unsafe {
         asm!(
             "mov rsp, 0xFFFFFFFFFFFFFFFF",
             "jmp {}",
             in(reg) entrypoint,
-            in("rdi") bootinfo_addr | PHYS_MEM_OFFSET,
-            in("rsi") loader_start,
-            in("rdx") loader_end
+            in("rdi") &raw const handoff,
         )
     }
+
+    Ok(())
+}
+
+/// Blocks the persistent log region so it survives until the kernel reads it, unless anything already loaded
+/// overlaps it, in which case the previous session's log is lost anyway
+fn reserve_pstore(
+    frame_alloc: &mut BitmapFrameAlloc,
+    bootinfo: &BootInfo,
+    in_use: &[(usize, usize)],
+) {
+    let Some(start) = pstore::locate(bootinfo) else {
+        log::trace!("no memory suitable for persistent log");
+        return;
+    };
+    let (start, end) = (start.as_usize(), start.as_usize() + config::PSTORE_SIZE);
+
+    if let Some((used_start, used_end)) = in_use
+        .iter()
+        .find(|&&(used_start, used_end)| used_start < end && start < used_end)
+    {
+        log::warn!(
+            "persistent log region 0x{start:X}-0x{end:X} overlaps 0x{used_start:X}-0x{used_end:X}, not reserving"
+        );
+        return;
+    }
+
+    log::trace!("blocking persistent log region 0x{start:X}-0x{end:X}");
+    frame_alloc.block_region(
+        Frame::containing_address(PhysAddr::new(start))
+            ..=Frame::containing_address(PhysAddr::new(end - 1)),
+    );
+}
+
+/// Hashes the kernel module, checking it against the hash passed as `kernel_sha256=<hex>` on the command line
+/// if present
+fn verify_kernel(data: &[u8], bootinfo: &BootInfo) -> Result<(), LoaderError> {
+    let actual = Sha256::digest(data);
+    log::info!("kernel sha256: {actual}");
+
+    let expected = command_line(bootinfo).and_then(|command| {
+        command
+            .split_ascii_whitespace()
+            .find_map(|option| option.strip_prefix("kernel_sha256="))
+    });
+
+    let Some(expected) = expected else {
+        log::trace!("no expected kernel hash given, skipping verification");
+        return Ok(());
+    };
+
+    let expected =
+        Digest::from_hex(expected).ok_or(LoaderError::BadCommandLine("kernel_sha256"))?;
+
+    if expected != actual {
+        return Err(LoaderError::KernelHashMismatch { expected, actual });
+    }
+
+    log::trace!("kernel hash matches expected hash");
+    Ok(())
+}
+
+/// Returns the boot command line, if present and valid UTF-8
+fn command_line(bootinfo: &BootInfo) -> Option<&str> {
+    bootinfo
+        .boot_command_line
+        .as_ref()
+        .and_then(|command_line| command_line.command.to_str().ok())
+}
+
+/// Decompresses a gzip compressed kernel into the first free memory after `after`, returning the start and end
+/// addresses of the decompressed kernel
+fn decompress_kernel(
+    data: &[u8],
+    after: usize,
+    memory_map: &MemoryMap,
+) -> Result<(usize, usize), LoaderError> {
+    let size = gzip::decompressed_size(data)
+        .ok_or(LoaderError::Decompression(DecompressError::BadHeader))?;
+
+    let start = checked_align_up(after, FRAME_SIZE).ok_or(LoaderError::NotEnoughMemory)?;
+    let end = start
+        .checked_add(size)
+        .ok_or(LoaderError::NotEnoughMemory)?;
+
+    // destination must be usable RAM, and within the first 1GiB which is identity mapped at this point
+    let in_ram = memory_map.entries.iter().any(|entry| {
+        entry.entry_type() == MemoryEntryType::RAM
+            && entry.base_addr as usize <= start
+            && end <= (entry.base_addr + entry.length) as usize
+    });
+
+    if !in_ram || end > IDENTITY_MAPPED_END {
+        return Err(LoaderError::NotEnoughMemory);
+    }
+
+    log::trace!(
+        "decompressing {} byte kernel to {start:#X}-{end:#X} ({size} bytes)",
+        data.len()
+    );
+
+    let output = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, size) };
+    gzip::decompress(data, output).map_err(LoaderError::Decompression)?;
+
+    Ok((start, end))
 }
 
-/// Finds where loader lies within memory
-fn loader_range(section_headers: &'static [SectionHeader]) -> (usize, usize) {
+/// Finds where loader lies within memory.
+///
+/// Section headers hold linked addresses, so if the bootloader reports where the image was actually loaded, the
+/// range is shifted to match.
+fn loader_range(section_headers: &[SectionHeader], load_base: Option<usize>) -> (usize, usize) {
     let start = section_headers
         .iter()
         .filter(|header| header.allocated())
@@ -252,19 +475,25 @@ fn loader_range(section_headers: &'static [SectionHeader]) -> (usize, usize) {
         .max()
         .unwrap() as usize;
 
-    (start, end)
+    match load_base {
+        Some(load_base) if load_base != start => {
+            log::trace!("loader linked at {start:#X} but loaded at {load_base:#X}");
+            (load_base, load_base + (end - start))
+        }
+        _ => (start, end),
+    }
 }
 
-/// Helper function for identity mapping a region
+/// Helper function for identity mapping a region, where `end_addr` is exclusive
 fn identity_map<A: FrameAllocator, T: DerefMut<Target = Mapper>>(
     log_str: &'static str,
     alloc: &mut A,
     table: &mut T,
     start_addr: usize,
     end_addr: usize,
-) {
-    let start_frame = Frame::containing_address(start_addr);
-    let end_frame = Frame::containing_address(end_addr);
+) -> Result<(), MapError> {
+    let start_frame = Frame::containing_address(PhysAddr::new(start_addr));
+    let end_frame = Frame::containing_address(PhysAddr::new(end_addr - 1));
 
     log::trace!(
         "mapping {log_str} at {:#X}-{:#X}",
@@ -273,8 +502,10 @@ fn identity_map<A: FrameAllocator, T: DerefMut<Target = Mapper>>(
     );
 
     for frame in start_frame..=end_frame {
-        table.identity_map(frame, EntryFlags::WRITABLE, alloc);
+        table.identity_map(frame, EntryFlags::WRITABLE, alloc)?;
     }
+
+    Ok(())
 }
 
 /// Maps frame allocator to 0xFFFFFFFF00000000
@@ -283,7 +514,7 @@ fn map_frame_allocator<A: FrameAllocator, T: DerefMut<Target = Mapper>>(
     table: &mut T,
     start_frame: Frame,
     end_frame: Frame,
-) {
+) -> Result<(), MapError> {
     log::trace!(
         "mapping frame allocator at phys addr {:#X}-{:#X}",
         start_frame.start_address(),
@@ -292,37 +523,41 @@ fn map_frame_allocator<A: FrameAllocator, T: DerefMut<Target = Mapper>>(
 
     table.map_range(
         (start_frame.start_address(), end_frame.start_address()),
-        (0xFFFFFFFF00000000, 0xFFFFFFFF1FFFFFFF),
+        (
+            VirtAddr::new(0xFFFFFFFF00000000),
+            VirtAddr::new(0xFFFFFFFF1FFFFFFF),
+        ),
         EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE,
         alloc,
         true,
-    );
+    )
 }
 
-/// Maps heap to 0xFFFFFFFF20000000
+/// Maps heap to [`HEAP_START`]
 fn map_heap<A: FrameAllocator, T: DerefMut<Target = Mapper>>(
     alloc: &mut A,
     table: &mut T,
     size: usize,
-) {
+) -> Result<(), MapError> {
     log::trace!("mapping heap");
 
-    let end_addr = (0xFFFFFFFF20000000 + size).min(0xFFFFFFFF3FFFFFFF);
-
-    let start_page = Page::containing_address(0xFFFFFFFF20000000);
-    let end_page = Page::containing_address(end_addr);
+    let start_page = Page::containing_address(VirtAddr::new(HEAP_START));
+    let end_page =
+        Page::containing_address(VirtAddr::new(HEAP_START + size.min(HEAP_MAX_SIZE) - 1));
 
     for page in start_page..=end_page {
-        table.map(page, EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE, alloc);
+        table.map(page, EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE, alloc)?;
     }
+
+    Ok(())
 }
 
-/// Maps physical memory to 0xFFFF800000000000
+/// Maps physical memory to [`KERNEL_PHYS_MEM_OFFSET`]
 fn map_phys_memory<A: FrameAllocator, T: DerefMut<Target = Mapper>>(
     alloc: &mut A,
     table: &mut T,
     memory_map: &MemoryMap,
-) {
+) -> Result<(), MapError> {
     log::trace!("mapping physical memory");
 
     let highest_address = memory_map
@@ -333,10 +568,13 @@ fn map_phys_memory<A: FrameAllocator, T: DerefMut<Target = Mapper>>(
         .unwrap() as usize;
 
     table.map_range(
-        (0, highest_address),
-        (0xFFFF800000000000, 0xFFFFBFFFFFFFFFFF),
+        (PhysAddr::new(0), PhysAddr::new(highest_address)),
+        (
+            VirtAddr::new(KERNEL_PHYS_MEM_OFFSET),
+            VirtAddr::new(KERNEL_PHYS_MEM_OFFSET + PHYS_MEM_MAPPING_SIZE - 1),
+        ),
         EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE,
         alloc,
         true,
-    );
+    )
 }
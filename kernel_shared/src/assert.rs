@@ -0,0 +1,76 @@
+//! Assertions which can recover rather than panic.
+//!
+//! [`kassert!`](crate::kassert) checks a condition inside a fallible function. If it doesn't hold, the failure is
+//! logged and the function returns the given error, so one misbehaving driver doesn't take the whole machine down
+//! during a long test run. Setting the `assert_panic` tunable escalates every failure to a panic, which is the
+//! default in debug builds.
+
+use core::{
+    fmt::Arguments,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::config;
+
+/// Number of assertions which have failed and been recovered from
+static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of assertions which have failed without panicking
+pub fn failures() -> usize {
+    FAILURES.load(Ordering::Relaxed)
+}
+
+/// Reports a failed assertion, panicking if `assert_panic` is set. Called by the assertion macros.
+#[doc(hidden)]
+#[cold]
+pub fn fail(message: Arguments, file: &'static str, line: u32) {
+    if config::ASSERT_PANIC.enabled() {
+        panic!("assertion failed: {message}");
+    }
+
+    FAILURES.fetch_add(1, Ordering::Relaxed);
+    log::error!("assertion failed at {file}:{line}: {message}");
+}
+
+/// Asserts a condition holds, otherwise logging and returning `Err` with the given error converted with
+/// [`Into`], or panicking if `assert_panic` is set. Can only be used in functions returning [`Result`].
+#[macro_export]
+macro_rules! kassert {
+    ($cond:expr, $err:expr $(,)?) => {
+        $crate::kassert!($cond, $err, "{}", stringify!($cond))
+    };
+    ($cond:expr, $err:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::assert::fail(format_args!($($arg)+), file!(), line!());
+            return Err($err.into());
+        }
+    };
+}
+
+/// Asserts two values are equal, behaving like [`kassert!`](crate::kassert) otherwise
+#[macro_export]
+macro_rules! kassert_eq {
+    ($left:expr, $right:expr, $err:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => $crate::kassert!(
+                *left == *right,
+                $err,
+                "`{} == {}` (left: {:?}, right: {:?})",
+                stringify!($left),
+                stringify!($right),
+                left,
+                right
+            ),
+        }
+    };
+}
+
+/// Like [`kassert!`](crate::kassert), but only checked in debug builds
+#[macro_export]
+macro_rules! kdebug_assert {
+    ($($arg:tt)+) => {
+        if cfg!(debug_assertions) {
+            $crate::kassert!($($arg)+);
+        }
+    };
+}
@@ -0,0 +1,59 @@
+//! Block devices, and views of the partitions on them
+
+pub mod partition;
+
+use core::fmt::{Display, Formatter};
+
+/// Size of a sector in bytes. Every device is addressed in sectors of this size.
+pub const SECTOR_SIZE: usize = 512;
+
+/// An error reading from or writing to a block device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockError {
+    /// Access went past the end of the device
+    OutOfRange,
+    /// Buffer length was not a multiple of the sector size
+    UnalignedBuffer,
+    /// Device reported an error
+    Io,
+    /// Device does not support writes
+    ReadOnly,
+}
+
+impl Display for BlockError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::OutOfRange => write!(f, "access past end of block device"),
+            Self::UnalignedBuffer => {
+                write!(f, "buffer is not a multiple of {SECTOR_SIZE} bytes")
+            }
+            Self::Io => write!(f, "block device reported an error"),
+            Self::ReadOnly => write!(f, "block device is read only"),
+        }
+    }
+}
+
+/// A device addressed in fixed size sectors
+pub trait BlockDevice {
+    /// Returns the number of sectors on the device
+    fn sector_count(&self) -> u64;
+
+    /// Reads `buffer.len() / SECTOR_SIZE` sectors starting at `lba` into the buffer
+    fn read_sectors(&self, lba: u64, buffer: &mut [u8]) -> Result<(), BlockError>;
+
+    /// Writes `buffer.len() / SECTOR_SIZE` sectors starting at `lba` from the buffer
+    fn write_sectors(&self, lba: u64, buffer: &[u8]) -> Result<(), BlockError>;
+}
+
+/// Checks a transfer of `length` bytes starting at `lba` fits within a device of `sector_count` sectors
+pub fn check_range(lba: u64, length: usize, sector_count: u64) -> Result<(), BlockError> {
+    if !length.is_multiple_of(SECTOR_SIZE) {
+        return Err(BlockError::UnalignedBuffer);
+    }
+
+    let sectors = (length / SECTOR_SIZE) as u64;
+    match lba.checked_add(sectors) {
+        Some(end) if end <= sector_count => Ok(()),
+        _ => Err(BlockError::OutOfRange),
+    }
+}
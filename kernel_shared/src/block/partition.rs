@@ -0,0 +1,658 @@
+//! MBR and GPT partition table parsing, exposing each partition as its own [`BlockDevice`]
+
+use core::fmt::{Debug, Display, Formatter};
+use std::{
+    collections::ArrayVec,
+    compression::gzip::{crc32, crc32_update},
+};
+
+use crate::block::{BlockDevice, BlockError, SECTOR_SIZE, check_range};
+
+/// Maximum number of partitions returned by [`scan`], any more are ignored
+pub const MAX_PARTITIONS: usize = 16;
+
+/// Offset of the partition entries within the MBR
+const MBR_ENTRIES_OFFSET: usize = 0x1BE;
+
+/// Size of an MBR partition entry
+const MBR_ENTRY_SIZE: usize = 16;
+
+/// Boot signature at the end of the MBR
+const MBR_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+
+/// MBR partition type of the single entry covering a GPT disk
+const MBR_TYPE_GPT_PROTECTIVE: u8 = 0xEE;
+
+/// Signature at the start of the GPT header
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+
+/// Smallest valid GPT header size, as of revision 1.0
+const GPT_MIN_HEADER_SIZE: usize = 92;
+
+/// Smallest valid GPT partition entry size
+const GPT_MIN_ENTRY_SIZE: usize = 128;
+
+/// Largest GPT partition entry array read, in bytes. This is the size the spec reserves, holding 128 entries of the
+/// minimum size, so anything bigger is treated as a corrupt header rather than read
+const GPT_MAX_ENTRIES_SIZE: usize = 16 * 1024;
+
+/// A GUID, stored in the mixed endian layout used on disk
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Guid(pub [u8; 16]);
+
+impl Guid {
+    /// All zero GUID, marking an unused GPT entry
+    pub const UNUSED: Self = Self([0; 16]);
+
+    /// EFI system partition, C12A7328-F81F-11D2-BA4B-00A0C93EC93B
+    pub const EFI_SYSTEM: Self = Self([
+        0x28, 0x73, 0x2A, 0xC1, 0x1F, 0xF8, 0xD2, 0x11, 0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E, 0xC9,
+        0x3B,
+    ]);
+
+    /// Microsoft basic data partition (FAT, NTFS), EBD0A0A2-B9E5-4433-87C0-68B6B72699C7
+    pub const BASIC_DATA: Self = Self([
+        0xA2, 0xA0, 0xD0, 0xEB, 0xE5, 0xB9, 0x33, 0x44, 0x87, 0xC0, 0x68, 0xB6, 0xB7, 0x26, 0x99,
+        0xC7,
+    ]);
+
+    /// Linux filesystem data, 0FC63DAF-8483-4772-8E79-3D69D8477DE4
+    pub const LINUX_FILESYSTEM: Self = Self([
+        0xAF, 0x3D, 0xC6, 0x0F, 0x83, 0x84, 0x72, 0x47, 0x8E, 0x79, 0x3D, 0x69, 0xD8, 0x47, 0x7D,
+        0xE4,
+    ]);
+}
+
+impl Display for Guid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let b = &self.0;
+
+        // first three groups are little endian, the rest are stored as written
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-",
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            u16::from_le_bytes([b[4], b[5]]),
+            u16::from_le_bytes([b[6], b[7]]),
+            b[8],
+            b[9]
+        )?;
+        b[10..].iter().try_for_each(|byte| write!(f, "{byte:02X}"))
+    }
+}
+
+impl Debug for Guid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+/// What a partition contains, as recorded in the partition table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionKind {
+    /// MBR partition type byte
+    Mbr(u8),
+    /// GPT partition type GUID
+    Gpt(Guid),
+}
+
+/// A partition found on a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionEntry {
+    /// Index of the entry in the partition table
+    pub index: usize,
+    /// First sector of the partition
+    pub start_lba: u64,
+    /// Number of sectors in the partition
+    pub sector_count: u64,
+    /// Partition type
+    pub kind: PartitionKind,
+}
+
+/// A single partition, accessed with sector numbers relative to its start
+pub struct Partition<'a, D: BlockDevice> {
+    /// Device the partition is on
+    device: &'a D,
+    /// Location of the partition on the device
+    entry: PartitionEntry,
+}
+
+impl<'a, D: BlockDevice> Partition<'a, D> {
+    /// Constructs a view of a partition on the device
+    pub const fn new(device: &'a D, entry: PartitionEntry) -> Self {
+        Self { device, entry }
+    }
+
+    /// Returns the partition table entry describing this partition
+    pub const fn entry(&self) -> &PartitionEntry {
+        &self.entry
+    }
+}
+
+impl<D: BlockDevice> BlockDevice for Partition<'_, D> {
+    fn sector_count(&self) -> u64 {
+        self.entry.sector_count
+    }
+
+    fn read_sectors(&self, lba: u64, buffer: &mut [u8]) -> Result<(), BlockError> {
+        check_range(lba, buffer.len(), self.entry.sector_count)?;
+        self.device.read_sectors(self.entry.start_lba + lba, buffer)
+    }
+
+    fn write_sectors(&self, lba: u64, buffer: &[u8]) -> Result<(), BlockError> {
+        check_range(lba, buffer.len(), self.entry.sector_count)?;
+        self.device
+            .write_sectors(self.entry.start_lba + lba, buffer)
+    }
+}
+
+/// An error reading a partition table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionError {
+    /// Device could not be read
+    Block(BlockError),
+    /// Sector 0 does not end in the MBR boot signature
+    MissingMbr,
+    /// Protective MBR was found, but the GPT header is invalid
+    BadGptHeader,
+    /// GPT partition entries do not match their checksum
+    BadGptEntries,
+    /// A partition extends past the end of the device
+    PartitionOutOfRange(usize),
+}
+
+impl From<BlockError> for PartitionError {
+    fn from(error: BlockError) -> Self {
+        Self::Block(error)
+    }
+}
+
+impl Display for PartitionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Block(error) => write!(f, "failed to read partition table: {error}"),
+            Self::MissingMbr => write!(f, "no MBR boot signature"),
+            Self::BadGptHeader => write!(f, "invalid GPT header"),
+            Self::BadGptEntries => write!(f, "GPT partition entries failed checksum"),
+            Self::PartitionOutOfRange(index) => {
+                write!(f, "partition {index} extends past end of device")
+            }
+        }
+    }
+}
+
+/// Finds every partition on a device, reading GPT if the MBR is protective and MBR primary partitions otherwise.
+/// Extended MBR partitions are not followed.
+pub fn scan<D: BlockDevice>(
+    device: &D,
+) -> Result<ArrayVec<PartitionEntry, MAX_PARTITIONS>, PartitionError> {
+    let mut sector = [0; SECTOR_SIZE];
+    device.read_sectors(0, &mut sector)?;
+
+    if sector[SECTOR_SIZE - 2..] != MBR_SIGNATURE {
+        return Err(PartitionError::MissingMbr);
+    }
+
+    let entries = (0..4).map(|index| {
+        let offset = MBR_ENTRIES_OFFSET + index * MBR_ENTRY_SIZE;
+        let entry = &sector[offset..offset + MBR_ENTRY_SIZE];
+
+        (
+            index,
+            entry[4],
+            read_u32(entry, 8) as u64,
+            read_u32(entry, 12) as u64,
+        )
+    });
+
+    if entries
+        .clone()
+        .any(|(_, kind, _, _)| kind == MBR_TYPE_GPT_PROTECTIVE)
+    {
+        return scan_gpt(device);
+    }
+
+    let mut partitions = ArrayVec::new();
+    for (index, kind, start_lba, sector_count) in entries {
+        if kind == 0 || sector_count == 0 {
+            continue;
+        }
+
+        let entry = PartitionEntry {
+            index,
+            start_lba,
+            sector_count,
+            kind: PartitionKind::Mbr(kind),
+        };
+        check_entry(device, &entry)?;
+        let _ = partitions.push(entry);
+    }
+
+    Ok(partitions)
+}
+
+/// Reads the GPT header at LBA 1 and the partition entries it points to
+fn scan_gpt<D: BlockDevice>(
+    device: &D,
+) -> Result<ArrayVec<PartitionEntry, MAX_PARTITIONS>, PartitionError> {
+    let mut header = [0; SECTOR_SIZE];
+    device.read_sectors(1, &mut header)?;
+
+    let header_size = read_u32(&header, 12) as usize;
+    if &header[..8] != GPT_SIGNATURE || !(GPT_MIN_HEADER_SIZE..=SECTOR_SIZE).contains(&header_size)
+    {
+        return Err(PartitionError::BadGptHeader);
+    }
+
+    // header checksum is calculated with its own field zeroed
+    let expected_crc = read_u32(&header, 16);
+    header[16..20].fill(0);
+    if crc32(&header[..header_size]) != expected_crc {
+        return Err(PartitionError::BadGptHeader);
+    }
+
+    let entries = GptEntryArray {
+        lba: read_u64(&header, 72),
+        count: read_u32(&header, 80) as usize,
+        size: read_u32(&header, 84) as usize,
+    };
+    let entries_crc = read_u32(&header, 88);
+
+    if entries.size < GPT_MIN_ENTRY_SIZE
+        || !entries.size.is_power_of_two()
+        || entries.size > SECTOR_SIZE
+        || entries.count * entries.size > GPT_MAX_ENTRIES_SIZE
+    {
+        return Err(PartitionError::BadGptHeader);
+    }
+
+    match entries.lba.checked_add(entries.sector_count()) {
+        Some(end) if end <= device.sector_count() => {}
+        _ => return Err(PartitionError::BadGptHeader),
+    }
+
+    // the checksum covers the whole array, so check it before trusting anything in it
+    let mut crc = 0;
+    entries.for_each(device, |_, raw| {
+        crc = crc32_update(crc, raw);
+        Ok(())
+    })?;
+
+    if crc != entries_crc {
+        return Err(PartitionError::BadGptEntries);
+    }
+
+    let mut partitions = ArrayVec::new();
+    entries.for_each(device, |index, raw| {
+        let kind = Guid(raw[..16].try_into().unwrap());
+        if kind == Guid::UNUSED {
+            return Ok(());
+        }
+
+        // the end is inclusive, so a partition ending at the last possible LBA has one too many sectors to count
+        let start_lba = read_u64(raw, 32);
+        let end_lba = read_u64(raw, 40);
+        let sector_count = end_lba
+            .checked_sub(start_lba)
+            .and_then(|count| count.checked_add(1))
+            .ok_or(PartitionError::PartitionOutOfRange(index))?;
+
+        let entry = PartitionEntry {
+            index,
+            start_lba,
+            sector_count,
+            kind: PartitionKind::Gpt(kind),
+        };
+        check_entry(device, &entry)?;
+
+        if partitions.push(entry).is_err() {
+            log::warn!("ignoring GPT partition {index}, only {MAX_PARTITIONS} are supported");
+        }
+
+        Ok(())
+    })?;
+
+    Ok(partitions)
+}
+
+/// Location and layout of the GPT partition entry array
+struct GptEntryArray {
+    /// First sector of the array
+    lba: u64,
+    /// Number of entries
+    count: usize,
+    /// Size of each entry in bytes, a power of two no bigger than a sector
+    size: usize,
+}
+
+impl GptEntryArray {
+    /// Returns the number of sectors the array spans
+    fn sector_count(&self) -> u64 {
+        self.count.div_ceil(SECTOR_SIZE / self.size) as u64
+    }
+
+    /// Reads the array a sector at a time, calling `f` with the index and raw bytes of each entry in order
+    fn for_each<D: BlockDevice>(
+        &self,
+        device: &D,
+        mut f: impl FnMut(usize, &[u8]) -> Result<(), PartitionError>,
+    ) -> Result<(), PartitionError> {
+        let entries_per_sector = SECTOR_SIZE / self.size;
+        let mut sector = [0; SECTOR_SIZE];
+
+        for sector_index in 0..self.sector_count() {
+            device.read_sectors(self.lba + sector_index, &mut sector)?;
+
+            for (slot, raw) in sector.chunks_exact(self.size).enumerate() {
+                let index = sector_index as usize * entries_per_sector + slot;
+                if index >= self.count {
+                    break;
+                }
+
+                f(index, raw)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks a partition fits within the device
+fn check_entry<D: BlockDevice>(device: &D, entry: &PartitionEntry) -> Result<(), PartitionError> {
+    match entry.start_lba.checked_add(entry.sector_count) {
+        Some(end) if end <= device.sector_count() => Ok(()),
+        _ => Err(PartitionError::PartitionOutOfRange(entry.index)),
+    }
+}
+
+/// Reads a little endian u32 at the given offset
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Reads a little endian u64 at the given offset
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+
+    use super::*;
+
+    /// Number of sectors on the test disk
+    const DISK_SECTORS: usize = 128;
+
+    /// LBA the test GPT entry array starts at
+    const ENTRIES_LBA: u64 = 2;
+
+    /// Number of entries in the test GPT entry array
+    const ENTRY_COUNT: usize = 128;
+
+    /// Block device backed by memory
+    struct MemDisk(RefCell<[u8; DISK_SECTORS * SECTOR_SIZE]>);
+
+    impl MemDisk {
+        /// Constructs a zeroed disk
+        fn new() -> Self {
+            Self(RefCell::new([0; DISK_SECTORS * SECTOR_SIZE]))
+        }
+
+        /// Runs `f` over the bytes of the sector at `lba`
+        fn sector(&self, lba: u64, f: impl FnOnce(&mut [u8])) {
+            let offset = lba as usize * SECTOR_SIZE;
+            f(&mut self.0.borrow_mut()[offset..offset + SECTOR_SIZE]);
+        }
+
+        /// Writes an MBR with the given (type, start, length) primary partitions
+        fn write_mbr(&self, partitions: &[(u8, u32, u32)]) {
+            self.sector(0, |sector| {
+                for (index, &(kind, start, length)) in partitions.iter().enumerate() {
+                    let entry = &mut sector[MBR_ENTRIES_OFFSET + index * MBR_ENTRY_SIZE..];
+                    entry[4] = kind;
+                    entry[8..12].copy_from_slice(&start.to_le_bytes());
+                    entry[12..16].copy_from_slice(&length.to_le_bytes());
+                }
+                sector[SECTOR_SIZE - 2..].copy_from_slice(&MBR_SIGNATURE);
+            });
+        }
+
+        /// Writes a protective MBR and a GPT with the given (type, first, last) partitions, in the first entries
+        fn write_gpt(&self, partitions: &[(Guid, u64, u64)]) {
+            self.write_mbr(&[(MBR_TYPE_GPT_PROTECTIVE, 1, DISK_SECTORS as u32 - 1)]);
+
+            for (index, &(kind, first, last)) in partitions.iter().enumerate() {
+                self.entry(index, |entry| {
+                    entry[..16].copy_from_slice(&kind.0);
+                    entry[32..40].copy_from_slice(&first.to_le_bytes());
+                    entry[40..48].copy_from_slice(&last.to_le_bytes());
+                });
+            }
+
+            self.header(|header| {
+                header[..8].copy_from_slice(GPT_SIGNATURE);
+                header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+                header[12..16].copy_from_slice(&(GPT_MIN_HEADER_SIZE as u32).to_le_bytes());
+                header[24..32].copy_from_slice(&1u64.to_le_bytes());
+                header[72..80].copy_from_slice(&ENTRIES_LBA.to_le_bytes());
+                header[80..84].copy_from_slice(&(ENTRY_COUNT as u32).to_le_bytes());
+                header[84..88].copy_from_slice(&(GPT_MIN_ENTRY_SIZE as u32).to_le_bytes());
+            });
+            self.update_gpt_crcs();
+        }
+
+        /// Runs `f` over the GPT header
+        fn header(&self, f: impl FnOnce(&mut [u8])) {
+            self.sector(1, f);
+        }
+
+        /// Runs `f` over the GPT entry at `index`
+        fn entry(&self, index: usize, f: impl FnOnce(&mut [u8])) {
+            let offset = ENTRIES_LBA as usize * SECTOR_SIZE + index * GPT_MIN_ENTRY_SIZE;
+            f(&mut self.0.borrow_mut()[offset..offset + GPT_MIN_ENTRY_SIZE]);
+        }
+
+        /// Recalculates the entry array and header checksums
+        fn update_gpt_crcs(&self) {
+            let offset = ENTRIES_LBA as usize * SECTOR_SIZE;
+            let entries_crc =
+                crc32(&self.0.borrow()[offset..offset + ENTRY_COUNT * GPT_MIN_ENTRY_SIZE]);
+
+            self.header(|header| {
+                header[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+                header[16..20].fill(0);
+                let crc = crc32(&header[..GPT_MIN_HEADER_SIZE]);
+                header[16..20].copy_from_slice(&crc.to_le_bytes());
+            });
+        }
+    }
+
+    impl BlockDevice for MemDisk {
+        fn sector_count(&self) -> u64 {
+            DISK_SECTORS as u64
+        }
+
+        fn read_sectors(&self, lba: u64, buffer: &mut [u8]) -> Result<(), BlockError> {
+            check_range(lba, buffer.len(), DISK_SECTORS as u64)?;
+            let offset = lba as usize * SECTOR_SIZE;
+            buffer.copy_from_slice(&self.0.borrow()[offset..offset + buffer.len()]);
+            Ok(())
+        }
+
+        fn write_sectors(&self, lba: u64, buffer: &[u8]) -> Result<(), BlockError> {
+            check_range(lba, buffer.len(), DISK_SECTORS as u64)?;
+            let offset = lba as usize * SECTOR_SIZE;
+            self.0.borrow_mut()[offset..offset + buffer.len()].copy_from_slice(buffer);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn missing_mbr() {
+        assert_eq!(
+            scan(&MemDisk::new()).unwrap_err(),
+            PartitionError::MissingMbr
+        );
+    }
+
+    #[test]
+    fn mbr_partitions() {
+        let disk = MemDisk::new();
+        disk.write_mbr(&[(0x0C, 1, 63), (0, 0, 0), (0x83, 64, 64)]);
+
+        let partitions = scan(&disk).unwrap();
+        assert_eq!(
+            partitions.as_slice(),
+            [
+                PartitionEntry {
+                    index: 0,
+                    start_lba: 1,
+                    sector_count: 63,
+                    kind: PartitionKind::Mbr(0x0C),
+                },
+                PartitionEntry {
+                    index: 2,
+                    start_lba: 64,
+                    sector_count: 64,
+                    kind: PartitionKind::Mbr(0x83),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn mbr_partition_past_end() {
+        let disk = MemDisk::new();
+        disk.write_mbr(&[(0x0C, 1, 63), (0x83, 64, 65)]);
+
+        assert_eq!(
+            scan(&disk).unwrap_err(),
+            PartitionError::PartitionOutOfRange(1)
+        );
+    }
+
+    #[test]
+    fn gpt_partitions() {
+        let disk = MemDisk::new();
+        disk.write_gpt(&[
+            (Guid::EFI_SYSTEM, 34, 63),
+            (Guid::UNUSED, 0, 0),
+            (Guid::LINUX_FILESYSTEM, 64, 127),
+        ]);
+
+        let partitions = scan(&disk).unwrap();
+        assert_eq!(
+            partitions.as_slice(),
+            [
+                PartitionEntry {
+                    index: 0,
+                    start_lba: 34,
+                    sector_count: 30,
+                    kind: PartitionKind::Gpt(Guid::EFI_SYSTEM),
+                },
+                PartitionEntry {
+                    index: 2,
+                    start_lba: 64,
+                    sector_count: 64,
+                    kind: PartitionKind::Gpt(Guid::LINUX_FILESYSTEM),
+                },
+            ]
+        );
+
+        let partition = Partition::new(&disk, partitions[1]);
+        let mut sector = [0; SECTOR_SIZE];
+        assert_eq!(partition.read_sectors(63, &mut sector), Ok(()));
+        assert_eq!(
+            partition.read_sectors(64, &mut sector),
+            Err(BlockError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn gpt_bad_header_checksum() {
+        let disk = MemDisk::new();
+        disk.write_gpt(&[(Guid::EFI_SYSTEM, 34, 63)]);
+        disk.header(|header| header[40] ^= 1);
+
+        assert_eq!(scan(&disk).unwrap_err(), PartitionError::BadGptHeader);
+    }
+
+    #[test]
+    fn gpt_corrupt_entry_fails_checksum_first() {
+        let disk = MemDisk::new();
+        disk.write_gpt(&[(Guid::EFI_SYSTEM, 34, 63)]);
+
+        // without the checksum, this would be reported as a partition past the end of the disk
+        disk.entry(0, |entry| entry[47] = 0x80);
+
+        assert_eq!(scan(&disk).unwrap_err(), PartitionError::BadGptEntries);
+    }
+
+    #[test]
+    fn gpt_entry_past_end() {
+        let disk = MemDisk::new();
+        disk.write_gpt(&[(Guid::EFI_SYSTEM, 34, DISK_SECTORS as u64)]);
+
+        assert_eq!(
+            scan(&disk).unwrap_err(),
+            PartitionError::PartitionOutOfRange(0)
+        );
+    }
+
+    #[test]
+    fn gpt_entry_bad_range() {
+        // ending before it starts, and ending at the last LBA so the sector count overflows
+        for (start, end) in [(40, 39), (0, u64::MAX)] {
+            let disk = MemDisk::new();
+            disk.write_gpt(&[(Guid::BASIC_DATA, 34, 63), (Guid::EFI_SYSTEM, start, end)]);
+
+            assert_eq!(
+                scan(&disk).unwrap_err(),
+                PartitionError::PartitionOutOfRange(1),
+                "partition from {start:#X} to {end:#X}"
+            );
+        }
+    }
+
+    #[test]
+    fn gpt_bad_entry_layout() {
+        let cases: [(u64, u32, u32); 5] = [
+            // entry size below the minimum, and not a power of two
+            (ENTRIES_LBA, ENTRY_COUNT as u32, 96),
+            (ENTRIES_LBA, ENTRY_COUNT as u32, 192),
+            // more than 16 KiB of entries
+            (ENTRIES_LBA, ENTRY_COUNT as u32 + 1, 128),
+            // entry array past the end of the disk, and overflowing the LBA
+            (DISK_SECTORS as u64 - 1, ENTRY_COUNT as u32, 128),
+            (u64::MAX, ENTRY_COUNT as u32, 128),
+        ];
+
+        for (lba, count, size) in cases {
+            let disk = MemDisk::new();
+            disk.write_gpt(&[(Guid::EFI_SYSTEM, 34, 63)]);
+            disk.header(|header| {
+                header[72..80].copy_from_slice(&lba.to_le_bytes());
+                header[80..84].copy_from_slice(&count.to_le_bytes());
+                header[84..88].copy_from_slice(&size.to_le_bytes());
+            });
+            disk.update_gpt_crcs();
+
+            assert_eq!(
+                scan(&disk).unwrap_err(),
+                PartitionError::BadGptHeader,
+                "entries at {lba:#X}, {count} of {size} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn gpt_partitions_past_limit_are_ignored() {
+        let disk = MemDisk::new();
+        let partitions: [(Guid, u64, u64); MAX_PARTITIONS + 2] =
+            core::array::from_fn(|index| (Guid::BASIC_DATA, 34 + index as u64, 34 + index as u64));
+        disk.write_gpt(&partitions);
+
+        assert_eq!(scan(&disk).unwrap().len(), MAX_PARTITIONS);
+    }
+}
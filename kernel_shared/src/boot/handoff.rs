@@ -0,0 +1,18 @@
+//! Information passed from the loader to the kernel, alongside the boot information from the bootloader
+
+/// Everything the loader tells the kernel when jumping to it.
+///
+/// The loader passes a pointer to this in `rdi`. It lives in the loader's memory, which is identity mapped until the
+/// kernel reclaims it, so the kernel must copy it before then.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct BootHandoff {
+    /// Physical address of the boot information
+    pub bootinfo_addr: usize,
+    /// Physical address the loader starts at
+    pub loader_start: usize,
+    /// Physical address the loader ends at
+    pub loader_end: usize,
+    /// Virtual address physical memory is mapped at in the kernel's page tables
+    pub phys_mem_offset: usize,
+}
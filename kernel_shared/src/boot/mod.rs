@@ -0,0 +1,98 @@
+//! Boot protocol independent view of the information passed by the bootloader.
+//!
+//! The kernel only consumes boot information through [`BootProtocol`], so supporting another bootloader only
+//! requires a new implementation of the trait, rather than changes to kernel initialisation.
+
+pub mod handoff;
+pub mod multiboot;
+
+use crate::mem::addr::PhysAddr;
+
+/// Information every supported boot protocol can provide
+pub trait BootProtocol {
+    /// Name of the protocol, for logging
+    fn name(&self) -> &'static str;
+
+    /// Command line the kernel was booted with, if any
+    fn command_line(&self) -> Option<&str>;
+
+    /// Regions of physical memory described by the firmware
+    fn memory_regions(&self) -> impl Iterator<Item = MemoryRegion> + '_;
+
+    /// Modules loaded alongside the kernel
+    fn modules(&self) -> impl Iterator<Item = BootModule<'_>> + '_;
+
+    /// Root ACPI tables provided by the firmware, most preferred first
+    fn acpi_roots(&self) -> impl Iterator<Item = AcpiRoot> + '_;
+
+    /// Framebuffer set up by the bootloader, if any
+    fn framebuffer(&self) -> Option<Framebuffer>;
+
+    /// Attempts to find a module with the given name, ignoring any arguments passed after it
+    fn module_by_name(&self, name: &str) -> Option<BootModule<'_>> {
+        self.modules().find(|module| module.name == name)
+    }
+}
+
+/// A contiguous region of physical memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    /// Address of the first byte of the region
+    pub start: PhysAddr,
+    /// Length of the region in bytes
+    pub length: usize,
+    /// What the region can be used for
+    pub kind: MemoryRegionKind,
+}
+
+/// What a region of memory can be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryRegionKind {
+    /// Free for use
+    Usable,
+    /// Contains ACPI tables, usable once they have been parsed
+    AcpiReclaimable,
+    /// Must be preserved across hibernation
+    AcpiNvs,
+    /// Defective memory which must not be used
+    Defective,
+    /// Reserved by firmware or hardware
+    Reserved,
+}
+
+/// A module loaded alongside the kernel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootModule<'a> {
+    /// Physical address of the start of the module
+    pub start: PhysAddr,
+    /// Length of the module in bytes
+    pub length: usize,
+    /// Name of the module, the first word of its command line
+    pub name: &'a str,
+    /// Anything on the module's command line after its name
+    pub arguments: &'a str,
+}
+
+/// Physical address of the root ACPI table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcpiRoot {
+    /// Root system description table, with 32-bit table pointers
+    Rsdt(PhysAddr),
+    /// Extended system description table, with 64-bit table pointers
+    Xsdt(PhysAddr),
+}
+
+/// A linear framebuffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Framebuffer {
+    /// Physical address of the first pixel
+    pub start: PhysAddr,
+    /// Number of bytes between the start of each row
+    pub pitch: usize,
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+    /// Bits per pixel
+    pub bpp: u8,
+}
@@ -0,0 +1,85 @@
+//! [`BootProtocol`] backend for multiboot2
+
+use multiboot::prelude::{BootInfo, MemoryEntryType};
+
+use crate::{
+    boot::{AcpiRoot, BootModule, BootProtocol, Framebuffer, MemoryRegion, MemoryRegionKind},
+    mem::addr::PhysAddr,
+};
+
+/// Framebuffer type for direct RGB colour, the only type usable as a linear framebuffer
+const FRAMEBUFFER_TYPE_RGB: u8 = 1;
+
+impl BootProtocol for BootInfo {
+    fn name(&self) -> &'static str {
+        "multiboot2"
+    }
+
+    fn command_line(&self) -> Option<&str> {
+        self.boot_command_line
+            .as_ref()
+            .and_then(|command_line| command_line.command.to_str().ok())
+    }
+
+    fn memory_regions(&self) -> impl Iterator<Item = MemoryRegion> + '_ {
+        self.memory_map
+            .iter()
+            .flat_map(|memory_map| memory_map.entries)
+            .map(|entry| MemoryRegion {
+                start: PhysAddr::new(entry.base_addr as usize),
+                length: entry.length as usize,
+                kind: match entry.entry_type() {
+                    MemoryEntryType::RAM => MemoryRegionKind::Usable,
+                    MemoryEntryType::ACPI => MemoryRegionKind::AcpiReclaimable,
+                    MemoryEntryType::PRESERVED_ON_HIBERNATION => MemoryRegionKind::AcpiNvs,
+                    MemoryEntryType::DEFECTIVE => MemoryRegionKind::Defective,
+                    MemoryEntryType::RESERVED | MemoryEntryType::UNKNOWN(_) => {
+                        MemoryRegionKind::Reserved
+                    }
+                },
+            })
+    }
+
+    fn modules(&self) -> impl Iterator<Item = BootModule<'_>> + '_ {
+        self.modules
+            .iter()
+            .filter_map(|module| module.as_ref())
+            .map(|module| BootModule {
+                start: PhysAddr::new(module.module_addr as usize),
+                length: module.module_len as usize,
+                name: module.name().unwrap_or_default(),
+                arguments: module.arguments(),
+            })
+    }
+
+    fn acpi_roots(&self) -> impl Iterator<Item = AcpiRoot> + '_ {
+        // prefer the XSDT, as the RSDT can't point to tables above 4 GiB. an RSDPv2 also has an RSDT to fall back on
+        let xsdt = self
+            .rsdpv2
+            .as_ref()
+            .filter(|rsdp| rsdp.xsdt_addr != 0)
+            .map(|rsdp| AcpiRoot::Xsdt(PhysAddr::new(rsdp.xsdt_addr as usize)));
+        let rsdt = self
+            .rsdpv2
+            .as_ref()
+            .map(|rsdp| rsdp.rsdt_addr)
+            .or(self.rsdpv1.as_ref().map(|rsdp| rsdp.rsdt_addr))
+            .filter(|&addr| addr != 0)
+            .map(|addr| AcpiRoot::Rsdt(PhysAddr::new(addr as usize)));
+
+        xsdt.into_iter().chain(rsdt)
+    }
+
+    fn framebuffer(&self) -> Option<Framebuffer> {
+        self.framebuffer_info
+            .as_ref()
+            .filter(|info| info.framebuffer_type == FRAMEBUFFER_TYPE_RGB)
+            .map(|info| Framebuffer {
+                start: PhysAddr::new(info.framebuffer_addr as usize),
+                pitch: info.framebuffer_pitch as usize,
+                width: info.framebuffer_width as usize,
+                height: info.framebuffer_height as usize,
+                bpp: info.framebuffer_bpp,
+            })
+    }
+}
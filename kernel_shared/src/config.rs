@@ -0,0 +1,423 @@
+//! Kernel configuration.
+//!
+//! Compile-time options are plain constants (or cargo features, toggled in `config.toml` at the root of the
+//! repository). Runtime tunables can be changed with `name=value` options on the boot command line, or later with
+//! [`set`].
+
+use core::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use log::LevelFilter;
+
+use crate::fault;
+
+/// Size of kernel heap in bytes
+pub const HEAP_SIZE: usize = 128 * 1024; // 128 KiB
+
+/// Size of kernel stack in bytes
+pub const STACK_SIZE: usize = 128 * 1024; // 128 KiB
+
+/// Whether frames are zeroed when freed, set by the `ZERO_OUT_FREED_MEMORY` feature
+pub const ZERO_OUT_FREED_MEMORY: bool = cfg!(feature = "ZERO_OUT_FREED_MEMORY");
+
+/// Whether the kernel runs the exception self-test after boot, set by the `EXCEPTION_SELFTEST` feature
+pub const EXCEPTION_SELFTEST: bool = cfg!(feature = "EXCEPTION_SELFTEST");
+
+/// Whether page faults are handled on their own interrupt stack, set by the `PAGE_FAULT_IST` feature
+pub const PAGE_FAULT_IST: bool = cfg!(feature = "PAGE_FAULT_IST");
+
+/// Whether log output is coloured by default, set by the `LOG_COLOUR` feature. Disable for dumb terminals.
+pub const LOG_COLOUR: bool = cfg!(feature = "LOG_COLOUR");
+
+/// Whether the kernel moves free frames into a buddy allocator rather than using the loader's bitmap allocator, set
+/// by the `BUDDY_FRAME_ALLOC` feature
+pub const BUDDY_FRAME_ALLOC: bool = cfg!(feature = "BUDDY_FRAME_ALLOC");
+
+/// Number of lines of output kept by the text console for scrolling back through
+pub const CONSOLE_SCROLLBACK_LINES: usize = 200;
+
+/// Size of the persistent log region kept across warm reboots, in bytes
+pub const PSTORE_SIZE: usize = 64 * 1024; // 64 KiB
+
+/// Number of recent log lines kept to be included in crash dumps
+pub const LOG_HISTORY_LINES: usize = 32;
+
+/// Number of log lines kept from before any console is attached, to be written once one is
+pub const EARLY_LOG_LINES: usize = 32;
+
+/// Most verbose log level, used until the command line has been parsed
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Trace;
+
+/// All compile-time options, so they can be dumped. Any new option above needs adding here too.
+pub static COMPILE_TIME_OPTIONS: [CompileTimeOption; 12] = [
+    CompileTimeOption::new("HEAP_SIZE", OptionValue::Size(HEAP_SIZE)),
+    CompileTimeOption::new("STACK_SIZE", OptionValue::Size(STACK_SIZE)),
+    CompileTimeOption::new(
+        "ZERO_OUT_FREED_MEMORY",
+        OptionValue::Flag(ZERO_OUT_FREED_MEMORY),
+    ),
+    CompileTimeOption::new("EXCEPTION_SELFTEST", OptionValue::Flag(EXCEPTION_SELFTEST)),
+    CompileTimeOption::new("PAGE_FAULT_IST", OptionValue::Flag(PAGE_FAULT_IST)),
+    CompileTimeOption::new("LOG_COLOUR", OptionValue::Flag(LOG_COLOUR)),
+    CompileTimeOption::new("BUDDY_FRAME_ALLOC", OptionValue::Flag(BUDDY_FRAME_ALLOC)),
+    CompileTimeOption::new(
+        "CONSOLE_SCROLLBACK_LINES",
+        OptionValue::Count(CONSOLE_SCROLLBACK_LINES),
+    ),
+    CompileTimeOption::new("PSTORE_SIZE", OptionValue::Size(PSTORE_SIZE)),
+    CompileTimeOption::new("LOG_HISTORY_LINES", OptionValue::Count(LOG_HISTORY_LINES)),
+    CompileTimeOption::new("EARLY_LOG_LINES", OptionValue::Count(EARLY_LOG_LINES)),
+    CompileTimeOption::new(
+        "DEFAULT_LOG_LEVEL",
+        OptionValue::LogLevel(DEFAULT_LOG_LEVEL),
+    ),
+];
+
+/// Maximum level of messages to log
+pub static LOG_LEVEL: Tunable = Tunable::new(
+    "log_level",
+    "maximum level of messages to log (off, error, warn, info, debug, trace)",
+    DEFAULT_LOG_LEVEL as usize,
+    TunableKind::LogLevel,
+);
+
+/// Interval between timer interrupts in milliseconds
+pub static TIMER_INTERVAL_MS: Tunable = Tunable::new(
+    "timer_interval_ms",
+    "interval between timer interrupts in milliseconds",
+    500,
+    TunableKind::Integer,
+);
+
+/// Number of times each exception is logged in full before logs are summarised
+pub static EXCEPTION_LOG_LIMIT: Tunable = Tunable::new(
+    "exception_log_limit",
+    "number of times each exception is logged in full before logs are summarised",
+    8,
+    TunableKind::Integer,
+);
+
+/// Whether log lines are coloured by level with ANSI escape codes
+pub static LOG_COLOURED: Tunable = Tunable::new(
+    "log_colour",
+    "whether log lines are coloured by level with ANSI escape codes",
+    LOG_COLOUR as usize,
+    TunableKind::Boolean,
+);
+
+/// Whether log lines are prefixed with their level
+pub static LOG_SHOW_LEVEL: Tunable = Tunable::new(
+    "log_show_level",
+    "whether log lines are prefixed with their level",
+    true as usize,
+    TunableKind::Boolean,
+);
+
+/// Whether log lines are prefixed with the module they came from
+pub static LOG_SHOW_TARGET: Tunable = Tunable::new(
+    "log_show_target",
+    "whether log lines are prefixed with the module they came from",
+    true as usize,
+    TunableKind::Boolean,
+);
+
+/// Least severe level of messages written to the serial console
+pub static CONSOLE_SERIAL_LEVEL: Tunable = Tunable::new(
+    "console_serial_level",
+    "least severe level of messages written to serial (off, error, warn, info, debug, trace)",
+    LevelFilter::Trace as usize,
+    TunableKind::LogLevel,
+);
+
+/// Least severe level of messages written to the EGA text console
+pub static CONSOLE_EGA_LEVEL: Tunable = Tunable::new(
+    "console_ega_level",
+    "least severe level of messages written to the EGA console (off, error, warn, info, debug, trace)",
+    LevelFilter::Info as usize,
+    TunableKind::LogLevel,
+);
+
+/// Whether failed `kassert!`s panic, rather than logging and returning an error
+pub static ASSERT_PANIC: Tunable = Tunable::new(
+    "assert_panic",
+    "whether failed kernel assertions panic instead of logging and returning an error",
+    cfg!(debug_assertions) as usize,
+    TunableKind::Boolean,
+);
+
+/// Number of times the serial line status is polled before a byte is dropped, or 0 to wait forever
+pub static SERIAL_TIMEOUT_SPINS: Tunable = Tunable::new(
+    "serial_timeout_spins",
+    "times the serial port is polled before a byte is dropped (0 waits forever)",
+    100_000,
+    TunableKind::Integer,
+);
+
+/// What the kernel does once a panic has been reported
+pub static PANIC_ACTION: Tunable = Tunable::new(
+    "panic_action",
+    "what to do after a panic (halt, reboot, poweroff)",
+    0,
+    TunableKind::Choice(&["halt", "reboot", "poweroff"]),
+);
+
+/// All runtime tunables
+pub static TUNABLES: [&Tunable; 14] = [
+    &LOG_LEVEL,
+    &TIMER_INTERVAL_MS,
+    &EXCEPTION_LOG_LIMIT,
+    &LOG_COLOURED,
+    &LOG_SHOW_LEVEL,
+    &LOG_SHOW_TARGET,
+    &CONSOLE_SERIAL_LEVEL,
+    &CONSOLE_EGA_LEVEL,
+    &ASSERT_PANIC,
+    &SERIAL_TIMEOUT_SPINS,
+    &PANIC_ACTION,
+    &fault::FRAME_ALLOC.interval,
+    &fault::ACPI.interval,
+    &fault::APIC.interval,
+];
+
+/// Value of a compile-time option, which decides how it is displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionValue {
+    /// Size in bytes, displayed in hex
+    Size(usize),
+    /// Number of items
+    Count(usize),
+    /// Boolean flag, usually set by a cargo feature
+    Flag(bool),
+    /// Log level filter
+    LogLevel(LevelFilter),
+}
+
+/// A constant fixed when the kernel is built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompileTimeOption {
+    /// Name of the constant
+    name: &'static str,
+    /// Value of the constant
+    value: OptionValue,
+}
+
+impl CompileTimeOption {
+    /// Constructs an option with the given name and value
+    const fn new(name: &'static str, value: OptionValue) -> Self {
+        Self { name, value }
+    }
+
+    /// Returns the name of the option
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the value of the option
+    pub fn value(&self) -> OptionValue {
+        self.value
+    }
+}
+
+impl Display for CompileTimeOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.value {
+            OptionValue::Size(size) => write!(f, "{}={size:#X}", self.name),
+            OptionValue::Count(count) => write!(f, "{}={count}", self.name),
+            OptionValue::Flag(flag) => write!(f, "{}={flag}", self.name),
+            OptionValue::LogLevel(level) => write!(f, "{}={level}", self.name),
+        }
+    }
+}
+
+/// An error encountered while changing a tunable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// No tunable has the given name
+    UnknownTunable,
+    /// Value could not be parsed for the tunable
+    InvalidValue(&'static str),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownTunable => write!(f, "unknown tunable"),
+            Self::InvalidValue(name) => write!(f, "invalid value for tunable `{name}`"),
+        }
+    }
+}
+
+/// How the value of a tunable is parsed and displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TunableKind {
+    /// Unsigned integer
+    Integer,
+    /// Log level filter
+    LogLevel,
+    /// Boolean flag, stored as 0 or 1
+    Boolean,
+    /// One of the given names, stored as its index
+    Choice(&'static [&'static str]),
+}
+
+/// A value which can be changed at runtime
+pub struct Tunable {
+    /// Name used on the command line
+    name: &'static str,
+    /// Human readable description
+    description: &'static str,
+    /// Value used if not overridden
+    default: usize,
+    /// How the value is parsed
+    kind: TunableKind,
+    /// Current value
+    value: AtomicUsize,
+}
+
+impl Tunable {
+    /// Constructs a tunable with the given default value
+    pub const fn new(
+        name: &'static str,
+        description: &'static str,
+        default: usize,
+        kind: TunableKind,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            default,
+            kind,
+            value: AtomicUsize::new(default),
+        }
+    }
+
+    /// Returns the name of the tunable
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the description of the tunable
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// Returns the current value
+    pub fn get(&self) -> usize {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Returns the current value as a log level filter
+    pub fn level_filter(&self) -> LevelFilter {
+        LevelFilter::iter()
+            .nth(self.get())
+            .unwrap_or(DEFAULT_LOG_LEVEL)
+    }
+
+    /// Returns the current value as a boolean flag
+    pub fn enabled(&self) -> bool {
+        self.get() != 0
+    }
+
+    /// Returns the name of the current value, if the tunable is a choice
+    pub fn choice(&self) -> Option<&'static str> {
+        match self.kind {
+            TunableKind::Choice(choices) => choices.get(self.get()).copied(),
+            _ => None,
+        }
+    }
+
+    /// Parses and sets a new value
+    pub fn set(&self, value: &str) -> Result<(), ConfigError> {
+        let value = match self.kind {
+            TunableKind::Integer => value.parse().ok(),
+            TunableKind::LogLevel => LevelFilter::from_str(value)
+                .ok()
+                .map(|level| level as usize),
+            TunableKind::Boolean => match value {
+                "true" | "on" | "1" => Some(1),
+                "false" | "off" | "0" => Some(0),
+                _ => None,
+            },
+            TunableKind::Choice(choices) => choices.iter().position(|&choice| choice == value),
+        }
+        .ok_or(ConfigError::InvalidValue(self.name))?;
+
+        self.value.store(value, Ordering::Relaxed);
+
+        // log level is only checked by the `log` crate, so it needs telling
+        if core::ptr::eq(self, &LOG_LEVEL) {
+            log::set_max_level(log_level());
+        }
+
+        Ok(())
+    }
+
+    /// Restores the default value
+    pub fn reset(&self) {
+        self.value.store(self.default, Ordering::Relaxed);
+    }
+}
+
+impl Display for Tunable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.kind {
+            TunableKind::Integer => write!(f, "{}={}", self.name, self.get()),
+            TunableKind::LogLevel => write!(f, "{}={}", self.name, self.level_filter()),
+            TunableKind::Boolean => write!(f, "{}={}", self.name, self.enabled()),
+            TunableKind::Choice(_) => {
+                write!(f, "{}={}", self.name, self.choice().unwrap_or("?"))
+            }
+        }
+    }
+}
+
+/// Returns the current maximum log level
+pub fn log_level() -> LevelFilter {
+    LOG_LEVEL.level_filter()
+}
+
+/// Finds the tunable with the given name
+pub fn find(name: &str) -> Option<&'static Tunable> {
+    TUNABLES
+        .iter()
+        .find(|tunable| tunable.name == name)
+        .copied()
+}
+
+/// Sets the tunable with the given name
+pub fn set(name: &str, value: &str) -> Result<(), ConfigError> {
+    find(name).ok_or(ConfigError::UnknownTunable)?.set(value)
+}
+
+/// Applies any `name=value` options on the command line which match a tunable.
+///
+/// Other options are ignored, as they may be meant for the loader.
+pub fn parse_command_line(command_line: &str) {
+    for option in command_line.split_ascii_whitespace() {
+        let Some((name, value)) = option.split_once('=') else {
+            continue;
+        };
+
+        if let Some(tunable) = find(name)
+            && let Err(err) = tunable.set(value)
+        {
+            log::warn!("ignoring command line option `{option}`: {err}");
+        }
+    }
+}
+
+/// Logs the value of every compile-time option and tunable
+pub fn dump() {
+    log::info!("compile-time configuration:");
+    for option in &COMPILE_TIME_OPTIONS {
+        log::info!("\t{option}");
+    }
+
+    log::info!("runtime tunables:");
+    for tunable in TUNABLES {
+        log::info!("\t{tunable} ({})", tunable.description);
+    }
+}
@@ -0,0 +1,60 @@
+//! Deterministic fault injection, making chosen subsystems fail so their error paths can be exercised.
+//!
+//! Each fault point is a tunable, so it can be enabled from the boot command line with `fault_<point>=N`, which
+//! makes every Nth call through that point fail. 0 disables the fault point, which is the default.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::{Tunable, TunableKind};
+
+/// Frame allocation returns no frame
+pub static FRAME_ALLOC: FaultPoint = FaultPoint::new(
+    "fault_frame_alloc",
+    "fail every Nth frame allocation, 0 to disable",
+);
+
+/// ACPI tables are treated as missing, forcing the legacy hardware fallbacks
+pub static ACPI: FaultPoint = FaultPoint::new(
+    "fault_acpi",
+    "fail every Nth attempt to find ACPI tables, 0 to disable",
+);
+
+/// APIC setup fails, forcing the 8259 PIC fallback
+pub static APIC: FaultPoint = FaultPoint::new(
+    "fault_apic",
+    "fail every Nth attempt to set up the APICs, 0 to disable",
+);
+
+/// A place in the code which can be made to fail on demand
+pub struct FaultPoint {
+    /// Interval between failures, 0 if disabled
+    pub(crate) interval: Tunable,
+    /// Number of times the fault point has been reached
+    calls: AtomicUsize,
+}
+
+impl FaultPoint {
+    /// Constructs a disabled fault point
+    const fn new(name: &'static str, description: &'static str) -> Self {
+        Self {
+            interval: Tunable::new(name, description, 0, TunableKind::Integer),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records a call through the fault point, returning whether the caller should fail
+    pub fn should_fail(&self) -> bool {
+        let interval = self.interval.get();
+        if interval == 0 {
+            return false;
+        }
+
+        let call = self.calls.fetch_add(1, Ordering::Relaxed) + 1;
+        if !call.is_multiple_of(interval) {
+            return false;
+        }
+
+        log::warn!("injecting fault `{}` on call {call}", self.interval.name());
+        true
+    }
+}
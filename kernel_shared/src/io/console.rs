@@ -0,0 +1,213 @@
+//! Text console with an off-screen scroll-back buffer, drawn to whichever text backend is active
+
+use core::fmt::Write;
+
+/// Number of columns in every console line
+pub const COLUMNS: usize = 80;
+
+/// Screen which can display a grid of character cells
+pub trait TextBackend {
+    /// Returns the number of rows on screen
+    fn height(&self) -> usize;
+
+    /// Writes a cell, where the low byte is the character and the high byte is the colour attribute
+    fn write_cell(&mut self, row: usize, column: usize, cell: u16);
+
+    /// Moves the hardware cursor, or hides it if `position` is None
+    fn set_cursor(&mut self, position: Option<(usize, usize)>);
+}
+
+/// Console keeping the last `LINES` lines of output, so they can be scrolled back through
+pub struct Console<B: TextBackend, const LINES: usize> {
+    /// Screen being drawn to
+    backend: B,
+    /// Ring of lines, where `lines[head]` is the oldest
+    lines: [[u16; COLUMNS]; LINES],
+    /// Index of oldest line
+    head: usize,
+    /// Number of lines written, including the current one
+    count: usize,
+    /// Column of the next character in the current line
+    column: usize,
+    /// Colour attribute for newly written characters
+    colour: u8,
+    /// Number of lines the view is scrolled back from the bottom
+    scroll: usize,
+    /// Whether the hardware cursor is shown
+    cursor_visible: bool,
+}
+
+impl<B: TextBackend, const LINES: usize> Console<B, LINES> {
+    /// Blank cell in the default colour
+    const BLANK: u16 = 0x0720;
+
+    /// Constructs an empty console drawing to the given backend
+    pub const fn new(backend: B) -> Self {
+        assert!(LINES > 0, "console needs at least one line");
+
+        Self {
+            backend,
+            lines: [[Self::BLANK; COLUMNS]; LINES],
+            head: 0,
+            count: 1,
+            column: 0,
+            colour: 0x07,
+            scroll: 0,
+            cursor_visible: false,
+        }
+    }
+
+    /// Returns the colour attribute used for newly written characters
+    pub fn colour(&self) -> u8 {
+        self.colour
+    }
+
+    /// Sets the colour attribute used for any following writes
+    pub fn set_colour(&mut self, colour: u8) -> &mut Self {
+        self.colour = colour;
+
+        self
+    }
+
+    /// Shows or hides the hardware cursor
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+        self.update_cursor();
+    }
+
+    /// Writes a single byte, handling newlines and wrapping
+    pub fn write_byte(&mut self, byte: u8) {
+        match byte {
+            b'\n' => self.new_line(),
+            byte => {
+                if self.column >= COLUMNS {
+                    self.new_line();
+                }
+
+                // only printable ascii can be displayed, so substitute anything else with a block
+                let byte = match byte {
+                    0x20..=0x7E => byte,
+                    _ => 0xFE,
+                };
+
+                let cell = ((self.colour as u16) << 8) | byte as u16;
+                let (line, column) = (self.count - 1, self.column);
+                self.line_mut(line)[column] = cell;
+                self.column += 1;
+
+                // only draw if the current line is on screen
+                if self.scroll == 0 {
+                    let row = self.visible_rows() - 1;
+                    self.backend.write_cell(row, column, cell);
+                    self.update_cursor();
+                }
+            }
+        }
+    }
+
+    /// Scrolls the view back by up to `lines` lines
+    pub fn scroll_up(&mut self, lines: usize) {
+        let max_scroll = self.count.saturating_sub(self.backend.height());
+        self.scroll = (self.scroll + lines).min(max_scroll);
+        self.redraw();
+    }
+
+    /// Scrolls the view forward by up to `lines` lines
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+        self.redraw();
+    }
+
+    /// Scrolls the view back by a screen
+    pub fn page_up(&mut self) {
+        self.scroll_up(self.backend.height());
+    }
+
+    /// Scrolls the view forward by a screen
+    pub fn page_down(&mut self) {
+        self.scroll_down(self.backend.height());
+    }
+
+    /// Scrolls back to the most recent output
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_down(self.scroll);
+    }
+
+    /// Redraws every row on screen from the scroll-back buffer
+    pub fn redraw(&mut self) {
+        let height = self.backend.height();
+
+        // index of the line drawn on the bottom row of the screen
+        let bottom = self.count - 1 - self.scroll;
+
+        for row in 0..height {
+            // lines are drawn from the top of the screen, as the buffer may not fill it
+            let line = (bottom + 1 + row).checked_sub(height.min(self.count));
+
+            for column in 0..COLUMNS {
+                let cell = match line {
+                    Some(line) if line <= bottom => self.line(line)[column],
+                    _ => Self::BLANK,
+                };
+                self.backend.write_cell(row, column, cell);
+            }
+        }
+
+        self.update_cursor();
+    }
+
+    /// Moves to the start of the next line, discarding the oldest line if the buffer is full
+    fn new_line(&mut self) {
+        self.column = 0;
+
+        if self.count < LINES {
+            self.count += 1;
+        } else {
+            self.head = (self.head + 1) % LINES;
+        }
+
+        let last = self.count - 1;
+        *self.line_mut(last) = [Self::BLANK; COLUMNS];
+
+        // keep the same output on screen if scrolled back
+        if self.scroll > 0 {
+            let max_scroll = self.count.saturating_sub(self.backend.height());
+            self.scroll = (self.scroll + 1).min(max_scroll);
+        }
+
+        self.redraw();
+    }
+
+    /// Returns the number of rows on screen that are in use
+    fn visible_rows(&self) -> usize {
+        self.backend.height().min(self.count)
+    }
+
+    /// Moves the hardware cursor to the end of the output, hiding it if that is off screen
+    fn update_cursor(&mut self) {
+        let position = (self.cursor_visible && self.scroll == 0)
+            .then(|| (self.visible_rows() - 1, self.column.min(COLUMNS - 1)));
+
+        self.backend.set_cursor(position);
+    }
+
+    /// Returns the line at `index`, counting from the oldest
+    fn line(&self, index: usize) -> &[u16; COLUMNS] {
+        &self.lines[(self.head + index) % LINES]
+    }
+
+    /// Returns the line at `index` mutably, counting from the oldest
+    fn line_mut(&mut self, index: usize) -> &mut [u16; COLUMNS] {
+        &mut self.lines[(self.head + index) % LINES]
+    }
+}
+
+impl<B: TextBackend, const LINES: usize> Write for Console<B, LINES> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+//! EGA text mode backend for [`Console`](crate::io::console::Console)
+
+use crate::{
+    io::{
+        console::{COLUMNS, TextBackend},
+        port::Port,
+    },
+    mem::addr::PhysAddr,
+};
+
+/// Physical address of the EGA text buffer
+const BUFFER_ADDR: usize = 0xB8000;
+
+/// Number of rows on screen
+const HEIGHT: usize = 25;
+
+/// CRT controller register selecting the cursor start scanline, and whether the cursor is disabled
+const CURSOR_START: u8 = 0x0A;
+
+/// CRT controller register holding the high byte of the cursor position
+const CURSOR_LOCATION_HIGH: u8 = 0x0E;
+
+/// CRT controller register holding the low byte of the cursor position
+const CURSOR_LOCATION_LOW: u8 = 0x0F;
+
+/// 80x25 EGA text buffer, accessed through the physical memory mapping so it stays valid before and after the page
+/// table switch
+pub struct EgaBuffer {
+    /// CRT controller index port
+    crtc_index: Port<u8>,
+    /// CRT controller data port
+    crtc_data: Port<u8>,
+}
+
+impl EgaBuffer {
+    /// Constructs a handle to the EGA text buffer
+    pub const fn new() -> Self {
+        Self {
+            crtc_index: Port::new(0x3D4),
+            crtc_data: Port::new(0x3D5),
+        }
+    }
+
+    /// Writes a value to a CRT controller register
+    fn write_crtc(&mut self, register: u8, value: u8) {
+        unsafe {
+            self.crtc_index.write(register);
+            self.crtc_data.write(value);
+        }
+    }
+}
+
+impl Default for EgaBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextBackend for EgaBuffer {
+    fn height(&self) -> usize {
+        HEIGHT
+    }
+
+    fn write_cell(&mut self, row: usize, column: usize, cell: u16) {
+        let buffer = PhysAddr::new(BUFFER_ADDR).as_hhdm_ptr::<u16>();
+
+        unsafe { core::ptr::write_volatile(buffer.add(row * COLUMNS + column), cell) };
+    }
+
+    fn set_cursor(&mut self, position: Option<(usize, usize)>) {
+        match position {
+            Some((row, column)) => {
+                let location = (row * COLUMNS + column) as u16;
+
+                self.write_crtc(CURSOR_LOCATION_HIGH, (location >> 8) as u8);
+                self.write_crtc(CURSOR_LOCATION_LOW, location as u8);
+                // scanlines 14-15, so an underline cursor
+                self.write_crtc(CURSOR_START, 14);
+            }
+            // bit 5 disables the cursor
+            None => self.write_crtc(CURSOR_START, 0x20),
+        }
+    }
+}
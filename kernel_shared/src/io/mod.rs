@@ -1,4 +1,8 @@
 //! Code relating to I/O operations
 
+pub mod console;
+pub mod ega;
 pub mod port;
 pub mod serial;
+pub mod serial_demux;
+pub mod sinks;
@@ -1,8 +1,12 @@
-//! Module for sending data across a serial connection
+//! Module for sending and receiving data across a serial connection
+//!
+//! Sending waits for the transmit buffer to empty, but only for `serial_timeout_spins` polls of the line status.
+//! If the port doesn't become ready in time the byte is dropped and counted, and the port is marked as stalled so
+//! later bytes are dropped straight away until it recovers. This way a wedged or missing UART can never hang logging.
 
-use core::fmt::Write;
+use core::fmt::{Display, Formatter, Write};
 
-use crate::io::port::Port;
+use crate::{config, io::port::Port};
 
 /// Module containing constants for serial ports
 #[allow(missing_docs)]
@@ -11,33 +15,65 @@ pub mod ports {
 
     use crate::io::serial::SerialPort;
 
-    pub static COM1: Mutex<SerialPort<0x3F8>> = Mutex::new(SerialPort);
-    pub static COM2: Mutex<SerialPort<0x2F8>> = Mutex::new(SerialPort);
-    pub static COM3: Mutex<SerialPort<0x3E8>> = Mutex::new(SerialPort);
-    pub static COM4: Mutex<SerialPort<0x2E8>> = Mutex::new(SerialPort);
-    pub static COM5: Mutex<SerialPort<0x5F8>> = Mutex::new(SerialPort);
-    pub static COM6: Mutex<SerialPort<0x4F8>> = Mutex::new(SerialPort);
-    pub static COM7: Mutex<SerialPort<0x5E8>> = Mutex::new(SerialPort);
-    pub static COM8: Mutex<SerialPort<0x4E8>> = Mutex::new(SerialPort);
+    pub static COM1: Mutex<SerialPort<0x3F8>> = Mutex::new(SerialPort::new());
+    pub static COM2: Mutex<SerialPort<0x2F8>> = Mutex::new(SerialPort::new());
+    pub static COM3: Mutex<SerialPort<0x3E8>> = Mutex::new(SerialPort::new());
+    pub static COM4: Mutex<SerialPort<0x2E8>> = Mutex::new(SerialPort::new());
+    pub static COM5: Mutex<SerialPort<0x5F8>> = Mutex::new(SerialPort::new());
+    pub static COM6: Mutex<SerialPort<0x4F8>> = Mutex::new(SerialPort::new());
+    pub static COM7: Mutex<SerialPort<0x5E8>> = Mutex::new(SerialPort::new());
+    pub static COM8: Mutex<SerialPort<0x4E8>> = Mutex::new(SerialPort::new());
 }
 
 pub use ports::*;
 
 use crate::x86::without_interrupts;
 
-/// Waits until `self` contains the OUTPUT_EMPTY flag
-macro_rules! wait_for_output_empty {
-    ($self:expr) => {
-        while !$self.line_status().contains(LineStatusFlags::OUTPUT_EMPTY) {
-            core::hint::spin_loop()
+/// An error encountered while sending over a serial port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialError {
+    /// Transmit buffer is still full
+    Busy,
+    /// Transmit buffer did not empty in time, so the byte was dropped
+    TimedOut,
+}
+
+impl Display for SerialError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Busy => write!(f, "serial port busy"),
+            Self::TimedOut => write!(f, "timed out waiting for serial port"),
         }
-    };
+    }
 }
 
 /// Wrapper type for a port with serial functionality
-pub struct SerialPort<const PORT: u16>;
+pub struct SerialPort<const PORT: u16> {
+    /// Number of bytes dropped because the port did not become ready in time
+    dropped: usize,
+    /// Whether the last wait timed out, in which case bytes are dropped without waiting until the port recovers
+    stalled: bool,
+}
 
 impl<const PORT: u16> SerialPort<PORT> {
+    /// Constructs a handle to the port, which must be initialised with [`Self::init`] before use
+    pub const fn new() -> Self {
+        Self {
+            dropped: 0,
+            stalled: false,
+        }
+    }
+
+    /// Returns the number of bytes dropped because the port did not become ready in time
+    pub const fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Returns whether the port is stalled, meaning bytes are being dropped without waiting
+    pub const fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
     /// Initialises the port as a serial port.
     ///
     /// ## Safety
@@ -70,31 +106,104 @@ impl<const PORT: u16> SerialPort<PORT> {
         }
     }
 
-    /// Sends a byte down the serial port
+    /// Sends a byte down the serial port, waiting up to the configured timeout for each byte written.
+    ///
+    /// Backspace is sent as three bytes, to also clear the character on the terminal.
     ///
     /// ## Safety
     /// The caller must guarantee the port is a valid serial port which will not cause
     /// undefined behaviour when written to or read from.
-    pub unsafe fn send(&mut self, data: u8) {
+    pub unsafe fn send(&mut self, data: u8) -> Result<(), SerialError> {
         unsafe {
             match data {
                 8 | 0x7F => {
                     // special code to handle backspace
-                    wait_for_output_empty!(self);
-                    self.port_data().write(8);
+                    self.send_raw(8)?;
+                    self.send_raw(b' ')?;
+                    self.send_raw(8)
+                }
+                // otherwise just send data
+                _ => self.send_raw(data),
+            }
+        }
+    }
+
+    /// Sends a byte down the serial port only if it can be done without waiting.
+    ///
+    /// Unlike [`Self::send`], the byte is sent as-is and isn't counted as dropped if the port is busy.
+    ///
+    /// ## Safety
+    /// The caller must guarantee the port is a valid serial port which will not cause
+    /// undefined behaviour when written to or read from.
+    pub unsafe fn try_send(&mut self, data: u8) -> Result<(), SerialError> {
+        unsafe {
+            if !self.line_status().contains(LineStatusFlags::OUTPUT_EMPTY) {
+                return Err(SerialError::Busy);
+            }
 
-                    wait_for_output_empty!(self);
-                    self.port_data().write(b' ');
+            self.port_data().write(data);
+        }
 
-                    wait_for_output_empty!(self);
-                    self.port_data().write(8);
-                }
-                _ => {
-                    // otherwise just send data
-                    wait_for_output_empty!(self);
-                    self.port_data().write(data);
-                }
+        Ok(())
+    }
+
+    /// Reads a received byte, if one is waiting
+    ///
+    /// ## Safety
+    /// The caller must guarantee the port is a valid serial port which will not cause
+    /// undefined behaviour when written to or read from.
+    pub unsafe fn try_receive(&mut self) -> Option<u8> {
+        unsafe {
+            self.line_status()
+                .contains(LineStatusFlags::INPUT_FULL)
+                .then(|| self.port_data().read())
+        }
+    }
+
+    /// Waits for the transmit buffer to empty then writes a byte, dropping it if that takes too long
+    ///
+    /// ## Safety
+    /// The caller must guarantee the port is a valid serial port which will not cause
+    /// undefined behaviour when written to or read from.
+    unsafe fn send_raw(&mut self, data: u8) -> Result<(), SerialError> {
+        unsafe {
+            if !self.wait_for_output_empty() {
+                self.dropped += 1;
+                return Err(SerialError::TimedOut);
             }
+
+            self.port_data().write(data);
+        }
+
+        Ok(())
+    }
+
+    /// Polls until the transmit buffer is empty, returning false if it didn't empty in time
+    ///
+    /// ## Safety
+    /// The caller must guarantee the port is a valid serial port which will not cause
+    /// undefined behaviour when written to or read from.
+    unsafe fn wait_for_output_empty(&mut self) -> bool {
+        // a stalled port is only polled once, so a dead UART doesn't cost the full timeout for every byte
+        let limit = match self.stalled {
+            true => 1,
+            false => config::SERIAL_TIMEOUT_SPINS.get(),
+        };
+
+        let mut spins = 0;
+        loop {
+            if unsafe { self.line_status() }.contains(LineStatusFlags::OUTPUT_EMPTY) {
+                self.stalled = false;
+                return true;
+            }
+
+            spins += 1;
+            if limit != 0 && spins >= limit {
+                self.stalled = true;
+                return false;
+            }
+
+            core::hint::spin_loop()
         }
     }
 
@@ -138,11 +247,18 @@ impl<const PORT: u16> SerialPort<PORT> {
     }
 }
 
+impl<const PORT: u16> Default for SerialPort<PORT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<const PORT: u16> Write for SerialPort<PORT> {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        // dropped bytes are counted rather than reported, so a wedged port never fails a log line
         for byte in s.bytes() {
             unsafe {
-                self.send(byte);
+                let _ = self.send(byte);
             }
         }
 
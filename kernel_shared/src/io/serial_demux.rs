@@ -0,0 +1,427 @@
+//! Splits input from a serial port between the GDB remote protocol and everything else, so one port can be used
+//! interactively and for debugging without rebuilding.
+//!
+//! GDB packets look like `$<data>#<two hex digit checksum>`. Input from a `$` onwards is held back until it either
+//! completes a packet, which goes to [`GDB_INPUT`], or can't be one, in which case it goes to [`CONSOLE_INPUT`] as
+//! it was typed. Acknowledgements (`+` and `-`) and interrupt requests (Ctrl-C) are single bytes which could just as
+//! well be typed, so they only go to GDB while it is attached, meaning a packet has been seen since it last
+//! detached.
+//!
+//! The checksum isn't verified here, as the stub needs to reply to bad packets anyway.
+
+use core::fmt::{Display, Formatter};
+use std::{
+    collections::{array_vec::ArrayVec, ring_buffer::MpscRingBuffer},
+    mutex::Mutex,
+};
+
+use crate::io::serial::SerialPort;
+
+/// Longest packet which is recognised, including the `$`, `#` and checksum
+pub const MAX_PACKET_LENGTH: usize = 512;
+
+/// Complete GDB packets and the acknowledgements and interrupt requests sent between them, for the GDB stub
+pub static GDB_INPUT: MpscRingBuffer<u8, 1024> = MpscRingBuffer::new();
+
+/// Everything else, for the console
+pub static CONSOLE_INPUT: MpscRingBuffer<u8, 256> = MpscRingBuffer::new();
+
+/// Demultiplexer for COM1, fed by its receive interrupt
+static COM1_DEMUX: Mutex<SerialDemux> = Mutex::new(SerialDemux::new());
+
+/// Byte starting a packet
+const PACKET_START: u8 = b'$';
+/// Byte separating a packet's data from its checksum
+const CHECKSUM_START: u8 = b'#';
+/// Byte GDB sends to interrupt the target
+const INTERRUPT: u8 = 0x03;
+
+/// Where the demultiplexer is within the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Not in a packet
+    Console,
+    /// Between the `$` and `#` of a possible packet
+    Data,
+    /// Reading the checksum of a possible packet, having read the given number of digits
+    Checksum(u8),
+}
+
+/// Bytes received by a [`SerialDemux`], and how many were dropped because a queue was full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DemuxStats {
+    /// Packets sent to the GDB stub
+    pub packets: usize,
+    /// Bytes sent to the console
+    pub console_bytes: usize,
+    /// Bytes dropped because their queue was full
+    pub dropped: usize,
+}
+
+impl Display for DemuxStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} packets, {} console bytes, {} dropped",
+            self.packets, self.console_bytes, self.dropped
+        )
+    }
+}
+
+/// Splits a stream of bytes between [`GDB_INPUT`] and [`CONSOLE_INPUT`]
+#[derive(Debug)]
+pub struct SerialDemux {
+    /// Where the demultiplexer is within the input
+    state: State,
+    /// Possible packet being read
+    pending: ArrayVec<u8, MAX_PACKET_LENGTH>,
+    /// Whether GDB is attached, so ambiguous single bytes belong to it
+    gdb_attached: bool,
+    /// Whether GDB has asked to detach, so only the acknowledgement of the reply still belongs to it
+    detaching: bool,
+    /// Bytes routed so far
+    stats: DemuxStats,
+}
+
+impl SerialDemux {
+    /// Constructs a demultiplexer, with GDB not attached
+    pub const fn new() -> Self {
+        Self {
+            state: State::Console,
+            pending: ArrayVec::new(),
+            gdb_attached: false,
+            detaching: false,
+            stats: DemuxStats {
+                packets: 0,
+                console_bytes: 0,
+                dropped: 0,
+            },
+        }
+    }
+
+    /// Returns whether GDB is attached
+    pub const fn gdb_attached(&self) -> bool {
+        self.gdb_attached
+    }
+
+    /// Returns the bytes routed so far
+    pub const fn stats(&self) -> DemuxStats {
+        self.stats
+    }
+
+    /// Routes the next byte of input
+    pub fn push(&mut self, byte: u8) {
+        match self.state {
+            State::Console => match byte {
+                PACKET_START => {
+                    self.state = State::Data;
+                    let _ = self.pending.push(byte);
+                }
+                b'+' | b'-' | INTERRUPT if self.gdb_attached => {
+                    self.send_gdb(&[byte]);
+
+                    if self.detaching && byte != INTERRUPT {
+                        self.gdb_attached = false;
+                        self.detaching = false;
+                    }
+                }
+                _ => self.send_console(byte),
+            },
+            State::Data => match byte {
+                // a new packet before this one finished, so this one never was
+                PACKET_START => {
+                    self.flush_to_console();
+                    self.push(byte);
+                }
+                // packets only contain line breaks in binary data, which is only sent once GDB is attached
+                b'\r' | b'\n' if !self.gdb_attached => {
+                    self.flush_to_console();
+                    self.send_console(byte);
+                }
+                _ => {
+                    if self.push_pending(byte) && byte == CHECKSUM_START {
+                        self.state = State::Checksum(0);
+                    }
+                }
+            },
+            State::Checksum(digits) if byte.is_ascii_hexdigit() => {
+                if !self.push_pending(byte) {
+                    return;
+                }
+
+                if digits == 1 {
+                    self.finish_packet();
+                } else {
+                    self.state = State::Checksum(digits + 1);
+                }
+            }
+            // not a checksum, so not a packet
+            State::Checksum(_) => {
+                self.flush_to_console();
+                self.push(byte);
+            }
+        }
+    }
+
+    /// Adds a byte to the possible packet, returning whether it was added. If the packet would get too long it is
+    /// given up on, and everything including the byte goes to the console.
+    fn push_pending(&mut self, byte: u8) -> bool {
+        if self.pending.push(byte).is_err() {
+            self.flush_to_console();
+            self.send_console(byte);
+            return false;
+        }
+
+        true
+    }
+
+    /// Sends a complete packet to the GDB stub
+    fn finish_packet(&mut self) {
+        self.gdb_attached = true;
+
+        // detaching (`D`) and killing (`k`) end the session once GDB acknowledges the reply
+        self.detaching = matches!(self.pending.as_slice().get(1), Some(b'D' | b'k'));
+
+        let packet = core::mem::take(&mut self.pending);
+        self.send_gdb(packet.as_slice());
+        self.stats.packets += 1;
+        self.state = State::Console;
+    }
+
+    /// Hands everything held back to the console, as it wasn't a packet after all
+    fn flush_to_console(&mut self) {
+        let pending = core::mem::take(&mut self.pending);
+        for &byte in pending.as_slice() {
+            self.send_console(byte);
+        }
+
+        self.state = State::Console;
+    }
+
+    /// Queues bytes for the GDB stub, dropping all of them if they don't fit so it never sees part of a packet
+    fn send_gdb(&mut self, bytes: &[u8]) {
+        if GDB_INPUT.capacity() - GDB_INPUT.len() < bytes.len() {
+            self.stats.dropped += bytes.len();
+            return;
+        }
+
+        for &byte in bytes {
+            if GDB_INPUT.push(byte).is_err() {
+                self.stats.dropped += 1;
+            }
+        }
+    }
+
+    /// Queues a byte for the console
+    fn send_console(&mut self, byte: u8) {
+        match CONSOLE_INPUT.push(byte) {
+            Ok(()) => self.stats.console_bytes += 1,
+            Err(_) => self.stats.dropped += 1,
+        }
+    }
+}
+
+impl Default for SerialDemux {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Routes every byte waiting in COM1's receive buffer. Called from its receive interrupt.
+pub fn receive_com1() {
+    // the port only holds its drop count, so a fresh one can be used for receiving without taking the COM1 lock,
+    // which the interrupted code may hold
+    let mut port = SerialPort::<0x3F8>::new();
+    let mut demux = COM1_DEMUX.lock();
+
+    while let Some(byte) = unsafe { port.try_receive() } {
+        demux.push(byte);
+    }
+}
+
+/// Returns the bytes routed from COM1 so far
+pub fn com1_stats() -> DemuxStats {
+    crate::x86::without_interrupts(|| COM1_DEMUX.lock().stats())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+    use std::mutex::MutexGuard;
+
+    use super::*;
+
+    /// Held by each test, as they all share the input queues
+    static QUEUES: Mutex<()> = Mutex::new(());
+
+    /// Takes the queue lock and empties both queues
+    fn lock_queues() -> MutexGuard<'static, ()> {
+        let guard = QUEUES.lock();
+        drain(&GDB_INPUT);
+        drain(&CONSOLE_INPUT);
+
+        guard
+    }
+
+    /// Returns everything in a queue, emptying it
+    fn drain<const N: usize>(queue: &MpscRingBuffer<u8, N>) -> Vec<u8> {
+        core::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    /// Routes every byte
+    fn push_all(demux: &mut SerialDemux, bytes: &[u8]) {
+        for &byte in bytes {
+            demux.push(byte);
+        }
+    }
+
+    /// Returns a demultiplexer which GDB is attached to
+    fn attached() -> SerialDemux {
+        let mut demux = SerialDemux::new();
+        push_all(&mut demux, b"$?#3f");
+        drain(&GDB_INPUT);
+
+        demux
+    }
+
+    /// Returns a packet of the given total length, with a checksum which is not checked
+    fn packet_of_length(length: usize) -> Vec<u8> {
+        let mut packet = Vec::from(*b"$");
+        packet.resize(length - 3, b'a');
+        packet.extend_from_slice(b"#00");
+
+        packet
+    }
+
+    #[test]
+    fn complete_packet() {
+        let _queues = lock_queues();
+        let mut demux = SerialDemux::new();
+
+        push_all(&mut demux, b"ab$qSupported#37cd");
+        assert_eq!(drain(&GDB_INPUT), b"$qSupported#37");
+        assert_eq!(drain(&CONSOLE_INPUT), b"abcd");
+        assert!(demux.gdb_attached());
+        assert_eq!(
+            demux.stats(),
+            DemuxStats {
+                packets: 1,
+                console_bytes: 4,
+                dropped: 0
+            }
+        );
+    }
+
+    #[test]
+    fn restarted_packet() {
+        let _queues = lock_queues();
+        let mut demux = SerialDemux::new();
+
+        // a `$` within the data or checksum starts again, and what came before was typed
+        push_all(&mut demux, b"$abc$g#1$m0,4#fd");
+        assert_eq!(drain(&CONSOLE_INPUT), b"$abc$g#1");
+        assert_eq!(drain(&GDB_INPUT), b"$m0,4#fd");
+    }
+
+    #[test]
+    fn bad_checksum_digit() {
+        let _queues = lock_queues();
+        let mut demux = SerialDemux::new();
+
+        push_all(&mut demux, b"$g#x1");
+        assert_eq!(drain(&CONSOLE_INPUT), b"$g#x1");
+        assert!(drain(&GDB_INPUT).is_empty());
+        assert!(!demux.gdb_attached());
+    }
+
+    #[test]
+    fn line_breaks() {
+        let _queues = lock_queues();
+
+        // before GDB attaches, a line break means the input was typed
+        let mut demux = SerialDemux::new();
+        push_all(&mut demux, b"$ls\r\n");
+        assert_eq!(drain(&CONSOLE_INPUT), b"$ls\r\n");
+        assert!(!demux.gdb_attached());
+
+        // after it attaches, binary data may contain them
+        let mut demux = attached();
+        push_all(&mut demux, b"$X0,2:\r\n#ab");
+        assert_eq!(drain(&GDB_INPUT), b"$X0,2:\r\n#ab");
+        assert!(drain(&CONSOLE_INPUT).is_empty());
+    }
+
+    #[test]
+    fn longest_packet() {
+        let _queues = lock_queues();
+        let mut demux = SerialDemux::new();
+
+        let packet = packet_of_length(MAX_PACKET_LENGTH);
+        push_all(&mut demux, &packet);
+        assert_eq!(drain(&GDB_INPUT), packet);
+        assert!(drain(&CONSOLE_INPUT).is_empty());
+    }
+
+    #[test]
+    fn overflow() {
+        let _queues = lock_queues();
+
+        // overflowing on each byte of the checksum, as well as in the data
+        for length in MAX_PACKET_LENGTH + 1..=MAX_PACKET_LENGTH + 3 {
+            let mut demux = SerialDemux::new();
+
+            // the console queue is shorter than a packet, so the rest is dropped
+            let packet = packet_of_length(length);
+            push_all(&mut demux, &packet);
+            assert_eq!(drain(&CONSOLE_INPUT), packet[..CONSOLE_INPUT.capacity()]);
+            assert!(drain(&GDB_INPUT).is_empty());
+            assert!(!demux.gdb_attached());
+
+            let stats = demux.stats();
+            assert_eq!(stats.packets, 0);
+            assert_eq!(stats.console_bytes + stats.dropped, length);
+
+            // and the next packet is still recognised
+            push_all(&mut demux, b"$g#67");
+            assert_eq!(drain(&GDB_INPUT), b"$g#67");
+        }
+    }
+
+    #[test]
+    fn single_bytes() {
+        let _queues = lock_queues();
+
+        // acknowledgements and interrupts could just as well be typed until GDB attaches
+        let mut demux = SerialDemux::new();
+        push_all(&mut demux, b"+-\x03");
+        assert_eq!(drain(&CONSOLE_INPUT), b"+-\x03");
+
+        let mut demux = attached();
+        push_all(&mut demux, b"+-\x03");
+        assert_eq!(drain(&GDB_INPUT), b"+-\x03");
+        assert!(drain(&CONSOLE_INPUT).is_empty());
+    }
+
+    #[test]
+    fn detach() {
+        let _queues = lock_queues();
+
+        for packet in [b"$D#44", b"$k#6b"] {
+            let mut demux = attached();
+            push_all(&mut demux, packet);
+            assert_eq!(drain(&GDB_INPUT), packet);
+
+            // an interrupt doesn't acknowledge the reply, so GDB stays attached until the acknowledgement
+            push_all(&mut demux, b"\x03");
+            assert!(demux.gdb_attached());
+            push_all(&mut demux, b"+");
+            assert_eq!(drain(&GDB_INPUT), b"\x03+");
+            assert!(!demux.gdb_attached());
+
+            push_all(&mut demux, b"+");
+            assert_eq!(drain(&CONSOLE_INPUT), b"+");
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! Multiplexing log output to every attached console, each with its own minimum level.
+//!
+//! Lines logged before any sink is attached are kept, and written to the first sink once it is attached.
+//!
+//! Logging is reached from the panic handler, so never waits on a lock: if one is held, the record goes straight to
+//! serial, or is dropped for a console it can't be drawn to.
+//!
+//! Each sink's level is read from a tunable, so it can be set on the command line (e.g.
+//! `console_ega_level=warn`) or changed at runtime. Sinks can also be attached and detached at runtime, e.g. to stop
+//! drawing to EGA once a framebuffer console takes over.
+
+use core::fmt::Write;
+use std::{collections::ArrayVec, mutex::Mutex};
+
+use log::Record;
+
+use crate::{
+    config::Tunable,
+    io::{
+        console::{Console, TextBackend},
+        serial::{COM1, SerialPort},
+    },
+    logger::{EarlyLog, LogFormat},
+    x86::without_interrupts,
+};
+
+/// Maximum number of sinks which can be attached at once
+const MAX_SINKS: usize = 4;
+
+/// Every console log output is sent to
+pub static CONSOLES: ConsoleManager = ConsoleManager::new();
+
+/// The serial console on COM1
+pub static SERIAL_SINK: SerialSink = SerialSink;
+
+/// Somewhere log lines can be written to
+pub trait ConsoleSink: Sync {
+    /// Name used to detach the sink
+    fn name(&self) -> &'static str;
+
+    /// Writes a single log record as a line. This is called while panicking, so must not wait on a lock held by
+    /// whatever panicked.
+    fn write_record(&self, record: &Record, format: LogFormat);
+}
+
+/// A sink and the tunable holding its minimum level
+#[derive(Clone, Copy)]
+struct Attached {
+    /// Sink to write to
+    sink: &'static dyn ConsoleSink,
+    /// Least severe level written to the sink
+    level: &'static Tunable,
+}
+
+/// Set of consoles log output is copied to
+pub struct ConsoleManager {
+    /// Attached sinks
+    sinks: Mutex<ArrayVec<Attached, MAX_SINKS>>,
+    /// Lines logged before any sink was attached
+    early: Mutex<EarlyLog>,
+}
+
+impl ConsoleManager {
+    /// Constructs a manager with no sinks
+    pub const fn new() -> Self {
+        Self {
+            sinks: Mutex::new(ArrayVec::new()),
+            early: Mutex::new(EarlyLog::new()),
+        }
+    }
+
+    /// Starts writing records at or above the level in `level` to the sink, returning false if too many sinks are
+    /// attached. Attaching a sink which is already attached only updates its level.
+    pub fn attach(&self, sink: &'static dyn ConsoleSink, level: &'static Tunable) -> bool {
+        without_interrupts(|| {
+            let mut sinks = self.sinks.lock();
+
+            if let Some(attached) = sinks
+                .iter_mut()
+                .find(|attached| attached.sink.name() == sink.name())
+            {
+                attached.level = level;
+                return true;
+            }
+
+            if sinks.push(Attached { sink, level }).is_err() {
+                return false;
+            }
+
+            // the first sink gets everything logged before it was attached
+            if sinks.len() == 1 {
+                let format = LogFormat::from_config();
+                self.early.lock().drain(|record| {
+                    if record.level() <= level.level_filter() {
+                        sink.write_record(record, format);
+                    }
+                });
+            }
+
+            true
+        })
+    }
+
+    /// Stops writing to the sink with the given name, returning whether it was attached
+    pub fn detach(&self, name: &str) -> bool {
+        without_interrupts(|| {
+            let mut sinks = self.sinks.lock();
+            let len = sinks.len();
+
+            sinks.retain(|attached| attached.sink.name() != name);
+            sinks.len() != len
+        })
+    }
+
+    /// Returns whether a sink with the given name is attached
+    pub fn is_attached(&self, name: &str) -> bool {
+        without_interrupts(|| {
+            self.sinks
+                .lock()
+                .iter()
+                .any(|attached| attached.sink.name() == name)
+        })
+    }
+
+    /// Writes a record to every sink whose level allows it, or keeps it for later if no sinks are attached.
+    ///
+    /// If the sinks are locked, such as by a panic while writing to them, the record goes straight to serial instead.
+    pub fn log(&self, record: &Record, format: LogFormat) {
+        without_interrupts(|| {
+            let Some(sinks) = self.sinks.try_lock() else {
+                write_serial(record, format);
+                return;
+            };
+
+            if sinks.is_empty() {
+                match self.early.try_lock() {
+                    Some(mut early) => early.push(record),
+                    None => write_serial(record, format),
+                }
+                return;
+            }
+
+            for attached in sinks.iter() {
+                if record.level() <= attached.level.level_filter() {
+                    attached.sink.write_record(record, format);
+                }
+            }
+        });
+    }
+}
+
+impl Default for ConsoleManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sink writing to COM1
+pub struct SerialSink;
+
+impl ConsoleSink for SerialSink {
+    fn name(&self) -> &'static str {
+        "serial"
+    }
+
+    fn write_record(&self, record: &Record, format: LogFormat) {
+        write_serial(record, format);
+    }
+}
+
+/// Writes a record to COM1 as a line. If COM1 is locked, whoever holds it was interrupted by a panic or is stuck, so
+/// the record is written around the lock rather than waiting, and may be interleaved with their output.
+fn write_serial(record: &Record, format: LogFormat) {
+    without_interrupts(|| match COM1.try_lock() {
+        Some(mut port) => {
+            let _ = write!(port, "{}\n\r", format.format(record));
+        }
+        None => {
+            let _ = write!(SerialPort::<0x3F8>::new(), "{}\n\r", format.format(record));
+        }
+    });
+}
+
+/// Sink drawing to a text console, colouring each line by level
+pub struct TextConsoleSink<B: TextBackend, const LINES: usize> {
+    /// Name used to detach the sink
+    name: &'static str,
+    /// Console drawn to
+    console: Mutex<Console<B, LINES>>,
+}
+
+impl<B: TextBackend, const LINES: usize> TextConsoleSink<B, LINES> {
+    /// Constructs a sink drawing to the given console
+    pub const fn new(name: &'static str, console: Console<B, LINES>) -> Self {
+        Self {
+            name,
+            console: Mutex::new(console),
+        }
+    }
+
+    /// Returns the console being drawn to
+    pub fn console(&self) -> &Mutex<Console<B, LINES>> {
+        &self.console
+    }
+}
+
+/// Returns the text colour attribute used for a level
+const fn level_attribute(level: log::Level) -> u8 {
+    match level {
+        log::Level::Error => 0x0C,
+        log::Level::Warn => 0x0E,
+        log::Level::Info => 0x07,
+        log::Level::Debug => 0x03,
+        log::Level::Trace => 0x08,
+    }
+}
+
+impl<B: TextBackend + Send, const LINES: usize> ConsoleSink for TextConsoleSink<B, LINES> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn write_record(&self, record: &Record, format: LogFormat) {
+        // the console may be locked by whatever panicked, and the record still reaches serial
+        let Some(mut console) = self.console.try_lock() else {
+            return;
+        };
+        let colour_before = console.colour();
+
+        // text consoles don't understand ANSI escapes, so colour with attributes instead
+        console.set_colour(level_attribute(record.level()));
+        let format = LogFormat {
+            colour: false,
+            ..format
+        };
+        let _ = write!(console, "{}", format.format(record));
+        console.write_byte(b'\n');
+
+        console.set_colour(colour_before);
+    }
+}
@@ -8,13 +8,17 @@
 #![feature(iter_intersperse)]
 #![feature(abi_x86_interrupt)]
 
+pub mod assert;
+pub mod block;
+pub mod boot;
+pub mod config;
+pub mod fault;
 pub mod io;
 pub mod logger;
 pub mod mem;
+pub mod nvram;
+pub mod pstore;
+pub mod random;
+pub mod time;
+pub mod timer;
 pub mod x86;
-
-/// Size of kernel heap in bytes
-pub const HEAP_SIZE: usize = 128 * 1024; // 128 KiB
-
-/// Size of kernel stack in bytes
-pub const STACK_SIZE: usize = 128 * 1024; // 128 KiB
@@ -1,25 +1,81 @@
-//! Simple logger that just writes to serial
+//! Simple logger that writes to every attached console, keeping the most recent lines for crash dumps
 
-use log::{LevelFilter, Log, SetLoggerError};
+use core::fmt::{Display, Formatter, Write};
+use std::{
+    init_once::InitOnce,
+    mutex::{Mutex, MutexGuard},
+};
 
-use crate::serial_println;
+use log::{Level, LevelFilter, Log, Record, SetLoggerError};
+
+use crate::{config, io::sinks::CONSOLES, pstore::PersistentLog, x86::without_interrupts};
+
+/// Maximum length of a line kept in the log history, longer lines are truncated
+const HISTORY_LINE_LENGTH: usize = 128;
 
 /// Logger
 pub struct Logger {
     /// Level filter
     level: LevelFilter,
+    /// Most recently logged lines
+    history: Mutex<LogHistory>,
+    /// Log kept across warm reboots, once one has been attached
+    persistent: Mutex<Option<&'static mut PersistentLog>>,
+    /// Guards against the logger being initialised twice
+    initialised: InitOnce,
 }
 
 impl Logger {
     /// Constructs logger with given filter level
     pub const fn new(level: LevelFilter) -> Self {
-        Self { level }
+        Self {
+            level,
+            history: Mutex::new(LogHistory::new()),
+            persistent: Mutex::new(None),
+            initialised: InitOnce::new(),
+        }
     }
 
-    /// Initialises logger
+    /// Installs the logger. Lines are kept until a console is attached to [`CONSOLES`], then written to it.
+    ///
+    /// Only the first call does anything, so this is safe to call as early as possible and again later. Later calls
+    /// return `Ok` even if the first call failed.
     pub fn init(&'static self) -> Result<(), SetLoggerError> {
-        log::set_max_level(self.level);
-        log::set_logger(self)
+        let init = || {
+            log::set_max_level(self.level);
+            log::set_logger(self)
+        };
+
+        match self.initialised.call_once(init) {
+            Some((result, _)) => result,
+            None => Ok(()),
+        }
+    }
+
+    /// Returns whether [`Self::init`] has been called
+    pub fn is_initialised(&self) -> bool {
+        self.initialised.is_initialised()
+    }
+
+    /// Starts copying every logged line into the given persistent log
+    pub fn attach_persistent(&self, log: &'static mut PersistentLog) {
+        without_interrupts(|| *self.persistent.lock() = Some(log));
+    }
+
+    /// Flushes the persistent log to memory, if attached. Skipped if it is currently being written to.
+    pub fn flush_persistent(&self) {
+        if let Some(persistent) = self.persistent.try_lock()
+            && let Some(log) = persistent.as_ref()
+        {
+            log.flush();
+        }
+    }
+
+    /// Returns the most recently logged lines, or `None` if they are currently being written to.
+    ///
+    /// This never blocks, so is safe to call while panicking.
+    pub fn history(&self) -> Option<MutexGuard<'_, LogHistory>> {
+        self.history.try_lock()
     }
 }
 
@@ -30,14 +86,274 @@ impl Log for Logger {
 
     fn log(&self, record: &log::Record) {
         if self.enabled(record.metadata()) {
-            serial_println!(
-                "{:>5} | {:>40} | {}",
-                record.level(),
-                record.module_path().unwrap(),
-                record.args()
-            );
+            CONSOLES.log(record, LogFormat::from_config());
+
+            // either may be locked by whatever panicked, and waiting would hang before the crash record is written,
+            // so the line is left out of whichever is busy
+            without_interrupts(|| {
+                if let Some(mut history) = self.history.try_lock() {
+                    history.push(record);
+                }
+
+                if let Some(mut persistent) = self.persistent.try_lock()
+                    && let Some(persistent) = persistent.as_mut()
+                {
+                    let format = LogFormat {
+                        colour: false,
+                        ..LogFormat::from_config()
+                    };
+                    let _ = writeln!(persistent, "{}", format.format(record));
+                }
+            });
         }
     }
 
     fn flush(&self) {}
 }
+
+/// How log lines are written, to every sink
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogFormat {
+    /// Whether the level is coloured with ANSI escape codes
+    pub colour: bool,
+    /// Whether lines are prefixed with their level
+    pub show_level: bool,
+    /// Whether lines are prefixed with the module they came from
+    pub show_target: bool,
+}
+
+impl LogFormat {
+    /// Returns the format selected by the runtime tunables
+    pub fn from_config() -> Self {
+        Self {
+            colour: config::LOG_COLOURED.enabled(),
+            show_level: config::LOG_SHOW_LEVEL.enabled(),
+            show_target: config::LOG_SHOW_TARGET.enabled(),
+        }
+    }
+
+    /// Returns a displayable log line for the record in this format
+    pub fn format<'a>(self, record: &'a log::Record<'a>) -> FormattedRecord<'a> {
+        FormattedRecord {
+            format: self,
+            record,
+        }
+    }
+}
+
+/// Returns the ANSI escape code used to colour a level
+const fn level_colour(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => "\x1b[31m",
+        log::Level::Warn => "\x1b[33m",
+        log::Level::Info => "\x1b[32m",
+        log::Level::Debug => "\x1b[34m",
+        log::Level::Trace => "\x1b[90m",
+    }
+}
+
+/// ANSI escape code resetting colours
+const RESET_COLOUR: &str = "\x1b[0m";
+
+/// A log record formatted as a single line
+pub struct FormattedRecord<'a> {
+    /// Format to use
+    format: LogFormat,
+    /// Record to format
+    record: &'a log::Record<'a>,
+}
+
+impl Display for FormattedRecord<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let level = self.record.level();
+
+        if self.format.show_level {
+            if self.format.colour {
+                write!(f, "{}{level:>5}{RESET_COLOUR} | ", level_colour(level))?;
+            } else {
+                write!(f, "{level:>5} | ")?;
+            }
+        }
+
+        if self.format.show_target {
+            write!(f, "{:>40} | ", self.record.target())?;
+        }
+
+        write!(f, "{}", self.record.args())
+    }
+}
+
+/// A single line of log history
+#[derive(Clone, Copy)]
+struct HistoryLine {
+    /// Bytes of the line, only the first `length` of which are valid
+    bytes: [u8; HISTORY_LINE_LENGTH],
+    /// Number of bytes written
+    length: usize,
+}
+
+impl HistoryLine {
+    /// Constructs an empty line
+    const fn new() -> Self {
+        Self {
+            bytes: [0; HISTORY_LINE_LENGTH],
+            length: 0,
+        }
+    }
+
+    /// Returns the contents of the line
+    fn as_str(&self) -> &str {
+        // truncation happens on a character boundary, so this is always valid
+        core::str::from_utf8(&self.bytes[..self.length]).unwrap_or("")
+    }
+}
+
+impl Write for HistoryLine {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut end = s.len().min(HISTORY_LINE_LENGTH - self.length);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        self.bytes[self.length..self.length + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.length += end;
+
+        Ok(())
+    }
+}
+
+/// Ring buffer of the most recently logged lines
+pub struct LogHistory {
+    /// Stored lines
+    lines: [HistoryLine; config::LOG_HISTORY_LINES],
+    /// Index the next line will be written to
+    next: usize,
+    /// Number of lines stored
+    count: usize,
+}
+
+impl LogHistory {
+    /// Constructs an empty history
+    const fn new() -> Self {
+        Self {
+            lines: [HistoryLine::new(); config::LOG_HISTORY_LINES],
+            next: 0,
+            count: 0,
+        }
+    }
+
+    /// Records a log message, overwriting the oldest line if full
+    fn push(&mut self, record: &log::Record) {
+        let line = &mut self.lines[self.next];
+        *line = HistoryLine::new();
+        let _ = write!(
+            line,
+            "{} {} {}",
+            record.level(),
+            record.module_path().unwrap_or("?"),
+            record.args()
+        );
+
+        self.next = (self.next + 1) % config::LOG_HISTORY_LINES;
+        self.count = (self.count + 1).min(config::LOG_HISTORY_LINES);
+    }
+
+    /// Iterates over stored lines, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        let start =
+            (self.next + config::LOG_HISTORY_LINES - self.count) % config::LOG_HISTORY_LINES;
+
+        (0..self.count).map(move |i| self.lines[(start + i) % config::LOG_HISTORY_LINES].as_str())
+    }
+}
+
+/// A line logged before any console was attached
+#[derive(Clone, Copy)]
+struct EarlyLine {
+    /// Level of the record
+    level: Level,
+    /// Length of the target at the start of `line`
+    target_length: usize,
+    /// Target of the record followed by its message
+    line: HistoryLine,
+}
+
+/// Ring buffer of lines logged before any console was attached, so they can be written once one is
+pub(crate) struct EarlyLog {
+    /// Stored lines
+    lines: [EarlyLine; config::EARLY_LOG_LINES],
+    /// Index the next line will be written to
+    next: usize,
+    /// Number of lines stored
+    count: usize,
+    /// Number of lines overwritten before they could be written
+    dropped: usize,
+}
+
+impl EarlyLog {
+    /// Constructs an empty log
+    pub(crate) const fn new() -> Self {
+        Self {
+            lines: [EarlyLine {
+                level: Level::Trace,
+                target_length: 0,
+                line: HistoryLine::new(),
+            }; config::EARLY_LOG_LINES],
+            next: 0,
+            count: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Records a log message, overwriting the oldest line if full
+    pub(crate) fn push(&mut self, record: &Record) {
+        let mut line = HistoryLine::new();
+        let _ = write!(line, "{}", record.target());
+        let target_length = line.length;
+        let _ = write!(line, "{}", record.args());
+
+        self.lines[self.next] = EarlyLine {
+            level: record.level(),
+            target_length,
+            line,
+        };
+
+        if self.count == config::EARLY_LOG_LINES {
+            self.dropped += 1;
+        }
+        self.next = (self.next + 1) % config::EARLY_LOG_LINES;
+        self.count = (self.count + 1).min(config::EARLY_LOG_LINES);
+    }
+
+    /// Passes every stored line to `write` as a record, oldest first, then empties the log
+    pub(crate) fn drain(&mut self, mut write: impl FnMut(&Record)) {
+        if self.dropped > 0 {
+            write(
+                &Record::builder()
+                    .level(Level::Warn)
+                    .target(module_path!())
+                    .args(format_args!(
+                        "{} early log lines were dropped",
+                        self.dropped
+                    ))
+                    .build(),
+            );
+        }
+
+        let start = (self.next + config::EARLY_LOG_LINES - self.count) % config::EARLY_LOG_LINES;
+        for i in 0..self.count {
+            let early = &self.lines[(start + i) % config::EARLY_LOG_LINES];
+            let (target, message) = early.line.as_str().split_at(early.target_length);
+
+            write(
+                &Record::builder()
+                    .level(early.level)
+                    .target(target)
+                    .args(format_args!("{message}"))
+                    .build(),
+            );
+        }
+
+        *self = Self::new();
+    }
+}
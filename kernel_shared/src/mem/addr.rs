@@ -0,0 +1,171 @@
+//! Typed physical and virtual addresses, so the two can't be mixed up
+
+use core::{
+    fmt::{Debug, Display, Formatter, LowerHex, UpperHex},
+    ops::{Add, AddAssign, Sub, SubAssign},
+};
+use std::{align_down, align_up, is_aligned};
+
+use crate::mem::phys_mem_offset;
+
+/// A physical memory address
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct PhysAddr(usize);
+
+impl PhysAddr {
+    /// Constructs a physical address
+    pub const fn new(addr: usize) -> Self {
+        Self(addr)
+    }
+
+    /// Returns the raw address
+    pub const fn as_usize(self) -> usize {
+        self.0
+    }
+
+    /// Returns the virtual address this physical address is mapped to within the physical memory mapping
+    pub fn to_virt(self) -> VirtAddr {
+        VirtAddr::new(self.0 + phys_mem_offset())
+    }
+
+    /// Returns a pointer to this address within the physical memory mapping
+    pub fn as_hhdm_ptr<T>(self) -> *mut T {
+        self.to_virt().as_mut_ptr()
+    }
+
+    /// Aligns downwards to the given alignment
+    pub fn align_down(self, align: usize) -> Self {
+        Self(align_down(self.0, align))
+    }
+
+    /// Aligns upwards to the given alignment
+    pub fn align_up(self, align: usize) -> Self {
+        Self(align_up(self.0, align))
+    }
+
+    /// Checks if the address has the given alignment
+    pub fn is_aligned(self, align: usize) -> bool {
+        is_aligned(self.0, align)
+    }
+}
+
+/// A virtual memory address, which is always canonical
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct VirtAddr(usize);
+
+impl VirtAddr {
+    /// Constructs a virtual address, panicking if it is not canonical
+    pub fn new(addr: usize) -> Self {
+        assert!(
+            !(0x0000_8000_0000_0000..0xFFFF_8000_0000_0000).contains(&addr),
+            "invalid address: 0x{addr:x}"
+        );
+
+        Self(addr)
+    }
+
+    /// Constructs a virtual address from a pointer
+    pub fn from_ptr<T: ?Sized>(ptr: *const T) -> Self {
+        Self::new(ptr as *const () as usize)
+    }
+
+    /// Returns the raw address
+    pub const fn as_usize(self) -> usize {
+        self.0
+    }
+
+    /// Returns the address as a pointer
+    pub const fn as_ptr<T>(self) -> *const T {
+        self.0 as *const T
+    }
+
+    /// Returns the address as a mutable pointer
+    pub const fn as_mut_ptr<T>(self) -> *mut T {
+        self.0 as *mut T
+    }
+
+    /// Aligns downwards to the given alignment
+    pub fn align_down(self, align: usize) -> Self {
+        Self::new(align_down(self.0, align))
+    }
+
+    /// Aligns upwards to the given alignment
+    pub fn align_up(self, align: usize) -> Self {
+        Self::new(align_up(self.0, align))
+    }
+
+    /// Checks if the address has the given alignment
+    pub fn is_aligned(self, align: usize) -> bool {
+        is_aligned(self.0, align)
+    }
+}
+
+/// Implements arithmetic and formatting shared by both address types
+macro_rules! impl_addr_ops {
+    ($addr:ident) => {
+        impl Add<usize> for $addr {
+            type Output = Self;
+
+            fn add(self, rhs: usize) -> Self::Output {
+                Self::new(self.0 + rhs)
+            }
+        }
+
+        impl AddAssign<usize> for $addr {
+            fn add_assign(&mut self, rhs: usize) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl Sub<usize> for $addr {
+            type Output = Self;
+
+            fn sub(self, rhs: usize) -> Self::Output {
+                Self::new(self.0 - rhs)
+            }
+        }
+
+        impl SubAssign<usize> for $addr {
+            fn sub_assign(&mut self, rhs: usize) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl Sub<$addr> for $addr {
+            type Output = usize;
+
+            fn sub(self, rhs: $addr) -> Self::Output {
+                self.0 - rhs.0
+            }
+        }
+
+        impl Debug for $addr {
+            fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+                write!(f, concat!(stringify!($addr), "({:#X})"), self.0)
+            }
+        }
+
+        impl Display for $addr {
+            fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+                write!(f, "{:#X}", self.0)
+            }
+        }
+
+        impl LowerHex for $addr {
+            fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+                LowerHex::fmt(&self.0, f)
+            }
+        }
+
+        impl UpperHex for $addr {
+            fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+                UpperHex::fmt(&self.0, f)
+            }
+        }
+    };
+}
+
+impl_addr_ops!(PhysAddr);
+impl_addr_ops!(VirtAddr);
@@ -2,6 +2,8 @@
 
 use core::iter::Step;
 
+use crate::mem::addr::PhysAddr;
+
 /// Size of a frame in bytes
 pub const FRAME_SIZE: usize = 4096;
 
@@ -14,15 +16,15 @@ pub struct Frame {
 
 impl Frame {
     /// Returns the frame which contains a given address
-    pub fn containing_address(address: usize) -> Self {
+    pub fn containing_address(address: PhysAddr) -> Self {
         Self {
-            number: address / FRAME_SIZE,
+            number: address.as_usize() / FRAME_SIZE,
         }
     }
 
     /// Returns the start address of the frame
-    pub fn start_address(&self) -> usize {
-        self.number * FRAME_SIZE
+    pub fn start_address(&self) -> PhysAddr {
+        PhysAddr::new(self.number * FRAME_SIZE)
     }
 }
 
@@ -1,17 +1,24 @@
 //! Code for allocating physical memory using a bitmap, where one frame = one bit
 
+use core::ops::Range;
+use std::bitmap::Bitmap;
+
 use multiboot::prelude::{MemoryEntryType, MemoryMapEntry};
 
-use crate::mem::{
-    frame::{FRAME_SIZE, Frame},
-    frame_alloc::FrameAllocator,
+use crate::{
+    fault,
+    mem::{
+        addr::PhysAddr,
+        frame::{FRAME_SIZE, Frame},
+        frame_alloc::{FrameAllocator, low_mem},
+    },
 };
 
 /// Stores information about frames within a single region of usable memory
 #[repr(C)]
 struct BitmapRegion {
     /// Base memory address of region
-    region_base_addr: usize,
+    region_base_addr: PhysAddr,
     /// Length of region in bytes
     region_size: usize,
     /// Number of entries within bitmap
@@ -21,21 +28,31 @@ struct BitmapRegion {
 }
 
 impl BitmapRegion {
-    /// Value to shift by in order to get offset into array
-    const SHIFT_VALUE: u32 = usize::BITS.ilog2();
+    /// Returns the bitmap tracking this region's frames
+    fn bitmap(&self) -> &Bitmap {
+        // safety: `bitmap_length` words were written directly after the header when constructing the region
+        Bitmap::from_slice(unsafe {
+            core::slice::from_raw_parts(self.bitmap.as_ptr(), self.bitmap_length)
+        })
+    }
 
-    /// Value to and with in order to get offset into usize
-    const AND_MASK: usize = (1 << Self::SHIFT_VALUE) - 1;
+    /// Returns the bitmap tracking this region's frames, mutably
+    fn bitmap_mut(&mut self) -> &mut Bitmap {
+        // safety: `bitmap_length` words were written directly after the header when constructing the region
+        Bitmap::from_slice_mut(unsafe {
+            core::slice::from_raw_parts_mut(self.bitmap.as_mut_ptr(), self.bitmap_length)
+        })
+    }
 
     /// Gets the frame at a given index
     fn get_frame(&self, index: usize) -> Option<Frame> {
         // make sure we're actually in range
-        if index > self.bitmap_length * 64 {
+        if index >= self.bitmap().len() {
             return None;
         }
 
         Some(Frame::containing_address(
-            index * FRAME_SIZE + self.region_base_addr,
+            self.region_base_addr + index * FRAME_SIZE,
         ))
     }
 
@@ -53,65 +70,13 @@ impl BitmapRegion {
         Some((frame_addr - self.region_base_addr) / 4096)
     }
 
-    /// Finds the index of the first unset bit, returning None if all set
-    fn find_first_unset(&self) -> Option<usize> {
-        for i in 0..self.bitmap_length {
-            let entry = unsafe { *self.bitmap.get_unchecked(i) };
-
-            // if all bits set, skip
-            if entry == !0 {
-                continue;
-            }
-
-            // otherwise at least one bit is unset
-            let unset_bit_idx = entry.trailing_ones() as usize;
-
-            return Some((i << Self::SHIFT_VALUE) | unset_bit_idx);
-        }
-        None
-    }
-
-    /// Sets a given bit to 1
-    fn set_bit(&mut self, index: usize) {
-        // make sure we're actually in range
-        if index > self.bitmap_length * 64 {
-            return;
-        }
-
-        // find indices
-        let array_index = index >> Self::SHIFT_VALUE;
-        let entry_index = index & Self::AND_MASK;
-
-        // and finally set bit
-        unsafe { *self.bitmap.get_unchecked_mut(array_index) |= 1 << entry_index };
-    }
-
-    /// Sets a given bit to 0
-    fn unset_bit(&mut self, index: usize) {
-        // make sure we're actually in range
-        if index > self.bitmap_length * 64 {
-            return;
-        }
-
-        // find indices
-        let array_index = index >> Self::SHIFT_VALUE;
-        let entry_index = index & Self::AND_MASK;
-
-        // and finally set bit
-        unsafe { *self.bitmap.get_unchecked_mut(array_index) &= !(1 << entry_index) };
-    }
-
     /// Sets all entries to '1' (used) in unavailable memory
     fn block_unavailable_regions(&mut self) {
         let final_index = self.region_size / 4096;
 
-        let array_index = final_index >> Self::SHIFT_VALUE;
-        let entry_index = final_index & Self::AND_MASK;
-
-        unsafe {
-            *self.bitmap.get_unchecked_mut(array_index) |=
-                !(!0usize << (64 - entry_index)) << entry_index
-        };
+        let bitmap = self.bitmap_mut();
+        let len = bitmap.len();
+        bitmap.set_range(final_index.min(len)..len);
     }
 }
 
@@ -131,7 +96,7 @@ impl BitmapFrameAlloc {
     /// This function uses a **lot** of raw memory operations - both `addr` and `memory_map_entries` must be valid.
     #[allow(clippy::mut_from_ref)]
     pub unsafe fn new(
-        phys_addr: usize,
+        phys_addr: PhysAddr,
         addr: usize,
         memory_map_entries: &'static [MemoryMapEntry],
     ) -> (&'static mut Self, usize) {
@@ -143,7 +108,7 @@ impl BitmapFrameAlloc {
         // start by writing the frame alloc itself
         let region_count = memory_map_entries
             .iter()
-            .filter(|region| region.entry_type == MemoryEntryType::RAM)
+            .filter(|region| region.entry_type() == MemoryEntryType::RAM)
             .count();
 
         unsafe {
@@ -156,7 +121,7 @@ impl BitmapFrameAlloc {
 
         for region in memory_map_entries
             .iter()
-            .filter(|region| region.entry_type == MemoryEntryType::RAM)
+            .filter(|region| region.entry_type() == MemoryEntryType::RAM)
         {
             log::trace!(
                 "setting up memory region at base addr 0x{:016X} with length 0x{:X}",
@@ -218,14 +183,15 @@ impl BitmapFrameAlloc {
         unsafe { &mut *(address as *mut BitmapFrameAlloc) }
     }
 
-    /// Finds the first free frame, returning the region it lies in and the index within that region if it exists
-    fn first_free_frame(&mut self) -> Option<(&mut BitmapRegion, usize)> {
+    /// Finds the first run of `count` free frames within a single region, returning the region it lies in and the
+    /// index of the first frame within that region if it exists
+    fn first_free_run(&mut self, count: usize) -> Option<(&mut BitmapRegion, usize)> {
         let mut region = self.first_region;
 
         for _ in 0..self.region_count {
             let region_ref = unsafe { &mut *region };
 
-            if let Some(index) = region_ref.find_first_unset() {
+            if let Some(index) = region_ref.bitmap().find_zero_run(count) {
                 return Some((region_ref, index));
             }
 
@@ -256,7 +222,7 @@ impl BitmapFrameAlloc {
     /// Blocks an individual frame from being assigned
     pub fn block_frame(&mut self, frame: Frame) {
         if let Some((region, index)) = self.find_frame_index(frame) {
-            region.set_bit(index);
+            region.bitmap_mut().set(index);
         }
     }
 
@@ -271,8 +237,94 @@ impl BitmapFrameAlloc {
 
         for frame in frame_range {
             if let Some((region, index)) = self.find_frame_index(frame) {
-                region.set_bit(index);
+                region.bitmap_mut().set(index);
+            }
+        }
+    }
+
+    /// Returns if the frame is tracked by this allocator and marked as in use
+    pub fn is_frame_used(&mut self, frame: Frame) -> bool {
+        self.find_frame_index(frame)
+            .is_some_and(|(region, index)| region.bitmap().get(index))
+    }
+
+    /// Returns the number of frames in use and the total number of usable frames, across all regions
+    pub fn frame_counts(&self) -> (usize, usize) {
+        let (mut used, mut total) = (0, 0);
+
+        let mut region = self.first_region;
+        for _ in 0..self.region_count {
+            let region_ref = unsafe { &*region };
+            let bitmap = region_ref.bitmap();
+            let frames = (region_ref.region_size / FRAME_SIZE).min(bitmap.len());
+
+            // bits past the end of the region are permanently set, so don't count them as used
+            used += bitmap.count_ones() - (bitmap.len() - frames);
+            total += frames;
+
+            // move to next region
+            region = unsafe { region.byte_add(24 + region_ref.bitmap_length * size_of::<usize>()) };
+        }
+
+        (used, total)
+    }
+
+    /// Returns the range of frames spanning every region, or `None` if there are no regions
+    pub fn tracked_frames(&self) -> Option<Range<Frame>> {
+        let mut span: Option<Range<Frame>> = None;
+
+        let mut region = self.first_region;
+        for _ in 0..self.region_count {
+            let region_ref = unsafe { &*region };
+            let start = Frame::containing_address(region_ref.region_base_addr);
+            let end = Frame {
+                number: start.number + region_ref.region_size / FRAME_SIZE,
+            };
+
+            span = Some(match span {
+                Some(span) => {
+                    Frame {
+                        number: span.start.number.min(start.number),
+                    }..Frame {
+                        number: span.end.number.max(end.number),
+                    }
+                }
+                None => start..end,
+            });
+
+            // move to next region
+            region = unsafe { region.byte_add(24 + region_ref.bitmap_length * size_of::<usize>()) };
+        }
+
+        span
+    }
+
+    /// Marks every free frame as used, passing each run of frames taken to `f`. Used to hand free memory over to
+    /// another allocator.
+    pub fn take_free_frames(&mut self, mut f: impl FnMut(Range<Frame>)) {
+        let mut region = self.first_region;
+        for _ in 0..self.region_count {
+            let region_ref = unsafe { &mut *region };
+            let first_frame = Frame::containing_address(region_ref.region_base_addr);
+
+            // bits past the end of the region are always set, so a run never extends outside it
+            let bitmap = region_ref.bitmap_mut();
+            let mut index = 0;
+            while let Some(start) = (index..bitmap.len()).find(|&index| !bitmap.get(index)) {
+                index = (start..bitmap.len())
+                    .find(|&index| bitmap.get(index))
+                    .unwrap_or(bitmap.len());
+                bitmap.set_range(start..index);
+
+                f(Frame {
+                    number: first_frame.number + start,
+                }..Frame {
+                    number: first_frame.number + index,
+                });
             }
+
+            // move to next region
+            region = unsafe { region.byte_add(24 + region_ref.bitmap_length * size_of::<usize>()) };
         }
     }
 
@@ -300,23 +352,46 @@ impl BitmapFrameAlloc {
 
 impl FrameAllocator for BitmapFrameAlloc {
     fn allocate_frame(&mut self) -> Option<Frame> {
-        let (region, index) = self.first_free_frame()?;
+        self.allocate_frames(1)
+    }
+
+    fn deallocate_frame(&mut self, frame: Frame) {
+        self.deallocate_frames(frame, 1);
+    }
+
+    fn allocate_frames(&mut self, count: usize) -> Option<Frame> {
+        if count == 0 || fault::FRAME_ALLOC.should_fail() {
+            return None;
+        }
+
+        // bits past the end of each region are always set, so a run never extends outside its region
+        let (region, index) = self.first_free_run(count)?;
 
-        region.set_bit(index);
+        region.bitmap_mut().set_range(index..index + count);
         region.get_frame(index)
     }
 
-    fn deallocate_frame(&mut self, frame: Frame) {
+    fn deallocate_frames(&mut self, frame: Frame, count: usize) {
+        // see [`low_mem::is_low_frame`] for why low frames are never freed
+        if low_mem::is_low_frame(frame) {
+            log::trace!("not freeing low frame at {}", frame.start_address());
+            return;
+        }
+
         let (region, index) = self.find_frame_index(frame).unwrap();
+        assert!(
+            index + count <= region.region_size / FRAME_SIZE,
+            "freed frames at {} run past the end of their region",
+            frame.start_address()
+        );
 
-        #[cfg(feature = "ZERO_OUT_FREED_MEMORY")]
-        {
-            let addr = frame.start_address() | crate::mem::PHYS_MEM_OFFSET;
+        if crate::config::ZERO_OUT_FREED_MEMORY {
+            let addr = frame.start_address().to_virt();
 
             log::trace!("zeroing memory at {addr:#X}");
-            unsafe { core::ptr::write_bytes(addr as *mut u8, 0, FRAME_SIZE) };
+            unsafe { core::ptr::write_bytes(addr.as_mut_ptr::<u8>(), 0, FRAME_SIZE * count) };
         }
 
-        region.unset_bit(index);
+        region.bitmap_mut().clear_range(index..index + count);
     }
 }
@@ -0,0 +1,648 @@
+//! Code for allocating physical memory using a buddy allocator, which can hand out contiguous blocks of frames.
+//!
+//! Free blocks are `2^order` frames, aligned to their size. Each order has a doubly linked free list, with the links
+//! stored in the free frames themselves through the physical memory mapping. Allocating splits larger blocks in half
+//! until one of the right order is left, and freeing merges a block with its buddy for as long as the buddy is free,
+//! so both are `O(MAX_ORDER)`.
+
+use core::ops::Range;
+use std::align_down;
+
+use crate::{
+    fault,
+    mem::{
+        frame::{FRAME_SIZE, Frame},
+        frame_alloc::{FrameAllocator, bitmap::BitmapFrameAlloc, low_mem},
+    },
+};
+
+/// Largest order of block handed out, so blocks are at most `2^MAX_ORDER` frames (4 MiB)
+pub const MAX_ORDER: usize = 10;
+
+/// State of a frame which is not the first frame of a free block
+const NOT_FREE_HEAD: u8 = u8::MAX;
+
+/// End of a free list
+const NO_BLOCK: usize = usize::MAX;
+
+/// Links between free blocks of the same order, stored at the start of each free block
+struct FreeLink {
+    /// Index of the previous free block, or [`NO_BLOCK`]
+    prev: usize,
+    /// Index of the next free block, or [`NO_BLOCK`]
+    next: usize,
+}
+
+/// Allocates power-of-two sized, naturally aligned blocks of frames from a contiguous span of physical memory
+pub struct BuddyFrameAlloc {
+    /// First frame in the span, aligned to a block of [`MAX_ORDER`]
+    base: Frame,
+    /// Order of the free block starting at each frame, or [`NOT_FREE_HEAD`]
+    states: &'static mut [u8],
+    /// Index of the first free block of each order, or [`NO_BLOCK`]
+    free_lists: [usize; MAX_ORDER + 1],
+    /// Number of free frames
+    free_frames: usize,
+    /// Number of usable frames, whether free or not
+    usable_frames: usize,
+}
+
+impl BuddyFrameAlloc {
+    /// Constructs an allocator covering one frame per entry of `states`, starting at `base`. Every frame starts out in
+    /// use, and must be handed over with [`Self::add_region`].
+    ///
+    /// Panics if `base` is not aligned to a block of [`MAX_ORDER`].
+    pub fn new(base: Frame, states: &'static mut [u8]) -> Self {
+        assert!(
+            base.number.is_multiple_of(1 << MAX_ORDER),
+            "buddy allocator base {:#X} is not aligned to its largest block",
+            base.start_address()
+        );
+
+        states.fill(NOT_FREE_HEAD);
+
+        Self {
+            base,
+            states,
+            free_lists: [NO_BLOCK; MAX_ORDER + 1],
+            free_frames: 0,
+            usable_frames: 0,
+        }
+    }
+
+    /// Constructs an allocator spanning every region of `bitmap`, and moves every free frame out of `bitmap` into it.
+    /// The allocator and the state of each frame are stored in frames allocated from `bitmap`, one byte per frame.
+    ///
+    /// Returns `None` if `bitmap` has no regions, or not enough contiguous free memory to hold the state.
+    ///
+    /// ## Safety
+    /// Every frame tracked by `bitmap` must be mapped in the physical memory mapping. `bitmap` must not be used to
+    /// allocate or free frames afterwards.
+    pub unsafe fn from_bitmap(bitmap: &mut BitmapFrameAlloc) -> Option<&'static mut Self> {
+        let span = bitmap.tracked_frames()?;
+        let base = Frame {
+            number: align_down(span.start.number, 1 << MAX_ORDER),
+        };
+        let frame_count = span.end.number - base.number;
+
+        // the allocator goes at the start of its frames, followed by the state of each frame
+        let storage_frames = (size_of::<Self>() + frame_count).div_ceil(FRAME_SIZE);
+        let storage: *mut Self = bitmap
+            .allocate_frames(storage_frames)?
+            .start_address()
+            .as_hhdm_ptr();
+
+        // safety: the frames were just allocated, and are mapped in the physical memory mapping
+        let alloc = unsafe {
+            let states = core::slice::from_raw_parts_mut(storage.add(1).cast::<u8>(), frame_count);
+            storage.write(Self::new(base, states));
+            &mut *storage
+        };
+
+        // safety: the frames were free in `bitmap`, which no longer hands them out
+        bitmap.take_free_frames(|frames| unsafe { alloc.add_region(frames) });
+
+        // frames which were already in use are usable too, once they are freed
+        alloc.usable_frames = bitmap.frame_counts().1;
+
+        Some(alloc)
+    }
+
+    /// Returns the number of frames in use and the total number of usable frames
+    pub fn frame_counts(&self) -> (usize, usize) {
+        (
+            self.usable_frames.saturating_sub(self.free_frames),
+            self.usable_frames,
+        )
+    }
+
+    /// Returns if the frame is covered by this allocator and not free. Frames which were never handed over with
+    /// [`Self::add_region`] count as used.
+    pub fn is_frame_used(&self, frame: Frame) -> bool {
+        self.index(frame).is_some_and(|index| !self.is_free(index))
+    }
+
+    /// Marks a range of frames as free and usable, ignoring any outside the span covered.
+    ///
+    /// ## Safety
+    /// The frames must be usable RAM, mapped in the physical memory mapping, and not in use by anything else.
+    pub unsafe fn add_region(&mut self, frames: Range<Frame>) {
+        let start = frames.start.number.max(self.base.number) - self.base.number;
+        let end = frames
+            .end
+            .number
+            .saturating_sub(self.base.number)
+            .min(self.states.len());
+
+        if start < end {
+            self.usable_frames += end - start;
+            unsafe { self.free_range(start, end) };
+        }
+    }
+
+    /// Allocates `2^order` contiguous frames aligned to their size, returning the first
+    pub fn allocate_contiguous(&mut self, order: usize) -> Option<Frame> {
+        if order > MAX_ORDER || fault::FRAME_ALLOC.should_fail() {
+            return None;
+        }
+
+        // take the smallest free block which is large enough, then split off the unused halves
+        let mut split_order =
+            (order..=MAX_ORDER).find(|&order| self.free_lists[order] != NO_BLOCK)?;
+        let index = self.free_lists[split_order];
+        self.unlink(index, split_order);
+
+        while split_order > order {
+            split_order -= 1;
+            self.link(index + (1 << split_order), split_order);
+        }
+
+        self.free_frames -= 1 << order;
+        Some(self.frame(index))
+    }
+
+    /// Frees `2^order` contiguous frames allocated with [`Self::allocate_contiguous`]
+    ///
+    /// ## Safety
+    /// The frames must have been allocated with the same order, and must not be used afterwards.
+    pub unsafe fn deallocate_contiguous(&mut self, frame: Frame, order: usize) {
+        if let Some(index) = self.prepare_free(frame, 1 << order) {
+            unsafe { self.free_block(index, order) };
+        }
+    }
+
+    /// Checks `count` frames being freed are covered and not already free, and zeroes them if
+    /// [`crate::config::ZERO_OUT_FREED_MEMORY`] is set. Returns the index of the first frame, or `None` if the frames
+    /// are in low memory and must not be freed.
+    fn prepare_free(&mut self, frame: Frame, count: usize) -> Option<usize> {
+        // see [`low_mem::is_low_frame`] for why low frames are never freed
+        if low_mem::is_low_frame(frame) {
+            log::trace!("not freeing low frame at {}", frame.start_address());
+            return None;
+        }
+
+        let index = self
+            .index(frame)
+            .filter(|&index| index + count <= self.states.len())
+            .expect("freed frames not covered by allocator");
+        if let Some(free) = (index..index + count).find(|&index| self.is_free(index)) {
+            panic!("frame {:#X} freed twice", self.frame(free).start_address());
+        }
+
+        if crate::config::ZERO_OUT_FREED_MEMORY {
+            let addr = frame.start_address().to_virt();
+
+            log::trace!("zeroing memory at {addr:#X}");
+            unsafe { core::ptr::write_bytes(addr.as_mut_ptr::<u8>(), 0, FRAME_SIZE * count) };
+        }
+
+        Some(index)
+    }
+
+    /// Frees a range of frames by index as the largest aligned blocks which fit, so it doesn't have to be merged up
+    /// frame by frame
+    ///
+    /// ## Safety
+    /// The frames must be within the span, and not in use by anything else.
+    unsafe fn free_range(&mut self, start: usize, end: usize) {
+        let mut index = start;
+        while index < end {
+            let order = (0..=MAX_ORDER)
+                .rev()
+                .find(|&order| index.is_multiple_of(1 << order) && index + (1 << order) <= end)
+                .unwrap_or(0);
+
+            unsafe { self.free_block(index, order) };
+            index += 1 << order;
+        }
+    }
+
+    /// Frees a block, merging it with its buddy for as long as the buddy is also free
+    ///
+    /// ## Safety
+    /// The block must be within the span, and not in use by anything else.
+    unsafe fn free_block(&mut self, mut index: usize, mut order: usize) {
+        self.free_frames += 1 << order;
+
+        while order < MAX_ORDER {
+            let buddy = index ^ (1 << order);
+            if self.states.get(buddy) != Some(&(order as u8)) {
+                break;
+            }
+
+            self.unlink(buddy, order);
+            index = index.min(buddy);
+            order += 1;
+        }
+
+        self.link(index, order);
+    }
+
+    /// Pushes a block onto the free list of the given order
+    fn link(&mut self, index: usize, order: usize) {
+        let next = self.free_lists[order];
+
+        unsafe {
+            self.free_link(index).write(FreeLink {
+                prev: NO_BLOCK,
+                next,
+            });
+            if next != NO_BLOCK {
+                (*self.free_link(next)).prev = index;
+            }
+        }
+
+        self.free_lists[order] = index;
+        self.states[index] = order as u8;
+    }
+
+    /// Removes a block from the free list of the given order
+    fn unlink(&mut self, index: usize, order: usize) {
+        let FreeLink { prev, next } = unsafe { self.free_link(index).read() };
+
+        unsafe {
+            match prev {
+                NO_BLOCK => self.free_lists[order] = next,
+                prev => (*self.free_link(prev)).next = next,
+            }
+            if next != NO_BLOCK {
+                (*self.free_link(next)).prev = prev;
+            }
+        }
+
+        self.states[index] = NOT_FREE_HEAD;
+    }
+
+    /// Returns if the frame at an index is within a free block. Blocks are aligned to their size, so the only
+    /// candidates are the blocks of each order which the index would be aligned down to.
+    fn is_free(&self, index: usize) -> bool {
+        (0..=MAX_ORDER).any(|order| {
+            let head = index & !((1 << order) - 1);
+            self.states.get(head) == Some(&(order as u8))
+        })
+    }
+
+    /// Returns the links stored in a free block
+    fn free_link(&self, index: usize) -> *mut FreeLink {
+        self.frame(index).start_address().as_hhdm_ptr()
+    }
+
+    /// Returns the frame at an index within the span
+    fn frame(&self, index: usize) -> Frame {
+        Frame {
+            number: self.base.number + index,
+        }
+    }
+
+    /// Returns the index of a frame within the span, if covered
+    fn index(&self, frame: Frame) -> Option<usize> {
+        frame
+            .number
+            .checked_sub(self.base.number)
+            .filter(|&index| index < self.states.len())
+    }
+}
+
+impl FrameAllocator for BuddyFrameAlloc {
+    fn allocate_frame(&mut self) -> Option<Frame> {
+        self.allocate_contiguous(0)
+    }
+
+    fn deallocate_frame(&mut self, frame: Frame) {
+        self.deallocate_frames(frame, 1);
+    }
+
+    fn allocate_frames(&mut self, count: usize) -> Option<Frame> {
+        if count == 0 {
+            return None;
+        }
+
+        // allocate the smallest block which fits, and give back the frames past `count`
+        let first = self.allocate_contiguous(count.next_power_of_two().trailing_zeros() as usize)?;
+        let index = first.number - self.base.number;
+        unsafe { self.free_range(index + count, index + count.next_power_of_two()) };
+
+        Some(first)
+    }
+
+    fn deallocate_frames(&mut self, frame: Frame, count: usize) {
+        if let Some(index) = self.prepare_free(frame, count) {
+            unsafe { self.free_range(index, index + count) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::{
+        alloc::{Layout, alloc_zeroed, dealloc},
+        vec,
+    };
+
+    use multiboot::prelude::MemoryMapEntry;
+
+    use super::*;
+    use crate::mem::set_phys_mem_offset;
+
+    /// Frames in a block of [`MAX_ORDER`]
+    const MAX_BLOCK: usize = 1 << MAX_ORDER;
+
+    /// Zeroed host memory standing in for physical memory, aligned to a block of [`MAX_ORDER`]. The physical memory
+    /// mapping is at offset 0 in tests, so host addresses are also physical addresses.
+    struct TestMemory {
+        /// Start of the memory
+        ptr: *mut u8,
+        /// Layout the memory was allocated with
+        layout: Layout,
+    }
+
+    impl TestMemory {
+        /// Allocates `frames` frames of memory
+        fn new(frames: usize) -> Self {
+            // safety: nothing in these tests relies on physical memory being mapped anywhere else
+            unsafe { set_phys_mem_offset(0) };
+
+            let layout =
+                Layout::from_size_align(frames * FRAME_SIZE, MAX_BLOCK * FRAME_SIZE).unwrap();
+            let ptr = unsafe { alloc_zeroed(layout) };
+            assert!(!ptr.is_null(), "failed to allocate test memory");
+
+            Self { ptr, layout }
+        }
+
+        /// Returns the frame at an index into the memory
+        fn frame(&self, index: usize) -> Frame {
+            Frame {
+                number: self.ptr.addr() / FRAME_SIZE + index,
+            }
+        }
+
+        /// Constructs an allocator covering all of the memory, with nothing free
+        fn allocator(&self) -> BuddyFrameAlloc {
+            BuddyFrameAlloc::new(
+                self.frame(0),
+                vec![0; self.layout.size() / FRAME_SIZE].leak(),
+            )
+        }
+    }
+
+    impl Drop for TestMemory {
+        fn drop(&mut self) {
+            unsafe { dealloc(self.ptr, self.layout) };
+        }
+    }
+
+    impl BuddyFrameAlloc {
+        /// Returns the number of blocks on the free list of an order
+        fn free_block_count(&self, order: usize) -> usize {
+            let mut count = 0;
+            let mut index = self.free_lists[order];
+            while index != NO_BLOCK {
+                count += 1;
+                index = unsafe { (*self.free_link(index)).next };
+            }
+
+            count
+        }
+    }
+
+    /// Xorshift generator, so tests are random but repeatable
+    struct Rng(u64);
+
+    impl Rng {
+        /// Returns a random number below `bound`
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 as usize % bound
+        }
+    }
+
+    #[test]
+    fn split_and_merge() {
+        let memory = TestMemory::new(2 * MAX_BLOCK);
+        let mut alloc = memory.allocator();
+        unsafe { alloc.add_region(memory.frame(0)..memory.frame(2 * MAX_BLOCK)) };
+        assert_eq!(alloc.frame_counts(), (0, 2 * MAX_BLOCK));
+        assert_eq!(alloc.free_block_count(MAX_ORDER), 2);
+
+        // a largest block is split all the way down, leaving one free block of every smaller order
+        let frame = alloc.allocate_contiguous(0).unwrap();
+        assert!(alloc.is_frame_used(frame));
+        assert!(!alloc.is_frame_used(memory.frame(1)));
+        assert_eq!(alloc.frame_counts(), (1, 2 * MAX_BLOCK));
+        for order in 0..MAX_ORDER {
+            assert_eq!(alloc.free_block_count(order), 1);
+        }
+        assert_eq!(alloc.free_block_count(MAX_ORDER), 1);
+
+        // and freeing it merges every half back together
+        unsafe { alloc.deallocate_contiguous(frame, 0) };
+        assert_eq!(alloc.frame_counts(), (0, 2 * MAX_BLOCK));
+        for order in 0..MAX_ORDER {
+            assert_eq!(alloc.free_block_count(order), 0);
+        }
+        assert_eq!(alloc.free_block_count(MAX_ORDER), 2);
+    }
+
+    #[test]
+    fn merge_in_any_order() {
+        let memory = TestMemory::new(MAX_BLOCK);
+        let mut alloc = memory.allocator();
+        unsafe { alloc.add_region(memory.frame(0)..memory.frame(MAX_BLOCK)) };
+
+        let mut frames = [memory.frame(0); MAX_BLOCK];
+        for frame in &mut frames {
+            *frame = alloc.allocate_frame().unwrap();
+        }
+        assert!(alloc.allocate_frame().is_none());
+
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+        for index in (1..MAX_BLOCK).rev() {
+            frames.swap(index, rng.below(index + 1));
+        }
+        for frame in frames {
+            alloc.deallocate_frame(frame);
+        }
+
+        assert_eq!(alloc.frame_counts(), (0, MAX_BLOCK));
+        assert_eq!(alloc.free_block_count(MAX_ORDER), 1);
+        assert_eq!(alloc.allocate_contiguous(MAX_ORDER), Some(memory.frame(0)));
+    }
+
+    #[test]
+    fn unaligned_region() {
+        let memory = TestMemory::new(2 * MAX_BLOCK);
+        let mut alloc = memory.allocator();
+        unsafe { alloc.add_region(memory.frame(3)..memory.frame(MAX_BLOCK + 5)) };
+        assert_eq!(alloc.frame_counts(), (0, MAX_BLOCK + 2));
+
+        // no largest block fits, but the aligned half block within the region does
+        assert_eq!(alloc.allocate_contiguous(MAX_ORDER), None);
+        assert_eq!(
+            alloc.allocate_contiguous(MAX_ORDER - 1),
+            Some(memory.frame(MAX_BLOCK / 2))
+        );
+
+        let mut remaining = 0;
+        while let Some(frame) = alloc.allocate_frame() {
+            let index = frame.number - memory.frame(0).number;
+            assert!(
+                (3..MAX_BLOCK / 2).contains(&index) || (MAX_BLOCK..MAX_BLOCK + 5).contains(&index)
+            );
+            remaining += 1;
+        }
+        assert_eq!(remaining, MAX_BLOCK / 2 + 2);
+        assert_eq!(alloc.frame_counts(), (MAX_BLOCK + 2, MAX_BLOCK + 2));
+
+        // frames never handed over count as used
+        assert!(alloc.is_frame_used(memory.frame(0)));
+        assert!(alloc.is_frame_used(memory.frame(2 * MAX_BLOCK - 1)));
+    }
+
+    #[test]
+    fn region_outside_span() {
+        let memory = TestMemory::new(2 * MAX_BLOCK);
+        let mut alloc = BuddyFrameAlloc::new(memory.frame(0), vec![0; MAX_BLOCK].leak());
+
+        // only the parts of regions within the span are used
+        let before = Frame {
+            number: memory.frame(0).number - 4,
+        };
+        unsafe { alloc.add_region(before..memory.frame(4)) };
+        unsafe { alloc.add_region(memory.frame(MAX_BLOCK - 4)..memory.frame(MAX_BLOCK + 4)) };
+        assert_eq!(alloc.frame_counts(), (0, 8));
+        assert!(!alloc.is_frame_used(before));
+        assert!(!alloc.is_frame_used(memory.frame(MAX_BLOCK)));
+
+        unsafe { alloc.add_region(memory.frame(MAX_BLOCK)..memory.frame(2 * MAX_BLOCK)) };
+        assert_eq!(alloc.frame_counts(), (0, 8));
+    }
+
+    #[test]
+    #[should_panic(expected = "freed frames not covered by allocator")]
+    fn free_outside_span() {
+        let memory = TestMemory::new(2 * MAX_BLOCK);
+        let mut alloc = BuddyFrameAlloc::new(memory.frame(0), vec![0; MAX_BLOCK].leak());
+        unsafe { alloc.add_region(memory.frame(0)..memory.frame(MAX_BLOCK)) };
+
+        alloc.deallocate_frame(memory.frame(MAX_BLOCK));
+    }
+
+    #[test]
+    #[should_panic(expected = "freed twice")]
+    fn double_free_within_frames() {
+        let memory = TestMemory::new(MAX_BLOCK);
+        let mut alloc = memory.allocator();
+        unsafe { alloc.add_region(memory.frame(0)..memory.frame(MAX_BLOCK)) };
+
+        // only a frame in the middle of the run is already free
+        let first = alloc.allocate_frames(4).unwrap();
+        alloc.deallocate_frame(Frame {
+            number: first.number + 2,
+        });
+        alloc.deallocate_frames(first, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "freed twice")]
+    fn double_free_within_block() {
+        let memory = TestMemory::new(MAX_BLOCK);
+        let mut alloc = memory.allocator();
+        unsafe { alloc.add_region(memory.frame(0)..memory.frame(MAX_BLOCK)) };
+
+        let first = alloc.allocate_contiguous(3).unwrap();
+        alloc.deallocate_frames(
+            Frame {
+                number: first.number + 4,
+            },
+            4,
+        );
+        unsafe { alloc.deallocate_contiguous(first, 3) };
+    }
+
+    #[test]
+    fn random_allocations() {
+        let memory = TestMemory::new(4 * MAX_BLOCK);
+        let mut alloc = memory.allocator();
+
+        // the region leaves out a frame at the start and a few at the end, so only the middle blocks merge fully
+        let region = 1..4 * MAX_BLOCK - 3;
+        unsafe { alloc.add_region(memory.frame(region.start)..memory.frame(region.end)) };
+
+        let mut owned = vec![false; 4 * MAX_BLOCK];
+        let mut live = vec![];
+        let mut used = 0;
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+
+        for _ in 0..4000 {
+            if live.is_empty() || rng.below(5) < 3 {
+                let count = 1 + rng.below(40);
+                let Some(first) = alloc.allocate_frames(count) else {
+                    continue;
+                };
+
+                let start = first.number - memory.frame(0).number;
+                assert!(region.start <= start && start + count <= region.end);
+                for frame in &mut owned[start..start + count] {
+                    assert!(!*frame, "frame handed out twice");
+                    *frame = true;
+                }
+
+                live.push((first, count));
+                used += count;
+            } else {
+                let (first, count) = live.swap_remove(rng.below(live.len()));
+                alloc.deallocate_frames(first, count);
+
+                let start = first.number - memory.frame(0).number;
+                owned[start..start + count].fill(false);
+                used -= count;
+            }
+
+            assert_eq!(alloc.frame_counts(), (used, region.len()));
+        }
+
+        for (first, count) in live {
+            alloc.deallocate_frames(first, count);
+        }
+
+        assert_eq!(alloc.frame_counts(), (0, region.len()));
+        assert_eq!(alloc.free_block_count(MAX_ORDER), 2);
+    }
+
+    #[test]
+    fn from_bitmap() {
+        let memory = TestMemory::new(2 * MAX_BLOCK);
+
+        // ram starts a few frames in, leaving room for the bitmap allocator before it
+        let ram_start = memory.frame(5).start_address().as_usize() as u64;
+        let ram_length = (2 * MAX_BLOCK - 8) * FRAME_SIZE;
+        let entries: &'static [MemoryMapEntry] = vec![unsafe {
+            core::mem::transmute::<[u64; 3], MemoryMapEntry>([ram_start, ram_length as u64, 1])
+        }]
+        .leak();
+
+        let bitmap_addr = memory.frame(0).start_address();
+        let (bitmap, _) =
+            unsafe { BitmapFrameAlloc::new(bitmap_addr, bitmap_addr.as_usize(), entries) };
+        let used = bitmap.allocate_frames(3).unwrap();
+        assert_eq!(used, memory.frame(5));
+
+        let alloc = unsafe { BuddyFrameAlloc::from_bitmap(bitmap) }.unwrap();
+        assert!(bitmap.allocate_frame().is_none());
+
+        // frames already in use and the allocator's own frame stay used, and everything else moves over
+        assert_eq!(alloc.frame_counts(), (4, 2 * MAX_BLOCK - 8));
+        assert!(alloc.is_frame_used(memory.frame(4)));
+        assert!(alloc.is_frame_used(memory.frame(8)));
+        assert!(!alloc.is_frame_used(memory.frame(9)));
+
+        alloc.deallocate_frames(used, 3);
+        assert_eq!(alloc.frame_counts(), (1, 2 * MAX_BLOCK - 8));
+        assert!(!alloc.is_frame_used(memory.frame(5)));
+    }
+}
@@ -0,0 +1,174 @@
+//! Policy for memory below 1 MiB, which firmware may report as RAM even though the IVT, BDA, EBDA, VGA memory and
+//! option ROMs live there.
+//!
+//! The whole of low memory is reserved in the frame allocator so it is never handed out for general use. Drivers
+//! which need something there (such as the SMP trampoline, which has to be below 1 MiB) instead claim specific
+//! frames through [`claim`], which refuses anything firmware owns.
+
+use core::{
+    fmt::{Display, Formatter},
+    ops::Range,
+};
+use std::{bitmap::Bitmap, mutex::Mutex};
+
+use crate::{
+    mem::{
+        addr::PhysAddr,
+        frame::{FRAME_SIZE, Frame},
+        frame_alloc::bitmap::BitmapFrameAlloc,
+        phys::phys_read_volatile,
+    },
+    x86::without_interrupts,
+};
+
+/// End of low memory, which is never handed out by the frame allocator
+pub const LOW_MEMORY_END: PhysAddr = PhysAddr::new(0x100000);
+
+/// Start of VGA memory and the BIOS area, which is never RAM
+const VIDEO_MEMORY_START: PhysAddr = PhysAddr::new(0xA0000);
+
+/// Address in the BDA holding the segment the EBDA starts at
+const BDA_EBDA_SEGMENT: PhysAddr = PhysAddr::new(0x40E);
+
+/// Lowest address the EBDA is assumed to start at if the BDA doesn't give a plausible one
+const EBDA_FALLBACK_START: PhysAddr = PhysAddr::new(0x80000);
+
+/// Number of frames in low memory
+const LOW_FRAMES: usize = LOW_MEMORY_END.as_usize() / FRAME_SIZE;
+
+/// Low frames claimed by drivers, one bit per frame
+static CLAIMED: Mutex<[usize; LOW_FRAMES / usize::BITS as usize]> =
+    Mutex::new([0; LOW_FRAMES / usize::BITS as usize]);
+
+/// An error claiming or releasing low frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LowMemoryError {
+    /// Frame is not below [`LOW_MEMORY_END`]
+    NotLowMemory(Frame),
+    /// Frame holds the IVT, BDA, EBDA or the BIOS area
+    Firmware(Frame),
+    /// Frame is not RAM according to the memory map
+    NotRam(Frame),
+    /// Frame has already been claimed
+    AlreadyClaimed(Frame),
+    /// Frame was released without being claimed
+    NotClaimed(Frame),
+}
+
+impl Display for LowMemoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotLowMemory(frame) => {
+                write!(f, "frame {} is not in low memory", frame.start_address())
+            }
+            Self::Firmware(frame) => {
+                write!(f, "frame {} is used by firmware", frame.start_address())
+            }
+            Self::NotRam(frame) => write!(f, "frame {} is not ram", frame.start_address()),
+            Self::AlreadyClaimed(frame) => {
+                write!(f, "frame {} is already claimed", frame.start_address())
+            }
+            Self::NotClaimed(frame) => write!(f, "frame {} is not claimed", frame.start_address()),
+        }
+    }
+}
+
+/// Returns if the frame is below [`LOW_MEMORY_END`].
+///
+/// Frame allocators must ignore frees of low frames, as low memory stays reserved even if something mapped there is
+/// torn down, such as boot memory being reclaimed.
+pub fn is_low_frame(frame: Frame) -> bool {
+    frame.start_address() < LOW_MEMORY_END
+}
+
+/// Returns the EBDA, from the segment stored in the BDA up to VGA memory, or `None` if the BDA doesn't point
+/// somewhere plausible
+pub fn ebda() -> Option<Range<PhysAddr>> {
+    // safety: the BDA is always present on PCs, and all physical memory is mapped
+    let segment: u16 = unsafe { phys_read_volatile(BDA_EBDA_SEGMENT) };
+    let start = PhysAddr::new((segment as usize) << 4);
+
+    (EBDA_FALLBACK_START..VIDEO_MEMORY_START)
+        .contains(&start)
+        .then_some(start..VIDEO_MEMORY_START)
+}
+
+/// Reserves all of low memory in the frame allocator, so it is only ever used through [`claim`]
+pub fn reserve(frame_alloc: &mut BitmapFrameAlloc) {
+    log::trace!("blocking low memory 0x0-{LOW_MEMORY_END:#X}");
+    frame_alloc.block_region(
+        Frame::containing_address(PhysAddr::new(0))..Frame::containing_address(LOW_MEMORY_END),
+    );
+
+    match ebda() {
+        Some(ebda) => log::trace!("\t* ebda at {}-{}", ebda.start, ebda.end),
+        None => log::trace!("\t* no ebda pointer, assuming it starts at {EBDA_FALLBACK_START}"),
+    }
+}
+
+/// Returns the first address owned by firmware above the IVT and BDA
+fn firmware_start() -> PhysAddr {
+    ebda().map_or(EBDA_FALLBACK_START, |ebda| ebda.start)
+}
+
+/// Claims a range of low frames for a driver, which must be RAM and not owned by firmware. Either every frame is
+/// claimed, or none are.
+///
+/// The frames stay reserved in the frame allocator, and must be handed back with [`release`] rather than
+/// deallocated.
+pub fn claim(frame_alloc: &BitmapFrameAlloc, frames: Range<Frame>) -> Result<(), LowMemoryError> {
+    let firmware_start = firmware_start();
+
+    without_interrupts(|| {
+        let mut claimed = CLAIMED.lock();
+        let claimed = Bitmap::from_slice_mut(&mut *claimed);
+
+        for frame in frames.clone() {
+            if !is_low_frame(frame) {
+                return Err(LowMemoryError::NotLowMemory(frame));
+            }
+            // frame 0 holds the IVT and BDA, and can't be used as a page table anyway
+            if frame.number == 0 || frame.start_address() + FRAME_SIZE > firmware_start {
+                return Err(LowMemoryError::Firmware(frame));
+            }
+            if !frame_alloc.is_frame_tracked(frame) {
+                return Err(LowMemoryError::NotRam(frame));
+            }
+            if claimed.get(frame.number) {
+                return Err(LowMemoryError::AlreadyClaimed(frame));
+            }
+        }
+
+        for frame in frames {
+            claimed.set(frame.number);
+        }
+
+        Ok(())
+    })
+}
+
+/// Releases a range of low frames previously claimed with [`claim`], so they can be claimed again. Either every
+/// frame is released, or none are.
+pub fn release(frames: Range<Frame>) -> Result<(), LowMemoryError> {
+    without_interrupts(|| {
+        let mut claimed = CLAIMED.lock();
+        let claimed = Bitmap::from_slice_mut(&mut *claimed);
+
+        if let Some(frame) = frames
+            .clone()
+            .find(|&frame| !is_low_frame(frame) || !claimed.get(frame.number))
+        {
+            return Err(if is_low_frame(frame) {
+                LowMemoryError::NotClaimed(frame)
+            } else {
+                LowMemoryError::NotLowMemory(frame)
+            });
+        }
+
+        for frame in frames {
+            claimed.clear(frame.number);
+        }
+
+        Ok(())
+    })
+}
@@ -1,6 +1,8 @@
 //! Code for handling allocating physical frames
 
 pub mod bitmap;
+pub mod buddy;
+pub mod low_mem;
 
 use crate::mem::frame::Frame;
 
@@ -11,4 +13,11 @@ pub trait FrameAllocator {
 
     /// Deallocates a frame, freeing it for future use
     fn deallocate_frame(&mut self, frame: Frame);
+
+    /// Allocates `count` physically contiguous frames, returning the first, or None if not possible
+    fn allocate_frames(&mut self, count: usize) -> Option<Frame>;
+
+    /// Deallocates `count` contiguous frames starting at `frame`, which must have been allocated with
+    /// [`Self::allocate_frames`]
+    fn deallocate_frames(&mut self, frame: Frame, count: usize);
 }
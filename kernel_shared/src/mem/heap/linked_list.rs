@@ -0,0 +1,137 @@
+//! First-fit allocator over a linked list of free regions, for allocations too large for a slab
+
+use core::{alloc::Layout, mem::size_of, ptr::NonNull};
+use std::align_up;
+
+/// A free region, stored at the start of the region itself
+struct Region {
+    /// Size of the region in bytes, including this header
+    size: usize,
+    /// Next free region, which is always at a higher address
+    next: Option<NonNull<Region>>,
+}
+
+/// Granularity of allocations, so every region is large and aligned enough to hold its header
+pub(super) const MIN_BLOCK_SIZE: usize = size_of::<Region>().next_power_of_two();
+
+/// Free regions sorted by address, with neighbouring regions merged
+pub(super) struct LinkedListAllocator {
+    /// Lowest free region
+    head: Option<NonNull<Region>>,
+    /// Total size of every free region
+    free: usize,
+}
+
+// regions are only reached through the allocator, which is always behind a lock
+unsafe impl Send for LinkedListAllocator {}
+
+impl LinkedListAllocator {
+    /// Constructs an allocator with no free memory
+    pub(super) const fn new() -> Self {
+        Self {
+            head: None,
+            free: 0,
+        }
+    }
+
+    /// Returns the number of bytes which are free
+    pub(super) fn free(&self) -> usize {
+        self.free
+    }
+
+    /// Returns the number of bytes actually taken by an allocation with the given layout
+    pub(super) fn block_size(layout: Layout) -> usize {
+        align_up(layout.size().max(MIN_BLOCK_SIZE), MIN_BLOCK_SIZE)
+    }
+
+    /// Allocates from the first region which fits the layout, or returns `None` if none do
+    pub(super) fn alloc(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let size = Self::block_size(layout);
+        let align = layout.align().max(MIN_BLOCK_SIZE);
+
+        let mut link = &raw mut self.head;
+        while let Some(region) = unsafe { *link } {
+            let start = region.as_ptr() as usize;
+            let end = start + unsafe { region.as_ref().size };
+
+            let alloc_start = align_up(start, align);
+            let alloc_end = alloc_start.checked_add(size)?;
+
+            if alloc_end > end {
+                link = unsafe { &raw mut (*region.as_ptr()).next };
+                continue;
+            }
+
+            // unlink the region, then hand back whatever is left either side of the allocation. both remainders
+            // are multiples of the block size, as are all region boundaries
+            unsafe {
+                *link = region.as_ref().next;
+                self.free -= end - start;
+
+                if alloc_start > start {
+                    self.add_region(start, alloc_start - start);
+                }
+                if end > alloc_end {
+                    self.add_region(alloc_end, end - alloc_end);
+                }
+            }
+
+            return NonNull::new(alloc_start as *mut u8);
+        }
+
+        None
+    }
+
+    /// Frees an allocation
+    ///
+    /// ## Safety
+    /// The allocation must have been made by this allocator with the same layout, and must not be used afterwards.
+    pub(super) unsafe fn dealloc(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { self.add_region(ptr.as_ptr() as usize, Self::block_size(layout)) };
+    }
+
+    /// Adds a region of memory to the free list, merging it with its neighbours
+    ///
+    /// ## Safety
+    /// The region must be writable, aligned to [`MIN_BLOCK_SIZE`], a multiple of it in size, and not used by
+    /// anything else.
+    pub(super) unsafe fn add_region(&mut self, start: usize, size: usize) {
+        self.free += size;
+
+        // find the regions either side of the new one
+        let mut prev: Option<NonNull<Region>> = None;
+        let mut next = self.head;
+        while let Some(region) = next
+            && (region.as_ptr() as usize) < start
+        {
+            prev = next;
+            next = unsafe { region.as_ref().next };
+        }
+
+        let mut size = size;
+        unsafe {
+            if let Some(region) = next
+                && start + size == region.as_ptr() as usize
+            {
+                size += region.as_ref().size;
+                next = region.as_ref().next;
+            }
+
+            if let Some(mut region) = prev
+                && region.as_ptr() as usize + region.as_ref().size == start
+            {
+                region.as_mut().size += size;
+                region.as_mut().next = next;
+                return;
+            }
+
+            let region = NonNull::new_unchecked(start as *mut Region);
+            region.write(Region { size, next });
+
+            match prev {
+                Some(mut prev) => prev.as_mut().next = Some(region),
+                None => self.head = Some(region),
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Kernel heap, backing `alloc` collections such as `Vec`, `Box` and `BTreeMap`.
+//!
+//! Small allocations are served from slabs of fixed size blocks, which are quick to allocate and free and don't
+//! fragment. Anything larger or more strictly aligned comes from a first-fit linked list allocator, which also
+//! provides the memory slabs are carved from.
+//!
+//! The heap starts out as the region the loader mapped, and can be extended with frames from the frame allocator.
+
+mod linked_list;
+mod slab;
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt::{Display, Formatter},
+    ptr::{NonNull, null_mut},
+};
+use std::{is_aligned, mutex::Mutex};
+
+use crate::{
+    mem::{
+        addr::VirtAddr,
+        frame_alloc::FrameAllocator,
+        heap::{
+            linked_list::LinkedListAllocator,
+            slab::{SLAB_SIZE, SlabAllocator},
+        },
+        page::{PAGE_SIZE, Page},
+        paging::{entry::EntryFlags, mapper::Mapper},
+    },
+    x86::without_interrupts,
+};
+
+/// Address the heap is mapped at by the loader
+pub const HEAP_START: usize = 0xFFFFFFFF20000000;
+
+/// Largest the heap can grow to, keeping it clear of the memory after it
+pub const HEAP_MAX_SIZE: usize = 256 * 1024 * 1024; // 256 MiB
+
+/// An error setting up or growing the heap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeapError {
+    /// Heap has not been initialised
+    NotInitialised,
+    /// Heap was already initialised
+    AlreadyInitialised,
+    /// Region is not page aligned, or would grow the heap past [`HEAP_MAX_SIZE`]
+    BadRegion,
+    /// No frames were left to back the heap with
+    OutOfFrames,
+}
+
+impl Display for HeapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotInitialised => write!(f, "heap not initialised"),
+            Self::AlreadyInitialised => write!(f, "heap already initialised"),
+            Self::BadRegion => write!(f, "heap region is unaligned or too large"),
+            Self::OutOfFrames => write!(f, "out of frames to back heap"),
+        }
+    }
+}
+
+/// Usage of the heap, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Total size of the heap
+    pub size: usize,
+    /// Bytes handed out, including rounding up to block sizes
+    pub used: usize,
+    /// Bytes free in the fallback allocator, not counting free slab blocks
+    pub free: usize,
+}
+
+/// State of an initialised heap
+struct Heap {
+    /// Small allocations
+    slabs: SlabAllocator,
+    /// Large allocations, and memory for slabs
+    fallback: LinkedListAllocator,
+    /// Start of the heap
+    start: usize,
+    /// End of the heap, where it is extended from
+    end: usize,
+    /// Bytes handed out
+    used: usize,
+}
+
+impl Heap {
+    /// Allocates memory for the layout, or returns `None` if the heap is full
+    fn alloc(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let Some(class) = SlabAllocator::class(layout) else {
+            let ptr = self.fallback.alloc(layout)?;
+            self.used += LinkedListAllocator::block_size(layout);
+            return Some(ptr);
+        };
+
+        let ptr = match self.slabs.alloc(class) {
+            Some(ptr) => ptr,
+            None => {
+                let slab = self
+                    .fallback
+                    .alloc(Layout::from_size_align(SLAB_SIZE, SLAB_SIZE).unwrap())?;
+                unsafe { self.slabs.refill(class, slab) };
+
+                self.slabs.alloc(class)?
+            }
+        };
+        self.used += SlabAllocator::block_size(class);
+
+        Some(ptr)
+    }
+
+    /// Frees memory allocated with the same layout
+    ///
+    /// ## Safety
+    /// The memory must have been allocated by this heap with the same layout, and must not be used afterwards.
+    unsafe fn dealloc(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        match SlabAllocator::class(layout) {
+            Some(class) => unsafe {
+                self.slabs.dealloc(class, ptr);
+                self.used -= SlabAllocator::block_size(class);
+            },
+            None => unsafe {
+                self.fallback.dealloc(ptr, layout);
+                self.used -= LinkedListAllocator::block_size(layout);
+            },
+        }
+    }
+}
+
+/// The kernel heap, usable as the global allocator once initialised
+pub struct KernelHeap {
+    /// Heap state, set once initialised
+    heap: Mutex<Option<Heap>>,
+}
+
+impl KernelHeap {
+    /// Constructs a heap which fails every allocation until initialised
+    pub const fn new() -> Self {
+        Self {
+            heap: Mutex::new(None),
+        }
+    }
+
+    /// Initialises the heap over the given region
+    ///
+    /// ## Safety
+    /// The region must be mapped writable, and must not be used by anything else.
+    pub unsafe fn init(&self, start: VirtAddr, size: usize) -> Result<(), HeapError> {
+        if !start.is_aligned(PAGE_SIZE) || !is_aligned(size, PAGE_SIZE) || size > HEAP_MAX_SIZE {
+            return Err(HeapError::BadRegion);
+        }
+
+        without_interrupts(|| {
+            let mut heap = self.heap.lock();
+            if heap.is_some() {
+                return Err(HeapError::AlreadyInitialised);
+            }
+
+            let start = start.as_usize();
+            let mut fallback = LinkedListAllocator::new();
+            unsafe { fallback.add_region(start, size) };
+
+            *heap = Some(Heap {
+                slabs: SlabAllocator::new(),
+                fallback,
+                start,
+                end: start + size,
+                used: 0,
+            });
+
+            Ok(())
+        })
+    }
+
+    /// Grows the heap by at least `bytes`, backing it with frames from the frame allocator
+    pub fn extend<A: FrameAllocator>(
+        &self,
+        mapper: &mut Mapper,
+        frame_alloc: &mut A,
+        bytes: usize,
+    ) -> Result<(), HeapError> {
+        without_interrupts(|| {
+            let mut heap = self.heap.lock();
+            let heap = heap.as_mut().ok_or(HeapError::NotInitialised)?;
+
+            let pages = bytes.div_ceil(PAGE_SIZE);
+            if heap.end - heap.start + pages * PAGE_SIZE > HEAP_MAX_SIZE {
+                return Err(HeapError::BadRegion);
+            }
+
+            // each page is added as soon as it is mapped, so running out of frames partway still grows the heap
+            for _ in 0..pages {
+                let page = Page::containing_address(VirtAddr::new(heap.end));
+                mapper
+                    .map(
+                        page,
+                        EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE,
+                        frame_alloc,
+                    )
+                    .map_err(|_| HeapError::OutOfFrames)?;
+
+                unsafe { heap.fallback.add_region(heap.end, PAGE_SIZE) };
+                heap.end += PAGE_SIZE;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Returns how much of the heap is in use, or `None` if it isn't initialised
+    pub fn stats(&self) -> Option<HeapStats> {
+        without_interrupts(|| {
+            self.heap.lock().as_ref().map(|heap| HeapStats {
+                size: heap.end - heap.start,
+                used: heap.used,
+                free: heap.fallback.free(),
+            })
+        })
+    }
+}
+
+impl Default for KernelHeap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        without_interrupts(|| {
+            self.heap
+                .lock()
+                .as_mut()
+                .and_then(|heap| heap.alloc(layout))
+                .map_or(null_mut(), NonNull::as_ptr)
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let Some(ptr) = NonNull::new(ptr) else {
+            return;
+        };
+
+        without_interrupts(|| {
+            if let Some(heap) = self.heap.lock().as_mut() {
+                unsafe { heap.dealloc(ptr, layout) };
+            }
+        });
+    }
+}
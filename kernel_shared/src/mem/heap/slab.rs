@@ -0,0 +1,89 @@
+//! Slabs of fixed size blocks, for small allocations
+
+use core::{alloc::Layout, ptr::NonNull};
+
+use crate::mem::page::PAGE_SIZE;
+
+/// Size of the blocks handed out by each slab, smallest first
+const BLOCK_SIZES: [usize; 8] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+
+/// Size and alignment of the memory each slab is refilled with
+pub(super) const SLAB_SIZE: usize = PAGE_SIZE;
+
+/// A free block, linking to the next free block of the same size
+struct FreeBlock {
+    /// Next free block, if any
+    next: Option<NonNull<FreeBlock>>,
+}
+
+/// Free lists of blocks for each block size.
+///
+/// Slabs are carved from page aligned memory, so every block is aligned to its own size. Blocks are never handed
+/// back to the memory they were carved from, as a slab's blocks are rarely all free at once.
+pub(super) struct SlabAllocator {
+    /// Head of the free list for each block size
+    free: [Option<NonNull<FreeBlock>>; BLOCK_SIZES.len()],
+}
+
+// free blocks are only reached through the allocator, which is always behind a lock
+unsafe impl Send for SlabAllocator {}
+
+impl SlabAllocator {
+    /// Constructs an allocator with no free blocks
+    pub(super) const fn new() -> Self {
+        Self {
+            free: [None; BLOCK_SIZES.len()],
+        }
+    }
+
+    /// Returns the index of the smallest block size which can hold the layout, or `None` if it needs the fallback
+    /// allocator
+    pub(super) fn class(layout: Layout) -> Option<usize> {
+        let size = layout.size().max(layout.align());
+
+        BLOCK_SIZES
+            .iter()
+            .position(|&block_size| block_size >= size)
+    }
+
+    /// Returns the size of blocks in the given class
+    pub(super) const fn block_size(class: usize) -> usize {
+        BLOCK_SIZES[class]
+    }
+
+    /// Takes a free block of the given class, or returns `None` if the slab needs refilling
+    pub(super) fn alloc(&mut self, class: usize) -> Option<NonNull<u8>> {
+        let block = self.free[class]?;
+        self.free[class] = unsafe { block.as_ref().next };
+
+        Some(block.cast())
+    }
+
+    /// Returns a block to the given class
+    ///
+    /// ## Safety
+    /// The block must have been allocated from this class, and must not be used afterwards.
+    pub(super) unsafe fn dealloc(&mut self, class: usize, ptr: NonNull<u8>) {
+        let block = ptr.cast::<FreeBlock>();
+        unsafe {
+            block.write(FreeBlock {
+                next: self.free[class],
+            })
+        };
+
+        self.free[class] = Some(block);
+    }
+
+    /// Splits a slab of memory into blocks of the given class
+    ///
+    /// ## Safety
+    /// The slab must be [`SLAB_SIZE`] bytes aligned to [`SLAB_SIZE`], and must not be used by anything else.
+    pub(super) unsafe fn refill(&mut self, class: usize, slab: NonNull<u8>) {
+        let block_size = Self::block_size(class);
+
+        // pushed in reverse so blocks are handed out in address order
+        for offset in (0..SLAB_SIZE).step_by(block_size).rev() {
+            unsafe { self.dealloc(class, slab.byte_add(offset)) };
+        }
+    }
+}
@@ -1,25 +1,60 @@
 //! Code for memory management, such as paging and frame allocation.
 
-use std::{align_down, align_up};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::{align_down, align_up, init_once::InitOnce, is_aligned};
 
-use crate::mem::page::PAGE_SIZE;
+use crate::mem::{
+    addr::VirtAddr,
+    page::{HUGE_L3_PAGE_SIZE, PAGE_SIZE},
+};
 
+pub mod addr;
 pub mod frame;
 pub mod frame_alloc;
+pub mod heap;
 pub mod page;
 pub mod paging;
+pub mod phys;
 
-/// Offset of physical memory within mappings
-pub const PHYS_MEM_OFFSET: usize = 0xFFFF800000000000;
+/// Offset of physical memory within the loader's boot page tables, and the kernel's unless the loader picks another
+pub const DEFAULT_PHYS_MEM_OFFSET: usize = 0xFFFF800000000000;
+
+/// Offset of physical memory within the current mappings
+static PHYS_MEM_OFFSET: AtomicUsize = AtomicUsize::new(DEFAULT_PHYS_MEM_OFFSET);
+
+/// Guards against the physical memory offset changing once it has been set
+static PHYS_MEM_OFFSET_SET: InitOnce = InitOnce::new();
+
+/// Returns the offset of physical memory within the current mappings
+pub fn phys_mem_offset() -> usize {
+    PHYS_MEM_OFFSET.load(Ordering::Relaxed)
+}
+
+/// Sets the offset of physical memory within the current mappings, returning false if it was already set.
+///
+/// Panics if the offset is not a canonical address aligned to a huge page.
+///
+/// ## Safety
+/// All physical memory must be mapped at the offset, and nothing may hold a pointer into the previous mapping.
+pub unsafe fn set_phys_mem_offset(offset: usize) -> bool {
+    assert!(
+        is_aligned(VirtAddr::new(offset).as_usize(), HUGE_L3_PAGE_SIZE),
+        "physical memory offset {offset:#X} is not aligned to a huge page"
+    );
+
+    PHYS_MEM_OFFSET_SET
+        .call_once(|| PHYS_MEM_OFFSET.store(offset, Ordering::Relaxed))
+        .is_some()
+}
 
 /// Align downwards - returns the greatest _x_ with alignment of page size
-/// such that _x_ <= addr. `align` must be power of 2
+/// such that _x_ <= addr
 pub fn align_down_to_page(addr: usize) -> usize {
     align_down(addr, PAGE_SIZE)
 }
 
 /// Align upwards - returns the smallest _x_ with alignment of page size
-/// such that _x_ >= addr. `align` must be power of 2
+/// such that _x_ >= addr. Panics if _x_ would overflow
 pub fn align_up_to_page(addr: usize) -> usize {
     align_up(addr, PAGE_SIZE)
 }
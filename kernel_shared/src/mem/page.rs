@@ -2,6 +2,8 @@
 
 use core::iter::Step;
 
+use crate::mem::addr::VirtAddr;
+
 /// Size of a normal page in bytes
 pub const PAGE_SIZE: usize = 0x1000;
 
@@ -20,20 +22,15 @@ pub struct Page {
 
 impl Page {
     /// Returns the page that contains the specified virtual address
-    pub fn containing_address(address: usize) -> Page {
-        assert!(
-            !(0x0000_8000_0000_0000..0xFFFF_8000_0000_0000).contains(&address),
-            "invalid address: 0x{address:x}"
-        );
-
+    pub fn containing_address(address: VirtAddr) -> Page {
         Page {
-            number: address / PAGE_SIZE,
+            number: address.as_usize() / PAGE_SIZE,
         }
     }
 
     /// Returns the start address of the page
-    pub fn start_address(&self) -> usize {
-        self.number * PAGE_SIZE
+    pub fn start_address(&self) -> VirtAddr {
+        VirtAddr::new(self.number * PAGE_SIZE)
     }
 
     /// Returns index into p4 table
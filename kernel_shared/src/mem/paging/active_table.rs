@@ -23,8 +23,7 @@ impl ActivePageTable {
     /// # Safety
     /// This should only ever be called once
     pub unsafe fn new() -> Self {
-        let table = CR3::read().0.start_address() + super::PHYS_MEM_OFFSET;
-        let table = table as *mut Table<Level4>;
+        let table = CR3::read().0.start_address().as_hhdm_ptr::<Table<Level4>>();
 
         Self {
             mapper: unsafe { Mapper::new(table) },
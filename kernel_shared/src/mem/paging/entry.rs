@@ -4,7 +4,7 @@ use std::elf::section_header::SectionHeader;
 
 use bitflags::bitflags;
 
-use crate::mem::frame::Frame;
+use crate::mem::{addr::PhysAddr, frame::Frame};
 
 bitflags! {
     /// Stores possible flags for a page entry
@@ -91,9 +91,14 @@ impl Entry {
     /// Sets the entry to the given frame and flags
     pub fn set(&mut self, frame: Frame, flags: EntryFlags) {
         // ensure address is page aligned and smaller than 2^52
-        assert_eq!(frame.start_address() & !Self::ADDRESS_MASK, 0);
+        assert_eq!(frame.start_address().as_usize() & !Self::ADDRESS_MASK, 0);
 
-        self.0 = (frame.start_address() as u64) | flags.bits();
+        self.0 = (frame.start_address().as_usize() as u64) | flags.bits();
+    }
+
+    /// Replaces the flags, keeping the frame the same
+    pub fn set_flags(&mut self, flags: EntryFlags) {
+        self.0 = (self.0 & Self::ADDRESS_MASK as u64) | flags.bits();
     }
 
     /// Returns the flags
@@ -104,9 +109,9 @@ impl Entry {
     /// Returns the frame the entry points to, if it exists
     pub fn pointed_frame(&self) -> Option<Frame> {
         if self.flags().contains(EntryFlags::PRESENT) {
-            Some(Frame::containing_address(
+            Some(Frame::containing_address(PhysAddr::new(
                 self.0 as usize & Self::ADDRESS_MASK,
-            ))
+            )))
         } else {
             None
         }
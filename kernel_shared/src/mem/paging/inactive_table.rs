@@ -25,9 +25,9 @@ impl InactivePageTable {
     /// This should only ever be called with a valid frame
     pub unsafe fn new(frame: Frame) -> Self {
         unsafe {
-            core::ptr::write_bytes(frame.start_address() as *mut u64, 0, 512);
+            core::ptr::write_bytes(frame.start_address().as_usize() as *mut u64, 0, 512);
         }
-        let table = frame.start_address() as *mut Table<Level4>;
+        let table = frame.start_address().as_usize() as *mut Table<Level4>;
 
         Self {
             mapper: unsafe { Mapper::new(table) },
@@ -1,16 +1,19 @@
 //! Code for mapping a virtual address to a physical address
 
-use core::ptr::NonNull;
-use std::is_aligned;
+use core::{
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{collections::ArrayVec, is_aligned};
 
 use crate::{
     mem::{
-        align_down_to_page,
+        addr::{PhysAddr, VirtAddr},
         frame::Frame,
         frame_alloc::FrameAllocator,
         page::{HUGE_L2_PAGE_SIZE, HUGE_L3_PAGE_SIZE, PAGE_SIZE, Page},
         paging::{
-            ENTRY_COUNT,
+            ENTRY_COUNT, MapError,
             entry::EntryFlags,
             table::{Level4, Table},
         },
@@ -18,6 +21,30 @@ use crate::{
     x86::invalidate_address,
 };
 
+/// Start of the range of virtual addresses which can't be modified, see [`lock_range`]
+static LOCKED_START: AtomicUsize = AtomicUsize::new(0);
+
+/// End (exclusive) of the range of virtual addresses which can't be modified, see [`lock_range`]
+static LOCKED_END: AtomicUsize = AtomicUsize::new(0);
+
+/// Prevents pages within `start..end` from being mapped, unmapped or having their flags changed, other than through
+/// [`Mapper::update_flags_unchecked`]. Replaces any previously locked range.
+pub fn lock_range(start: VirtAddr, end: VirtAddr) {
+    LOCKED_START.store(start.as_usize(), Ordering::Relaxed);
+    LOCKED_END.store(end.as_usize(), Ordering::Relaxed);
+}
+
+/// Panics if the page lies within the locked range
+fn assert_unlocked(page: Page) {
+    let locked = LOCKED_START.load(Ordering::Relaxed)..LOCKED_END.load(Ordering::Relaxed);
+
+    assert!(
+        !locked.contains(&page.start_address().as_usize()),
+        "attempted to modify locked page at {}",
+        page.start_address()
+    );
+}
+
 /// A struct to map addresses with the stored L4 table
 pub struct Mapper {
     /// Base L4 table to use
@@ -46,11 +73,11 @@ impl Mapper {
     }
 
     /// Translates a given virtual address to its physical address
-    pub fn translate(&self, virt_addr: usize) -> Option<usize> {
-        let offset = virt_addr % PAGE_SIZE;
+    pub fn translate(&self, virt_addr: VirtAddr) -> Option<PhysAddr> {
+        let offset = virt_addr.as_usize() % PAGE_SIZE;
 
         self.translate_page(Page::containing_address(virt_addr))
-            .map(|frame| frame.number * PAGE_SIZE + offset)
+            .map(|frame| frame.start_address() + offset)
     }
 
     /// Finds the frame that a given page points to
@@ -97,9 +124,16 @@ impl Mapper {
     }
 
     /// Maps a given page to any available frame, using the provided flags
-    pub fn map<A: FrameAllocator>(&mut self, page: Page, flags: EntryFlags, allocator: &mut A) {
-        let frame = allocator.allocate_frame().expect("out of memory");
+    pub fn map<A: FrameAllocator>(
+        &mut self,
+        page: Page,
+        flags: EntryFlags,
+        allocator: &mut A,
+    ) -> Result<(), MapError> {
+        let frame = allocator.allocate_frame().ok_or(MapError::OutOfFrames)?;
+
         self.map_to(page, frame, flags, allocator)
+            .inspect_err(|_| allocator.deallocate_frame(frame))
     }
 
     /// Maps a given page to a given frame, using the provided flags
@@ -109,15 +143,18 @@ impl Mapper {
         frame: Frame,
         flags: EntryFlags,
         allocator: &mut A,
-    ) {
+    ) -> Result<(), MapError> {
+        assert_unlocked(page);
+
         let p4 = self.p4_mut();
-        let p3 = p4.next_table_create(page.p4_index(), allocator);
-        let p2 = p3.next_table_create(page.p3_index(), allocator);
-        let p1 = p2.next_table_create(page.p2_index(), allocator);
+        let p3 = p4.next_table_create(page.p4_index(), allocator)?;
+        let p2 = p3.next_table_create(page.p3_index(), allocator)?;
+        let p1 = p2.next_table_create(page.p2_index(), allocator)?;
 
         assert!(p1[page.p1_index()].is_unused());
 
         p1[page.p1_index()].set(frame, flags | EntryFlags::PRESENT);
+        Ok(())
     }
 
     /// Maps a given page to a given frame, using the provided flags and a 2MiB page entry
@@ -127,15 +164,16 @@ impl Mapper {
         frame: Frame,
         flags: EntryFlags,
         allocator: &mut A,
-    ) {
+    ) -> Result<(), MapError> {
         let p4 = self.p4_mut();
-        let p3 = p4.next_table_create(page.p4_index(), allocator);
-        let p2 = p3.next_table_create(page.p3_index(), allocator);
+        let p3 = p4.next_table_create(page.p4_index(), allocator)?;
+        let p2 = p3.next_table_create(page.p3_index(), allocator)?;
 
         assert_eq!(page.p1_index(), 0);
         assert!(p2[page.p2_index()].is_unused());
 
         p2[page.p2_index()].set(frame, flags | EntryFlags::PRESENT | EntryFlags::HUGE_PAGE);
+        Ok(())
     }
 
     /// Maps a given page to a given frame, using the provided flags and a 1GiB page entry
@@ -145,15 +183,16 @@ impl Mapper {
         frame: Frame,
         flags: EntryFlags,
         allocator: &mut A,
-    ) {
+    ) -> Result<(), MapError> {
         let p4 = self.p4_mut();
-        let p3 = p4.next_table_create(page.p4_index(), allocator);
+        let p3 = p4.next_table_create(page.p4_index(), allocator)?;
 
         assert_eq!(page.p1_index(), 0);
         assert_eq!(page.p2_index(), 0);
         assert!(p3[page.p3_index()].is_unused());
 
         p3[page.p3_index()].set(frame, flags | EntryFlags::PRESENT | EntryFlags::HUGE_PAGE);
+        Ok(())
     }
 
     /// Identity maps a given frame, using the provided flags
@@ -162,43 +201,59 @@ impl Mapper {
         frame: Frame,
         flags: EntryFlags,
         allocator: &mut A,
-    ) {
-        let page = Page::containing_address(frame.start_address());
+    ) -> Result<(), MapError> {
+        let page = Page::containing_address(VirtAddr::new(frame.start_address().as_usize()));
         self.map_to(page, frame, flags, allocator)
     }
 
     /// Maps a range of addresses. `use_huge_tables` should be used carefully since they can not currently be unmapped
+    ///
+    /// Only as much as fits in the shorter of the two ranges is mapped. If frames run out partway, the pages mapped so
+    /// far are left mapped.
+    ///
+    /// Panics if either range ends before it starts.
     pub fn map_range<A: FrameAllocator>(
         &mut self,
-        phys_range: (usize, usize),
-        virt_range: (usize, usize),
+        phys_range: (PhysAddr, PhysAddr),
+        virt_range: (VirtAddr, VirtAddr),
         flags: EntryFlags,
         allocator: &mut A,
         use_huge_tables: bool,
-    ) {
+    ) -> Result<(), MapError> {
         // first make sure to align to pages
-        let start_phys = align_down_to_page(phys_range.0);
-        let end_phys = align_down_to_page(phys_range.1);
+        let start_phys = phys_range.0.align_down(PAGE_SIZE);
+        let end_phys = phys_range.1.align_down(PAGE_SIZE);
 
-        let start_virt = align_down_to_page(virt_range.0);
-        let end_virt = align_down_to_page(virt_range.1);
+        let start_virt = virt_range.0.align_down(PAGE_SIZE);
+        let end_virt = virt_range.1.align_down(PAGE_SIZE);
 
         log::trace!("mapping {start_virt:#X}-{end_virt:#X} to {start_phys:#X}-{end_phys:#X}");
+        assert!(
+            start_phys <= end_phys && start_virt <= end_virt,
+            "map_range given a range which ends before it starts"
+        );
 
         // check how addresses are aligned relative to each other to check if huge tables are even possible
-        let huge_l3_possible =
-            use_huge_tables && is_aligned(start_virt - start_phys, HUGE_L3_PAGE_SIZE);
-        let huge_l2_possible =
-            use_huge_tables && is_aligned(start_virt - start_phys, HUGE_L2_PAGE_SIZE);
-
-        let to_map = (end_phys - start_phys).min(start_virt - end_virt);
+        let huge_l3_possible = use_huge_tables
+            && is_aligned(
+                start_virt.as_usize() - start_phys.as_usize(),
+                HUGE_L3_PAGE_SIZE,
+            );
+        let huge_l2_possible = use_huge_tables
+            && is_aligned(
+                start_virt.as_usize() - start_phys.as_usize(),
+                HUGE_L2_PAGE_SIZE,
+            );
+
+        // both lengths are end - start, so a virtual range shorter than the physical one can't be overrun
+        let to_map = (end_phys - start_phys).min(end_virt - start_virt);
         let mut mapped = 0;
 
         while mapped <= to_map {
             if huge_l3_possible
                 && to_map - mapped >= HUGE_L3_PAGE_SIZE
-                && is_aligned(start_phys + mapped, HUGE_L3_PAGE_SIZE)
-                && is_aligned(start_virt + mapped, HUGE_L3_PAGE_SIZE)
+                && (start_phys + mapped).is_aligned(HUGE_L3_PAGE_SIZE)
+                && (start_virt + mapped).is_aligned(HUGE_L3_PAGE_SIZE)
             {
                 // if need to map more than HUGE_L3_PAGE_SIZE and addresses are aligned, map a 1GiB page
                 self.map_to_huge_l3(
@@ -206,13 +261,13 @@ impl Mapper {
                     Frame::containing_address(start_phys + mapped),
                     flags,
                     allocator,
-                );
+                )?;
 
                 mapped += HUGE_L3_PAGE_SIZE;
             } else if huge_l2_possible
                 && to_map - mapped >= HUGE_L2_PAGE_SIZE
-                && is_aligned(start_phys + mapped, HUGE_L2_PAGE_SIZE)
-                && is_aligned(start_virt + mapped, HUGE_L2_PAGE_SIZE)
+                && (start_phys + mapped).is_aligned(HUGE_L2_PAGE_SIZE)
+                && (start_virt + mapped).is_aligned(HUGE_L2_PAGE_SIZE)
             {
                 // then repeat for 2MiB page
                 self.map_to_huge_l2(
@@ -220,7 +275,7 @@ impl Mapper {
                     Frame::containing_address(start_phys + mapped),
                     flags,
                     allocator,
-                );
+                )?;
 
                 mapped += HUGE_L2_PAGE_SIZE;
             } else {
@@ -230,11 +285,51 @@ impl Mapper {
                     Frame::containing_address(start_phys + mapped),
                     flags,
                     allocator,
-                );
+                )?;
 
                 mapped += PAGE_SIZE;
             }
         }
+
+        Ok(())
+    }
+
+    /// Replaces the flags of a mapped 4KiB page, returning the previous flags.
+    ///
+    /// Returns None if the page isn't mapped, or is part of a huge page. Panics if the page is locked.
+    pub fn update_flags(&mut self, page: Page, flags: EntryFlags) -> Option<EntryFlags> {
+        assert_unlocked(page);
+
+        unsafe { self.update_flags_unchecked(page, flags) }
+    }
+
+    /// Replaces the flags of a mapped 4KiB page, returning the previous flags, even if the page is locked.
+    ///
+    /// Returns None if the page isn't mapped, or is part of a huge page.
+    ///
+    /// # Safety
+    /// Nothing can rely on the page keeping its current flags, such as kernel code relying on being read only.
+    pub unsafe fn update_flags_unchecked(
+        &mut self,
+        page: Page,
+        flags: EntryFlags,
+    ) -> Option<EntryFlags> {
+        let p1 = self
+            .p4_mut()
+            .next_table_mut(page.p4_index())
+            .and_then(|p3| p3.next_table_mut(page.p3_index()))
+            .and_then(|p2| p2.next_table_mut(page.p2_index()))?;
+
+        let entry = &mut p1[page.p1_index()];
+        let old_flags = entry.flags();
+        if !old_flags.contains(EntryFlags::PRESENT) {
+            return None;
+        }
+
+        entry.set_flags(flags | EntryFlags::PRESENT);
+        invalidate_address(page.start_address());
+
+        Some(old_flags)
     }
 
     /// Unmaps a given page
@@ -242,6 +337,7 @@ impl Mapper {
     where
         A: FrameAllocator,
     {
+        assert_unlocked(page);
         assert!(self.translate(page.start_address()).is_some());
 
         let p3 = self
@@ -258,8 +354,7 @@ impl Mapper {
         let frame = p1[page.p1_index()].pointed_frame().unwrap();
         p1[page.p1_index()].set_unused();
 
-        invalidate_address(frame.start_address());
-        allocator.deallocate_frame(frame);
+        let mut unused_tables = ArrayVec::<Frame, 3>::new();
 
         // TODO: remove repeated code
         if free_unused_tables {
@@ -268,9 +363,7 @@ impl Mapper {
                 p2[page.p2_index()].set_unused();
 
                 log::trace!("freeing unused p1 table at frame {p1_frame:?}");
-
-                invalidate_address(p1_frame.start_address());
-                allocator.deallocate_frame(p1_frame);
+                let _ = unused_tables.push(p1_frame);
             }
 
             if p2.is_empty() {
@@ -278,8 +371,7 @@ impl Mapper {
                 p3[page.p3_index()].set_unused();
 
                 log::trace!("freeing unused p2 table at frame {p2_frame:?}");
-                invalidate_address(p2_frame.start_address());
-                allocator.deallocate_frame(p2_frame);
+                let _ = unused_tables.push(p2_frame);
             }
 
             if p3.is_empty() {
@@ -287,9 +379,18 @@ impl Mapper {
                 self.p4_mut()[page.p4_index()].set_unused();
 
                 log::trace!("freeing unused p3 table at frame {p3_frame:?}");
-                invalidate_address(p3_frame.start_address());
-                allocator.deallocate_frame(p3_frame);
+                let _ = unused_tables.push(p3_frame);
             }
         }
+
+        // invlpg is keyed by virtual address, so must be given the page rather than any of the frames being freed. It
+        // also drops every cached paging-structure entry, so one flush covers the tables unlinked above, and has to
+        // happen before any of those frames can be handed out again
+        invalidate_address(page.start_address());
+
+        allocator.deallocate_frame(frame);
+        for &table in unused_tables.as_slice() {
+            allocator.deallocate_frame(table);
+        }
     }
 }
@@ -1,5 +1,7 @@
 //! Module for paging
 
+use core::fmt::{Display, Formatter};
+
 pub mod active_table;
 pub mod entry;
 pub mod inactive_table;
@@ -9,5 +11,17 @@ pub mod table;
 /// Number of entries per page (4KiB / 8 bytes)
 const ENTRY_COUNT: usize = 512;
 
-/// Offset for physical memory mapping
-const PHYS_MEM_OFFSET: usize = 0xFFFF800000000000;
+/// An error mapping a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    /// No frames were left for the page or the tables leading to it
+    OutOfFrames,
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::OutOfFrames => write!(f, "out of frames to map page"),
+        }
+    }
+}
@@ -6,9 +6,10 @@ use core::{
 };
 
 use crate::mem::{
+    addr::VirtAddr,
     frame_alloc::FrameAllocator,
     paging::{
-        ENTRY_COUNT, PHYS_MEM_OFFSET,
+        ENTRY_COUNT, MapError,
         entry::{Entry, EntryFlags},
     },
 };
@@ -75,13 +76,13 @@ impl<L: TableLevel> Table<L> {
 
 impl<L: HierarchicalLevel> Table<L> {
     /// Finds address of the next level table at the given index
-    fn next_table_address(&self, index: usize) -> Option<usize> {
+    fn next_table_address(&self, index: usize) -> Option<VirtAddr> {
         let entry_flags = self[index].flags();
 
         if !entry_flags.contains(EntryFlags::HUGE_PAGE) {
             self[index]
                 .pointed_frame()
-                .map(|frame| frame.start_address() | PHYS_MEM_OFFSET)
+                .map(|frame| frame.start_address().to_virt())
         } else {
             None
         }
@@ -90,13 +91,13 @@ impl<L: HierarchicalLevel> Table<L> {
     /// Finds the next level table at the given index
     pub fn next_table(&self, index: usize) -> Option<&Table<L::NextLevel>> {
         self.next_table_address(index)
-            .map(|address| unsafe { &*(address as *const _) })
+            .map(|address| unsafe { &*address.as_ptr() })
     }
 
     /// Finds the next level table at the given index
     pub fn next_table_mut(&mut self, index: usize) -> Option<&mut Table<L::NextLevel>> {
         self.next_table_address(index)
-            .map(|address| unsafe { &mut *(address as *mut _) })
+            .map(|address| unsafe { &mut *address.as_mut_ptr() })
     }
 
     /// Finds the next level table with the specified index, creating a blank table if it doesn't exist
@@ -104,7 +105,7 @@ impl<L: HierarchicalLevel> Table<L> {
         &mut self,
         index: usize,
         allocator: &mut A,
-    ) -> &mut Table<L::NextLevel> {
+    ) -> Result<&mut Table<L::NextLevel>, MapError> {
         // create table if doesnt exist
         if self.next_table(index).is_none() {
             assert!(
@@ -113,14 +114,14 @@ impl<L: HierarchicalLevel> Table<L> {
             );
 
             // allocate a frame, point to it, and make sure its zeroed
-            let frame = allocator.allocate_frame().expect("no available frames");
+            let frame = allocator.allocate_frame().ok_or(MapError::OutOfFrames)?;
 
             self.entries[index].set(frame, EntryFlags::PRESENT | EntryFlags::WRITABLE);
             self.next_table_mut(index).unwrap().zero();
         }
 
         // we know next table either already existed, or we created it
-        self.next_table_mut(index).unwrap()
+        Ok(self.next_table_mut(index).unwrap())
     }
 }
 
@@ -0,0 +1,44 @@
+//! Helpers for accessing physical memory through the physical memory mapping
+
+use core::ptr::{read_unaligned, read_volatile, write_volatile};
+
+use crate::mem::addr::PhysAddr;
+
+/// Reads a value from physical memory. The address does not need to be aligned.
+///
+/// ## Safety
+/// `addr` must lie within the physical memory mapping, and hold a valid `T`.
+pub unsafe fn phys_read<T: Copy>(addr: PhysAddr) -> T {
+    unsafe { read_unaligned(addr.as_hhdm_ptr::<T>()) }
+}
+
+/// Reads a value from physical memory without the read being elided or reordered, such as for MMIO registers.
+///
+/// ## Safety
+/// `addr` must lie within the physical memory mapping, be aligned for `T`, and hold a valid `T`.
+pub unsafe fn phys_read_volatile<T: Copy>(addr: PhysAddr) -> T {
+    unsafe { read_volatile(addr.as_hhdm_ptr::<T>()) }
+}
+
+/// Writes a value to physical memory without the write being elided or reordered, such as for MMIO registers.
+///
+/// ## Safety
+/// `addr` must lie within the physical memory mapping and be aligned for `T`, and writing to it must not break
+/// anything else relying on that memory.
+pub unsafe fn phys_write_volatile<T: Copy>(addr: PhysAddr, value: T) {
+    unsafe { write_volatile(addr.as_hhdm_ptr::<T>(), value) }
+}
+
+/// Returns a slice of `len` values starting at the given physical address.
+///
+/// ## Safety
+/// The whole slice must lie within the physical memory mapping, be aligned for `T`, hold valid values, and not be
+/// modified for as long as the slice is used.
+pub unsafe fn phys_slice<T>(addr: PhysAddr, len: usize) -> &'static [T] {
+    debug_assert!(
+        addr.is_aligned(align_of::<T>()),
+        "unaligned physical slice at {addr}"
+    );
+
+    unsafe { core::slice::from_raw_parts(addr.as_hhdm_ptr::<T>(), len) }
+}
@@ -0,0 +1,172 @@
+//! A few bytes of settings persisted across reboots in spare CMOS NVRAM, so no storage driver is needed.
+//!
+//! The settings block is [`LENGTH`] bytes starting at [`BASE`], laid out as a magic byte, a version, the settings
+//! themselves, and a checksum byte making the whole block sum to zero. The registers used are outside those the
+//! RTC and common firmware (including QEMU's) rely on.
+
+use core::fmt::{Display, Formatter};
+use std::static_assert;
+
+use log::LevelFilter;
+
+use crate::x86::{
+    hardware::cmos::{CMOS_SIZE, Cmos},
+    without_interrupts,
+};
+
+/// First CMOS register used by the settings block
+pub const BASE: u8 = 0x60;
+
+/// Length of the settings block in bytes
+pub const LENGTH: usize = 16;
+
+static_assert!(BASE as usize + LENGTH <= CMOS_SIZE as usize);
+
+/// Marks the block as holding settings rather than whatever was there before
+const MAGIC: u8 = 0xA7;
+
+/// Layout version, bumped whenever fields move
+const VERSION: u8 = 1;
+
+/// Offset of the log level, `0xFF` if unset
+const OFFSET_LOG_LEVEL: usize = 2;
+
+/// Offset of the boot status
+const OFFSET_BOOT_STATUS: usize = 3;
+
+/// Value stored for an unset log level
+const LOG_LEVEL_UNSET: u8 = 0xFF;
+
+/// An error loading settings from NVRAM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NvramError {
+    /// Block does not start with the magic byte, so settings were never saved
+    Missing,
+    /// Settings were saved by an incompatible version
+    UnsupportedVersion(u8),
+    /// Block does not sum to zero
+    BadChecksum,
+}
+
+impl Display for NvramError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "no settings saved in NVRAM"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "NVRAM settings have unsupported version {version}")
+            }
+            Self::BadChecksum => write!(f, "NVRAM settings failed checksum"),
+        }
+    }
+}
+
+/// How far the last boot got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BootStatus {
+    /// Nothing recorded
+    Unknown = 0,
+    /// Kernel started initialising, but didn't finish
+    Booting = 1,
+    /// Kernel finished initialising
+    Running = 2,
+    /// Kernel panicked
+    Panicked = 3,
+}
+
+impl BootStatus {
+    /// Decodes a stored boot status, treating anything unrecognised as unknown
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Booting,
+            2 => Self::Running,
+            3 => Self::Panicked,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Settings stored in NVRAM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    /// Log level to use before the command line is applied
+    pub log_level: Option<LevelFilter>,
+    /// How far the last boot got
+    pub boot_status: BootStatus,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            log_level: None,
+            boot_status: BootStatus::Unknown,
+        }
+    }
+}
+
+impl Settings {
+    /// Encodes the settings into a block, including the checksum
+    fn to_bytes(self) -> [u8; LENGTH] {
+        let mut bytes = [0; LENGTH];
+        bytes[0] = MAGIC;
+        bytes[1] = VERSION;
+        bytes[OFFSET_LOG_LEVEL] = self.log_level.map_or(LOG_LEVEL_UNSET, |level| level as u8);
+        bytes[OFFSET_BOOT_STATUS] = self.boot_status as u8;
+
+        bytes[LENGTH - 1] = 0u8.wrapping_sub(checksum(&bytes));
+        bytes
+    }
+
+    /// Decodes settings from a block, checking its magic, version and checksum
+    fn from_bytes(bytes: &[u8; LENGTH]) -> Result<Self, NvramError> {
+        if bytes[0] != MAGIC {
+            return Err(NvramError::Missing);
+        }
+        if bytes[1] != VERSION {
+            return Err(NvramError::UnsupportedVersion(bytes[1]));
+        }
+        if checksum(bytes) != 0 {
+            return Err(NvramError::BadChecksum);
+        }
+
+        Ok(Self {
+            log_level: LevelFilter::iter().nth(bytes[OFFSET_LOG_LEVEL] as usize),
+            boot_status: BootStatus::from_u8(bytes[OFFSET_BOOT_STATUS]),
+        })
+    }
+}
+
+/// Wrapping sum of every byte
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Reads settings from NVRAM
+pub fn load() -> Result<Settings, NvramError> {
+    let bytes = without_interrupts(|| {
+        let mut cmos = Cmos::new();
+        core::array::from_fn(|i| cmos.read(BASE + i as u8))
+    });
+
+    Settings::from_bytes(&bytes)
+}
+
+/// Writes settings to NVRAM
+pub fn store(settings: &Settings) {
+    let bytes = settings.to_bytes();
+
+    without_interrupts(|| {
+        let mut cmos = Cmos::new();
+        for (i, byte) in bytes.into_iter().enumerate() {
+            // SAFETY: the settings block is outside the registers used by the RTC and firmware
+            unsafe { cmos.write(BASE + i as u8, byte) };
+        }
+    });
+}
+
+/// Loads settings, falling back to defaults if none are saved, then stores them again after `f` modifies them
+pub fn update(f: impl FnOnce(&mut Settings)) {
+    let mut settings = load().unwrap_or_default();
+    f(&mut settings);
+    store(&settings);
+}
@@ -0,0 +1,146 @@
+//! Persistent log store, kept in a region of RAM which survives warm reboots.
+//!
+//! The loader reserves the region so nothing else is allocated there, and the kernel checks it on boot for the
+//! previous session's log before reusing it for this one. The region is chosen from the memory map alone, so it
+//! lands in the same place every boot on the same machine.
+
+use core::fmt::Write;
+use std::assert_eq_size;
+
+use crate::{
+    boot::{BootProtocol, MemoryRegionKind},
+    config,
+    mem::{addr::PhysAddr, frame::FRAME_SIZE},
+};
+
+/// Marks a region as holding a persistent log, "RUSTYLOG"
+const MAGIC: u64 = u64::from_le_bytes(*b"RUSTYLOG");
+
+/// Size of the header before the log data
+const HEADER_SIZE: usize = 4 * size_of::<u64>();
+
+/// Number of bytes of log data kept
+const CAPACITY: usize = config::PSTORE_SIZE - HEADER_SIZE;
+
+/// Persistent store is only placed below 4GiB, which is mapped by every loader
+const MAX_ADDRESS: usize = 0x1_0000_0000;
+
+/// Returns the physical address the persistent store should be placed at: the end of the highest usable memory
+/// region below 4GiB.
+pub fn locate(boot_info: &impl BootProtocol) -> Option<PhysAddr> {
+    boot_info
+        .memory_regions()
+        .filter(|region| region.kind == MemoryRegionKind::Usable)
+        .filter_map(|region| {
+            let end = (region.start + region.length).as_usize().min(MAX_ADDRESS);
+            let start = PhysAddr::new(end.checked_sub(config::PSTORE_SIZE)?).align_down(FRAME_SIZE);
+
+            // leave the bulk of the region for everything else
+            (start >= region.start + region.length / 2).then_some(start)
+        })
+        .max()
+}
+
+/// Log stored in persistent memory, as a ring of bytes
+#[repr(C)]
+pub struct PersistentLog {
+    /// Always [`MAGIC`] if the region holds a log
+    magic: u64,
+    /// Index the next byte will be written to
+    head: u64,
+    /// Number of valid bytes
+    length: u64,
+    /// Wrapping sum of every data byte, updated as bytes are overwritten
+    checksum: u64,
+    /// Log data
+    data: [u8; CAPACITY],
+}
+
+assert_eq_size!(PersistentLog, config::PSTORE_SIZE);
+
+impl PersistentLog {
+    /// Returns the persistent log stored at the given address, which may hold anything until checked with
+    /// [`Self::is_valid`].
+    ///
+    /// ## Safety
+    /// `addr` must point to [`config::PSTORE_SIZE`] bytes of memory which are reserved for the persistent log.
+    pub unsafe fn from_addr(addr: *mut u8) -> &'static mut Self {
+        unsafe { &mut *(addr as *mut Self) }
+    }
+
+    /// Whether the region holds an intact log, left by a previous session
+    pub fn is_valid(&self) -> bool {
+        self.magic == MAGIC
+            && self.head < CAPACITY as u64
+            && self.length <= CAPACITY as u64
+            && self.checksum == Self::sum(&self.data)
+    }
+
+    /// Returns the stored log as two slices, oldest first, if it is intact.
+    ///
+    /// If the log has wrapped, the first line is likely cut off, so it is skipped.
+    pub fn contents(&self) -> Option<(&[u8], &[u8])> {
+        if !self.is_valid() {
+            return None;
+        }
+
+        let (head, length) = (self.head as usize, self.length as usize);
+
+        if length < CAPACITY {
+            return Some((&self.data[..length], &[]));
+        }
+
+        let (newer, older) = self.data.split_at(head);
+        match older.iter().position(|&byte| byte == b'\n') {
+            Some(newline) => Some((&older[newline + 1..], newer)),
+            None => Some((
+                &[],
+                &newer[newer.iter().position(|&byte| byte == b'\n')? + 1..],
+            )),
+        }
+    }
+
+    /// Clears the log, marking the region as holding a valid empty log
+    pub fn reset(&mut self) {
+        self.data.fill(0);
+        self.head = 0;
+        self.length = 0;
+        self.checksum = 0;
+        self.magic = MAGIC;
+    }
+
+    /// Writes all cached log data back to memory, so it survives a reset which doesn't flush caches
+    pub fn flush(&self) {
+        unsafe { core::arch::asm!("wbinvd", options(nostack, preserves_flags)) };
+    }
+
+    /// Appends a byte, overwriting the oldest byte if full
+    fn push(&mut self, byte: u8) {
+        let head = self.head as usize;
+
+        self.checksum = self
+            .checksum
+            .wrapping_sub(self.data[head] as u64)
+            .wrapping_add(byte as u64);
+        self.data[head] = byte;
+
+        self.head = ((head + 1) % CAPACITY) as u64;
+        self.length = (self.length + 1).min(CAPACITY as u64);
+    }
+
+    /// Sums the given bytes as the checksum does
+    fn sum(data: &[u8]) -> u64 {
+        data.iter()
+            .fold(0u64, |sum, &byte| sum.wrapping_add(byte as u64))
+    }
+}
+
+impl Write for PersistentLog {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for byte in s.bytes() {
+            self.push(byte);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! ChaCha20 based pseudo-random number generator, as described in RFC 8439
+
+/// "expand 32-byte k", the ChaCha constant
+const CONSTANTS: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+/// Number of 32-bit words in a single block
+const BLOCK_WORDS: usize = 16;
+
+/// Number of double rounds performed per block (ChaCha20)
+const DOUBLE_ROUNDS: usize = 10;
+
+/// Cryptographically secure pseudo-random number generator using the ChaCha20 block function as a keystream
+pub struct ChaChaRng {
+    /// Key, counter and nonce words used as input to the block function
+    state: [u32; BLOCK_WORDS],
+    /// Most recently generated block of output
+    buffer: [u32; BLOCK_WORDS],
+    /// Index of next unused word in `buffer`
+    index: usize,
+}
+
+impl ChaChaRng {
+    /// Constructs a generator from a 256-bit seed
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let mut state = [0u32; BLOCK_WORDS];
+        state[..4].copy_from_slice(&CONSTANTS);
+
+        for (word, bytes) in state[4..12].iter_mut().zip(seed.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        // counter and nonce start at zero
+
+        Self {
+            state,
+            buffer: [0; BLOCK_WORDS],
+            index: BLOCK_WORDS,
+        }
+    }
+
+    /// Mixes additional entropy into the key, discarding any buffered output
+    pub fn reseed(&mut self, seed: [u8; 32]) {
+        // derive the new key from the old keystream so previous seeds still contribute
+        let mut key = [0u32; 8];
+        for word in key.iter_mut() {
+            *word = self.next_u32();
+        }
+
+        for ((state, old), bytes) in self.state[4..12]
+            .iter_mut()
+            .zip(key)
+            .zip(seed.chunks_exact(4))
+        {
+            *state = old ^ u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        self.index = BLOCK_WORDS;
+    }
+
+    /// Returns the next random 32-bit integer
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= BLOCK_WORDS {
+            self.refill();
+        }
+
+        let value = self.buffer[self.index];
+        self.index += 1;
+
+        value
+    }
+
+    /// Returns the next random 64-bit integer
+    pub fn next_u64(&mut self) -> u64 {
+        (self.next_u32() as u64) << 32 | self.next_u32() as u64
+    }
+
+    /// Fills the given buffer with random bytes
+    pub fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let mut chunks = dest.chunks_exact_mut(4);
+        for chunk in &mut chunks {
+            chunk.copy_from_slice(&self.next_u32().to_le_bytes());
+        }
+
+        let remainder = chunks.into_remainder();
+        let len = remainder.len();
+        remainder.copy_from_slice(&self.next_u32().to_le_bytes()[..len]);
+    }
+
+    /// Generates the next block of keystream and advances the counter
+    fn refill(&mut self) {
+        let mut working = self.state;
+
+        for _ in 0..DOUBLE_ROUNDS {
+            // column rounds
+            quarter_round(&mut working, 0, 4, 8, 12);
+            quarter_round(&mut working, 1, 5, 9, 13);
+            quarter_round(&mut working, 2, 6, 10, 14);
+            quarter_round(&mut working, 3, 7, 11, 15);
+
+            // diagonal rounds
+            quarter_round(&mut working, 0, 5, 10, 15);
+            quarter_round(&mut working, 1, 6, 11, 12);
+            quarter_round(&mut working, 2, 7, 8, 13);
+            quarter_round(&mut working, 3, 4, 9, 14);
+        }
+
+        for (output, (working, state)) in self
+            .buffer
+            .iter_mut()
+            .zip(working.iter().zip(self.state.iter()))
+        {
+            *output = working.wrapping_add(*state);
+        }
+
+        // 64-bit block counter, so this will never realistically wrap
+        let counter = ((self.state[13] as u64) << 32 | self.state[12] as u64).wrapping_add(1);
+        self.state[12] = counter as u32;
+        self.state[13] = (counter >> 32) as u32;
+
+        self.index = 0;
+    }
+}
+
+/// Performs the ChaCha quarter round on the given words of the state
+fn quarter_round(state: &mut [u32; BLOCK_WORDS], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
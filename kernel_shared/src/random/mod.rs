@@ -0,0 +1,189 @@
+//! Random number generation.
+//!
+//! Entropy is gathered from RDSEED/RDRAND where the processor supports them, falling back to timing
+//! jitter measured with the timestamp counter. This is used to seed a ChaCha20 generator shared by
+//! the whole kernel.
+
+pub mod chacha;
+
+use core::{arch::asm, fmt::Display};
+use std::mutex::Mutex;
+
+use crate::{
+    random::chacha::ChaChaRng,
+    x86::{cpuid, rdtsc, without_interrupts},
+};
+
+/// Number of times to retry RDRAND/RDSEED before giving up, as recommended by Intel
+const HARDWARE_RETRIES: usize = 10;
+
+/// Number of timing samples mixed into each word of jitter entropy
+const JITTER_SAMPLES: usize = 64;
+
+/// Kernel-wide generator, seeded on first use
+static RNG: Mutex<Option<ChaChaRng>> = Mutex::new(None);
+
+/// Source of entropy used to seed the generator, ordered from strongest to weakest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntropySource {
+    /// RDSEED instruction
+    RdSeed,
+    /// RDRAND instruction
+    RdRand,
+    /// Timestamp counter jitter
+    Jitter,
+}
+
+impl Display for EntropySource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::RdSeed => "RDSEED",
+            Self::RdRand => "RDRAND",
+            Self::Jitter => "TSC jitter",
+        })
+    }
+}
+
+/// Reads a random value with RDRAND, returning `None` if it is unsupported or keeps failing
+pub fn rdrand() -> Option<u64> {
+    if !cpuid::has_rdrand() {
+        return None;
+    }
+
+    for _ in 0..HARDWARE_RETRIES {
+        let value: u64;
+        let success: u8;
+
+        unsafe {
+            asm!("rdrand {}", "setc {}", out(reg) value, out(reg_byte) success, options(nomem, nostack));
+        }
+
+        if success != 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+/// Reads a random value with RDSEED, returning `None` if it is unsupported or keeps failing
+pub fn rdseed() -> Option<u64> {
+    if !cpuid::has_rdseed() {
+        return None;
+    }
+
+    for _ in 0..HARDWARE_RETRIES {
+        let value: u64;
+        let success: u8;
+
+        unsafe {
+            asm!("rdseed {}", "setc {}", out(reg) value, out(reg_byte) success, options(nomem, nostack));
+        }
+
+        if success != 0 {
+            return Some(value);
+        }
+
+        core::hint::spin_loop();
+    }
+
+    None
+}
+
+/// Gathers entropy from variation in how long a small amount of work takes, as measured by the
+/// timestamp counter.
+///
+/// This is much weaker than the hardware sources, and is only used when neither is available.
+pub fn jitter() -> u64 {
+    let mut value = 0u64;
+
+    for _ in 0..JITTER_SAMPLES {
+        let start = rdtsc();
+
+        // some work whose duration depends on caches and pipeline state
+        let mut scratch = start;
+        for _ in 0..(start & 0xF) + 16 {
+            scratch = core::hint::black_box(scratch.rotate_left(7) ^ 0x9E3779B97F4A7C15);
+        }
+
+        let delta = rdtsc().wrapping_sub(start) ^ scratch;
+        value = value.rotate_left(5) ^ delta;
+    }
+
+    value
+}
+
+/// Collects a 256-bit seed from the best available entropy source, returning the weakest source used
+pub fn gather_seed() -> ([u8; 32], EntropySource) {
+    let mut seed = [0u8; 32];
+    let mut source = EntropySource::RdSeed;
+
+    for chunk in seed.chunks_exact_mut(8) {
+        let value = match rdseed() {
+            Some(value) => value,
+            None => match rdrand() {
+                Some(value) => {
+                    source = source.max(EntropySource::RdRand);
+                    value
+                }
+                None => {
+                    source = EntropySource::Jitter;
+                    jitter()
+                }
+            },
+        };
+
+        // mix in the timestamp counter too, it can't hurt
+        chunk.copy_from_slice(&(value ^ rdtsc()).to_le_bytes());
+    }
+
+    (seed, source)
+}
+
+/// Seeds the kernel-wide generator if it has not been already
+pub fn init() {
+    with_rng(|_| ());
+}
+
+/// Mixes fresh entropy into the kernel-wide generator
+pub fn reseed() {
+    let (seed, source) = gather_seed();
+    log::trace!("reseeding RNG from {source}");
+
+    with_rng(|rng| rng.reseed(seed));
+}
+
+/// Returns a random 32-bit integer from the kernel-wide generator
+pub fn next_u32() -> u32 {
+    with_rng(ChaChaRng::next_u32)
+}
+
+/// Returns a random 64-bit integer from the kernel-wide generator
+pub fn next_u64() -> u64 {
+    with_rng(ChaChaRng::next_u64)
+}
+
+/// Fills the given buffer with random bytes from the kernel-wide generator
+pub fn fill_bytes(dest: &mut [u8]) {
+    with_rng(|rng| rng.fill_bytes(dest));
+}
+
+/// Runs the closure with the kernel-wide generator, seeding it first if needed
+fn with_rng<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut ChaChaRng) -> R,
+{
+    // interrupt handlers may want randomness too, so must not be interrupted while holding the lock
+    without_interrupts(|| {
+        let mut rng = RNG.lock();
+
+        let rng = rng.get_or_insert_with(|| {
+            let (seed, source) = gather_seed();
+            log::info!("seeded RNG from {source}");
+
+            ChaChaRng::from_seed(seed)
+        });
+
+        f(rng)
+    })
+}
@@ -0,0 +1,144 @@
+//! Monotonic and wall-clock time.
+//!
+//! Monotonic time counts nanoseconds since the timestamp counter was reset, and never goes backwards. Wall-clock time
+//! is read from the RTC once at boot, then advanced with monotonic time. It can be stepped with [`set`], or
+//! disciplined gradually with [`slew`] and [`set_frequency`] so time keeps moving forwards (e.g. by an NTP client).
+
+use std::mutex::Mutex;
+
+use crate::x86::{
+    delay,
+    hardware::rtc::{DateTime, Rtc},
+    rdtsc, without_interrupts,
+};
+
+/// Nanoseconds in a second
+const NS_PER_SECOND: i128 = 1_000_000_000;
+
+/// Largest frequency adjustment accepted by [`set_frequency`], 500ppm
+pub const MAX_FREQUENCY_PPB: i64 = 500_000;
+
+/// Rate an offset passed to [`slew`] is applied at, 500ppm
+const SLEW_RATE_PPB: i128 = 500_000;
+
+/// Kernel wall clock, unset until [`init`]
+static WALL_CLOCK: Mutex<Option<WallClock>> = Mutex::new(None);
+
+/// Wall-clock time as a linear function of monotonic time, rebased whenever it is adjusted
+#[derive(Debug, Clone, Copy)]
+struct WallClock {
+    /// Monotonic time the clock was last rebased at
+    base_monotonic_ns: u64,
+    /// Wall-clock time at `base_monotonic_ns`, in nanoseconds since the unix epoch
+    base_wall_ns: u64,
+    /// Rate correction, in parts per billion
+    frequency_ppb: i64,
+    /// Offset still to be slewed in since the last rebase
+    slew_ns: i64,
+}
+
+impl WallClock {
+    /// Returns wall-clock time at the given monotonic time, and how much of the slew has been applied by then
+    fn at(&self, monotonic_ns: u64) -> (u64, i64) {
+        let elapsed = monotonic_ns.saturating_sub(self.base_monotonic_ns) as i128;
+
+        let max_slew = elapsed * SLEW_RATE_PPB / NS_PER_SECOND;
+        let slewed = (self.slew_ns as i128).clamp(-max_slew, max_slew);
+
+        let wall = self.base_wall_ns as i128
+            + elapsed
+            + elapsed * self.frequency_ppb as i128 / NS_PER_SECOND
+            + slewed;
+
+        (wall.max(0) as u64, slewed as i64)
+    }
+
+    /// Moves the base to the current monotonic time, so adjustments only apply from now on
+    fn rebase(&mut self) {
+        let now = monotonic_ns();
+        let (wall, slewed) = self.at(now);
+
+        self.base_monotonic_ns = now;
+        self.base_wall_ns = wall;
+        self.slew_ns -= slewed;
+    }
+}
+
+/// Returns nanoseconds since the timestamp counter was reset
+pub fn monotonic_ns() -> u64 {
+    (rdtsc() as u128 * NS_PER_SECOND as u128 / delay::tsc_frequency_hz() as u128) as u64
+}
+
+/// Starts the wall clock from the RTC, which is assumed to hold UTC
+pub fn init() {
+    let date_time = Rtc::new().read();
+    let monotonic = monotonic_ns();
+
+    without_interrupts(|| {
+        *WALL_CLOCK.lock() = Some(WallClock {
+            base_monotonic_ns: monotonic,
+            base_wall_ns: date_time.to_unix_seconds() * NS_PER_SECOND as u64,
+            frequency_ppb: 0,
+            slew_ns: 0,
+        });
+    });
+
+    log::trace!("\t* wall clock started at {date_time}");
+}
+
+/// Returns nanoseconds since the unix epoch, or [`None`] if the wall clock hasn't been started
+pub fn now_ns() -> Option<u64> {
+    without_interrupts(|| {
+        WALL_CLOCK
+            .lock()
+            .as_ref()
+            .map(|clock| clock.at(monotonic_ns()).0)
+    })
+}
+
+/// Returns the current date and time in UTC, or [`None`] if the wall clock hasn't been started
+pub fn now() -> Option<DateTime> {
+    now_ns().map(|ns| DateTime::from_unix_seconds(ns / NS_PER_SECOND as u64))
+}
+
+/// Steps the wall clock to the given number of nanoseconds since the unix epoch, cancelling any slew in progress
+pub fn set(unix_ns: u64) {
+    without_interrupts(|| {
+        if let Some(clock) = WALL_CLOCK.lock().as_mut() {
+            clock.base_monotonic_ns = monotonic_ns();
+            clock.base_wall_ns = unix_ns;
+            clock.slew_ns = 0;
+        }
+    });
+}
+
+/// Gradually moves the wall clock by `offset_ns`, running at most 500ppm fast or slow until it has been applied.
+/// Replaces any slew still in progress.
+pub fn slew(offset_ns: i64) {
+    without_interrupts(|| {
+        if let Some(clock) = WALL_CLOCK.lock().as_mut() {
+            clock.rebase();
+            clock.slew_ns = offset_ns;
+        }
+    });
+}
+
+/// Sets the rate correction of the wall clock in parts per billion, clamped to [`MAX_FREQUENCY_PPB`]
+pub fn set_frequency(ppb: i64) {
+    without_interrupts(|| {
+        if let Some(clock) = WALL_CLOCK.lock().as_mut() {
+            clock.rebase();
+            clock.frequency_ppb = ppb.clamp(-MAX_FREQUENCY_PPB, MAX_FREQUENCY_PPB);
+        }
+    });
+}
+
+/// Returns the offset which has not been slewed in yet, in nanoseconds
+pub fn remaining_slew_ns() -> i64 {
+    without_interrupts(|| {
+        WALL_CLOCK.lock().as_ref().map_or(0, |clock| {
+            let (_, slewed) = clock.at(monotonic_ns());
+            clock.slew_ns - slewed
+        })
+    })
+}
@@ -0,0 +1,157 @@
+//! Callbacks run from the timer interrupt once their deadline passes
+//!
+//! Deadlines are only checked on each timer interrupt, so a callback may run up to one timer interval late.
+//! Callbacks run inside the interrupt handler, so must be short and must not wait on a lock.
+
+use core::fmt::Display;
+use std::{collections::ArrayVec, duration::Duration, mutex::Mutex};
+
+use crate::{time, x86::without_interrupts};
+
+/// Maximum number of callbacks which can be waiting at once
+const MAX_TIMERS: usize = 16;
+
+/// The queue run by the timer interrupt
+static TIMERS: Mutex<TimerQueue> = Mutex::new(TimerQueue::new());
+
+/// Error returned when a callback can't be scheduled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull;
+
+impl Display for QueueFull {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "timer queue is full")
+    }
+}
+
+/// A callback waiting for its deadline
+#[derive(Clone, Copy)]
+struct Timer {
+    /// Monotonic time in nanoseconds after which the callback runs
+    deadline_ns: u64,
+    /// Function to run
+    callback: fn(),
+}
+
+/// Fixed size set of waiting callbacks
+struct TimerQueue {
+    /// Callbacks which haven't run yet, in no particular order
+    timers: ArrayVec<Timer, MAX_TIMERS>,
+}
+
+impl TimerQueue {
+    /// Constructs an empty queue
+    const fn new() -> Self {
+        Self {
+            timers: ArrayVec::new(),
+        }
+    }
+
+    /// Adds a callback to run once the monotonic clock reaches `deadline_ns`
+    fn schedule(&mut self, deadline_ns: u64, callback: fn()) -> Result<(), QueueFull> {
+        self.timers
+            .push(Timer {
+                deadline_ns,
+                callback,
+            })
+            .map_err(|_| QueueFull)
+    }
+
+    /// Removes and returns a callback whose deadline is at or before `now_ns`, if any
+    fn take_expired(&mut self, now_ns: u64) -> Option<fn()> {
+        let index = self
+            .timers
+            .as_slice()
+            .iter()
+            .position(|timer| timer.deadline_ns <= now_ns)?;
+
+        Some(self.timers.swap_remove(index).callback)
+    }
+}
+
+/// Runs `callback` from the timer interrupt once `delay` has passed
+pub fn schedule(delay: Duration, callback: fn()) -> Result<(), QueueFull> {
+    let deadline_ns = time::monotonic_ns().saturating_add(delay.as_nanoseconds() as u64);
+
+    // the timer interrupt takes the same lock
+    without_interrupts(|| TIMERS.lock().schedule(deadline_ns, callback))
+}
+
+/// Runs every callback whose deadline has passed, called from the timer interrupt
+pub fn run_expired() {
+    // nothing is queued until schedule has read the clock, so checking first means the interrupt never calibrates it
+    if TIMERS
+        .try_lock()
+        .is_none_or(|timers| timers.timers.is_empty())
+    {
+        return;
+    }
+
+    let now_ns = time::monotonic_ns();
+
+    loop {
+        // anything interrupted holding the lock is picked up on the next tick instead.
+        // the lock is dropped before running the callback, so callbacks can schedule more
+        let Some(callback) = TIMERS
+            .try_lock()
+            .and_then(|mut timers| timers.take_expired(now_ns))
+        else {
+            return;
+        };
+
+        callback();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Which of the test callbacks have run, as a bit each
+    static RAN: AtomicUsize = AtomicUsize::new(0);
+
+    /// Callback setting bit 0 of [`RAN`]
+    fn first() {
+        RAN.fetch_or(1, Ordering::Relaxed);
+    }
+
+    /// Callback setting bit 1 of [`RAN`]
+    fn second() {
+        RAN.fetch_or(2, Ordering::Relaxed);
+    }
+
+    /// Callback which does nothing
+    fn nothing() {}
+
+    #[test]
+    fn expired_only() {
+        let mut queue = TimerQueue::new();
+        queue.schedule(200, second).unwrap();
+        queue.schedule(100, first).unwrap();
+
+        assert!(queue.take_expired(99).is_none());
+
+        queue.take_expired(150).unwrap()();
+        assert_eq!(RAN.load(Ordering::Relaxed), 1);
+        assert!(queue.take_expired(150).is_none());
+
+        queue.take_expired(200).unwrap()();
+        assert_eq!(RAN.load(Ordering::Relaxed), 3);
+        assert!(queue.take_expired(u64::MAX).is_none());
+    }
+
+    #[test]
+    fn full() {
+        let mut queue = TimerQueue::new();
+        for _ in 0..MAX_TIMERS {
+            queue.schedule(0, nothing).unwrap();
+        }
+
+        assert_eq!(queue.schedule(0, nothing), Err(QueueFull));
+
+        assert!(queue.take_expired(0).is_some());
+        assert_eq!(queue.schedule(0, nothing), Ok(()));
+    }
+}
@@ -0,0 +1,52 @@
+//! Walking the stack using frame pointers
+//!
+//! The target is built with frame pointers forced on, so every function starts by pushing the caller's `rbp` and
+//! pointing `rbp` at it, giving a linked list of frames where the return address sits just above each saved `rbp`.
+
+use core::arch::asm;
+
+/// Stack frames are only followed if they lie in the higher half, where every kernel stack is mapped
+const MIN_FRAME_ADDRESS: usize = 0xFFFF_8000_0000_0000;
+
+/// Iterator over the return addresses of each stack frame, innermost first
+pub struct Backtrace {
+    /// Frame pointer of the next frame to visit
+    frame_pointer: usize,
+}
+
+impl Backtrace {
+    /// Begins a backtrace from the caller's frame
+    #[inline(always)]
+    pub fn capture() -> Self {
+        let frame_pointer: usize;
+
+        unsafe {
+            asm!("mov {}, rbp", out(reg) frame_pointer, options(nomem, nostack, preserves_flags));
+        }
+
+        Self { frame_pointer }
+    }
+}
+
+impl Iterator for Backtrace {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        // stop on the null frame pointer the stack starts with, or anything which doesn't look like a stack frame
+        if self.frame_pointer < MIN_FRAME_ADDRESS || !self.frame_pointer.is_multiple_of(8) {
+            return None;
+        }
+
+        let frame = self.frame_pointer as *const usize;
+        let (previous, return_address) = unsafe { (frame.read(), frame.add(1).read()) };
+
+        // stacks grow down, so callers' frames must be at higher addresses - anything else is corrupt
+        self.frame_pointer = if previous > self.frame_pointer {
+            previous
+        } else {
+            0
+        };
+
+        (return_address != 0).then_some(return_address)
+    }
+}
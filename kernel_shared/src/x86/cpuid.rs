@@ -0,0 +1,82 @@
+//! Querying processor features with the CPUID instruction
+
+use core::arch::asm;
+
+use bit_field::BitField;
+
+/// Registers returned by CPUID
+#[derive(Debug, Clone, Copy)]
+pub struct CpuidResult {
+    /// Value of EAX
+    pub eax: u32,
+    /// Value of EBX
+    pub ebx: u32,
+    /// Value of ECX
+    pub ecx: u32,
+    /// Value of EDX
+    pub edx: u32,
+}
+
+/// Executes CPUID with the given leaf and subleaf
+pub fn cpuid(leaf: u32, subleaf: u32) -> CpuidResult {
+    let (eax, ebx, ecx, edx): (u32, u64, u32, u32);
+
+    // rbx is reserved by LLVM, so has to be saved and restored around cpuid
+    unsafe {
+        asm!(
+        "mov {rbx}, rbx",
+        "cpuid",
+        "xchg {rbx}, rbx",
+        rbx = out(reg) ebx,
+        inout("eax") leaf => eax,
+        inout("ecx") subleaf => ecx,
+        out("edx") edx,
+        options(nomem, nostack, preserves_flags)
+        )
+    }
+
+    CpuidResult {
+        eax,
+        ebx: ebx as u32,
+        ecx,
+        edx,
+    }
+}
+
+/// Returns the highest basic leaf supported by the processor
+pub fn max_leaf() -> u32 {
+    cpuid(0, 0).eax
+}
+
+/// Returns true if the RDRAND instruction is supported
+pub fn has_rdrand() -> bool {
+    cpuid(1, 0).ecx.get_bit(30)
+}
+
+/// Returns true if the RDSEED instruction is supported
+pub fn has_rdseed() -> bool {
+    max_leaf() >= 7 && cpuid(7, 0).ebx.get_bit(18)
+}
+
+/// Returns the frequency the timestamp counter increments at in Hz, if the processor reports it
+pub fn tsc_frequency_hz() -> Option<u64> {
+    let max_leaf = max_leaf();
+
+    // leaf 0x15 gives the ratio of the TSC to the core crystal clock, and possibly the crystal frequency
+    if max_leaf >= 0x15 {
+        let result = cpuid(0x15, 0);
+        if result.eax != 0 && result.ebx != 0 && result.ecx != 0 {
+            return Some(result.ecx as u64 * result.ebx as u64 / result.eax as u64);
+        }
+    }
+
+    // otherwise fall back to the processor base frequency in MHz, which the TSC runs at on most processors
+    if max_leaf >= 0x16 {
+        let base_mhz = cpuid(0x16, 0).eax & 0xFFFF;
+        if base_mhz != 0 {
+            return Some(base_mhz as u64 * 1_000_000);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,343 @@
+//! Code for hardware breakpoints and watchpoints, using the debug registers.
+//!
+//! DR0-DR3 hold the address of each breakpoint, DR7 enables them and sets what they trigger on, and DR6 reports which
+//! one caused a debug exception. The registers belong to the current CPU, so breakpoints only apply to it.
+
+use core::{
+    arch::asm,
+    fmt::{Display, Formatter},
+};
+
+use bitflags::bitflags;
+
+use crate::{mem::addr::VirtAddr, x86::registers::CpuFlags};
+
+/// Number of hardware breakpoints, one for each of DR0-DR3
+pub const BREAKPOINT_COUNT: usize = 4;
+
+/// Bits of DR7 which are reserved as 1, plus exact data breakpoint detection recommended for all breakpoints
+const DR7_DEFAULT: u64 = (1 << 10) | (1 << 8);
+
+/// Value to write to DR6 to clear every status bit, leaving its reserved bits as 1
+const DR6_CLEAR: u64 = 0xFFFF0FF0;
+
+/// Accesses a breakpoint triggers on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BreakpointCondition {
+    /// Executing the instruction at the address, before it runs
+    Execute = 0b00,
+    /// Writing to the address, after the write
+    Write = 0b01,
+    /// Reading from or writing to the address, after the access
+    ReadWrite = 0b11,
+}
+
+/// Number of bytes a breakpoint covers, which its address must be aligned to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BreakpointSize {
+    /// 1 byte, which execute breakpoints must use
+    One = 0b00,
+    /// 2 bytes
+    Two = 0b01,
+    /// 4 bytes
+    Four = 0b11,
+    /// 8 bytes
+    Eight = 0b10,
+}
+
+impl BreakpointSize {
+    /// Returns the number of bytes covered
+    pub const fn bytes(self) -> usize {
+        match self {
+            Self::One => 1,
+            Self::Two => 2,
+            Self::Four => 4,
+            Self::Eight => 8,
+        }
+    }
+}
+
+/// A hardware breakpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Address to trigger on
+    pub addr: VirtAddr,
+    /// Accesses to trigger on
+    pub condition: BreakpointCondition,
+    /// Number of bytes covered from `addr`
+    pub size: BreakpointSize,
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.condition {
+            BreakpointCondition::Execute => write!(f, "execute breakpoint at {:#X}", self.addr),
+            BreakpointCondition::Write => write!(
+                f,
+                "write watchpoint on {} bytes at {:#X}",
+                self.size.bytes(),
+                self.addr
+            ),
+            BreakpointCondition::ReadWrite => write!(
+                f,
+                "read/write watchpoint on {} bytes at {:#X}",
+                self.size.bytes(),
+                self.addr
+            ),
+        }
+    }
+}
+
+/// An error setting a hardware breakpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakpointError {
+    /// Index is not below [`BREAKPOINT_COUNT`]
+    BadIndex(usize),
+    /// Address is not aligned to the breakpoint's size
+    Unaligned(VirtAddr),
+    /// Execute breakpoints must cover one byte
+    BadExecuteSize,
+}
+
+impl Display for BreakpointError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadIndex(index) => write!(f, "no hardware breakpoint {index}"),
+            Self::Unaligned(addr) => {
+                write!(f, "breakpoint address {addr:#X} not aligned to its size")
+            }
+            Self::BadExecuteSize => write!(f, "execute breakpoints must cover one byte"),
+        }
+    }
+}
+
+bitflags! {
+    /// DR6 status flags, reporting the cause of a debug exception
+    #[repr(transparent)]
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub struct Dr6Flags: u64 {
+        /// Breakpoint 0 was hit
+        const BREAKPOINT_0 = 1;
+        /// Breakpoint 1 was hit
+        const BREAKPOINT_1 = 1 << 1;
+        /// Breakpoint 2 was hit
+        const BREAKPOINT_2 = 1 << 2;
+        /// Breakpoint 3 was hit
+        const BREAKPOINT_3 = 1 << 3;
+        /// A debug register was accessed while general detect was enabled
+        const DEBUG_REGISTER_ACCESS = 1 << 13;
+        /// Single stepping with the trap flag
+        const SINGLE_STEP = 1 << 14;
+        /// Switching to a task with the debug trap flag set
+        const TASK_SWITCH = 1 << 15;
+    }
+}
+
+impl Dr6Flags {
+    /// Returns the indices of the breakpoints which were hit
+    pub fn breakpoints_hit(self) -> impl Iterator<Item = usize> {
+        (0..BREAKPOINT_COUNT).filter(move |&index| self.bits() & (1 << index) != 0)
+    }
+}
+
+/// DR6 register
+pub struct DR6;
+
+impl DR6 {
+    /// Reads the current value of DR6
+    pub fn read() -> Dr6Flags {
+        let value: u64;
+
+        unsafe {
+            asm!("mov {}, dr6", out(reg) value, options(nomem, nostack, preserves_flags));
+        }
+
+        Dr6Flags::from_bits_truncate(value)
+    }
+
+    /// Clears every status flag, which the CPU never does itself
+    pub fn clear() {
+        unsafe {
+            asm!("mov dr6, {}", in(reg) DR6_CLEAR, options(nomem, nostack, preserves_flags));
+        }
+    }
+}
+
+/// DR7 register
+pub struct DR7;
+
+impl DR7 {
+    /// Reads the current value of DR7
+    pub fn read() -> u64 {
+        let value: u64;
+
+        unsafe {
+            asm!("mov {}, dr7", out(reg) value, options(nomem, nostack, preserves_flags));
+        }
+
+        value
+    }
+
+    /// Writes the provided value to DR7
+    ///
+    /// ## Safety
+    /// Any breakpoint enabled must have its address set, and must not trigger anywhere a debug exception can't be
+    /// handled.
+    pub unsafe fn write(value: u64) {
+        unsafe {
+            asm!("mov dr7, {}", in(reg) value, options(nostack, preserves_flags));
+        }
+    }
+}
+
+/// Reads the address of breakpoint `index` from DR0-DR3.
+///
+/// Panics if `index` is not below [`BREAKPOINT_COUNT`].
+pub fn read_address(index: usize) -> usize {
+    let value: usize;
+
+    // the register is part of the instruction, so can't be picked at runtime
+    unsafe {
+        match index {
+            0 => asm!("mov {}, dr0", out(reg) value, options(nomem, nostack, preserves_flags)),
+            1 => asm!("mov {}, dr1", out(reg) value, options(nomem, nostack, preserves_flags)),
+            2 => asm!("mov {}, dr2", out(reg) value, options(nomem, nostack, preserves_flags)),
+            3 => asm!("mov {}, dr3", out(reg) value, options(nomem, nostack, preserves_flags)),
+            _ => panic!("no debug address register {index}"),
+        }
+    }
+
+    value
+}
+
+/// Writes the address of breakpoint `index` to DR0-DR3.
+///
+/// Panics if `index` is not below [`BREAKPOINT_COUNT`].
+///
+/// ## Safety
+/// If the breakpoint is enabled, it must not trigger anywhere a debug exception can't be handled.
+pub unsafe fn write_address(index: usize, addr: usize) {
+    unsafe {
+        match index {
+            0 => asm!("mov dr0, {}", in(reg) addr, options(nostack, preserves_flags)),
+            1 => asm!("mov dr1, {}", in(reg) addr, options(nostack, preserves_flags)),
+            2 => asm!("mov dr2, {}", in(reg) addr, options(nostack, preserves_flags)),
+            3 => asm!("mov dr3, {}", in(reg) addr, options(nostack, preserves_flags)),
+            _ => panic!("no debug address register {index}"),
+        }
+    }
+}
+
+/// Returns the bits of DR7 used by breakpoint `index`
+const fn dr7_mask(index: usize) -> u64 {
+    (0b11 << (index * 2)) | (0b1111 << (16 + index * 4))
+}
+
+/// Sets hardware breakpoint `index`, replacing any breakpoint already there
+///
+/// ## Safety
+/// The breakpoint must not trigger anywhere a debug exception can't be handled, such as within the debug exception
+/// handler or while it holds the log locks.
+pub unsafe fn set_breakpoint(index: usize, breakpoint: Breakpoint) -> Result<(), BreakpointError> {
+    if index >= BREAKPOINT_COUNT {
+        return Err(BreakpointError::BadIndex(index));
+    }
+    if breakpoint.condition == BreakpointCondition::Execute
+        && breakpoint.size != BreakpointSize::One
+    {
+        return Err(BreakpointError::BadExecuteSize);
+    }
+    if !breakpoint.addr.is_aligned(breakpoint.size.bytes()) {
+        return Err(BreakpointError::Unaligned(breakpoint.addr));
+    }
+
+    // disable the slot while changing its address, so it never triggers on a mix of old and new settings
+    let dr7 = (DR7::read() | DR7_DEFAULT) & !dr7_mask(index);
+    let enabled = dr7
+        | (1 << (index * 2))
+        | ((breakpoint.condition as u64) << (16 + index * 4))
+        | ((breakpoint.size as u64) << (18 + index * 4));
+
+    unsafe {
+        DR7::write(dr7);
+        write_address(index, breakpoint.addr.as_usize());
+        DR7::write(enabled);
+    }
+
+    Ok(())
+}
+
+/// Disables hardware breakpoint `index`, doing nothing if it isn't set
+pub fn clear_breakpoint(index: usize) {
+    if index < BREAKPOINT_COUNT {
+        // safety: disabling a breakpoint can't cause a debug exception
+        unsafe { DR7::write((DR7::read() | DR7_DEFAULT) & !dr7_mask(index)) };
+    }
+}
+
+/// Returns hardware breakpoint `index`, if it is set
+pub fn breakpoint(index: usize) -> Option<Breakpoint> {
+    let dr7 = DR7::read();
+    if index >= BREAKPOINT_COUNT || dr7 & (0b11 << (index * 2)) == 0 {
+        return None;
+    }
+
+    let condition = match (dr7 >> (16 + index * 4)) & 0b11 {
+        0b00 => BreakpointCondition::Execute,
+        0b01 => BreakpointCondition::Write,
+        0b11 => BreakpointCondition::ReadWrite,
+        // I/O breakpoints are never set by this module
+        _ => return None,
+    };
+    let size = match (dr7 >> (18 + index * 4)) & 0b11 {
+        0b00 => BreakpointSize::One,
+        0b01 => BreakpointSize::Two,
+        0b11 => BreakpointSize::Four,
+        _ => BreakpointSize::Eight,
+    };
+
+    Some(Breakpoint {
+        addr: VirtAddr::new(read_address(index)),
+        condition,
+        size,
+    })
+}
+
+/// Returns the index of the first hardware breakpoint which isn't set
+pub fn free_breakpoint() -> Option<usize> {
+    let dr7 = DR7::read();
+    (0..BREAKPOINT_COUNT).find(|&index| dr7 & (0b11 << (index * 2)) == 0)
+}
+
+/// Sets the trap flag, raising a debug exception after every instruction from the one after this returns
+///
+/// ## Safety
+/// Every instruction executed until [`clear_trap_flag`] is called must be somewhere a debug exception can be handled,
+/// and the debug exception handler must expect single steps.
+#[inline(always)]
+pub unsafe fn set_trap_flag() {
+    unsafe {
+        asm!(
+            "pushfq",
+            "or qword ptr [rsp], {trap_flag}",
+            "popfq",
+            trap_flag = const CpuFlags::TRAP_FLAG.bits(),
+        );
+    }
+}
+
+/// Clears the trap flag, so single stepping stops after this returns
+#[inline(always)]
+pub fn clear_trap_flag() {
+    unsafe {
+        asm!(
+            "pushfq",
+            "and qword ptr [rsp], {mask}",
+            "popfq",
+            // signed, as the immediate is sign extended to 64 bits
+            mask = const !(CpuFlags::TRAP_FLAG.bits() as i64),
+        );
+    }
+}
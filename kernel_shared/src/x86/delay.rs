@@ -0,0 +1,97 @@
+//! Calibrated busy-wait delays, counted with the timestamp counter.
+//!
+//! The TSC frequency is taken from CPUID where the processor reports it, and otherwise measured against PIT
+//! channel 2 the first time a delay is requested. This assumes an invariant TSC, which every processor the kernel
+//! is likely to run on has.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{
+    io::port::Port,
+    x86::{cpuid, hardware::pit::ProgrammableIntervalTimer, rdtsc, without_interrupts},
+};
+
+/// Frequency of the TSC in Hz, 0 until calibrated
+static TSC_HZ: AtomicU64 = AtomicU64::new(0);
+
+/// PIT ticks to calibrate over, roughly 10ms
+const CALIBRATION_TICKS: u16 = 11_932;
+
+/// Length of [`CALIBRATION_TICKS`] in nanoseconds
+const CALIBRATION_NS: u64 = 10_000_000;
+
+/// Keyboard controller port B, which gates PIT channel 2 and reports its output
+const PORT_B: u16 = 0x61;
+
+/// Bit in port B which gates PIT channel 2
+const GATE_BIT: u8 = 1 << 0;
+
+/// Bit in port B which connects PIT channel 2 to the speaker
+const SPEAKER_DATA_BIT: u8 = 1 << 1;
+
+/// Bit in port B reflecting the output of PIT channel 2
+const CHANNEL2_OUTPUT_BIT: u8 = 1 << 5;
+
+/// Returns the TSC frequency in Hz, calibrating it first if needed
+pub fn tsc_frequency_hz() -> u64 {
+    match TSC_HZ.load(Ordering::Relaxed) {
+        0 => calibrate(),
+        hz => hz,
+    }
+}
+
+/// Overrides the TSC frequency, for when a more accurate measurement is available (e.g. against the HPET)
+pub fn set_tsc_frequency_hz(hz: u64) {
+    TSC_HZ.store(hz, Ordering::Relaxed);
+}
+
+/// Determines the TSC frequency, from CPUID if reported and by measuring against the PIT otherwise
+pub fn calibrate() -> u64 {
+    let hz = cpuid::tsc_frequency_hz().unwrap_or_else(measure_against_pit);
+    log::trace!("TSC frequency {hz}Hz");
+
+    TSC_HZ.store(hz, Ordering::Relaxed);
+    hz
+}
+
+/// Counts how many TSC cycles pass while PIT channel 2 counts down [`CALIBRATION_TICKS`]
+fn measure_against_pit() -> u64 {
+    let mut pit = ProgrammableIntervalTimer::new();
+    let mut port_b: Port<u8> = Port::new(PORT_B);
+
+    let cycles = without_interrupts(|| unsafe {
+        // disconnect the speaker and hold the gate low while programming, so counting starts when we say
+        let saved = port_b.read();
+        port_b.write(saved & !(GATE_BIT | SPEAKER_DATA_BIT));
+
+        pit.set_channel2_one_shot(CALIBRATION_TICKS);
+
+        port_b.write((saved & !SPEAKER_DATA_BIT) | GATE_BIT);
+        let start = rdtsc();
+
+        while port_b.read() & CHANNEL2_OUTPUT_BIT == 0 {
+            core::hint::spin_loop();
+        }
+        let end = rdtsc();
+
+        port_b.write(saved);
+        end - start
+    });
+
+    cycles * 1_000_000_000 / CALIBRATION_NS
+}
+
+/// Busy-waits for at least the given number of nanoseconds
+pub fn delay_ns(ns: u64) {
+    let cycles = (ns as u128 * tsc_frequency_hz() as u128 / 1_000_000_000) as u64;
+    let start = rdtsc();
+
+    while rdtsc().wrapping_sub(start) < cycles {
+        core::hint::spin_loop();
+    }
+}
+
+/// Busy-waits for at least the given number of microseconds
+pub fn delay_us(us: u64) {
+    delay_ns(us * 1_000);
+}
@@ -1,6 +1,7 @@
 //! Code for constructing descriptor table pointers
 
 use core::{arch::asm, marker::PhantomData};
+use std::{assert_eq_size, assert_offset};
 
 use crate::x86::{gdt::GlobalDescriptorTable, idt::InterruptDescriptorTable};
 
@@ -45,6 +46,9 @@ pub struct DescriptorTablePointer<T> {
     phantom: PhantomData<T>,
 }
 
+assert_eq_size!(DescriptorTablePointer<GlobalDescriptorTable>, 10);
+assert_offset!(DescriptorTablePointer<GlobalDescriptorTable>, base, 2);
+
 impl DescriptorTablePointer<InterruptDescriptorTable> {
     /// Loads the given descriptor table as an interrupt descriptor table
     ///
@@ -1,6 +1,7 @@
 //! Code for representing exceptions
 
-use core::fmt::Display;
+use core::{fmt::Display, ptr::write_volatile};
+use std::{assert_eq_size, assert_offset};
 
 use crate::x86::{registers::CpuFlags, segment_selector::SegmentSelector};
 
@@ -24,6 +25,42 @@ pub struct ExceptionStackFrame {
     _reserved2: [u8; 6],
 }
 
+assert_eq_size!(ExceptionStackFrame, 40);
+assert_offset!(ExceptionStackFrame, code_segment, 8);
+assert_offset!(ExceptionStackFrame, cpu_flags, 16);
+assert_offset!(ExceptionStackFrame, stack_pointer, 24);
+assert_offset!(ExceptionStackFrame, stack_segment, 32);
+
+impl ExceptionStackFrame {
+    /// Returns a view of the frame through which a handler can change the state `iretq` restores.
+    ///
+    /// `x86-interrupt` handlers take the frame by value, but are given the frame the CPU pushed rather than a copy, so
+    /// writes to it take effect once the handler returns.
+    ///
+    /// ## Safety
+    /// `self` must be the frame passed to the running `x86-interrupt` handler, and anything written must be a valid
+    /// state to resume execution in.
+    pub unsafe fn as_mut_view(&mut self) -> ExceptionStackFrameMut<'_> {
+        ExceptionStackFrameMut(self)
+    }
+}
+
+/// Writable view of the frame the CPU pushed for an exception, returned by [`ExceptionStackFrame::as_mut_view`].
+/// Writes are volatile, since nothing visible to the compiler reads the frame afterwards.
+pub struct ExceptionStackFrameMut<'a>(&'a mut ExceptionStackFrame);
+
+impl ExceptionStackFrameMut<'_> {
+    /// Sets the address execution resumes at
+    pub fn set_instruction_pointer(&mut self, addr: u64) {
+        unsafe { write_volatile(&raw mut self.0.instruction_pointer, addr) };
+    }
+
+    /// Sets the cpu flags restored on return
+    pub fn set_cpu_flags(&mut self, flags: CpuFlags) {
+        unsafe { write_volatile(&raw mut self.0.cpu_flags, flags) };
+    }
+}
+
 impl Display for ExceptionStackFrame {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         writeln!(f, "Exception stack frame:")?;
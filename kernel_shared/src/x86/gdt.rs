@@ -3,17 +3,23 @@
 #![allow(missing_docs)]
 
 use core::sync::atomic::AtomicU64;
+use std::assert_eq_size;
 
 use bit_field::BitField;
 use bitflags::bitflags;
 
 use super::{PrivilegeLevel, segment_selector::SegmentSelector};
-use crate::x86::{descriptor_table_pointer::IntoDescriptorTable, tss::TaskStateSegment};
+use crate::x86::{
+    descriptor_table_pointer::IntoDescriptorTable,
+    tss::{TaskStateSegment, TssWithIoBitmap},
+};
 
 /// An entry within the GDT
 #[repr(transparent)]
 pub struct GdtEntry(AtomicU64);
 
+assert_eq_size!(GdtEntry, 8);
+
 impl GdtEntry {
     /// Constructs a new entry with the given value
     pub const fn new(val: u64) -> Self {
@@ -178,12 +184,19 @@ impl Descriptor {
 
     /// Returns a descriptor for the provided task state segment
     pub fn tss_segment(tss: &'static TaskStateSegment) -> Self {
-        let tss = tss as *const TaskStateSegment;
-        let ptr = tss as u64;
+        Self::tss_descriptor(tss as *const _ as u64, size_of::<TaskStateSegment>())
+    }
+
+    /// Returns a descriptor for the provided task state segment, covering its I/O permission bitmap
+    pub fn tss_segment_with_io_bitmap(tss: &'static TssWithIoBitmap) -> Self {
+        Self::tss_descriptor(tss as *const _ as u64, size_of::<TssWithIoBitmap>())
+    }
 
+    /// Returns a TSS descriptor for a segment of `size` bytes at `ptr`
+    fn tss_descriptor(ptr: u64, size: usize) -> Self {
         let mut low = DescriptorFlags::PRESENT.bits();
 
-        low.set_bits(0..16, (core::mem::size_of::<TaskStateSegment>() - 1) as u64);
+        low.set_bits(0..16, (size - 1) as u64);
         low.set_bits(16..40, ptr.get_bits(0..24));
         low.set_bits(40..44, 0b1001);
         low.set_bits(56..64, ptr.get_bits(24..32));
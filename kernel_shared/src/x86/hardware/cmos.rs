@@ -0,0 +1,56 @@
+//! CMOS memory, holding the real time clock and a small amount of battery-backed NVRAM
+
+use crate::io::port::Port;
+
+/// Bit in the CMOS index port which disables NMIs
+const NMI_DISABLE: u8 = 1 << 7;
+
+/// Number of addressable CMOS registers
+pub const CMOS_SIZE: u8 = 128;
+
+/// The 128 bytes of CMOS memory, accessed through an index and data port
+pub struct Cmos {
+    /// CMOS register select port
+    index: Port<u8>,
+    /// CMOS data port
+    data: Port<u8>,
+}
+
+impl Default for Cmos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cmos {
+    /// Constructs a handle to CMOS memory at the standard ports
+    pub const fn new() -> Self {
+        Self {
+            index: Port::new(0x70),
+            data: Port::new(0x71),
+        }
+    }
+
+    /// Reads a register, keeping NMIs disabled while selecting it
+    pub fn read(&mut self, register: u8) -> u8 {
+        debug_assert!(register < CMOS_SIZE);
+
+        unsafe {
+            self.index.write(NMI_DISABLE | register);
+            self.data.read()
+        }
+    }
+
+    /// Writes a register, keeping NMIs disabled while selecting it.
+    ///
+    /// # Safety
+    /// Registers used by the RTC or firmware must not be overwritten with invalid values.
+    pub unsafe fn write(&mut self, register: u8, value: u8) {
+        debug_assert!(register < CMOS_SIZE);
+
+        unsafe {
+            self.index.write(NMI_DISABLE | register);
+            self.data.write(value);
+        }
+    }
+}
@@ -1,7 +1,8 @@
 //! Structs for programming an individual HPET timer
 
+use crate::x86::hardware::msi::MsiMessage;
+
 /// An individual HPET timer
-#[allow(unused)]
 pub struct Timer {
     /// Register for querying capabilities and changing config
     configuration_capability_register: *mut u64,
@@ -99,6 +100,52 @@ impl Timer {
         self
     }
 
+    /// Returns if the timer can deliver interrupts directly to the local APICs over the FSB, bypassing the IO APIC
+    pub fn supports_fsb_delivery(&self) -> bool {
+        let config = unsafe { core::ptr::read_volatile(self.configuration_capability_register) };
+
+        config & (1 << 15) != 0
+    }
+
+    /// Returns if the timer interrupts are delivered over the FSB
+    pub fn is_fsb_enabled(&self) -> bool {
+        let config = unsafe { core::ptr::read_volatile(self.configuration_capability_register) };
+
+        config & (1 << 14) != 0
+    }
+
+    /// Sets if the timer interrupts are delivered over the FSB, instead of through the IO APIC routing.
+    ///
+    /// Should only be enabled if [`Timer::supports_fsb_delivery`], and after setting the message with
+    /// [`Timer::set_fsb_message`].
+    pub fn set_fsb_enabled(&mut self, fsb_enabled: bool) -> &mut Self {
+        let config = unsafe { core::ptr::read_volatile(self.configuration_capability_register) };
+        let config = (config & !(1 << 14)) | ((fsb_enabled as u64) << 14);
+
+        unsafe { core::ptr::write_volatile(self.configuration_capability_register, config) }
+
+        self
+    }
+
+    /// Reads the message written when delivering interrupts over the FSB
+    pub fn get_fsb_message(&self) -> MsiMessage {
+        let value = unsafe { core::ptr::read_volatile(self.fsb_interrupt_register) };
+
+        MsiMessage {
+            address: value >> 32,
+            data: value as u32,
+        }
+    }
+
+    /// Sets the message written when delivering interrupts over the FSB
+    pub fn set_fsb_message(&mut self, message: &MsiMessage) -> &mut Self {
+        let value = (message.address << 32) | message.data as u64;
+
+        unsafe { core::ptr::write_volatile(self.fsb_interrupt_register, value) }
+
+        self
+    }
+
     /// Reads the current comparator value
     pub fn get_comparator_value(&self) -> u64 {
         unsafe { core::ptr::read_volatile(self.comparator_register) }
@@ -1,6 +1,7 @@
 //! Code for programming an I/O APIC chip
 
 use core::fmt::{Display, Formatter};
+use std::collections::ArrayVec;
 
 /// Struct containing information about an I/O APIC chip
 #[derive(Debug)]
@@ -86,6 +87,44 @@ impl IoApic {
         self.set_redirection_entry(irq_number, entry)
     }
 
+    /// Routes the given redirection entry to the processor with the given APIC id
+    pub fn set_destination_cpu(&mut self, irq_number: u8, apic_id: u8) -> Option<()> {
+        self.modify_redirection_entry(irq_number, |entry| {
+            entry
+                .set_destination_mode(DestinationMode::Physical)
+                .set_destination(apic_id);
+        })
+    }
+
+    /// Returns the number of redirection entries this IOAPIC has
+    pub fn redirection_entry_count(&self) -> u8 {
+        self.max_redirection_entry + 1
+    }
+
+    /// Saves every redirection entry
+    pub fn save(&mut self) -> IoApicState {
+        let mut entries = ArrayVec::new();
+
+        for irq_number in 0..=self.max_redirection_entry {
+            let _ = entries.push(self.get_redirection_entry(irq_number).unwrap());
+        }
+
+        IoApicState { entries }
+    }
+
+    /// Restores redirection entries saved with [`IoApic::save`].
+    ///
+    /// Every entry is masked before any are rewritten, so no interrupt is delivered through a half-restored table.
+    pub fn restore(&mut self, state: &IoApicState) {
+        for irq_number in 0..state.entries.len() as u8 {
+            self.mask_redirection_entry(irq_number, true);
+        }
+
+        for (irq_number, &entry) in state.entries.iter().enumerate() {
+            self.set_redirection_entry(irq_number as u8, entry);
+        }
+    }
+
     /// Sets the mask for a given redirection entry
     pub fn mask_redirection_entry(&mut self, irq_number: u8, mask: bool) -> Option<()> {
         self.modify_redirection_entry(irq_number, |entry| {
@@ -94,8 +133,15 @@ impl IoApic {
     }
 }
 
+/// Every redirection entry of an IOAPIC, saved so they can be restored later
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoApicState {
+    /// Redirection entries, in order
+    entries: ArrayVec<RedirectionEntry, 256>,
+}
+
 /// A single redirection entry for the IO APIC
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct RedirectionEntry {
     /// Low 32 bits
     low: u32,
@@ -4,6 +4,48 @@ use crate::x86::hardware::local_apic::svr::SpuriousInterruptVectorRegister;
 
 pub mod svr;
 
+/// Offset of the task priority register
+const TPR: usize = 0x80;
+/// Offset of the version register, which also holds the number of LVT entries
+const VERSION: usize = 0x30;
+/// Offset of the spurious interrupt vector register
+const SVR: usize = 0xF0;
+/// Offset of the LVT timer register
+const LVT_TIMER: usize = 0x320;
+/// Offset of the LVT thermal sensor register, present if there are at least 6 LVT entries
+const LVT_THERMAL: usize = 0x330;
+/// Offset of the LVT performance counter register, present if there are at least 5 LVT entries
+const LVT_PERFORMANCE: usize = 0x340;
+/// Offset of the LVT LINT0 register
+const LVT_LINT0: usize = 0x350;
+/// Offset of the LVT LINT1 register
+const LVT_LINT1: usize = 0x360;
+/// Offset of the LVT error register
+const LVT_ERROR: usize = 0x370;
+/// Offset of the timer initial count register, writing which starts the timer
+const TIMER_INITIAL_COUNT: usize = 0x380;
+/// Offset of the timer divide configuration register
+const TIMER_DIVIDE: usize = 0x3E0;
+
+/// Local vector table and related configuration of a local APIC, saved so it can be restored later
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalApicState {
+    /// Task priority
+    task_priority: u32,
+    /// Spurious interrupt vector register, including the enable bit
+    spurious_vector: u32,
+    /// LVT timer, LINT0, LINT1 and error registers
+    lvt: [u32; 4],
+    /// LVT thermal sensor register, if present
+    lvt_thermal: Option<u32>,
+    /// LVT performance counter register, if present
+    lvt_performance: Option<u32>,
+    /// Timer divide configuration
+    timer_divide: u32,
+    /// Timer initial count
+    timer_initial_count: u32,
+}
+
 /// Local apic at known address
 #[derive(Debug)]
 pub struct LocalApic {
@@ -20,13 +62,80 @@ impl LocalApic {
         Self { base_addr }
     }
 
-    /// Signals that an interrupt has been handled
-    pub fn end_of_interrupt(&mut self) {
+    /// Returns the APIC id of the processor this local APIC belongs to
+    pub fn id(&self) -> u8 {
+        unsafe { (core::ptr::read_volatile((self.base_addr | 0x20) as *const u32) >> 24) as u8 }
+    }
+
+    /// Signals that an interrupt has been handled.
+    ///
+    /// Each processor has its own EOI register, so this needs no synchronisation and is safe to call from interrupt
+    /// handlers through a shared reference.
+    pub fn end_of_interrupt(&self) {
         unsafe {
             core::ptr::write_volatile((self.base_addr | 0xB0) as *mut u32, 0);
         }
     }
 
+    /// Saves the local vector table, timer configuration, task priority and spurious vector
+    pub fn save(&self) -> LocalApicState {
+        let max_lvt_entry = (self.read(VERSION) >> 16) & 0xFF;
+
+        LocalApicState {
+            task_priority: self.read(TPR),
+            spurious_vector: self.read(SVR),
+            lvt: [LVT_TIMER, LVT_LINT0, LVT_LINT1, LVT_ERROR].map(|offset| self.read(offset)),
+            lvt_thermal: (max_lvt_entry >= 5).then(|| self.read(LVT_THERMAL)),
+            lvt_performance: (max_lvt_entry >= 4).then(|| self.read(LVT_PERFORMANCE)),
+            timer_divide: self.read(TIMER_DIVIDE),
+            timer_initial_count: self.read(TIMER_INITIAL_COUNT),
+        }
+    }
+
+    /// Restores state saved with [`LocalApic::save`]. The timer restarts from its initial count.
+    ///
+    /// ## Safety
+    /// The state must have been saved from this processor's local APIC, and every vector it refers to must still
+    /// have a handler installed.
+    pub unsafe fn restore(&self, state: &LocalApicState) {
+        unsafe {
+            // enable the APIC first, as LVT writes are ignored while it is software disabled
+            self.write(SVR, state.spurious_vector);
+            self.write(TPR, state.task_priority);
+
+            for (offset, value) in [LVT_TIMER, LVT_LINT0, LVT_LINT1, LVT_ERROR]
+                .into_iter()
+                .zip(state.lvt)
+            {
+                self.write(offset, value);
+            }
+
+            if let Some(value) = state.lvt_thermal {
+                self.write(LVT_THERMAL, value);
+            }
+            if let Some(value) = state.lvt_performance {
+                self.write(LVT_PERFORMANCE, value);
+            }
+
+            // the initial count must be written last, as it starts the timer
+            self.write(TIMER_DIVIDE, state.timer_divide);
+            self.write(TIMER_INITIAL_COUNT, state.timer_initial_count);
+        }
+    }
+
+    /// Reads the register at the given offset
+    fn read(&self, offset: usize) -> u32 {
+        unsafe { core::ptr::read_volatile((self.base_addr | offset) as *const u32) }
+    }
+
+    /// Writes the register at the given offset
+    ///
+    /// ## Safety
+    /// The write must not break any interrupt handling the kernel relies on
+    unsafe fn write(&self, offset: usize, value: u32) {
+        unsafe { core::ptr::write_volatile((self.base_addr | offset) as *mut u32, value) }
+    }
+
     /// Returns a struct for modifying the Spurious Interrupt Vector Register
     pub const fn spurious_interrupt_vector_register(&self) -> SpuriousInterruptVectorRegister {
         unsafe { SpuriousInterruptVectorRegister::from_base_addr(self.base_addr) }
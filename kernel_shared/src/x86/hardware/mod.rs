@@ -1,6 +1,11 @@
 //! Code for representing hardware features
 
+pub mod cmos;
 pub mod hpet;
 pub mod io_apic;
 pub mod local_apic;
+pub mod msi;
 pub mod pit;
+pub mod ps2;
+pub mod rtc;
+pub mod speaker;
@@ -0,0 +1,41 @@
+//! Message Signalled Interrupts, where a device raises an interrupt by writing `data` to `address`
+
+/// Base of the address range which the local APICs decode MSI writes from
+const MSI_ADDRESS_BASE: u64 = 0xFEE0_0000;
+
+/// Address and data values to program into a device's MSI capability
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsiMessage {
+    /// Value for the message address register
+    pub address: u64,
+    /// Value for the message data register
+    pub data: u32,
+}
+
+impl MsiMessage {
+    /// Constructs a fixed, edge triggered message raising `vector` on the processor with the given APIC id
+    pub const fn new(vector: u8, apic_id: u8) -> Self {
+        Self {
+            address: MSI_ADDRESS_BASE | (apic_id as u64) << 12,
+            data: vector as u32,
+        }
+    }
+
+    /// Gets the interrupt vector that will be raised on the CPU
+    pub const fn get_interrupt_vector(&self) -> u8 {
+        (self.data & 0xFF) as u8
+    }
+
+    /// Gets the APIC id of the destination processor
+    pub const fn get_destination(&self) -> u8 {
+        ((self.address >> 12) & 0xFF) as u8
+    }
+
+    /// Sets the APIC id of the destination processor, using physical destination mode
+    pub const fn set_destination(&mut self, apic_id: u8) -> &mut Self {
+        // clear destination id and the redirection hint/destination mode bits
+        self.address = (self.address & !(0xFF << 12 | 0b1100)) | (apic_id as u64) << 12;
+
+        self
+    }
+}
@@ -2,6 +2,9 @@
 
 use crate::io::port::Port;
 
+/// Frequency of the oscillator driving the PIT, in Hz
+pub const PIT_FREQUENCY_HZ: usize = 1_193_182;
+
 /// Struct to represent the programmable interval timer
 #[allow(unused)]
 pub struct ProgrammableIntervalTimer {
@@ -17,6 +20,13 @@ pub struct ProgrammableIntervalTimer {
 
 impl Default for ProgrammableIntervalTimer {
     fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgrammableIntervalTimer {
+    /// Constructs the PIT at the standard ports
+    pub const fn new() -> Self {
         Self {
             channel0_port: Port::new(0x40),
             channel1_port: Port::new(0x41),
@@ -24,13 +34,49 @@ impl Default for ProgrammableIntervalTimer {
             mode_command_register: Port::new(0x43),
         }
     }
-}
 
-impl ProgrammableIntervalTimer {
     /// Disables the PIT from sending any interrupts
     pub fn disable_irq(&mut self) {
         unsafe {
             self.mode_command_register.write(0b00111010);
         }
     }
+
+    /// Programs channel 2 to output a square wave with a period of `reload` ticks of the PIT oscillator, which is
+    /// connected to the PC speaker
+    pub fn set_channel2_square_wave(&mut self, reload: u16) {
+        unsafe {
+            // channel 2, lobyte/hibyte access, mode 3 (square wave generator), binary
+            self.mode_command_register.write(0b10110110);
+
+            let [low, high] = reload.to_le_bytes();
+            self.channel2_port.write(low);
+            self.channel2_port.write(high);
+        }
+    }
+
+    /// Programs channel 2 to count down `reload` ticks once, raising its output when it reaches zero. Counting only
+    /// progresses while the channel's gate is high.
+    pub fn set_channel2_one_shot(&mut self, reload: u16) {
+        unsafe {
+            // channel 2, lobyte/hibyte access, mode 0 (interrupt on terminal count), binary
+            self.mode_command_register.write(0b10110000);
+
+            let [low, high] = reload.to_le_bytes();
+            self.channel2_port.write(low);
+            self.channel2_port.write(high);
+        }
+    }
+
+    /// Programs channel 0 to fire an interrupt every `reload` ticks of the PIT oscillator, where 0 means 65536
+    pub fn set_periodic(&mut self, reload: u16) {
+        unsafe {
+            // channel 0, lobyte/hibyte access, mode 2 (rate generator), binary
+            self.mode_command_register.write(0b00110100);
+
+            let [low, high] = reload.to_le_bytes();
+            self.channel0_port.write(low);
+            self.channel0_port.write(high);
+        }
+    }
 }
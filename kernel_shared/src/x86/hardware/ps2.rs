@@ -0,0 +1,342 @@
+//! 8042 PS/2 controller, which sits between the CPU and PS/2 keyboards and mice
+
+use core::fmt::{Display, Formatter};
+use std::mutex::Mutex;
+
+use bitflags::bitflags;
+
+use crate::io::port::Port;
+
+/// The system's PS/2 controller, shared by all PS/2 device drivers
+pub static PS2_CONTROLLER: Mutex<Ps2Controller> = Mutex::new(Ps2Controller::new());
+
+/// Number of times to poll the status register before giving up
+const TIMEOUT_ITERATIONS: usize = 100_000;
+
+/// Number of times to resend a device command the device asked to be resent
+const RESEND_ATTEMPTS: usize = 3;
+
+/// Controller command: read configuration byte
+const CMD_READ_CONFIG: u8 = 0x20;
+/// Controller command: write configuration byte
+const CMD_WRITE_CONFIG: u8 = 0x60;
+/// Controller command: disable second port
+const CMD_DISABLE_PORT2: u8 = 0xA7;
+/// Controller command: enable second port
+const CMD_ENABLE_PORT2: u8 = 0xA8;
+/// Controller command: test second port
+const CMD_TEST_PORT2: u8 = 0xA9;
+/// Controller command: test controller
+const CMD_SELF_TEST: u8 = 0xAA;
+/// Controller command: test first port
+const CMD_TEST_PORT1: u8 = 0xAB;
+/// Controller command: disable first port
+const CMD_DISABLE_PORT1: u8 = 0xAD;
+/// Controller command: enable first port
+const CMD_ENABLE_PORT1: u8 = 0xAE;
+/// Controller command: send next data byte to second port
+const CMD_WRITE_PORT2: u8 = 0xD4;
+
+/// Response to a successful controller self test
+const SELF_TEST_PASSED: u8 = 0x55;
+/// Response to a successful port test
+const PORT_TEST_PASSED: u8 = 0x00;
+/// Device response acknowledging a command
+const DEVICE_ACK: u8 = 0xFA;
+/// Device response asking for the last command to be resent
+const DEVICE_RESEND: u8 = 0xFE;
+
+bitflags! {
+    /// Status register
+    #[derive(Debug, Clone, Copy)]
+    pub struct Status: u8 {
+        /// Data is waiting to be read from the data port
+        const OUTPUT_FULL = 1 << 0;
+        /// Controller has not yet read the last byte written
+        const INPUT_FULL = 1 << 1;
+        /// Set once the system has passed POST
+        const SYSTEM = 1 << 2;
+        /// Last byte written was a command rather than data
+        const COMMAND = 1 << 3;
+        /// Timeout error
+        const TIMEOUT = 1 << 6;
+        /// Parity error
+        const PARITY = 1 << 7;
+    }
+
+    /// Controller configuration byte
+    #[derive(Debug, Clone, Copy)]
+    pub struct Configuration: u8 {
+        /// First port raises IRQ 1
+        const PORT1_INTERRUPT = 1 << 0;
+        /// Second port raises IRQ 12
+        const PORT2_INTERRUPT = 1 << 1;
+        /// Set once the system has passed POST
+        const SYSTEM = 1 << 2;
+        /// First port clock is disabled
+        const PORT1_CLOCK_DISABLED = 1 << 4;
+        /// Second port clock is disabled
+        const PORT2_CLOCK_DISABLED = 1 << 5;
+        /// Scancodes from the first port are translated to scan code set 1
+        const PORT1_TRANSLATION = 1 << 6;
+    }
+}
+
+/// One of the two ports on the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ps2Port {
+    /// First port, usually the keyboard
+    First,
+    /// Second port, usually the mouse
+    Second,
+}
+
+/// An error communicating with the PS/2 controller or a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ps2Error {
+    /// Controller did not become ready in time
+    Timeout,
+    /// Controller self test returned the given value
+    SelfTestFailed(u8),
+    /// Port interface test returned the given value
+    PortTestFailed(Ps2Port, u8),
+    /// Port is not present or failed its test
+    PortUnavailable(Ps2Port),
+    /// Device replied with something other than an acknowledgement
+    UnexpectedResponse(u8),
+}
+
+impl Display for Ps2Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "timed out waiting for PS/2 controller"),
+            Self::SelfTestFailed(value) => {
+                write!(f, "PS/2 controller self test failed with {value:#X}")
+            }
+            Self::PortTestFailed(port, value) => {
+                write!(f, "PS/2 {port:?} port test failed with {value:#X}")
+            }
+            Self::PortUnavailable(port) => write!(f, "PS/2 {port:?} port unavailable"),
+            Self::UnexpectedResponse(value) => {
+                write!(f, "unexpected response {value:#X} from PS/2 device")
+            }
+        }
+    }
+}
+
+/// The 8042 PS/2 controller
+pub struct Ps2Controller {
+    /// Port for reading and writing data
+    data: Port<u8>,
+    /// Port for reading status, and writing commands
+    command: Port<u8>,
+    /// Whether the first port passed its test
+    port1_available: bool,
+    /// Whether the second port exists and passed its test
+    port2_available: bool,
+}
+
+impl Ps2Controller {
+    /// Constructs the controller at the standard ports
+    pub const fn new() -> Self {
+        Self {
+            data: Port::new(0x60),
+            command: Port::new(0x64),
+            port1_available: false,
+            port2_available: false,
+        }
+    }
+
+    /// Initialises the controller, testing it and both ports, and enabling interrupts for working ports.
+    ///
+    /// Devices are left for their own drivers to reset.
+    pub fn init(&mut self, translation: bool) -> Result<(), Ps2Error> {
+        // stop devices sending data while we set things up
+        self.send_command(CMD_DISABLE_PORT1)?;
+        self.send_command(CMD_DISABLE_PORT2)?;
+        self.flush_output();
+
+        // disable interrupts and translation for now
+        let mut config = self.read_config()?;
+        config.remove(
+            Configuration::PORT1_INTERRUPT
+                | Configuration::PORT2_INTERRUPT
+                | Configuration::PORT1_TRANSLATION,
+        );
+        self.write_config(config)?;
+
+        match self.command_response(CMD_SELF_TEST)? {
+            SELF_TEST_PASSED => {}
+            value => return Err(Ps2Error::SelfTestFailed(value)),
+        }
+
+        // some controllers reset on self test, so make sure config is still correct
+        self.write_config(config)?;
+
+        // if enabling the second port clears its disabled clock bit, it must exist
+        self.send_command(CMD_ENABLE_PORT2)?;
+        let dual_channel = !self
+            .read_config()?
+            .contains(Configuration::PORT2_CLOCK_DISABLED);
+        self.send_command(CMD_DISABLE_PORT2)?;
+
+        self.port1_available = self.test_port(Ps2Port::First)?;
+        self.port2_available = dual_channel && self.test_port(Ps2Port::Second)?;
+
+        if !self.port1_available && !self.port2_available {
+            return Err(Ps2Error::PortUnavailable(Ps2Port::First));
+        }
+
+        // now enable whichever ports work
+        if self.port1_available {
+            self.send_command(CMD_ENABLE_PORT1)?;
+            config.insert(Configuration::PORT1_INTERRUPT);
+            config.remove(Configuration::PORT1_CLOCK_DISABLED);
+            config.set(Configuration::PORT1_TRANSLATION, translation);
+        }
+        if self.port2_available {
+            self.send_command(CMD_ENABLE_PORT2)?;
+            config.insert(Configuration::PORT2_INTERRUPT);
+            config.remove(Configuration::PORT2_CLOCK_DISABLED);
+        }
+
+        self.write_config(config)?;
+        self.flush_output();
+
+        log::trace!(
+            "PS/2 controller initialised, first port: {}, second port: {}",
+            self.port1_available,
+            self.port2_available
+        );
+
+        Ok(())
+    }
+
+    /// Returns whether the given port passed its test during initialisation
+    pub fn is_available(&self, port: Ps2Port) -> bool {
+        match port {
+            Ps2Port::First => self.port1_available,
+            Ps2Port::Second => self.port2_available,
+        }
+    }
+
+    /// Reads the status register
+    pub fn status(&mut self) -> Status {
+        Status::from_bits_retain(unsafe { self.command.read() })
+    }
+
+    /// Reads the controller configuration byte
+    pub fn read_config(&mut self) -> Result<Configuration, Ps2Error> {
+        self.command_response(CMD_READ_CONFIG)
+            .map(Configuration::from_bits_retain)
+    }
+
+    /// Writes the controller configuration byte
+    pub fn write_config(&mut self, config: Configuration) -> Result<(), Ps2Error> {
+        self.send_command(CMD_WRITE_CONFIG)?;
+        self.write_data(config.bits())
+    }
+
+    /// Sends a command to a device, retrying if asked to resend, and waits for it to be acknowledged
+    pub fn device_command(&mut self, port: Ps2Port, command: u8) -> Result<(), Ps2Error> {
+        if !self.is_available(port) {
+            return Err(Ps2Error::PortUnavailable(port));
+        }
+
+        for _ in 0..RESEND_ATTEMPTS {
+            self.write_device(port, command)?;
+
+            match self.read_data()? {
+                DEVICE_ACK => return Ok(()),
+                DEVICE_RESEND => continue,
+                value => return Err(Ps2Error::UnexpectedResponse(value)),
+            }
+        }
+
+        Err(Ps2Error::UnexpectedResponse(DEVICE_RESEND))
+    }
+
+    /// Writes a byte to a device without waiting for a response
+    pub fn write_device(&mut self, port: Ps2Port, value: u8) -> Result<(), Ps2Error> {
+        if port == Ps2Port::Second {
+            self.send_command(CMD_WRITE_PORT2)?;
+        }
+
+        self.write_data(value)
+    }
+
+    /// Waits for and reads a byte from the data port
+    pub fn read_data(&mut self) -> Result<u8, Ps2Error> {
+        self.wait_for(|status| status.contains(Status::OUTPUT_FULL))?;
+
+        Ok(unsafe { self.data.read() })
+    }
+
+    /// Discards any bytes waiting in the output buffer
+    pub fn flush_output(&mut self) {
+        for _ in 0..TIMEOUT_ITERATIONS {
+            if !self.status().contains(Status::OUTPUT_FULL) {
+                return;
+            }
+
+            unsafe { self.data.read() };
+        }
+    }
+
+    /// Runs the interface test for the given port, returning whether it passed
+    fn test_port(&mut self, port: Ps2Port) -> Result<bool, Ps2Error> {
+        let command = match port {
+            Ps2Port::First => CMD_TEST_PORT1,
+            Ps2Port::Second => CMD_TEST_PORT2,
+        };
+
+        match self.command_response(command)? {
+            PORT_TEST_PASSED => Ok(true),
+            value => {
+                log::warn!("{}", Ps2Error::PortTestFailed(port, value));
+                Ok(false)
+            }
+        }
+    }
+
+    /// Sends a command to the controller
+    fn send_command(&mut self, command: u8) -> Result<(), Ps2Error> {
+        self.wait_for(|status| !status.contains(Status::INPUT_FULL))?;
+
+        unsafe { self.command.write(command) };
+        Ok(())
+    }
+
+    /// Sends a command to the controller and reads its response
+    fn command_response(&mut self, command: u8) -> Result<u8, Ps2Error> {
+        self.send_command(command)?;
+        self.read_data()
+    }
+
+    /// Writes a byte to the data port
+    fn write_data(&mut self, value: u8) -> Result<(), Ps2Error> {
+        self.wait_for(|status| !status.contains(Status::INPUT_FULL))?;
+
+        unsafe { self.data.write(value) };
+        Ok(())
+    }
+
+    /// Polls the status register until the condition holds, or times out
+    fn wait_for<F: Fn(Status) -> bool>(&mut self, condition: F) -> Result<(), Ps2Error> {
+        for _ in 0..TIMEOUT_ITERATIONS {
+            if condition(self.status()) {
+                return Ok(());
+            }
+
+            core::hint::spin_loop();
+        }
+
+        Err(Ps2Error::Timeout)
+    }
+}
+
+impl Default for Ps2Controller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,186 @@
+//! CMOS real time clock
+
+use core::fmt::{Display, Formatter};
+
+use crate::x86::hardware::cmos::Cmos;
+
+/// Register holding seconds
+const REGISTER_SECONDS: u8 = 0x00;
+
+/// Register holding minutes
+const REGISTER_MINUTES: u8 = 0x02;
+
+/// Register holding hours, with bit 7 set for PM in 12 hour mode
+const REGISTER_HOURS: u8 = 0x04;
+
+/// Register holding day of the month
+const REGISTER_DAY: u8 = 0x07;
+
+/// Register holding month
+const REGISTER_MONTH: u8 = 0x08;
+
+/// Register holding the last two digits of the year
+const REGISTER_YEAR: u8 = 0x09;
+
+/// Status register A, bit 7 is set while the clock is updating
+const REGISTER_STATUS_A: u8 = 0x0A;
+
+/// Status register B, describing how values are encoded
+const REGISTER_STATUS_B: u8 = 0x0B;
+
+/// Bit in status register A set while an update is in progress
+const UPDATE_IN_PROGRESS: u8 = 1 << 7;
+
+/// Bit in status register B set if hours are in 24 hour format
+const FORMAT_24_HOUR: u8 = 1 << 1;
+
+/// Bit in status register B set if values are binary rather than BCD
+const FORMAT_BINARY: u8 = 1 << 2;
+
+/// Century assumed for the two digit year, as the century register's location is only given by the FADT
+const CENTURY: u16 = 2000;
+
+/// A calendar date and time, in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    /// Full year, e.g. 2024
+    pub year: u16,
+    /// Month, 1-12
+    pub month: u8,
+    /// Day of the month, 1-31
+    pub day: u8,
+    /// Hour, 0-23
+    pub hour: u8,
+    /// Minute, 0-59
+    pub minute: u8,
+    /// Second, 0-59
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Returns the number of seconds since the unix epoch, 1970-01-01T00:00:00Z
+    pub const fn to_unix_seconds(&self) -> u64 {
+        // days from civil, shifting the year to start in March so leap days fall at the end
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = (self.month as i64 + 9) % 12;
+        let day_of_year = (153 * month + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        (days * 86_400 + self.hour as i64 * 3_600 + self.minute as i64 * 60 + self.second as i64)
+            as u64
+    }
+
+    /// Constructs a date and time from seconds since the unix epoch
+    pub const fn from_unix_seconds(seconds: u64) -> Self {
+        let days = (seconds / 86_400) as i64 + 719_468;
+        let time = seconds % 86_400;
+
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3_600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+}
+
+impl Display for DateTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// The CMOS real time clock, which keeps time while the machine is off
+pub struct Rtc {
+    /// CMOS memory holding the clock registers
+    cmos: Cmos,
+}
+
+impl Default for Rtc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rtc {
+    /// Constructs the RTC at the standard ports
+    pub const fn new() -> Self {
+        Self { cmos: Cmos::new() }
+    }
+
+    /// Reads every time register once an update isn't in progress
+    fn read_raw(&mut self) -> [u8; 6] {
+        while self.cmos.read(REGISTER_STATUS_A) & UPDATE_IN_PROGRESS != 0 {
+            core::hint::spin_loop();
+        }
+
+        [
+            REGISTER_SECONDS,
+            REGISTER_MINUTES,
+            REGISTER_HOURS,
+            REGISTER_DAY,
+            REGISTER_MONTH,
+            REGISTER_YEAR,
+        ]
+        .map(|register| self.cmos.read(register))
+    }
+
+    /// Reads the current date and time, assumed to be kept in UTC
+    pub fn read(&mut self) -> DateTime {
+        // an update can start between checking the flag and reading, so read until two reads agree
+        let mut raw = self.read_raw();
+        loop {
+            let again = self.read_raw();
+            if again == raw {
+                break;
+            }
+            raw = again;
+        }
+
+        let format = self.cmos.read(REGISTER_STATUS_B);
+        let decode = |value: u8| {
+            if format & FORMAT_BINARY != 0 {
+                value
+            } else {
+                (value >> 4) * 10 + (value & 0x0F)
+            }
+        };
+
+        let [second, minute, hour, day, month, year] = raw;
+
+        // in 12 hour mode the PM flag is kept separately from the encoded hour, and 12 means midnight or noon
+        let pm = hour & 0x80 != 0;
+        let mut hour = decode(hour & 0x7F);
+        if format & FORMAT_24_HOUR == 0 {
+            hour = hour % 12 + if pm { 12 } else { 0 };
+        }
+
+        DateTime {
+            year: CENTURY + decode(year) as u16,
+            month: decode(month),
+            day: decode(day),
+            hour,
+            minute: decode(minute),
+            second: decode(second),
+        }
+    }
+}
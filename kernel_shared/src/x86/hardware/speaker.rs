@@ -0,0 +1,110 @@
+//! PC speaker, driven by channel 2 of the PIT
+
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::{duration::Duration, mutex::Mutex};
+
+use crate::{
+    io::port::Port,
+    time,
+    timer::{self, QueueFull},
+    x86::hardware::pit::{PIT_FREQUENCY_HZ, ProgrammableIntervalTimer},
+};
+
+/// The system's PC speaker
+pub static SPEAKER: Mutex<PcSpeaker> = Mutex::new(PcSpeaker::new());
+
+/// Monotonic time in nanoseconds at which the latest [`PcSpeaker::beep`] ends, so an earlier beep's stop doesn't cut it short
+static BEEP_END_NS: AtomicU64 = AtomicU64::new(0);
+
+/// Bit in the keyboard controller port B which gates PIT channel 2
+const GATE_BIT: u8 = 1 << 0;
+
+/// Bit in the keyboard controller port B which connects PIT channel 2 to the speaker
+const SPEAKER_DATA_BIT: u8 = 1 << 1;
+
+/// Bit in the keyboard controller port B which reads back the output of PIT channel 2
+const CHANNEL2_OUTPUT_BIT: u8 = 1 << 5;
+
+/// The PC speaker
+pub struct PcSpeaker {
+    /// PIT, whose channel 2 generates the tone
+    pit: ProgrammableIntervalTimer,
+    /// Keyboard controller port B, which connects the PIT to the speaker
+    port_b: Port<u8>,
+}
+
+impl PcSpeaker {
+    /// Constructs the speaker at the standard ports
+    pub const fn new() -> Self {
+        Self {
+            pit: ProgrammableIntervalTimer::new(),
+            port_b: Port::new(0x61),
+        }
+    }
+
+    /// Starts playing a tone at the given frequency in Hz, until [`PcSpeaker::stop`] is called
+    pub fn play(&mut self, frequency: usize) {
+        let reload = (PIT_FREQUENCY_HZ / frequency.max(1)).clamp(1, u16::MAX as usize);
+        self.pit.set_channel2_square_wave(reload as u16);
+
+        unsafe {
+            let value = self.port_b.read();
+            self.port_b.write(value | GATE_BIT | SPEAKER_DATA_BIT);
+        }
+    }
+
+    /// Stops playing any tone
+    pub fn stop(&mut self) {
+        unsafe {
+            let value = self.port_b.read();
+            self.port_b.write(value & !(GATE_BIT | SPEAKER_DATA_BIT));
+        }
+    }
+
+    /// Plays a tone at the given frequency in Hz for the given duration, returning straight away and stopping it from the timer queue
+    pub fn beep(&mut self, frequency: usize, duration: Duration) -> Result<(), QueueFull> {
+        // reading the clock may calibrate the TSC against PIT channel 2, so must happen before the tone is programmed
+        let end_ns = time::monotonic_ns().saturating_add(duration.as_nanoseconds() as u64);
+        BEEP_END_NS.store(end_ns, Ordering::Relaxed);
+
+        self.play(frequency);
+        timer::schedule(duration, stop_finished_beep).inspect_err(|_| self.stop())
+    }
+
+    /// Plays a tone at the given frequency in Hz for the given duration, busy-waiting until it finishes.
+    ///
+    /// The wait is timed by counting cycles of the tone itself, so this doesn't need the TSC, the timer queue or any lock,
+    /// and is safe to use from the panic handler.
+    pub fn beep_busy(&mut self, frequency: usize, duration: Duration) {
+        self.play(frequency);
+
+        let cycles = frequency as u64 * duration.as_microseconds() as u64 / 1_000_000;
+        for _ in 0..cycles {
+            while !self.channel2_output() {}
+            while self.channel2_output() {}
+        }
+
+        self.stop();
+    }
+
+    /// Reads the current level of the PIT channel 2 output, which toggles every half cycle of the tone
+    fn channel2_output(&mut self) -> bool {
+        unsafe { self.port_b.read() & CHANNEL2_OUTPUT_BIT != 0 }
+    }
+}
+
+/// Stops the speaker once the latest beep has finished, run from the timer queue
+fn stop_finished_beep() {
+    if time::monotonic_ns() < BEEP_END_NS.load(Ordering::Relaxed) {
+        return;
+    }
+
+    // port B holds no state in the speaker, so a fresh handle avoids waiting on the lock from an interrupt
+    PcSpeaker::new().stop();
+}
+
+impl Default for PcSpeaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -4,6 +4,7 @@ use core::{
     marker::PhantomData,
     ops::{Index, IndexMut},
 };
+use std::{assert_eq_size, assert_offset};
 
 use bit_field::BitField;
 
@@ -58,6 +59,8 @@ pub struct InterruptDescriptorTable {
     interrupts: [IdtEntry<HandlerFunc>; 256 - 32],
 }
 
+assert_eq_size!(InterruptDescriptorTable, 256 * 16);
+
 impl Default for InterruptDescriptorTable {
     fn default() -> InterruptDescriptorTable {
         InterruptDescriptorTable {
@@ -208,6 +211,11 @@ pub struct IdtEntry<F> {
     phantom: PhantomData<F>,
 }
 
+assert_eq_size!(IdtEntry<HandlerFunc>, 16);
+assert_offset!(IdtEntry<HandlerFunc>, options, 2);
+assert_offset!(IdtEntry<HandlerFunc>, middle_fn_pointer, 6);
+assert_offset!(IdtEntry<HandlerFunc>, high_fn_pointer, 8);
+
 impl<F: HandlerFuncType> IdtEntry<F> {
     /// Returns an entry with no function
     fn missing() -> Self {
@@ -248,6 +256,8 @@ pub struct EntryOptions {
     bits: u16,
 }
 
+assert_eq_size!(EntryOptions, 4);
+
 impl Default for EntryOptions {
     /// Constructs options with reasonable defaults (present = true, gate = true)
     fn default() -> Self {
@@ -1,5 +1,9 @@
 //! Wrapper functions for x86 intrinsics
 
+pub mod backtrace;
+pub mod cpuid;
+pub mod debug;
+pub mod delay;
 pub mod descriptor_table_pointer;
 pub mod exception;
 pub mod gdt;
@@ -7,11 +11,12 @@ pub mod hardware;
 pub mod idt;
 pub mod registers;
 pub mod segment_selector;
+pub mod smp;
 pub mod tss;
 
 use core::arch::asm;
 
-use crate::x86::registers::CpuFlags;
+use crate::{mem::addr::VirtAddr, x86::registers::CpuFlags};
 
 /// Privilege level
 pub enum PrivilegeLevel {
@@ -39,11 +44,11 @@ impl PrivilegeLevel {
 }
 
 /// Invalidates a given address in the TLB
-pub fn invalidate_address(addr: usize) {
+pub fn invalidate_address(addr: VirtAddr) {
     unsafe {
         asm!(
         "invlpg [{}]",
-        in(reg) addr as u64,
+        in(reg) addr.as_usize() as u64,
         options(nostack, preserves_flags)
         )
     }
@@ -56,6 +61,17 @@ pub fn halt() -> ! {
     }
 }
 
+/// Reads the timestamp counter
+pub fn rdtsc() -> u64 {
+    let (high, low): (u32, u32);
+
+    unsafe {
+        asm!("rdtsc", out("edx") high, out("eax") low, options(nomem, nostack, preserves_flags));
+    }
+
+    (high as u64) << 32 | low as u64
+}
+
 /// Returns true if interrupts are enabled
 pub fn are_interrupts_enabled() -> bool {
     CpuFlags::read().contains(CpuFlags::INTERRUPT_FLAG)
@@ -5,7 +5,7 @@ use core::{arch::asm, fmt::Display};
 
 use bitflags::bitflags;
 
-use crate::mem::frame::Frame;
+use crate::mem::{addr::PhysAddr, frame::Frame};
 
 /// CR3 register
 pub struct CR3;
@@ -20,7 +20,7 @@ impl CR3 {
         }
 
         let addr = val & 0x_000F_FFFF_FFFF_F000;
-        let frame = Frame::containing_address(addr as usize);
+        let frame = Frame::containing_address(PhysAddr::new(addr as usize));
 
         (frame, (val & 0xFFF) as u16)
     }
@@ -31,7 +31,7 @@ impl CR3 {
     /// `frame` and `flags` must be valid to write to `CR3`.
     pub unsafe fn write(frame: Frame, flags: u16) {
         let addr = frame.start_address();
-        let val = addr as u64 | flags as u64;
+        let val = addr.as_usize() as u64 | flags as u64;
 
         unsafe {
             asm!("mov cr3, {}", in(reg) val, options(nostack, preserves_flags));
@@ -51,6 +51,51 @@ impl CR3 {
     }
 }
 
+bitflags! {
+    /// CR0 control flags
+    #[repr(transparent)]
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub struct Cr0Flags: u64 {
+        const PROTECTED_MODE_ENABLE = 1;
+        const MONITOR_COPROCESSOR = 1 << 1;
+        const EMULATE_COPROCESSOR = 1 << 2;
+        const TASK_SWITCHED = 1 << 3;
+        const EXTENSION_TYPE = 1 << 4;
+        const NUMERIC_ERROR = 1 << 5;
+        const WRITE_PROTECT = 1 << 16;
+        const ALIGNMENT_MASK = 1 << 18;
+        const NOT_WRITE_THROUGH = 1 << 29;
+        const CACHE_DISABLE = 1 << 30;
+        const PAGING = 1 << 31;
+    }
+}
+
+/// CR0 register
+pub struct CR0;
+
+impl CR0 {
+    /// Reads the current value of CR0
+    pub fn read() -> Cr0Flags {
+        let value: u64;
+
+        unsafe {
+            asm!("mov {}, cr0", out(reg) value, options(nostack, preserves_flags));
+        }
+
+        Cr0Flags::from_bits_retain(value)
+    }
+
+    /// Writes the provided flags to CR0 register
+    ///
+    /// # Safety
+    /// `flags` must be valid to write to `CR0`, and must not break any assumptions about the current CPU mode.
+    pub unsafe fn write(flags: Cr0Flags) {
+        unsafe {
+            asm!("mov cr0, {}", in(reg) flags.bits(), options(nostack, preserves_flags));
+        }
+    }
+}
+
 /// CR2 register
 pub struct CR2;
 
@@ -67,6 +112,22 @@ impl CR2 {
     }
 }
 
+/// CR4 register
+pub struct CR4;
+
+impl CR4 {
+    /// Reads the current value of CR4
+    pub fn read() -> usize {
+        let value: usize;
+
+        unsafe {
+            asm!("mov {}, cr4", out(reg) value, options(nostack, preserves_flags));
+        }
+
+        value
+    }
+}
+
 bitflags! {
     /// CPU flags
     #[repr(transparent)]
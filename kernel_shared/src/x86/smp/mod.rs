@@ -0,0 +1,28 @@
+//! Code for starting application processors
+
+use core::fmt::{Display, Formatter};
+
+use crate::mem::addr::PhysAddr;
+
+pub mod trampoline;
+
+/// An error preparing to start application processors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmpError {
+    /// No low frame could be claimed for the trampoline
+    NoLowMemory,
+    /// Page table is above 4 GiB, so can't be loaded before entering long mode
+    PageTableTooHigh(PhysAddr),
+    /// No frames were left to map the trampoline
+    OutOfFrames,
+}
+
+impl Display for SmpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoLowMemory => write!(f, "no low memory for ap trampoline"),
+            Self::PageTableTooHigh(addr) => write!(f, "page table at {addr} is above 4 GiB"),
+            Self::OutOfFrames => write!(f, "out of frames to map ap trampoline"),
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Real mode trampoline which application processors start executing after a startup IPI.
+//!
+//! A startup IPI starts the AP in real mode at `vector * 0x1000`, so the trampoline has to be copied into a frame
+//! below 1 MiB. From there it switches to protected mode with its own GDT, enables paging with the page table it was
+//! given, and jumps to the entry point in long mode. Parameters are written into a block at the start of the copy,
+//! which the code addresses relative to where it was loaded.
+
+use core::{arch::global_asm, mem::offset_of};
+use std::{assert_eq_size, assert_offset};
+
+use crate::{
+    mem::{
+        addr::VirtAddr,
+        frame::{FRAME_SIZE, Frame},
+        frame_alloc::{bitmap::BitmapFrameAlloc, low_mem},
+        page::Page,
+        paging::{entry::EntryFlags, mapper::Mapper},
+    },
+    x86::smp::SmpError,
+};
+
+/// Offset of [`TrampolineParams`] within the trampoline, just past the jump over it
+const PARAMS_OFFSET: usize = 8;
+
+/// Offset of a field of [`TrampolineParams`] within the trampoline
+macro_rules! param {
+    ($field:ident) => {
+        PARAMS_OFFSET + offset_of!(TrampolineParams, $field)
+    };
+}
+
+global_asm!(
+    ".pushsection .rodata.ap_trampoline, \"a\"",
+    ".code16",
+    ".global ap_trampoline_start",
+    "ap_trampoline_start:",
+    "    jmp 2f",
+    // parameters, laid out as `TrampolineParams`, with addresses relative to the start of the trampoline
+    ".org {params}",
+    "    .quad 0, 0, 0, 0",
+    "    .long 3f - ap_trampoline_start",
+    "    .word 0x08, 0",
+    "    .long 4f - ap_trampoline_start",
+    "    .word 0x18, 0",
+    "    .word 0, {gdt_limit}",
+    "    .long {gdt}",
+    "    .quad 0",
+    "    .quad 0x00CF9A000000FFFF", // 32 bit code
+    "    .quad 0x00CF92000000FFFF", // data
+    "    .quad 0x00AF9A000000FFFF", // 64 bit code
+    "2:",
+    "    cli",
+    "    cld",
+    // the startup IPI sets cs to the start of the trampoline, so keep its linear address in ebx for later modes
+    "    mov ax, cs",
+    "    mov ds, ax",
+    "    movzx ebx, ax",
+    "    shl ebx, 4",
+    "    lgdt [{gdtr}]",
+    "    mov eax, cr0",
+    "    or eax, 1",
+    "    mov cr0, eax",
+    "    jmp fword ptr [{protected_mode_jump}]",
+    ".code32",
+    "3:",
+    "    mov ax, 0x10",
+    "    mov ds, ax",
+    "    mov es, ax",
+    "    mov ss, ax",
+    // enable PAE, long mode and NXE, then paging and write protect, the same as the loader does
+    "    mov eax, cr4",
+    "    or eax, 1 << 5",
+    "    mov cr4, eax",
+    "    mov eax, [ebx + {page_table}]",
+    "    mov cr3, eax",
+    "    mov ecx, 0xC0000080",
+    "    rdmsr",
+    "    or eax, (1 << 11) | (1 << 8)",
+    "    wrmsr",
+    "    mov eax, cr0",
+    "    or eax, (1 << 31) | (1 << 16)",
+    "    mov cr0, eax",
+    "    jmp fword ptr [ebx + {long_mode_jump}]",
+    ".code64",
+    "4:",
+    // the upper half of registers is undefined after entering long mode
+    "    mov ebx, ebx",
+    "    mov rsp, [rbx + {stack_top}]",
+    "    mov rdi, [rbx + {argument}]",
+    "    call [rbx + {entry}]",
+    "5:",
+    "    hlt",
+    "    jmp 5b",
+    ".global ap_trampoline_end",
+    "ap_trampoline_end:",
+    ".popsection",
+    params = const PARAMS_OFFSET,
+    gdt_limit = const size_of::<[u64; 4]>() - 1,
+    gdt = const param!(gdt),
+    gdtr = const param!(gdt_limit),
+    protected_mode_jump = const param!(protected_mode_jump),
+    long_mode_jump = const param!(long_mode_jump),
+    page_table = const param!(page_table),
+    stack_top = const param!(stack_top),
+    argument = const param!(argument),
+    entry = const param!(entry),
+);
+
+unsafe extern "C" {
+    /// Start of the trampoline blob
+    static ap_trampoline_start: u8;
+    /// End of the trampoline blob
+    static ap_trampoline_end: u8;
+}
+
+/// Pointer to code in another segment, as used by an indirect far jump
+#[repr(C)]
+struct FarPointer {
+    /// Offset within the segment, which is flat so this is the linear address
+    offset: u32,
+    /// Code segment selector within the trampoline's GDT
+    selector: u16,
+    /// Padding to keep the next field aligned
+    _padding: u16,
+}
+
+/// Parameters read by the trampoline, which must match the layout written in the blob
+#[repr(C)]
+struct TrampolineParams {
+    /// Physical address of the page table to load, which must be below 4 GiB
+    page_table: u64,
+    /// Entry point to call in long mode
+    entry: u64,
+    /// Stack pointer for the entry point
+    stack_top: u64,
+    /// Argument passed to the entry point
+    argument: u64,
+    /// Jump into protected mode
+    protected_mode_jump: FarPointer,
+    /// Jump into long mode
+    long_mode_jump: FarPointer,
+    /// Padding to keep the GDT base aligned
+    _gdtr_padding: u16,
+    /// Size of the GDT minus one
+    gdt_limit: u16,
+    /// Physical address of the GDT
+    gdt_base: u32,
+    /// Null, 32 bit code, data and 64 bit code descriptors
+    gdt: [u64; 4],
+}
+
+// the blob lays the parameters out by hand, so they must match it
+assert_eq_size!(FarPointer, 8);
+assert_eq_size!(TrampolineParams, 88);
+assert_offset!(TrampolineParams, protected_mode_jump, 32);
+assert_offset!(TrampolineParams, long_mode_jump, 40);
+assert_offset!(TrampolineParams, gdt_limit, 50);
+assert_offset!(TrampolineParams, gdt_base, 52);
+assert_offset!(TrampolineParams, gdt, 56);
+
+/// What an application processor runs once it reaches long mode
+#[derive(Debug, Clone, Copy)]
+pub struct ApStartup {
+    /// Page table to load, which must be below 4 GiB as it is loaded before entering long mode. It must map the
+    /// trampoline, which [`Trampoline::install`] does for the current table.
+    pub page_table: Frame,
+    /// Entry point, called with `argument`
+    pub entry: extern "C" fn(usize) -> !,
+    /// Top of the stack to run the entry point on
+    pub stack_top: VirtAddr,
+    /// Argument passed to the entry point, such as a pointer to per-CPU data
+    pub argument: usize,
+}
+
+/// The trampoline, copied into a low frame
+#[derive(Debug)]
+pub struct Trampoline {
+    /// Frame the trampoline was copied to
+    frame: Frame,
+}
+
+impl Trampoline {
+    /// Claims a low frame and copies the trampoline into it, identity mapping it so the AP can keep running from it
+    /// once paging is enabled
+    pub fn install(
+        mapper: &mut Mapper,
+        frame_alloc: &mut BitmapFrameAlloc,
+    ) -> Result<Self, SmpError> {
+        let blob = blob();
+        assert!(
+            blob.len() <= FRAME_SIZE,
+            "trampoline does not fit in a frame"
+        );
+
+        // frame 0 is never claimable, so start after it
+        let frame = (Frame { number: 1 }..Frame::containing_address(low_mem::LOW_MEMORY_END))
+            .find(|&frame| {
+                low_mem::claim(
+                    frame_alloc,
+                    frame..Frame {
+                        number: frame.number + 1,
+                    },
+                )
+                .is_ok()
+            })
+            .ok_or(SmpError::NoLowMemory)?;
+
+        let base = frame.start_address().as_usize() as u32;
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                blob.as_ptr(),
+                frame.start_address().as_hhdm_ptr(),
+                blob.len(),
+            );
+        }
+
+        let mut trampoline = Self { frame };
+
+        // addresses in the blob are relative to its start, so relocate them to where it was copied
+        let params = trampoline.params();
+        params.protected_mode_jump.offset += base;
+        params.long_mode_jump.offset += base;
+        params.gdt_base += base;
+
+        if mapper
+            .identity_map(frame, EntryFlags::empty(), frame_alloc)
+            .is_err()
+        {
+            low_mem::release(
+                frame..Frame {
+                    number: frame.number + 1,
+                },
+            )
+            .expect("trampoline frame was not claimed");
+            return Err(SmpError::OutOfFrames);
+        }
+        log::trace!("\t* ap trampoline installed at {}", frame.start_address());
+
+        Ok(trampoline)
+    }
+
+    /// Returns the vector to send in a startup IPI, which is the page the AP starts executing at
+    pub fn vector(&self) -> u8 {
+        self.frame.number as u8
+    }
+
+    /// Sets what the next AP started from this trampoline will run
+    pub fn prepare(&mut self, startup: &ApStartup) -> Result<(), SmpError> {
+        let page_table = startup.page_table.start_address();
+        if page_table.as_usize() > u32::MAX as usize {
+            return Err(SmpError::PageTableTooHigh(page_table));
+        }
+
+        let params = self.params();
+        params.page_table = page_table.as_usize() as u64;
+        params.entry = startup.entry as usize as u64;
+        params.stack_top = startup.stack_top.as_usize() as u64;
+        params.argument = startup.argument as u64;
+
+        Ok(())
+    }
+
+    /// Unmaps the trampoline and releases its frame, once every AP has started
+    pub fn uninstall(self, mapper: &mut Mapper, frame_alloc: &mut BitmapFrameAlloc) {
+        let page = Page::containing_address(VirtAddr::new(self.frame.start_address().as_usize()));
+
+        // low frames are never freed by the frame allocator, so this only removes the mapping
+        mapper.unmap(page, frame_alloc, true);
+        low_mem::release(
+            self.frame..Frame {
+                number: self.frame.number + 1,
+            },
+        )
+        .expect("trampoline frame was not claimed");
+    }
+
+    /// Returns the parameters within the copied trampoline
+    fn params(&mut self) -> &mut TrampolineParams {
+        let addr = self.frame.start_address() + PARAMS_OFFSET;
+        unsafe { &mut *addr.as_hhdm_ptr::<TrampolineParams>() }
+    }
+}
+
+/// Returns the trampoline blob
+fn blob() -> &'static [u8] {
+    let start = &raw const ap_trampoline_start;
+    let end = &raw const ap_trampoline_end;
+
+    unsafe { core::slice::from_raw_parts(start, end.addr() - start.addr()) }
+}
@@ -1,6 +1,11 @@
 //! Code for manipulating and using Task State Segments
 
-use core::fmt::Display;
+use core::{
+    fmt::Display,
+    ops::Range,
+    sync::atomic::{AtomicU8, Ordering},
+};
+use std::{assert_eq_size, assert_offset};
 
 /// A task state segment
 #[derive(Debug)]
@@ -22,6 +27,11 @@ pub struct TaskStateSegment {
     pub base_addr: u16,
 }
 
+assert_eq_size!(TaskStateSegment, 104);
+assert_offset!(TaskStateSegment, privilege_stack_table, 4);
+assert_offset!(TaskStateSegment, interrupt_stack_table, 36);
+assert_offset!(TaskStateSegment, base_addr, 102);
+
 impl Default for TaskStateSegment {
     fn default() -> Self {
         Self {
@@ -36,6 +46,63 @@ impl Default for TaskStateSegment {
     }
 }
 
+/// Number of bytes in an I/O permission bitmap covering every port, plus the trailing byte the CPU requires
+const IO_BITMAP_SIZE: usize = 65536 / 8 + 1;
+
+/// Bitmap of which I/O ports code running with CPL > IOPL may access, where a set bit denies access.
+///
+/// Bits are atomic so access can be granted or revoked while the bitmap is in use by the CPU.
+#[repr(transparent)]
+pub struct IoPermissionBitmap([AtomicU8; IO_BITMAP_SIZE]);
+
+impl IoPermissionBitmap {
+    /// Constructs a bitmap denying access to every port
+    pub const fn new() -> Self {
+        // the final byte must always be all ones, as the CPU may read a byte past the last port's bit
+        Self([const { AtomicU8::new(0xFF) }; IO_BITMAP_SIZE])
+    }
+
+    /// Allows access to the given ports
+    pub fn allow(&self, ports: Range<u16>) {
+        for port in ports {
+            self.0[port as usize / 8].fetch_and(!(1 << (port % 8)), Ordering::Relaxed);
+        }
+    }
+
+    /// Denies access to the given ports
+    pub fn deny(&self, ports: Range<u16>) {
+        for port in ports {
+            self.0[port as usize / 8].fetch_or(1 << (port % 8), Ordering::Relaxed);
+        }
+    }
+
+    /// Returns whether access to the given port is allowed
+    pub fn is_allowed(&self, port: u16) -> bool {
+        self.0[port as usize / 8].load(Ordering::Relaxed) & (1 << (port % 8)) == 0
+    }
+}
+
+impl Default for IoPermissionBitmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A task state segment immediately followed by its I/O permission bitmap.
+///
+/// The default TSS already points its I/O map base just past itself, where the bitmap lies.
+#[derive(Default)]
+#[repr(C)]
+pub struct TssWithIoBitmap {
+    /// Task state segment, with `base_addr` pointing at the bitmap
+    pub tss: TaskStateSegment,
+    /// Ports which user code may access
+    pub io_bitmap: IoPermissionBitmap,
+}
+
+// the default I/O map base is the size of the TSS, so the bitmap must follow it directly
+assert_offset!(TssWithIoBitmap, io_bitmap, size_of::<TaskStateSegment>());
+
 impl Display for TaskStateSegment {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let privilege_table = self.privilege_stack_table;
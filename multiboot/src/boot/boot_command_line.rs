@@ -23,10 +23,10 @@ impl BootTag for BootCommandLine {
         // size is 8 bytes for tag + size fields, so any more past that is string length
         let str_len = size - 8;
 
-        let command = unsafe {
-            // safety: we know this is a boot command line tag, so we expect a cstr
-            buffer.read_cstr(str_len as usize)?
-        };
+        // safety: the tag was bounds checked against the boot information, so the string is in bounds.
+        // it may still be missing its terminator, which from_bytes_until_nul rejects
+        let command = unsafe { buffer.read_slice(str_len as usize)? };
+        let command = CStr::from_bytes_until_nul(command).ok()?;
 
         Some(Self { command })
     }
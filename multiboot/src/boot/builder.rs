@@ -0,0 +1,197 @@
+//! Builds synthetic multiboot2 information, so [`BootInfo::new`](crate::boot::BootInfo::new) and code consuming it
+//! can be exercised on the host against crafted (and deliberately malformed) inputs.
+//!
+//! Only available with the `builder` feature, as nothing booting for real needs it.
+
+use crate::prelude::{
+    BasicMemInfo, BootCommandLine, BootTag, ElfSymbols, LoadBaseAddr, MemoryEntryType, MemoryMap,
+    MemoryMapEntry, Module, RSDPv1, RSDPv2,
+};
+
+/// Tags and the whole structure are aligned to 8 bytes
+const ALIGNMENT: usize = 8;
+
+/// Size of the type and size fields which start every tag
+const TAG_HEADER_SIZE: u32 = 8;
+
+/// Writes boot information tags into a caller-provided buffer.
+///
+/// The buffer should be 8 byte aligned, as real boot information is, and must outlive any
+/// [`BootInfo`](crate::boot::BootInfo) parsed from it.
+pub struct BootInfoBuilder<'a> {
+    /// Buffer being written to
+    buffer: &'a mut [u8],
+    /// Number of bytes written so far
+    offset: usize,
+    /// Whether a write didn't fit in the buffer
+    overflowed: bool,
+}
+
+impl<'a> BootInfoBuilder<'a> {
+    /// Constructs a builder writing to `buffer`, reserving space for the total size and reserved fields
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        let mut builder = Self {
+            buffer,
+            offset: 0,
+            overflowed: false,
+        };
+
+        builder.write(&0u32.to_ne_bytes());
+        builder.write(&0u32.to_ne_bytes());
+        builder
+    }
+
+    /// Appends bytes, recording an overflow if they don't fit
+    fn write(&mut self, bytes: &[u8]) {
+        match self.buffer.get_mut(self.offset..self.offset + bytes.len()) {
+            Some(dest) => dest.copy_from_slice(bytes),
+            None => self.overflowed = true,
+        }
+        self.offset += bytes.len();
+    }
+
+    /// Pads with zeroes up to the next 8 byte boundary
+    fn pad(&mut self) {
+        let padding = self.offset.next_multiple_of(ALIGNMENT) - self.offset;
+        self.write(&[0; ALIGNMENT][..padding]);
+    }
+
+    /// Writes a tag with the given type and payload, with `size` covering the header and payload.
+    ///
+    /// Passing a `size` which doesn't match the payload produces a malformed tag, for testing how the parser copes.
+    pub fn raw_tag_with_size(&mut self, tag_type: u32, size: u32, payload: &[&[u8]]) -> &mut Self {
+        self.write(&tag_type.to_ne_bytes());
+        self.write(&size.to_ne_bytes());
+        for part in payload {
+            self.write(part);
+        }
+        self.pad();
+
+        self
+    }
+
+    /// Writes a tag with the given type and payload, calculating its size
+    pub fn raw_tag(&mut self, tag_type: u32, payload: &[&[u8]]) -> &mut Self {
+        let size = TAG_HEADER_SIZE + payload.iter().map(|part| part.len() as u32).sum::<u32>();
+        self.raw_tag_with_size(tag_type, size, payload)
+    }
+
+    /// Writes a basic memory information tag, with sizes in KiB
+    pub fn basic_mem_info(&mut self, mem_lower: u32, mem_upper: u32) -> &mut Self {
+        self.raw_tag(
+            BasicMemInfo::TYPE,
+            &[&mem_lower.to_ne_bytes(), &mem_upper.to_ne_bytes()],
+        )
+    }
+
+    /// Writes a boot command line tag
+    pub fn command_line(&mut self, command: &str) -> &mut Self {
+        self.raw_tag(BootCommandLine::TYPE, &[command.as_bytes(), &[0]])
+    }
+
+    /// Writes a module tag, for a module occupying `start..end` in physical memory
+    pub fn module(&mut self, start: u32, end: u32, module_str: &str) -> &mut Self {
+        self.raw_tag(
+            Module::TYPE,
+            &[
+                &start.to_ne_bytes(),
+                &end.to_ne_bytes(),
+                module_str.as_bytes(),
+                &[0],
+            ],
+        )
+    }
+
+    /// Writes a memory map tag, from `(base address, length, type)` entries
+    pub fn memory_map(&mut self, entries: &[(u64, u64, MemoryEntryType)]) -> &mut Self {
+        const ENTRY_SIZE: u32 = size_of::<MemoryMapEntry>() as u32;
+
+        let size = TAG_HEADER_SIZE + 8 + entries.len() as u32 * ENTRY_SIZE;
+        self.raw_tag_with_size(
+            MemoryMap::TYPE,
+            size,
+            &[&ENTRY_SIZE.to_ne_bytes(), &0u32.to_ne_bytes()],
+        );
+
+        // the 16 byte header needs no padding, so entries follow it directly
+        for (base_addr, length, entry_type) in entries {
+            self.write(&base_addr.to_ne_bytes());
+            self.write(&length.to_ne_bytes());
+            self.write(&entry_type.as_u32().to_ne_bytes());
+            self.write(&0u32.to_ne_bytes());
+        }
+        self.pad();
+
+        self
+    }
+
+    /// Writes an RSDPv1 tag pointing at `rsdt_addr`, with a valid checksum
+    pub fn rsdp_v1(&mut self, oem_id: [u8; 6], rsdt_addr: u32) -> &mut Self {
+        self.raw_tag(RSDPv1::TYPE, &[&rsdp_v1(oem_id, 0, rsdt_addr)])
+    }
+
+    /// Writes an RSDPv2 tag pointing at `rsdt_addr` and `xsdt_addr`, with valid checksums
+    pub fn rsdp_v2(&mut self, oem_id: [u8; 6], rsdt_addr: u32, xsdt_addr: u64) -> &mut Self {
+        let mut extended = [0; 16];
+        extended[..4].copy_from_slice(&36u32.to_ne_bytes());
+        extended[4..12].copy_from_slice(&xsdt_addr.to_ne_bytes());
+        extended[12] = 0u8.wrapping_sub(checksum(&extended));
+
+        self.raw_tag(RSDPv2::TYPE, &[&rsdp_v1(oem_id, 2, rsdt_addr), &extended])
+    }
+
+    /// Writes an ELF symbols tag, from raw section headers
+    pub fn elf_symbols(
+        &mut self,
+        entry_count: u32,
+        entry_size: u32,
+        string_table_index: u32,
+        section_headers: &[u8],
+    ) -> &mut Self {
+        self.raw_tag(
+            ElfSymbols::TYPE,
+            &[
+                &entry_count.to_ne_bytes(),
+                &entry_size.to_ne_bytes(),
+                &string_table_index.to_ne_bytes(),
+                section_headers,
+            ],
+        )
+    }
+
+    /// Writes an image load base address tag
+    pub fn load_base_addr(&mut self, load_base_addr: u32) -> &mut Self {
+        self.raw_tag(LoadBaseAddr::TYPE, &[&load_base_addr.to_ne_bytes()])
+    }
+
+    /// Writes the end tag and total size, returning the finished boot information, or [`None`] if it didn't fit in
+    /// the buffer
+    pub fn finish(&mut self) -> Option<&[u8]> {
+        self.raw_tag(0, &[]);
+        if self.overflowed {
+            return None;
+        }
+
+        let size = self.offset as u32;
+        self.buffer[..4].copy_from_slice(&size.to_ne_bytes());
+
+        Some(&self.buffer[..self.offset])
+    }
+}
+
+/// Builds the RSDPv1 fields, with a valid checksum
+fn rsdp_v1(oem_id: [u8; 6], revision: u8, rsdt_addr: u32) -> [u8; 20] {
+    let mut rsdp = [0; 20];
+    rsdp[..8].copy_from_slice(b"RSD PTR ");
+    rsdp[9..15].copy_from_slice(&oem_id);
+    rsdp[15] = revision;
+    rsdp[16..].copy_from_slice(&rsdt_addr.to_ne_bytes());
+    rsdp[8] = 0u8.wrapping_sub(checksum(&rsdp));
+
+    rsdp
+}
+
+/// Wrapping sum of every byte
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
@@ -1,6 +1,7 @@
 //! ELF symbols
 
-use std::{cursor::Cursor, elf::section_header::SectionHeader};
+use core::ffi::CStr;
+use std::{collections::ArrayVec, cursor::Cursor, elf::section_header::SectionHeader};
 
 use crate::boot::boot_tag::BootTag;
 
@@ -15,15 +16,85 @@ pub struct ElfSymbols {
     pub entry_size: u32,
     /// Which index is the string table
     pub string_table_index: u32,
-    /// Section headers
-    pub section_headers: &'static [SectionHeader],
+    /// Section headers, which live in the boot information and so are only valid as long as it is. Only handed out
+    /// borrowed from self, so they can't outlive the [`BootInfo`](crate::boot::BootInfo).
+    section_headers: &'static [SectionHeader],
 }
 
 impl ElfSymbols {
+    /// Returns the section headers, borrowed from the boot information
+    pub const fn section_headers(&self) -> &[SectionHeader] {
+        self.section_headers
+    }
+
     /// Returns the header for the string table
     pub const fn string_header(&self) -> &SectionHeader {
         &self.section_headers[self.string_table_index as usize]
     }
+
+    /// Copies the section headers and section name string table into an owned value, so they can be kept after the
+    /// boot information is reclaimed. Returns [`None`] if there are more than `HEADERS` headers, or the string
+    /// table is larger than `STRINGS` bytes.
+    ///
+    /// # Safety
+    /// The string table must be readable at its section's address plus `string_table_offset`, e.g. the physical
+    /// memory offset when reading it through the higher half mapping.
+    pub unsafe fn copy_out<const HEADERS: usize, const STRINGS: usize>(
+        &self,
+        string_table_offset: usize,
+    ) -> Option<OwnedElfSymbols<HEADERS, STRINGS>> {
+        let mut section_headers = ArrayVec::new();
+        for header in self.section_headers {
+            section_headers.push(*header).ok()?;
+        }
+
+        let string_header = self.string_header();
+        let length = string_header.size as usize;
+        if length > STRINGS {
+            return None;
+        }
+
+        let mut string_table = [0; STRINGS];
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                (string_header.addr as usize + string_table_offset) as *const u8,
+                string_table.as_mut_ptr(),
+                length,
+            );
+        }
+
+        Some(OwnedElfSymbols {
+            string_table_index: self.string_table_index,
+            section_headers,
+            string_table,
+            string_table_length: length,
+        })
+    }
+}
+
+/// ELF section headers and their names, copied out of the boot information with [`ElfSymbols::copy_out`]
+pub struct OwnedElfSymbols<const HEADERS: usize, const STRINGS: usize> {
+    /// Which index is the string table
+    pub string_table_index: u32,
+    /// Copied section headers
+    section_headers: ArrayVec<SectionHeader, HEADERS>,
+    /// Copied section name string table
+    string_table: [u8; STRINGS],
+    /// Number of bytes of `string_table` in use
+    string_table_length: usize,
+}
+
+impl<const HEADERS: usize, const STRINGS: usize> OwnedElfSymbols<HEADERS, STRINGS> {
+    /// Returns the section headers
+    pub fn section_headers(&self) -> &[SectionHeader] {
+        &self.section_headers
+    }
+
+    /// Returns the name of a section, or [`None`] if its name is outside the string table
+    pub fn name(&self, header: &SectionHeader) -> Option<&CStr> {
+        let strings = &self.string_table[..self.string_table_length];
+        CStr::from_bytes_until_nul(strings.get(header.section_name as usize..)?).ok()
+    }
 }
 
 impl BootTag for ElfSymbols {
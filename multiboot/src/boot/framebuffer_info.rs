@@ -0,0 +1,51 @@
+//! Framebuffer info tag
+
+use std::cursor::Cursor;
+
+use crate::boot::boot_tag::BootTag;
+
+/// Framebuffer set up by the bootloader
+///
+/// https://www.gnu.org/software/grub/manual/multiboot2/multiboot.html#Framebuffer-info
+#[derive(Debug)]
+pub struct FramebufferInfo {
+    /// Physical address of the framebuffer
+    pub framebuffer_addr: u64,
+    /// Number of bytes between the start of each row
+    pub framebuffer_pitch: u32,
+    /// Width in pixels, or characters for EGA text mode
+    pub framebuffer_width: u32,
+    /// Height in pixels, or characters for EGA text mode
+    pub framebuffer_height: u32,
+    /// Bits per pixel
+    pub framebuffer_bpp: u8,
+    /// Type of framebuffer: 0 for indexed colour, 1 for direct RGB colour, 2 for EGA text
+    pub framebuffer_type: u8,
+}
+
+impl BootTag for FramebufferInfo {
+    const TYPE: u32 = 8;
+
+    fn read_from_buffer(buffer: &mut Cursor) -> Option<Self> {
+        let size = buffer.read_u32()?;
+
+        let framebuffer_addr = buffer.read_u64()?;
+        let framebuffer_pitch = buffer.read_u32()?;
+        let framebuffer_width = buffer.read_u32()?;
+        let framebuffer_height = buffer.read_u32()?;
+        let framebuffer_bpp = buffer.read_u8()?;
+        let framebuffer_type = buffer.read_u8()?;
+
+        // skip reserved field and colour info, which we don't use yet
+        buffer.increment_offset(size as usize - 30);
+
+        Some(Self {
+            framebuffer_addr,
+            framebuffer_pitch,
+            framebuffer_width,
+            framebuffer_height,
+            framebuffer_bpp,
+            framebuffer_type,
+        })
+    }
+}
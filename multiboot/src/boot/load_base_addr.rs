@@ -0,0 +1,26 @@
+//! Image load base physical address tag
+
+use std::cursor::Cursor;
+
+use crate::boot::boot_tag::BootTag;
+
+/// Physical address the image was actually loaded at, which only differs from the linked address if relocated
+///
+/// https://www.gnu.org/software/grub/manual/multiboot2/multiboot.html#Image-load-base-physical-address
+#[derive(Debug)]
+pub struct LoadBaseAddr {
+    /// Physical address of the start of the loaded image
+    pub load_base_addr: u32,
+}
+
+impl BootTag for LoadBaseAddr {
+    const TYPE: u32 = 21;
+
+    fn read_from_buffer(buffer: &mut Cursor) -> Option<Self> {
+        let _size = buffer.read_u32()?;
+
+        let load_base_addr = buffer.read_u32()?;
+
+        Some(Self { load_base_addr })
+    }
+}
@@ -1,7 +1,7 @@
 //! Memory map tag
 
 use core::fmt::Formatter;
-use std::cursor::Cursor;
+use std::{assert_eq_size, assert_offset, cursor::Cursor};
 
 use crate::boot::boot_tag::BootTag;
 
@@ -23,7 +23,7 @@ impl MemoryMap {
     pub fn contains_ram_map_at_addr(&self, addr: u64) -> bool {
         self.entries
             .iter()
-            .any(|entry| entry.entry_type == MemoryEntryType::RAM && entry.base_addr == addr)
+            .any(|entry| entry.entry_type() == MemoryEntryType::RAM && entry.base_addr == addr)
     }
 
     /// Whether memory map contains extended memory at 0x00100000
@@ -66,18 +66,29 @@ pub struct MemoryMapEntry {
     pub base_addr: u64,
     /// Size of memory region in bytes
     pub length: u64,
-    /// Type of entry
-    pub entry_type: MemoryEntryType,
+    /// Raw type of entry, see [`MemoryMapEntry::entry_type`]
+    entry_type: u32,
     /// Reserved field, should be 0
     pub _reserved: u32,
 }
 
+assert_eq_size!(MemoryMapEntry, 24);
+assert_offset!(MemoryMapEntry, length, 8);
+assert_offset!(MemoryMapEntry, entry_type, 16);
+
+impl MemoryMapEntry {
+    /// Returns the type of the region, which firmware may report as a value not known to this parser
+    pub const fn entry_type(&self) -> MemoryEntryType {
+        MemoryEntryType::from_u32(self.entry_type)
+    }
+}
+
 impl core::fmt::Display for MemoryMapEntry {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "{}: 0x{:016X}-0x{:016X} (len 0x{:X})",
-            self.entry_type,
+            self.entry_type(),
             self.base_addr,
             self.base_addr + self.length,
             self.length,
@@ -86,12 +97,11 @@ impl core::fmt::Display for MemoryMapEntry {
 }
 
 /// What type the memory region is
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[allow(non_camel_case_types)]
-#[repr(u32)]
 pub enum MemoryEntryType {
     /// Usable ram
-    RAM = 1,
+    RAM,
     /// Reserved by system
     RESERVED,
     /// Usable but containing ACPI data
@@ -100,6 +110,34 @@ pub enum MemoryEntryType {
     PRESERVED_ON_HIBERNATION,
     /// Defective ram modules
     DEFECTIVE,
+    /// Any other type, which should be treated as reserved
+    UNKNOWN(u32),
+}
+
+impl MemoryEntryType {
+    /// Decodes a raw memory map entry type
+    pub const fn from_u32(value: u32) -> Self {
+        match value {
+            1 => Self::RAM,
+            2 => Self::RESERVED,
+            3 => Self::ACPI,
+            4 => Self::PRESERVED_ON_HIBERNATION,
+            5 => Self::DEFECTIVE,
+            value => Self::UNKNOWN(value),
+        }
+    }
+
+    /// Returns the raw value of the type
+    pub const fn as_u32(self) -> u32 {
+        match self {
+            Self::RAM => 1,
+            Self::RESERVED => 2,
+            Self::ACPI => 3,
+            Self::PRESERVED_ON_HIBERNATION => 4,
+            Self::DEFECTIVE => 5,
+            Self::UNKNOWN(value) => value,
+        }
+    }
 }
 
 impl core::fmt::Display for MemoryEntryType {
@@ -115,6 +153,7 @@ impl core::fmt::Display for MemoryEntryType {
                 ACPI =>                     "                    ACPI",
                 PRESERVED_ON_HIBERNATION => "PRESERVED_ON_HIBERNATION",
                 DEFECTIVE =>                "               DEFECTIVE",
+                UNKNOWN(value) => return write!(f, "{:>16}{value:08X}", "UNKNOWN "),
             }
         )
     }
@@ -130,8 +169,9 @@ impl BootTag for MemoryMap {
         let entry_size = buffer.read_u32()?;
         let entry_version = buffer.read_u32()?;
 
+        // entries are reinterpreted in place, so they must be laid out exactly as expected
         if entry_size as usize != size_of::<MemoryMapEntry>() {
-            panic!("entry size does not match expected memory map entry size");
+            return None;
         }
 
         // then read the correct amount of entries
@@ -1,13 +1,16 @@
 //! Provides functionality for reading and processing the returned multiboot2 information
 
-use core::ffi::CStr;
-use std::cursor::Cursor;
+use core::{
+    ffi::CStr,
+    fmt::{Display, Formatter},
+};
+use std::{align_up, cursor::Cursor};
 
 use crate::{
     boot::boot_tag::BootTag,
     prelude::{
-        BasicMemInfo, BiosBootDevice, BootCommandLine, ElfSymbols, MemoryMap, Module, RSDPv1,
-        RSDPv2,
+        BasicMemInfo, BiosBootDevice, BootCommandLine, ElfSymbols, FramebufferInfo, LoadBaseAddr,
+        MemoryMap, Module, RSDPv1, RSDPv2,
     },
 };
 
@@ -15,11 +18,92 @@ pub mod basic_mem_info;
 pub mod bios_boot_device;
 pub mod boot_command_line;
 pub mod boot_tag;
+#[cfg(feature = "builder")]
+pub mod builder;
 pub mod elf_symbols;
+pub mod framebuffer_info;
+pub mod load_base_addr;
 pub mod mem_map;
 pub mod module;
 pub mod rsdp;
 
+/// Size of the total size and reserved fields starting the boot information
+const HEADER_SIZE: usize = 8;
+
+/// Size of the type and size fields starting every tag
+const TAG_HEADER_SIZE: usize = 8;
+
+/// Tags start on 8 byte boundaries
+const TAG_ALIGNMENT: usize = 8;
+
+/// Type of the tag ending the boot information
+const END_TAG_TYPE: u32 = 0;
+
+/// Maximum number of tags read before giving up, in case the end tag is missing
+const MAX_TAGS: usize = 256;
+
+/// An error parsing multiboot2 information
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootInfoError {
+    /// Total size is too small to hold the header and an end tag
+    BadTotalSize(u32),
+    /// Tag at the given offset has a size smaller than its own header
+    TagTooSmall {
+        /// Offset of the tag from the start of the boot information
+        offset: usize,
+        /// Size given by the tag
+        size: u32,
+    },
+    /// Tag at the given offset extends past the total size
+    TagOutOfBounds {
+        /// Offset of the tag from the start of the boot information
+        offset: usize,
+        /// Size given by the tag
+        size: u32,
+    },
+    /// A recognised tag could not be parsed
+    BadTag {
+        /// Type of the tag
+        tag_type: u32,
+        /// Offset of the tag from the start of the boot information
+        offset: usize,
+    },
+    /// Boot information ended without an end tag
+    MissingEndTag,
+    /// More than [`MAX_TAGS`] tags were found without reaching an end tag
+    TooManyTags,
+}
+
+impl Display for BootInfoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadTotalSize(size) => write!(f, "invalid total size {size}"),
+            Self::TagTooSmall { offset, size } => {
+                write!(f, "tag at offset {offset:#X} has invalid size {size}")
+            }
+            Self::TagOutOfBounds { offset, size } => {
+                write!(
+                    f,
+                    "tag at offset {offset:#X} with size {size} extends past end"
+                )
+            }
+            Self::BadTag { tag_type, offset } => {
+                write!(f, "tag of type {tag_type} at offset {offset:#X} is invalid")
+            }
+            Self::MissingEndTag => write!(f, "no end tag"),
+            Self::TooManyTags => write!(f, "more than {MAX_TAGS} tags"),
+        }
+    }
+}
+
+/// Reads a tag from a cursor covering just that tag
+fn read_tag<T: BootTag>(cursor: &mut Cursor, offset: usize) -> Result<T, BootInfoError> {
+    T::read_from_buffer(cursor).ok_or(BootInfoError::BadTag {
+        tag_type: T::TYPE,
+        offset,
+    })
+}
+
 /// Returned multiboot2 information
 ///
 /// https://www.gnu.org/software/grub/manual/multiboot2/multiboot.html#Boot-information-format
@@ -45,71 +129,99 @@ pub struct BootInfo {
     pub modules: [Option<Module>; 8],
     /// Elf symbols of loaded OS image
     pub elf_symbols: Option<ElfSymbols>,
+    /// Physical address the OS image was loaded at
+    pub load_base_addr: Option<LoadBaseAddr>,
+    /// Framebuffer set up by the bootloader
+    pub framebuffer_info: Option<FramebufferInfo>,
 }
 
 impl BootInfo {
-    /// Creates a new bootinfo struct from the given address
+    /// Creates a new bootinfo struct from the given address, checking every tag lies within the total size given
+    /// in its header
     ///
     /// # Safety
     /// This is **very** unsafe and must only ever be called with the address returned by multiboot2
-    pub unsafe fn new(addr: *const u32) -> Option<Self> {
-        let backing_slice = unsafe {
-            let total_size = *addr;
-            core::slice::from_raw_parts_mut(addr as *mut u8, total_size as usize)
-        };
-        let mut cursor = Cursor::from_mut(backing_slice);
+    pub unsafe fn new(addr: *const u32) -> Result<Self, BootInfoError> {
+        let total_size = unsafe { *addr };
+        if (total_size as usize) < HEADER_SIZE + TAG_HEADER_SIZE {
+            return Err(BootInfoError::BadTotalSize(total_size));
+        }
 
-        let size = cursor.read_u32()?;
-        let _reserved = cursor.read_u32()?;
+        let backing_slice =
+            unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, total_size as usize) };
 
         let mut info = BootInfo {
             addr: addr as usize,
-            size: size as usize,
+            size: total_size as usize,
             ..Self::default()
         };
 
-        while let Some(tag) = cursor.read_u32() {
-            match tag {
+        let mut offset = HEADER_SIZE;
+        for _ in 0..MAX_TAGS {
+            // every tag starts with its type and size, which must cover at least those fields
+            let header = backing_slice
+                .get(offset..offset + TAG_HEADER_SIZE)
+                .ok_or(BootInfoError::MissingEndTag)?;
+            let tag_type = u32::from_ne_bytes(header[..4].try_into().unwrap());
+            let size = u32::from_ne_bytes(header[4..].try_into().unwrap());
+
+            if (size as usize) < TAG_HEADER_SIZE {
+                return Err(BootInfoError::TagTooSmall { offset, size });
+            }
+            let end = offset
+                .checked_add(size as usize)
+                .filter(|end| *end <= backing_slice.len())
+                .ok_or(BootInfoError::TagOutOfBounds { offset, size })?;
+
+            if tag_type == END_TAG_TYPE {
+                return Ok(info);
+            }
+
+            // each tag is only given its own bytes, starting at its size field, so it can't read past its end
+            let mut cursor = Cursor::from_mut(&mut backing_slice[offset + 4..end]);
+
+            match tag_type {
                 BasicMemInfo::TYPE => {
-                    info.basic_mem_info = BasicMemInfo::read_from_buffer(&mut cursor);
+                    info.basic_mem_info = Some(read_tag(&mut cursor, offset)?);
                 }
                 BiosBootDevice::TYPE => {
-                    info.bios_boot_device = BiosBootDevice::read_from_buffer(&mut cursor);
+                    info.bios_boot_device = Some(read_tag(&mut cursor, offset)?);
                 }
                 BootCommandLine::TYPE => {
-                    info.boot_command_line = BootCommandLine::read_from_buffer(&mut cursor);
+                    info.boot_command_line = Some(read_tag(&mut cursor, offset)?);
                 }
                 MemoryMap::TYPE => {
-                    info.memory_map = MemoryMap::read_from_buffer(&mut cursor);
+                    info.memory_map = Some(read_tag(&mut cursor, offset)?);
                 }
                 RSDPv1::TYPE => {
-                    info.rsdpv1 = RSDPv1::read_from_buffer(&mut cursor);
+                    info.rsdpv1 = Some(read_tag(&mut cursor, offset)?);
                 }
                 RSDPv2::TYPE => {
-                    info.rsdpv2 = RSDPv2::read_from_buffer(&mut cursor);
+                    info.rsdpv2 = Some(read_tag(&mut cursor, offset)?);
                 }
                 Module::TYPE => {
+                    let module = read_tag(&mut cursor, offset)?;
                     if let Some(slot) = info.modules.iter_mut().find(|slot| slot.is_none()) {
-                        *slot = Module::read_from_buffer(&mut cursor);
+                        *slot = Some(module);
                     }
                 }
                 ElfSymbols::TYPE => {
-                    info.elf_symbols = ElfSymbols::read_from_buffer(&mut cursor);
+                    info.elf_symbols = Some(read_tag(&mut cursor, offset)?);
                 }
-                _ => {
-                    // we don't know this tag, so read another byte for size and skip that many
-                    if let Some(size) = cursor.read_u32() {
-                        cursor.increment_offset(size as usize - 8);
-                    }
+                LoadBaseAddr::TYPE => {
+                    info.load_base_addr = Some(read_tag(&mut cursor, offset)?);
                 }
+                FramebufferInfo::TYPE => {
+                    info.framebuffer_info = Some(read_tag(&mut cursor, offset)?);
+                }
+                // we don't know this tag, so skip it
+                _ => {}
             }
 
-            if cursor.offset() % 8 != 0 {
-                cursor.align_offset(8);
-            }
+            offset = align_up(end, TAG_ALIGNMENT);
         }
 
-        Some(info)
+        Err(BootInfoError::TooManyTags)
     }
 
     /// Attempts to find a module with the given string
@@ -119,4 +231,321 @@ impl BootInfo {
             .filter_map(|module| module.as_ref())
             .find(|module| module.module_str == module_str)
     }
+
+    /// Attempts to find a module with the given name, ignoring any arguments passed after it
+    pub fn module_by_name(&self, name: &str) -> Option<&Module> {
+        self.modules
+            .iter()
+            .filter_map(|module| module.as_ref())
+            .find(|module| module.name() == Some(name))
+    }
+}
+
+#[cfg(all(test, feature = "builder"))]
+mod tests {
+    use super::*;
+    use crate::{boot::builder::BootInfoBuilder, prelude::MemoryEntryType};
+
+    /// Buffer for boot information, aligned like the real thing
+    #[repr(align(8))]
+    struct Buffer([u8; 4096]);
+
+    impl Buffer {
+        /// Constructs a zeroed buffer
+        const fn new() -> Self {
+            Self([0; 4096])
+        }
+
+        /// Builds boot information into the buffer with `build`, then parses it
+        fn parse(
+            &mut self,
+            build: impl FnOnce(&mut BootInfoBuilder),
+        ) -> Result<BootInfo, BootInfoError> {
+            let mut builder = BootInfoBuilder::new(&mut self.0);
+            build(&mut builder);
+            builder
+                .finish()
+                .expect("boot information didn't fit in buffer");
+
+            self.parse_as_is()
+        }
+
+        /// Parses whatever is currently in the buffer
+        fn parse_as_is(&mut self) -> Result<BootInfo, BootInfoError> {
+            // safety: the buffer is aligned and at least as long as any total size the tests give
+            unsafe { BootInfo::new(self.0.as_mut_ptr() as *const u32) }
+        }
+
+        /// Overwrites the total size at the start of the boot information
+        fn set_total_size(&mut self, size: u32) {
+            self.0[..4].copy_from_slice(&size.to_ne_bytes());
+        }
+    }
+
+    #[test]
+    fn parses_every_tag() {
+        let mut buffer = Buffer::new();
+        let info = buffer
+            .parse(|builder| {
+                builder
+                    .basic_mem_info(639, 130_048)
+                    .command_line("log=trace quiet")
+                    .module(0x10_0000, 0x20_0000, "kernel compressed")
+                    .module(0x20_0000, 0x20_1000, "initrd")
+                    .memory_map(&[
+                        (0, 0x9_FC00, MemoryEntryType::RAM),
+                        (0x9_FC00, 0x400, MemoryEntryType::RESERVED),
+                        (0x10_0000, 0x7EE_0000, MemoryEntryType::RAM),
+                    ])
+                    .rsdp_v1(*b"BOCHS ", 0x7FE_2000)
+                    .rsdp_v2(*b"BOCHS ", 0x7FE_2000, 0x7FE_3000)
+                    .load_base_addr(0x20_0000)
+                    // unknown tags are skipped
+                    .raw_tag(0xDEAD, &[&[1, 2, 3]]);
+            })
+            .unwrap();
+
+        assert_eq!(
+            info.size,
+            u32::from_ne_bytes(buffer.0[..4].try_into().unwrap()) as usize
+        );
+
+        let basic = info.basic_mem_info.as_ref().unwrap();
+        assert_eq!((basic.mem_lower, basic.mem_upper), (639, 130_048));
+
+        assert_eq!(
+            info.boot_command_line.as_ref().unwrap().command.to_str(),
+            Ok("log=trace quiet")
+        );
+
+        let kernel = info.module_by_name("kernel").unwrap();
+        assert_eq!(
+            (kernel.module_addr, kernel.module_len),
+            (0x10_0000, 0x10_0000)
+        );
+        assert_eq!(kernel.arguments(), "compressed");
+        assert!(info.module_by_name("initrd").is_some());
+        assert!(info.module_by_name("missing").is_none());
+
+        let memory_map = info.memory_map.as_ref().unwrap();
+        assert_eq!(memory_map.entries.len(), 3);
+        assert_eq!(memory_map.entries[1].base_addr, 0x9_FC00);
+        assert_eq!(
+            memory_map.entries[1].entry_type(),
+            MemoryEntryType::RESERVED
+        );
+
+        let rsdpv1 = info.rsdpv1.as_ref().unwrap();
+        assert_eq!((rsdpv1.oem_id, rsdpv1.rsdt_addr), ("BOCHS ", 0x7FE_2000));
+        assert_eq!(info.rsdpv2.as_ref().unwrap().xsdt_addr, 0x7FE_3000);
+        assert_eq!(
+            info.load_base_addr.as_ref().unwrap().load_base_addr,
+            0x20_0000
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let mut buffer = Buffer::new();
+        let info = buffer.parse(|_| {}).unwrap();
+
+        assert_eq!(info.size, 16);
+        assert!(info.memory_map.is_none() && info.modules.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn bad_total_size() {
+        let mut buffer = Buffer::new();
+        buffer.set_total_size(8);
+
+        assert_eq!(
+            buffer.parse_as_is().unwrap_err(),
+            BootInfoError::BadTotalSize(8)
+        );
+    }
+
+    #[test]
+    fn tag_too_small() {
+        let mut buffer = Buffer::new();
+        let result = buffer.parse(|builder| {
+            builder.raw_tag_with_size(0xDEAD, 4, &[]);
+        });
+
+        assert_eq!(
+            result.unwrap_err(),
+            BootInfoError::TagTooSmall { offset: 8, size: 4 }
+        );
+    }
+
+    #[test]
+    fn tag_out_of_bounds() {
+        let mut buffer = Buffer::new();
+        // claims to run past the end tag and total size
+        let result = buffer.parse(|builder| {
+            builder
+                .basic_mem_info(639, 1024)
+                .raw_tag_with_size(0xDEAD, 64, &[&[0; 8]]);
+        });
+
+        assert_eq!(
+            result.unwrap_err(),
+            BootInfoError::TagOutOfBounds {
+                offset: 24,
+                size: 64
+            }
+        );
+    }
+
+    #[test]
+    fn tag_size_overflowing() {
+        let mut buffer = Buffer::new();
+        let result = buffer.parse(|builder| {
+            builder.raw_tag_with_size(0xDEAD, u32::MAX, &[]);
+        });
+
+        assert_eq!(
+            result.unwrap_err(),
+            BootInfoError::TagOutOfBounds {
+                offset: 8,
+                size: u32::MAX
+            }
+        );
+    }
+
+    #[test]
+    fn missing_end_tag() {
+        let mut buffer = Buffer::new();
+        buffer
+            .parse(|builder| {
+                builder.load_base_addr(0);
+            })
+            .unwrap();
+
+        // cut the end tag off
+        buffer.set_total_size(24);
+        assert_eq!(
+            buffer.parse_as_is().unwrap_err(),
+            BootInfoError::MissingEndTag
+        );
+    }
+
+    #[test]
+    fn bad_tags() {
+        let mut buffer = Buffer::new();
+
+        // a recognised tag which fails to parse is an error, rather than being dropped
+        let result = buffer.parse(|builder| {
+            builder
+                .command_line("quiet")
+                .raw_tag(RSDPv1::TYPE, &[&[0; 20]]);
+        });
+        assert_eq!(
+            result.unwrap_err(),
+            BootInfoError::BadTag {
+                tag_type: RSDPv1::TYPE,
+                offset: 24
+            }
+        );
+
+        // too short to hold the fields it should have
+        let result = buffer.parse(|builder| {
+            builder.raw_tag(BasicMemInfo::TYPE, &[&[0; 4]]);
+        });
+        assert_eq!(
+            result.unwrap_err(),
+            BootInfoError::BadTag {
+                tag_type: BasicMemInfo::TYPE,
+                offset: 8
+            }
+        );
+
+        // memory map entries which don't match the expected layout
+        let result = buffer.parse(|builder| {
+            builder.raw_tag(
+                MemoryMap::TYPE,
+                &[&16u32.to_ne_bytes(), &0u32.to_ne_bytes(), &[0; 16]],
+            );
+        });
+        assert_eq!(
+            result.unwrap_err(),
+            BootInfoError::BadTag {
+                tag_type: MemoryMap::TYPE,
+                offset: 8
+            }
+        );
+    }
+
+    #[test]
+    fn bad_strings() {
+        let mut buffer = Buffer::new();
+
+        // strings must be terminated within their tag
+        let result = buffer.parse(|builder| {
+            builder.raw_tag(BootCommandLine::TYPE, &[b"quiet"]);
+        });
+        assert_eq!(
+            result.unwrap_err(),
+            BootInfoError::BadTag {
+                tag_type: BootCommandLine::TYPE,
+                offset: 8
+            }
+        );
+
+        let result = buffer.parse(|builder| {
+            builder.raw_tag(
+                Module::TYPE,
+                &[
+                    &0x1000u32.to_ne_bytes(),
+                    &0x2000u32.to_ne_bytes(),
+                    b"kernel",
+                ],
+            );
+        });
+        assert_eq!(
+            result.unwrap_err(),
+            BootInfoError::BadTag {
+                tag_type: Module::TYPE,
+                offset: 8
+            }
+        );
+
+        // padding after the terminator is fine
+        let info = buffer
+            .parse(|builder| {
+                builder.raw_tag(BootCommandLine::TYPE, &[b"quiet\0\0\0"]);
+            })
+            .unwrap();
+        assert_eq!(
+            info.boot_command_line.as_ref().unwrap().command.to_str(),
+            Ok("quiet")
+        );
+    }
+
+    #[test]
+    fn module_ending_before_start() {
+        let mut buffer = Buffer::new();
+        let result = buffer.parse(|builder| {
+            builder.module(0x2000, 0x1000, "kernel");
+        });
+
+        assert_eq!(
+            result.unwrap_err(),
+            BootInfoError::BadTag {
+                tag_type: Module::TYPE,
+                offset: 8
+            }
+        );
+    }
+
+    #[test]
+    fn too_many_tags() {
+        let mut buffer = Buffer::new();
+        let result = buffer.parse(|builder| {
+            for _ in 0..MAX_TAGS {
+                builder.raw_tag(0xDEAD, &[]);
+            }
+        });
+
+        assert_eq!(result.unwrap_err(), BootInfoError::TooManyTags);
+    }
 }
@@ -18,6 +18,27 @@ pub struct Module {
     pub module_str: &'static CStr,
 }
 
+impl Module {
+    /// Returns the module string as UTF-8, if valid
+    pub fn as_str(&self) -> Option<&'static str> {
+        self.module_str.to_str().ok()
+    }
+
+    /// Returns the module name, which is the first whitespace-separated word of the module string
+    pub fn name(&self) -> Option<&'static str> {
+        self.as_str()?.split_whitespace().next()
+    }
+
+    /// Returns the arguments passed after the module name, or an empty string if there are none
+    pub fn arguments(&self) -> &'static str {
+        self.as_str()
+            .map(str::trim_start)
+            .and_then(|module_str| module_str.split_once(char::is_whitespace))
+            .map(|(_, arguments)| arguments.trim())
+            .unwrap_or_default()
+    }
+}
+
 impl BootTag for Module {
     const TYPE: u32 = 3;
 
@@ -26,11 +47,14 @@ impl BootTag for Module {
 
         let module_addr = buffer.read_u32()?;
         let module_end = buffer.read_u32()?;
-        let module_len = module_end - module_addr;
+        let module_len = module_end.checked_sub(module_addr)?;
 
         let str_len = size - 16;
 
-        let module_str = unsafe { buffer.read_cstr(str_len as usize)? };
+        // safety: the tag was bounds checked against the boot information, so the string is in bounds.
+        // it may still be missing its terminator, which from_bytes_until_nul rejects
+        let module_str = unsafe { buffer.read_slice(str_len as usize)? };
+        let module_str = CStr::from_bytes_until_nul(module_str).ok()?;
 
         Some(Self {
             module_addr,
@@ -27,11 +27,16 @@ impl BootTag for RSDPv1 {
     }
 }
 
+/// Length of an RSDPv2, including the RSDPv1 fields
+const RSDPV2_LENGTH: usize = 36;
+
 /// Copy of RSDPv2 as defined per ACPI 2.0 or later
 ///
 /// https://www.gnu.org/software/grub/manual/multiboot2/multiboot.html#ACPI-new-RSDP
 #[derive(Debug)]
 pub struct RSDPv2 {
+    /// Physical address of RSDT table, for software which doesn't understand the XSDT
+    pub rsdt_addr: u32,
     /// Address of XSDT data structure
     pub xsdt_addr: u64,
 }
@@ -40,38 +45,37 @@ impl BootTag for RSDPv2 {
     const TYPE: u32 = 15;
 
     fn read_from_buffer(buffer: &mut Cursor) -> Option<Self> {
-        let _size = buffer.read_u32()?;
+        let size = buffer.read_u32()?;
 
-        // only read to check checksum
-        let _v1_tag = read_rsdpv1(buffer)?;
+        let v1_tag = read_rsdpv1(buffer)?;
 
         let length = buffer.read_u32()?;
         let xsdt_addr = buffer.read_u64()?;
-        let mut checksum = buffer.read_u8()?;
+        let checksum = buffer.read_u8()?;
+        let reserved = [buffer.read_u8()?, buffer.read_u8()?, buffer.read_u8()?];
 
-        let _reserved = [buffer.read_u8()?, buffer.read_u8()?, buffer.read_u8()?];
-
-        // checksum _should_ be calculated using all fields, including v1 fields
-        // however, we check the v1 checksum in `read_rsdpv1` so we already know all v1 bytes sum to 0
-        // therefore we can ignore those fields and just compute checksum again for v2, checking that all
-        // bytes sum to 0
+        // length covers the whole structure, which must fit within the tag
+        if (length as usize) < RSDPV2_LENGTH || length > size.checked_sub(8)? {
+            return None;
+        }
 
-        // checksum + all other bytes should be 0 if valid
-        for byte in length
+        // the extended checksum covers every byte, but we check the v1 checksum in `read_rsdpv1` so we already know
+        // all v1 bytes sum to 0. therefore only the v2 fields need summing, which should also come to 0
+        let sum = length
             .to_ne_bytes()
             .iter()
             .chain(xsdt_addr.to_ne_bytes().iter())
-        {
-            checksum += byte;
-        }
+            .chain(reserved.iter())
+            .fold(checksum, |sum, byte| sum.wrapping_add(*byte));
 
-        if checksum != 0 {
+        if sum != 0 {
             return None;
         }
 
-        // TODO: emulate acpi 2.0+ properly so this can be tested
-
-        Some(Self { xsdt_addr })
+        Some(Self {
+            rsdt_addr: v1_tag.rsdt_addr,
+            xsdt_addr,
+        })
     }
 }
 
@@ -79,7 +83,7 @@ impl BootTag for RSDPv2 {
 fn read_rsdpv1(buffer: &mut Cursor) -> Option<RSDPv1> {
     let signature = unsafe { buffer.read_slice(8)? };
     if signature != b"RSD PTR " {
-        panic!("incorrect signature!");
+        return None;
     }
 
     let mut checksum = buffer.read_u8()?;
@@ -87,23 +91,145 @@ fn read_rsdpv1(buffer: &mut Cursor) -> Option<RSDPv1> {
     let revision = buffer.read_u8()?;
     let rsdt_addr = buffer.read_u32()?;
 
-    // checksum + all other bytes should be 0 if valid
+    // checksum + all other bytes should be 0 if valid, wrapping as a byte
     for byte in signature
         .iter()
         .chain(oemid)
         .chain(rsdt_addr.to_ne_bytes().iter())
     {
-        checksum += byte;
+        checksum = checksum.wrapping_add(*byte);
     }
-    checksum += revision;
+    checksum = checksum.wrapping_add(revision);
 
     if checksum != 0 {
         return None;
     }
 
     Some(RSDPv1 {
-        oem_id: unsafe { core::str::from_utf8_unchecked(oemid) },
+        oem_id: core::str::from_utf8(oemid).ok()?,
         revision,
         rsdt_addr,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Physical address of the RSDT in test tags
+    const RSDT_ADDR: u32 = 0x1234_5678;
+    /// Physical address of the XSDT in test tags
+    const XSDT_ADDR: u64 = 0xDEAD_BEEF_0000;
+
+    /// Sets `bytes[index]` so that all of `bytes` sum to 0
+    fn fix_checksum(bytes: &mut [u8], index: usize) {
+        bytes[index] = 0;
+        let sum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        bytes[index] = sum.wrapping_neg();
+    }
+
+    /// Builds a valid RSDPv1 tag, starting from the size field
+    fn v1_tag() -> [u8; 24] {
+        let mut tag = [0; 24];
+        tag[0..4].copy_from_slice(&28u32.to_ne_bytes());
+        tag[4..12].copy_from_slice(b"RSD PTR ");
+        tag[13..19].copy_from_slice(b"BOCHS ");
+        tag[19] = 0;
+        tag[20..24].copy_from_slice(&RSDT_ADDR.to_ne_bytes());
+        fix_checksum(&mut tag[4..24], 8);
+
+        tag
+    }
+
+    /// Builds a valid RSDPv2 tag with the given length field, starting from the size field
+    fn v2_tag(length: u32) -> [u8; 40] {
+        let mut tag = [0; 40];
+        tag[0..24].copy_from_slice(&v1_tag());
+        tag[0..4].copy_from_slice(&44u32.to_ne_bytes());
+        tag[19] = 2;
+        fix_checksum(&mut tag[4..24], 8);
+
+        tag[24..28].copy_from_slice(&length.to_ne_bytes());
+        tag[28..36].copy_from_slice(&XSDT_ADDR.to_ne_bytes());
+        fix_checksum(&mut tag[4..40], 32);
+
+        tag
+    }
+
+    #[test]
+    fn valid_v1() {
+        let mut tag = v1_tag();
+        let rsdp = RSDPv1::read_from_buffer(&mut Cursor::from_mut(&mut tag)).unwrap();
+
+        assert_eq!(rsdp.oem_id, "BOCHS ");
+        assert_eq!(rsdp.revision, 0);
+        assert_eq!(rsdp.rsdt_addr, RSDT_ADDR);
+    }
+
+    #[test]
+    fn valid_v2() {
+        let mut tag = v2_tag(RSDPV2_LENGTH as u32);
+        let rsdp = RSDPv2::read_from_buffer(&mut Cursor::from_mut(&mut tag)).unwrap();
+
+        assert_eq!(rsdp.rsdt_addr, RSDT_ADDR);
+        assert_eq!(rsdp.xsdt_addr, XSDT_ADDR);
+    }
+
+    #[test]
+    fn bad_checksum() {
+        let mut tag = v1_tag();
+        tag[12] = tag[12].wrapping_add(1);
+        assert!(RSDPv1::read_from_buffer(&mut Cursor::from_mut(&mut tag)).is_none());
+
+        // a v2 tag is rejected if its v1 fields are bad, even though the extended checksum covers them
+        let mut tag = v2_tag(RSDPV2_LENGTH as u32);
+        tag[12] = tag[12].wrapping_add(1);
+        tag[36] = tag[36].wrapping_sub(1);
+        assert!(RSDPv2::read_from_buffer(&mut Cursor::from_mut(&mut tag)).is_none());
+    }
+
+    #[test]
+    fn bad_signature() {
+        let mut tag = v1_tag();
+        tag[4] = b'X';
+        fix_checksum(&mut tag[4..24], 8);
+
+        assert!(RSDPv1::read_from_buffer(&mut Cursor::from_mut(&mut tag)).is_none());
+    }
+
+    #[test]
+    fn bad_extended_checksum() {
+        let mut tag = v2_tag(RSDPV2_LENGTH as u32);
+        tag[36] = tag[36].wrapping_add(1);
+
+        assert!(RSDPv2::read_from_buffer(&mut Cursor::from_mut(&mut tag)).is_none());
+    }
+
+    #[test]
+    fn bad_oem_id() {
+        let mut tag = v1_tag();
+        tag[13] = 0xFF;
+        fix_checksum(&mut tag[4..24], 8);
+
+        assert!(RSDPv1::read_from_buffer(&mut Cursor::from_mut(&mut tag)).is_none());
+    }
+
+    #[test]
+    fn v1_length() {
+        // a v2 structure claiming to be only as long as a v1 structure
+        let mut tag = v2_tag(20);
+        assert!(RSDPv2::read_from_buffer(&mut Cursor::from_mut(&mut tag)).is_none());
+    }
+
+    #[test]
+    fn length_past_tag() {
+        let mut tag = v2_tag(RSDPV2_LENGTH as u32 + 1);
+        assert!(RSDPv2::read_from_buffer(&mut Cursor::from_mut(&mut tag)).is_none());
+    }
+
+    #[test]
+    fn v1_tag_read_as_v2() {
+        let mut tag = v1_tag();
+        assert!(RSDPv2::read_from_buffer(&mut Cursor::from_mut(&mut tag)).is_none());
+    }
+}
@@ -3,7 +3,7 @@
 pub use crate::{
     boot::{
         basic_mem_info::*, bios_boot_device::*, boot_command_line::*, boot_tag::*, elf_symbols::*,
-        mem_map::*, module::*, rsdp::*, *,
+        framebuffer_info::*, load_base_addr::*, mem_map::*, module::*, rsdp::*, *,
     },
     header::{
         address::*, console_flags::*, dummy::*, efi_boot_services::*, entry_address::*, flags::*,
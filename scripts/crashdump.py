@@ -0,0 +1,180 @@
+#!/usr/bin/env python3
+"""Pretty-prints kernel crash dumps found in a serial log.
+
+Usage: crashdump.py [LOG] [--kernel PATH]
+
+Reads LOG (or stdin) and prints every crash dump in it. If the kernel debug file is given, backtrace and
+single step addresses are resolved with addr2line.
+
+The build splits debug info out of the kernel that gets booted, so pass target/rustyos.debug, written
+alongside the kernel by the same `make`. It has to come from the same build as the crashed kernel.
+The booted target/isofiles/boot/rustyos also works when built without COMPRESS_KERNEL, but only
+resolves function names, not lines.
+"""
+
+import argparse
+import json
+import shutil
+import subprocess
+import sys
+
+BEGIN_MARKER = "@@CRASHDUMP-BEGIN"
+END_MARKER = "@@CRASHDUMP-END"
+SUPPORTED_VERSION = "2"
+FRAME_SIZE = 4096
+
+
+def parse_dumps(lines):
+    """Yields a dict for each dump in the given lines."""
+    dump = None
+
+    for line in lines:
+        line = line.strip("\r\n")
+
+        if line.startswith(BEGIN_MARKER):
+            version = line[len(BEGIN_MARKER):].strip()
+            if version != SUPPORTED_VERSION:
+                print(f"warning: dump format version {version!r} is not supported", file=sys.stderr)
+            dump = {"msg": None, "cmdline": None, "reg": [], "bt": [], "mem": None, "step": [], "lock": [], "log": []}
+            continue
+
+        # the kernel ends lines with \n\r, which can read back as an extra empty line
+        if dump is None or not line:
+            continue
+
+        if line.startswith(END_MARKER):
+            yield dump
+            dump = None
+            continue
+
+        try:
+            record = json.loads(line)
+        except json.JSONDecodeError:
+            print(f"warning: skipping malformed record {line!r}", file=sys.stderr)
+            continue
+
+        kind = record.get("kind")
+        if kind == "msg":
+            location = record["location"]
+            dump["msg"] = record["message"] + (f" at {location}" if location is not None else "")
+        elif kind == "cmdline":
+            dump["cmdline"] = record["command_line"]
+        elif kind == "reg":
+            dump["reg"].append((record["name"], int(record["value"], 16)))
+        elif kind == "bt":
+            dump["bt"].append(int(record["address"], 16))
+        elif kind == "mem":
+            dump["mem"] = (record["used"], record["total"])
+        elif kind == "step":
+            dump["step"].append(int(record["address"], 16))
+        elif kind == "lock":
+            dump["lock"].append((
+                record["site"],
+                record["acquisitions"],
+                record["contended"],
+                record["spins"],
+                record["max_hold_cycles"],
+            ))
+        elif kind == "log":
+            dump["log"].append(record["line"])
+
+    if dump is not None:
+        print("warning: log ends part way through a dump", file=sys.stderr)
+        yield dump
+
+
+def symbolise(kernel, addresses, return_addresses=True):
+    """Returns a description of each address, using addr2line if a kernel binary is available."""
+    if kernel is None or not addresses:
+        return ["" for _ in addresses]
+
+    if shutil.which("addr2line") is None:
+        print("warning: addr2line not found, not resolving addresses", file=sys.stderr)
+        return ["" for _ in addresses]
+
+    # return addresses point after the call, so look up the byte before to get the calling line
+    adjust = 1 if return_addresses else 0
+    output = subprocess.run(
+        ["addr2line", "-f", "-C", "-i", "-a", "-e", kernel] + [hex(a - adjust) for a in addresses],
+        capture_output=True,
+        text=True,
+        check=True,
+    ).stdout.splitlines()
+
+    # output is an address line followed by (function, location) pairs, more than one if inlined
+    symbols = []
+    for line in output:
+        if line.startswith("0x"):
+            symbols.append([])
+        else:
+            symbols[-1].append(line)
+
+    return [
+        " <- ".join(f"{func} ({loc})" for func, loc in zip(pairs[::2], pairs[1::2]))
+        for pairs in symbols
+    ]
+
+
+def print_dump(index, dump, kernel):
+    print(f"=== crash dump {index} ===")
+    print(f"panic: {dump['msg']}")
+    if dump["cmdline"] is not None:
+        print(f"command line: {dump['cmdline']}")
+
+    print("\nregisters:")
+    regs = dump["reg"]
+    for i in range(0, len(regs), 3):
+        print("  " + "  ".join(f"{name:>6} {value:016x}" for name, value in regs[i:i + 3]))
+
+    print("\nbacktrace:")
+    if not dump["bt"]:
+        print("  <empty>")
+    for depth, (address, symbol) in enumerate(zip(dump["bt"], symbolise(kernel, dump["bt"]))):
+        print(f"  {depth:>3}: {address:016x} {symbol}")
+
+    if dump["mem"] is not None:
+        used, total = dump["mem"]
+        print(f"\nmemory: {used}/{total} frames in use "
+              f"({used * FRAME_SIZE // 1024} KiB of {total * FRAME_SIZE // 1024} KiB)")
+    else:
+        print("\nmemory: <not initialised>")
+
+    if dump["step"]:
+        print(f"\nlast {len(dump['step'])} single steps, oldest first:")
+        for address, symbol in zip(dump["step"], symbolise(kernel, dump["step"], return_addresses=False)):
+            print(f"  {address:016x} {symbol}")
+
+    if dump["lock"]:
+        print("\nlock sites, most contended first:")
+        print(f"  {'acquired':>10} {'contended':>10} {'spins':>12} {'max hold':>12}  site")
+        for site, acquisitions, contended, spins, max_hold in sorted(
+            dump["lock"], key=lambda lock: lock[3], reverse=True
+        ):
+            print(f"  {acquisitions:>10} {contended:>10} {spins:>12} {max_hold:>12}  {site}")
+
+    print(f"\nlast {len(dump['log'])} log lines:")
+    for line in dump["log"]:
+        print(f"  {line}")
+    print()
+
+
+def main():
+    parser = argparse.ArgumentParser(description="Pretty-print kernel crash dumps from a serial log")
+    parser.add_argument("log", nargs="?", type=argparse.FileType("r", errors="replace"), default=sys.stdin)
+    parser.add_argument(
+        "--kernel",
+        help="kernel debug file (target/rustyos.debug) to resolve backtrace addresses against",
+    )
+    args = parser.parse_args()
+
+    count = 0
+    for count, dump in enumerate(parse_dumps(args.log), start=1):
+        print_dump(count, dump, args.kernel)
+
+    if count == 0:
+        print("no crash dumps found", file=sys.stderr)
+        sys.exit(1)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,328 @@
+//! Bit-level bitmap over a slice of words
+
+use core::ops::Range;
+
+/// Bitmap backed by a slice of `usize`, where bit `i` is stored in word `i / usize::BITS`
+#[repr(transparent)]
+pub struct Bitmap {
+    /// Backing words
+    words: [usize],
+}
+
+impl Bitmap {
+    /// Number of bits in each word
+    const WORD_BITS: usize = usize::BITS as usize;
+
+    /// Views a slice of words as a bitmap
+    pub fn from_slice(words: &[usize]) -> &Self {
+        // safety: Bitmap is a transparent wrapper around [usize]
+        unsafe { &*(words as *const [usize] as *const Self) }
+    }
+
+    /// Views a mutable slice of words as a bitmap
+    pub fn from_slice_mut(words: &mut [usize]) -> &mut Self {
+        // safety: Bitmap is a transparent wrapper around [usize]
+        unsafe { &mut *(words as *mut [usize] as *mut Self) }
+    }
+
+    /// Returns the backing words
+    pub fn as_slice(&self) -> &[usize] {
+        &self.words
+    }
+
+    /// Returns the number of bits
+    pub fn len(&self) -> usize {
+        self.words.len() * Self::WORD_BITS
+    }
+
+    /// Returns true if there are no bits
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Returns the value of the bit at `index`.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn get(&self, index: usize) -> bool {
+        self.words[index / Self::WORD_BITS] & (1 << (index % Self::WORD_BITS)) != 0
+    }
+
+    /// Sets the bit at `index` to 1.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn set(&mut self, index: usize) {
+        self.words[index / Self::WORD_BITS] |= 1 << (index % Self::WORD_BITS);
+    }
+
+    /// Sets the bit at `index` to 0.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn clear(&mut self, index: usize) {
+        self.words[index / Self::WORD_BITS] &= !(1 << (index % Self::WORD_BITS));
+    }
+
+    /// Sets every bit within `range` to 1.
+    ///
+    /// Panics if `range` is out of range.
+    pub fn set_range(&mut self, range: Range<usize>) {
+        self.update_range(range, true);
+    }
+
+    /// Sets every bit within `range` to 0.
+    ///
+    /// Panics if `range` is out of range.
+    pub fn clear_range(&mut self, range: Range<usize>) {
+        self.update_range(range, false);
+    }
+
+    /// Sets every bit to 0
+    pub fn clear_all(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Finds the index of the first bit set to 0, returning None if all are set
+    pub fn find_first_zero(&self) -> Option<usize> {
+        self.words
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != !0)
+            .map(|(i, word)| i * Self::WORD_BITS + word.trailing_ones() as usize)
+    }
+
+    /// Finds the index of the first bit set to 1, returning None if none are set
+    pub fn find_first_one(&self) -> Option<usize> {
+        self.words
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != 0)
+            .map(|(i, word)| i * Self::WORD_BITS + word.trailing_zeros() as usize)
+    }
+
+    /// Finds the index of the first run of `count` consecutive bits set to 0, returning None if there isn't one
+    pub fn find_zero_run(&self, count: usize) -> Option<usize> {
+        let (mut start, mut length) = (0, 0);
+
+        let mut index = 0;
+        while index < self.len() && length < count {
+            let word = self.words[index / Self::WORD_BITS];
+
+            // skip whole words at once where possible, since most are either full or empty
+            let (zero, step) = if index % Self::WORD_BITS == 0 && (word == 0 || word == !0) {
+                (word == 0, Self::WORD_BITS)
+            } else {
+                (!self.get(index), 1)
+            };
+
+            if !zero {
+                length = 0;
+            } else {
+                if length == 0 {
+                    start = index;
+                }
+                length += step;
+            }
+
+            index += step;
+        }
+
+        (length >= count).then_some(start)
+    }
+
+    /// Returns the number of bits set to 1
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Sets every bit in `range` to `value`, a word at a time
+    fn update_range(&mut self, range: Range<usize>, value: bool) {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "bitmap range out of bounds"
+        );
+
+        let mut index = range.start;
+        while index < range.end {
+            let bit = index % Self::WORD_BITS;
+            let count = (Self::WORD_BITS - bit).min(range.end - index);
+
+            let mask = if count == Self::WORD_BITS {
+                !0
+            } else {
+                ((1 << count) - 1) << bit
+            };
+
+            let word = &mut self.words[index / Self::WORD_BITS];
+            if value {
+                *word |= mask;
+            } else {
+                *word &= !mask;
+            }
+
+            index += count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bitmap;
+
+    /// Number of bits in each word
+    const BITS: usize = usize::BITS as usize;
+
+    /// Small xorshift generator, so the randomised tests are repeatable
+    struct Rng(u64);
+
+    impl Rng {
+        /// Returns the next number in `0..bound`
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    /// Finds the first run of `count` zeroes by checking every start position
+    fn brute_force_zero_run(bits: &[bool], count: usize) -> Option<usize> {
+        (0..=bits.len().checked_sub(count)?)
+            .find(|&start| bits[start..start + count].iter().all(|bit| !bit))
+    }
+
+    #[test]
+    fn set_and_clear() {
+        let mut words = [0; 2];
+        let bitmap = Bitmap::from_slice_mut(&mut words);
+        assert_eq!(bitmap.len(), 2 * BITS);
+
+        bitmap.set(0);
+        bitmap.set(BITS - 1);
+        bitmap.set(BITS);
+        assert!(bitmap.get(0) && bitmap.get(BITS - 1) && bitmap.get(BITS));
+        assert!(!bitmap.get(1));
+        assert_eq!(bitmap.count_ones(), 3);
+
+        bitmap.clear(BITS - 1);
+        assert!(!bitmap.get(BITS - 1));
+        assert_eq!(words, [1, 1]);
+    }
+
+    #[test]
+    fn ranges_across_words() {
+        let mut words = [0; 3];
+        let bitmap = Bitmap::from_slice_mut(&mut words);
+
+        bitmap.set_range(BITS - 4..2 * BITS + 4);
+        assert_eq!(bitmap.as_slice(), [0xf << (BITS - 4), !0, 0xf]);
+
+        bitmap.clear_range(BITS - 2..BITS + 2);
+        assert_eq!(bitmap.as_slice(), [0x3 << (BITS - 4), !0 << 2, 0xf]);
+
+        bitmap.set_range(0..3 * BITS);
+        assert_eq!(bitmap.as_slice(), [!0; 3]);
+
+        bitmap.clear_range(BITS..2 * BITS);
+        assert_eq!(bitmap.as_slice(), [!0, 0, !0]);
+
+        // empty ranges change nothing, even at the very end
+        bitmap.set_range(BITS + 5..BITS + 5);
+        bitmap.clear_range(3 * BITS..3 * BITS);
+        assert_eq!(bitmap.as_slice(), [!0, 0, !0]);
+    }
+
+    #[test]
+    #[should_panic = "bitmap range out of bounds"]
+    fn range_past_end() {
+        let mut words = [0; 1];
+        Bitmap::from_slice_mut(&mut words).set_range(1..BITS + 1);
+    }
+
+    #[test]
+    fn find_first() {
+        let mut words = [!0, !0, 0];
+        let bitmap = Bitmap::from_slice_mut(&mut words);
+        assert_eq!(bitmap.find_first_zero(), Some(2 * BITS));
+        assert_eq!(bitmap.find_first_one(), Some(0));
+
+        bitmap.clear(BITS + 7);
+        assert_eq!(bitmap.find_first_zero(), Some(BITS + 7));
+
+        bitmap.clear_range(0..2 * BITS);
+        bitmap.set(2 * BITS + 3);
+        assert_eq!(bitmap.find_first_one(), Some(2 * BITS + 3));
+
+        bitmap.set_range(0..3 * BITS);
+        assert_eq!(bitmap.find_first_zero(), None);
+
+        bitmap.clear_all();
+        assert_eq!(bitmap.find_first_one(), None);
+        assert!(Bitmap::from_slice(&[]).find_first_zero().is_none());
+    }
+
+    #[test]
+    fn find_zero_run() {
+        let mut words = [!0; 3];
+        let bitmap = Bitmap::from_slice_mut(&mut words);
+        assert_eq!(bitmap.find_zero_run(1), None);
+        assert_eq!(bitmap.find_zero_run(0), Some(0));
+
+        // a run straddling a word boundary
+        bitmap.clear_range(BITS - 3..BITS + 5);
+        assert_eq!(bitmap.find_zero_run(8), Some(BITS - 3));
+        assert_eq!(bitmap.find_zero_run(9), None);
+
+        // a longer run later on, made of a whole word and its neighbours
+        bitmap.clear_range(2 * BITS - 1..3 * BITS);
+        assert_eq!(bitmap.find_zero_run(9), Some(2 * BITS - 1));
+        assert_eq!(bitmap.find_zero_run(BITS + 1), Some(2 * BITS - 1));
+        assert_eq!(bitmap.find_zero_run(BITS + 2), None);
+
+        bitmap.clear_all();
+        assert_eq!(bitmap.find_zero_run(3 * BITS), Some(0));
+        assert_eq!(bitmap.find_zero_run(3 * BITS + 1), None);
+    }
+
+    #[test]
+    fn find_zero_run_matches_brute_force() {
+        const WORDS: usize = 4;
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..if cfg!(miri) { 20 } else { 500 } {
+            let mut words = [0; WORDS];
+            let mut bits = [false; WORDS * BITS];
+            let bitmap = Bitmap::from_slice_mut(&mut words);
+
+            // build up runs of both values by applying random ranges, mirroring them into a plain array
+            for _ in 0..rng.below(16) {
+                let start = rng.below(bits.len());
+                let end = start + rng.below(bits.len() - start + 1);
+                let value = rng.below(3) != 0;
+
+                if value {
+                    bitmap.set_range(start..end);
+                } else {
+                    bitmap.clear_range(start..end);
+                }
+                bits[start..end].fill(value);
+            }
+
+            for (index, &bit) in bits.iter().enumerate() {
+                assert_eq!(bitmap.get(index), bit);
+            }
+            assert_eq!(bitmap.count_ones(), bits.iter().filter(|bit| **bit).count());
+            assert_eq!(bitmap.find_first_zero(), bits.iter().position(|bit| !bit));
+
+            for count in [1, 2, 3, 7, BITS - 1, BITS, BITS + 1, 2 * BITS + 3] {
+                assert_eq!(
+                    bitmap.find_zero_run(count),
+                    brute_force_zero_run(&bits, count),
+                    "run of {count} in {:x?}",
+                    bitmap.as_slice()
+                );
+            }
+        }
+    }
+}
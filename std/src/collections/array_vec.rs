@@ -0,0 +1,352 @@
+//! Vector with a fixed capacity, stored inline
+
+use core::{
+    fmt::{Debug, Formatter},
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+};
+
+/// Vector which stores up to `N` elements inline, without allocating
+pub struct ArrayVec<T, const N: usize> {
+    /// Backing storage, where only the first `len` elements are initialised
+    data: [MaybeUninit<T>; N],
+    /// Number of initialised elements
+    len: usize,
+}
+
+impl<T, const N: usize> ArrayVec<T, N> {
+    /// Constructs an empty vector
+    pub const fn new() -> Self {
+        Self {
+            data: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+
+    /// Returns the number of elements
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if there are no elements
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if no more elements can be added
+    pub const fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Returns the maximum number of elements
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Appends an element, returning it back if the vector is full
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+
+        self.data[self.len].write(value);
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// Removes and returns the last element
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        self.len -= 1;
+
+        // safety: element was initialised, and is no longer counted in `len` so won't be read again
+        Some(unsafe { self.data[self.len].assume_init_read() })
+    }
+
+    /// Inserts an element at `index`, shifting later elements along. Returns the element back if the vector is full.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) -> Result<(), T> {
+        assert!(index <= self.len, "insertion index out of bounds");
+
+        if self.is_full() {
+            return Err(value);
+        }
+
+        unsafe {
+            let ptr = self.data.as_mut_ptr().add(index);
+            core::ptr::copy(ptr, ptr.add(1), self.len - index);
+            (*ptr).write(value);
+        }
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// Removes and returns the element at `index`, shifting later elements back.
+    ///
+    /// Panics if `index >= len`.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index out of bounds");
+
+        unsafe {
+            let ptr = self.data.as_mut_ptr().add(index);
+            let value = (*ptr).assume_init_read();
+            core::ptr::copy(ptr.add(1), ptr, self.len - index - 1);
+            self.len -= 1;
+
+            value
+        }
+    }
+
+    /// Removes and returns the element at `index`, replacing it with the last element.
+    ///
+    /// Panics if `index >= len`.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index out of bounds");
+
+        let last = self.len - 1;
+        self.as_mut_slice().swap(index, last);
+
+        self.pop().unwrap()
+    }
+
+    /// Keeps only the elements for which `keep` returns true
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        let mut index = 0;
+
+        while index < self.len {
+            if keep(&self[index]) {
+                index += 1;
+            } else {
+                self.remove(index);
+            }
+        }
+    }
+
+    /// Removes all elements
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    /// Returns the elements as a slice
+    pub fn as_slice(&self) -> &[T] {
+        // safety: first `len` elements are initialised
+        unsafe { core::slice::from_raw_parts(self.data.as_ptr() as *const T, self.len) }
+    }
+
+    /// Returns the elements as a mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // safety: first `len` elements are initialised
+        unsafe { core::slice::from_raw_parts_mut(self.data.as_mut_ptr() as *mut T, self.len) }
+    }
+}
+
+impl<T, const N: usize> Default for ArrayVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayVec<T, N> {
+    fn drop(&mut self) {
+        unsafe { core::ptr::drop_in_place(self.as_mut_slice()) }
+    }
+}
+
+impl<T, const N: usize> Deref for ArrayVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArrayVec<T, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ArrayVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut ArrayVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = core::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_mut_slice().iter_mut()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for ArrayVec<T, N> {
+    fn clone(&self) -> Self {
+        let mut clone = Self::new();
+        for value in self {
+            // can't fail, as clone has the same capacity
+            let _ = clone.push(value.clone());
+        }
+
+        clone
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for ArrayVec<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for ArrayVec<T, N> {}
+
+impl<T: Debug, const N: usize> Debug for ArrayVec<T, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use super::ArrayVec;
+
+    /// Counts how many times it is dropped, to check every element is dropped exactly once
+    #[derive(Debug)]
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn push_pop() {
+        let mut vec = ArrayVec::<u32, 3>::new();
+        assert!(vec.is_empty());
+
+        assert_eq!(vec.push(1), Ok(()));
+        assert_eq!(vec.push(2), Ok(()));
+        assert_eq!(vec.push(3), Ok(()));
+        assert!(vec.is_full());
+        assert_eq!(vec.push(4), Err(4));
+
+        assert_eq!(vec.as_slice(), [1, 2, 3]);
+        assert_eq!(vec.pop(), Some(3));
+        assert_eq!(vec.pop(), Some(2));
+        assert_eq!(vec.pop(), Some(1));
+        assert_eq!(vec.pop(), None);
+    }
+
+    #[test]
+    fn insert() {
+        let mut vec = ArrayVec::<u32, 4>::new();
+        vec.insert(0, 2).unwrap();
+        vec.insert(0, 0).unwrap();
+        vec.insert(1, 1).unwrap();
+        vec.insert(3, 3).unwrap();
+        assert_eq!(vec.as_slice(), [0, 1, 2, 3]);
+
+        assert_eq!(vec.insert(2, 9), Err(9));
+        assert_eq!(vec.as_slice(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic = "insertion index out of bounds"]
+    fn insert_past_end() {
+        let mut vec = ArrayVec::<u32, 4>::new();
+        let _ = vec.insert(1, 0);
+    }
+
+    #[test]
+    fn remove() {
+        let mut vec = ArrayVec::<u32, 4>::new();
+        for value in 0..4 {
+            vec.push(value).unwrap();
+        }
+
+        assert_eq!(vec.remove(1), 1);
+        assert_eq!(vec.as_slice(), [0, 2, 3]);
+        assert_eq!(vec.remove(2), 3);
+        assert_eq!(vec.as_slice(), [0, 2]);
+        assert_eq!(vec.remove(0), 0);
+        assert_eq!(vec.as_slice(), [2]);
+    }
+
+    #[test]
+    fn swap_remove() {
+        let mut vec = ArrayVec::<u32, 4>::new();
+        for value in 0..4 {
+            vec.push(value).unwrap();
+        }
+
+        assert_eq!(vec.swap_remove(0), 0);
+        assert_eq!(vec.as_slice(), [3, 1, 2]);
+        assert_eq!(vec.swap_remove(2), 2);
+        assert_eq!(vec.as_slice(), [3, 1]);
+    }
+
+    #[test]
+    fn retain() {
+        let mut vec = ArrayVec::<u32, 8>::new();
+        for value in 0..8 {
+            vec.push(value).unwrap();
+        }
+
+        vec.retain(|&value| value % 3 != 0);
+        assert_eq!(vec.as_slice(), [1, 2, 4, 5, 7]);
+    }
+
+    #[test]
+    fn drops_every_element_once() {
+        let drops = Cell::new(0);
+
+        {
+            let mut vec = ArrayVec::<DropCounter, 8>::new();
+            for _ in 0..6 {
+                vec.push(DropCounter(&drops)).unwrap();
+            }
+
+            // elements handed back are dropped by the caller, not the vector
+            drop(vec.remove(2));
+            drop(vec.swap_remove(0));
+            drop(vec.pop());
+            assert_eq!(drops.get(), 3);
+
+            vec.insert(1, DropCounter(&drops)).unwrap();
+            vec.retain(|_| false);
+            assert_eq!(drops.get(), 7);
+
+            vec.push(DropCounter(&drops)).unwrap();
+            vec.push(DropCounter(&drops)).unwrap();
+        }
+
+        // the last two are dropped with the vector
+        assert_eq!(drops.get(), 9);
+    }
+
+    #[test]
+    fn clone_and_compare() {
+        let mut vec = ArrayVec::<u32, 4>::new();
+        vec.push(1).unwrap();
+        vec.push(2).unwrap();
+
+        let mut clone = vec.clone();
+        assert_eq!(clone, vec);
+
+        clone.push(3).unwrap();
+        assert_ne!(clone, vec);
+    }
+}
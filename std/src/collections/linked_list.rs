@@ -0,0 +1,324 @@
+//! Intrusive doubly-linked list, where the links are stored inside the elements themselves.
+//!
+//! As the list never owns its elements, nothing needs allocating, but the caller must make sure elements outlive
+//! their time in the list.
+
+use core::{cell::Cell, marker::PhantomData, ptr::NonNull};
+
+/// Links to the neighbouring elements, to be embedded in each element
+pub struct Links<T> {
+    /// Next element in the list
+    next: Cell<Option<NonNull<T>>>,
+    /// Previous element in the list
+    prev: Cell<Option<NonNull<T>>>,
+    /// Whether the element is currently in a list
+    linked: Cell<bool>,
+}
+
+impl<T> Links<T> {
+    /// Constructs unlinked links
+    pub const fn new() -> Self {
+        Self {
+            next: Cell::new(None),
+            prev: Cell::new(None),
+            linked: Cell::new(false),
+        }
+    }
+
+    /// Returns true if the element is currently in a list
+    pub fn is_linked(&self) -> bool {
+        self.linked.get()
+    }
+}
+
+impl<T> Default for Links<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Types which can be stored in a [`LinkedList`]
+///
+/// ## Safety
+/// `links` must always return the same [`Links`], which is not used by any other list.
+pub unsafe trait Linked: Sized {
+    /// Returns the links embedded in this element
+    fn links(&self) -> &Links<Self>;
+}
+
+/// Intrusive doubly-linked list
+pub struct LinkedList<T: Linked> {
+    /// First element
+    head: Option<NonNull<T>>,
+    /// Last element
+    tail: Option<NonNull<T>>,
+    /// Number of elements
+    len: usize,
+}
+
+// safety: the list only hands out references to elements, so can be sent wherever the elements can
+unsafe impl<T: Linked + Send> Send for LinkedList<T> {}
+
+impl<T: Linked> LinkedList<T> {
+    /// Constructs an empty list
+    pub const fn new() -> Self {
+        Self {
+            head: None,
+            tail: None,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of elements
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if there are no elements
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the first element
+    pub fn front(&self) -> Option<&T> {
+        self.head.map(|node| unsafe { node.as_ref() })
+    }
+
+    /// Returns the last element
+    pub fn back(&self) -> Option<&T> {
+        self.tail.map(|node| unsafe { node.as_ref() })
+    }
+
+    /// Adds an element to the end of the list
+    ///
+    /// ## Safety
+    /// `node` must be valid until it is removed from the list, and must not already be in a list.
+    pub unsafe fn push_back(&mut self, node: NonNull<T>) {
+        let links = unsafe { node.as_ref() }.links();
+        debug_assert!(!links.is_linked(), "node is already in a list");
+
+        links.prev.set(self.tail);
+        links.next.set(None);
+        links.linked.set(true);
+
+        match self.tail {
+            Some(tail) => unsafe { tail.as_ref() }.links().next.set(Some(node)),
+            None => self.head = Some(node),
+        }
+
+        self.tail = Some(node);
+        self.len += 1;
+    }
+
+    /// Adds an element to the start of the list
+    ///
+    /// ## Safety
+    /// `node` must be valid until it is removed from the list, and must not already be in a list.
+    pub unsafe fn push_front(&mut self, node: NonNull<T>) {
+        let links = unsafe { node.as_ref() }.links();
+        debug_assert!(!links.is_linked(), "node is already in a list");
+
+        links.next.set(self.head);
+        links.prev.set(None);
+        links.linked.set(true);
+
+        match self.head {
+            Some(head) => unsafe { head.as_ref() }.links().prev.set(Some(node)),
+            None => self.tail = Some(node),
+        }
+
+        self.head = Some(node);
+        self.len += 1;
+    }
+
+    /// Removes and returns the first element
+    pub fn pop_front(&mut self) -> Option<NonNull<T>> {
+        let head = self.head?;
+        unsafe { self.remove(head) };
+
+        Some(head)
+    }
+
+    /// Removes and returns the last element
+    pub fn pop_back(&mut self) -> Option<NonNull<T>> {
+        let tail = self.tail?;
+        unsafe { self.remove(tail) };
+
+        Some(tail)
+    }
+
+    /// Removes an element from anywhere in the list
+    ///
+    /// ## Safety
+    /// `node` must be in this list.
+    pub unsafe fn remove(&mut self, node: NonNull<T>) {
+        let links = unsafe { node.as_ref() }.links();
+        debug_assert!(links.is_linked(), "node is not in a list");
+
+        let (prev, next) = (links.prev.get(), links.next.get());
+
+        match prev {
+            Some(prev) => unsafe { prev.as_ref() }.links().next.set(next),
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => unsafe { next.as_ref() }.links().prev.set(prev),
+            None => self.tail = prev,
+        }
+
+        links.prev.set(None);
+        links.next.set(None);
+        links.linked.set(false);
+        self.len -= 1;
+    }
+
+    /// Returns an iterator over the elements, from front to back
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Linked> Default for LinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterator over the elements of a [`LinkedList`]
+pub struct Iter<'a, T: Linked> {
+    /// Next element to yield
+    next: Option<NonNull<T>>,
+    /// Borrow of the list, so it can't be modified while iterating
+    _phantom: PhantomData<&'a LinkedList<T>>,
+}
+
+impl<'a, T: Linked> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node: &'a T = unsafe { self.next?.as_ref() };
+        self.next = node.links().next.get();
+
+        Some(node)
+    }
+}
+
+impl<'a, T: Linked> IntoIterator for &'a LinkedList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr::NonNull;
+
+    use super::{Linked, LinkedList, Links};
+
+    /// Element which can be stored in a test list
+    struct Node {
+        /// Value to identify the node by
+        value: u32,
+        /// Links to the neighbouring nodes
+        links: Links<Node>,
+    }
+
+    impl Node {
+        /// Constructs an unlinked node
+        const fn new(value: u32) -> Self {
+            Self {
+                value,
+                links: Links::new(),
+            }
+        }
+    }
+
+    unsafe impl Linked for Node {
+        fn links(&self) -> &Links<Self> {
+            &self.links
+        }
+    }
+
+    /// Collects the values in a list, from front to back
+    fn values<const N: usize>(list: &LinkedList<Node>) -> [Option<u32>; N] {
+        let mut values = [None; N];
+        for (slot, node) in values.iter_mut().zip(list) {
+            *slot = Some(node.value);
+        }
+
+        values
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let nodes = [Node::new(0), Node::new(1), Node::new(2)];
+        let mut list = LinkedList::new();
+        assert!(list.is_empty());
+
+        unsafe {
+            list.push_back(NonNull::from(&nodes[1]));
+            list.push_front(NonNull::from(&nodes[0]));
+            list.push_back(NonNull::from(&nodes[2]));
+        }
+
+        assert_eq!(list.len(), 3);
+        assert_eq!(values(&list), [Some(0), Some(1), Some(2)]);
+        assert_eq!(list.front().map(|node| node.value), Some(0));
+        assert_eq!(list.back().map(|node| node.value), Some(2));
+        assert!(nodes.iter().all(|node| node.links.is_linked()));
+
+        assert_eq!(list.pop_front(), Some(NonNull::from(&nodes[0])));
+        assert_eq!(list.pop_back(), Some(NonNull::from(&nodes[2])));
+        assert_eq!(list.pop_back(), Some(NonNull::from(&nodes[1])));
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+        assert!(nodes.iter().all(|node| !node.links.is_linked()));
+    }
+
+    #[test]
+    fn remove_from_middle() {
+        let nodes = [Node::new(0), Node::new(1), Node::new(2), Node::new(3)];
+        let mut list = LinkedList::new();
+        for node in &nodes {
+            unsafe { list.push_back(NonNull::from(node)) };
+        }
+
+        unsafe { list.remove(NonNull::from(&nodes[1])) };
+        assert_eq!(values(&list), [Some(0), Some(2), Some(3), None]);
+        assert!(!nodes[1].links.is_linked());
+
+        unsafe { list.remove(NonNull::from(&nodes[2])) };
+        assert_eq!(values(&list), [Some(0), Some(3), None, None]);
+        assert_eq!(list.len(), 2);
+
+        // removed nodes can be pushed again
+        unsafe { list.push_front(NonNull::from(&nodes[2])) };
+        assert_eq!(values(&list), [Some(2), Some(0), Some(3), None]);
+    }
+
+    #[test]
+    fn remove_ends() {
+        let nodes = [Node::new(0), Node::new(1), Node::new(2)];
+        let mut list = LinkedList::new();
+        for node in &nodes {
+            unsafe { list.push_back(NonNull::from(node)) };
+        }
+
+        unsafe { list.remove(NonNull::from(&nodes[0])) };
+        assert_eq!(list.front().map(|node| node.value), Some(1));
+
+        unsafe { list.remove(NonNull::from(&nodes[2])) };
+        assert_eq!(list.back().map(|node| node.value), Some(1));
+
+        unsafe { list.remove(NonNull::from(&nodes[1])) };
+        assert!(list.is_empty());
+        assert_eq!(list.front().map(|node| node.value), None);
+        assert_eq!(list.back().map(|node| node.value), None);
+    }
+}
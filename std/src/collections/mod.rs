@@ -0,0 +1,9 @@
+//! Collections which don't need a heap
+
+pub mod array_vec;
+pub mod linked_list;
+pub mod ring_buffer;
+
+pub use array_vec::ArrayVec;
+pub use linked_list::{Linked, LinkedList, Links};
+pub use ring_buffer::{MpscRingBuffer, SpscRingBuffer};
@@ -0,0 +1,580 @@
+//! Fixed-capacity lock-free ring buffers, usable from interrupt context
+
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Single-producer single-consumer ring buffer holding up to `N` elements.
+///
+/// `N` must be a power of two. Use [`SpscRingBuffer::split`] to get the producer and consumer halves, or
+/// [`SpscRingBuffer::producer`] and [`SpscRingBuffer::consumer`] for a buffer in a static.
+pub struct SpscRingBuffer<T, const N: usize> {
+    /// Backing storage
+    buffer: [UnsafeCell<MaybeUninit<T>>; N],
+    /// Position of the next element to pop, only written by the consumer
+    head: AtomicUsize,
+    /// Position of the next element to push, only written by the producer
+    tail: AtomicUsize,
+}
+
+// safety: elements are only accessed by one side at a time, with ownership handed over by `head` and `tail`
+unsafe impl<T: Send, const N: usize> Sync for SpscRingBuffer<T, N> {}
+
+impl<T, const N: usize> SpscRingBuffer<T, N> {
+    /// Checked when the buffer is constructed, so an invalid `N` is a compile error
+    const VALID_CAPACITY: () = assert!(
+        N.is_power_of_two(),
+        "ring buffer capacity must be a power of two"
+    );
+
+    /// Constructs an empty ring buffer
+    pub const fn new() -> Self {
+        let () = Self::VALID_CAPACITY;
+
+        Self {
+            buffer: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the maximum number of elements
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of elements currently stored. May be out of date by the time it is used.
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        self.tail.load(Ordering::Acquire).wrapping_sub(head)
+    }
+
+    /// Returns true if there are no elements. May be out of date by the time it is used.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Splits the buffer into its producer and consumer halves
+    pub fn split(&mut self) -> (Producer<'_, T, N>, Consumer<'_, T, N>) {
+        (Producer { ring: self }, Consumer { ring: self })
+    }
+
+    /// Returns the producer half, for buffers which can't be borrowed mutably such as statics.
+    ///
+    /// ## Safety
+    /// There must be no other [`Producer`] for this buffer alive at the same time.
+    pub unsafe fn producer(&self) -> Producer<'_, T, N> {
+        Producer { ring: self }
+    }
+
+    /// Returns the consumer half, for buffers which can't be borrowed mutably such as statics.
+    ///
+    /// ## Safety
+    /// There must be no other [`Consumer`] for this buffer alive at the same time.
+    pub unsafe fn consumer(&self) -> Consumer<'_, T, N> {
+        Consumer { ring: self }
+    }
+
+    /// Pushes an element, returning it back if the buffer is full.
+    ///
+    /// ## Safety
+    /// Must not be called concurrently with another push.
+    unsafe fn push(&self, value: T) -> Result<(), T> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+
+        if tail.wrapping_sub(head) == N {
+            return Err(value);
+        }
+
+        // safety: slot is outside of head..tail, so the consumer won't touch it
+        unsafe { (*self.buffer[tail & (N - 1)].get()).write(value) };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Pops the oldest element.
+    ///
+    /// ## Safety
+    /// Must not be called concurrently with another pop.
+    unsafe fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+
+        if head == tail {
+            return None;
+        }
+
+        // safety: slot is inside head..tail, so was initialised by the producer, which won't touch it again until
+        // head moves past it
+        let value = unsafe { (*self.buffer[head & (N - 1)].get()).assume_init_read() };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+
+        Some(value)
+    }
+}
+
+impl<T, const N: usize> Default for SpscRingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for SpscRingBuffer<T, N> {
+    fn drop(&mut self) {
+        // safety: we have exclusive access
+        while unsafe { self.pop() }.is_some() {}
+    }
+}
+
+/// Producer half of a [`SpscRingBuffer`]
+pub struct Producer<'a, T, const N: usize> {
+    /// Buffer being pushed to
+    ring: &'a SpscRingBuffer<T, N>,
+}
+
+// safety: only one producer exists per buffer
+unsafe impl<T: Send, const N: usize> Send for Producer<'_, T, N> {}
+
+impl<T, const N: usize> Producer<'_, T, N> {
+    /// Pushes an element, returning it back if the buffer is full
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        // safety: only one producer exists, and it is borrowed mutably
+        unsafe { self.ring.push(value) }
+    }
+
+    /// Returns true if the buffer is full. Can only become false concurrently.
+    pub fn is_full(&self) -> bool {
+        self.ring.len() == N
+    }
+}
+
+/// Consumer half of a [`SpscRingBuffer`]
+pub struct Consumer<'a, T, const N: usize> {
+    /// Buffer being popped from
+    ring: &'a SpscRingBuffer<T, N>,
+}
+
+// safety: only one consumer exists per buffer
+unsafe impl<T: Send, const N: usize> Send for Consumer<'_, T, N> {}
+
+impl<T, const N: usize> Consumer<'_, T, N> {
+    /// Pops the oldest element
+    pub fn pop(&mut self) -> Option<T> {
+        // safety: only one consumer exists, and it is borrowed mutably
+        unsafe { self.ring.pop() }
+    }
+
+    /// Returns true if the buffer is empty. Can only become false concurrently.
+    pub fn is_empty(&self) -> bool {
+        self.ring.is_empty()
+    }
+}
+
+/// Slot in a [`MpscRingBuffer`]
+struct Slot<T> {
+    /// Sequence number, saying whether the slot is ready to be written (`== pos`) or read (`== pos + 1`)
+    sequence: AtomicUsize,
+    /// Stored element
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// Multi-producer ring buffer holding up to `N` elements, based on Dmitry Vyukov's bounded queue.
+///
+/// `N` must be a power of two, and at least 2. Any number of contexts may push concurrently. Popping is also safe to do
+/// concurrently, though is typically left to a single consumer.
+pub struct MpscRingBuffer<T, const N: usize> {
+    /// Backing storage
+    buffer: [Slot<T>; N],
+    /// Position of the next element to pop
+    head: AtomicUsize,
+    /// Position of the next element to push
+    tail: AtomicUsize,
+}
+
+// safety: each slot is claimed by exactly one producer or consumer at a time, via its sequence number
+unsafe impl<T: Send, const N: usize> Sync for MpscRingBuffer<T, N> {}
+
+impl<T, const N: usize> MpscRingBuffer<T, N> {
+    /// Checked when the buffer is constructed, so an invalid `N` is a compile error. With a single slot, a full
+    /// buffer's sequence numbers look the same as an empty one's, so at least two are needed.
+    const VALID_CAPACITY: () = assert!(
+        N.is_power_of_two() && N >= 2,
+        "ring buffer capacity must be a power of two, and at least 2"
+    );
+
+    /// Constructs an empty ring buffer
+    pub const fn new() -> Self {
+        let () = Self::VALID_CAPACITY;
+
+        let mut buffer = [const {
+            Slot {
+                sequence: AtomicUsize::new(0),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }
+        }; N];
+
+        let mut i = 0;
+        while i < N {
+            buffer[i].sequence = AtomicUsize::new(i);
+            i += 1;
+        }
+
+        Self {
+            buffer,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the maximum number of elements
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of elements currently stored. May be out of date by the time it is used.
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        self.tail.load(Ordering::Acquire).wrapping_sub(head).min(N)
+    }
+
+    /// Returns true if there are no elements. May be out of date by the time it is used.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pushes an element, returning it back if the buffer is full
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut pos = self.tail.load(Ordering::Relaxed);
+
+        loop {
+            let slot = &self.buffer[pos & (N - 1)];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+
+            match (sequence as isize).wrapping_sub(pos as isize) {
+                // slot is free, so try to claim it
+                0 => match self.tail.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // safety: slot was claimed above, so nothing else will access it until sequence is bumped
+                        unsafe { (*slot.value.get()).write(value) };
+                        slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                },
+                // slot still holds an element from the previous lap, so buffer is full
+                diff if diff < 0 => return Err(value),
+                // another producer got here first
+                _ => pos = self.tail.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Pops the oldest element
+    pub fn pop(&self) -> Option<T> {
+        let mut pos = self.head.load(Ordering::Relaxed);
+
+        loop {
+            let slot = &self.buffer[pos & (N - 1)];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+
+            match (sequence as isize).wrapping_sub(pos.wrapping_add(1) as isize) {
+                // slot holds an element, so try to claim it
+                0 => match self.head.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // safety: slot was claimed above, and was initialised by the producer which set sequence
+                        let value = unsafe { (*slot.value.get()).assume_init_read() };
+                        slot.sequence.store(pos.wrapping_add(N), Ordering::Release);
+
+                        return Some(value);
+                    }
+                    Err(current) => pos = current,
+                },
+                // slot hasn't been written yet, so buffer is empty
+                diff if diff < 0 => return None,
+                // another consumer got here first
+                _ => pos = self.head.load(Ordering::Relaxed),
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> Default for MpscRingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for MpscRingBuffer<T, N> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std as host;
+
+    use core::{
+        cell::Cell,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    use host::thread;
+
+    use super::{MpscRingBuffer, SpscRingBuffer};
+
+    /// Elements pushed by each producer in the threaded tests, kept small under miri as it is much slower
+    const COUNT: usize = if cfg!(miri) { 200 } else { 100_000 };
+
+    /// Counts how many times it is dropped, to check elements left in a buffer are dropped with it
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn spsc_fill_and_wrap() {
+        let mut ring = SpscRingBuffer::<u32, 4>::new();
+        let (mut producer, mut consumer) = ring.split();
+
+        for lap in 0..3 {
+            for i in 0..4 {
+                assert_eq!(producer.push(lap * 4 + i), Ok(()));
+            }
+            assert!(producer.is_full());
+            assert_eq!(producer.push(99), Err(99));
+
+            for i in 0..4 {
+                assert_eq!(consumer.pop(), Some(lap * 4 + i));
+            }
+            assert!(consumer.is_empty());
+            assert_eq!(consumer.pop(), None);
+        }
+    }
+
+    #[test]
+    fn spsc_single_slot() {
+        let mut ring = SpscRingBuffer::<u32, 1>::new();
+        let (mut producer, mut consumer) = ring.split();
+
+        assert_eq!(producer.push(1), Ok(()));
+        assert_eq!(producer.push(2), Err(2));
+        assert_eq!(consumer.pop(), Some(1));
+        assert_eq!(consumer.pop(), None);
+    }
+
+    #[test]
+    fn spsc_drops_remaining() {
+        let drops = Cell::new(0);
+
+        {
+            let mut ring = SpscRingBuffer::<DropCounter, 4>::new();
+            let (mut producer, mut consumer) = ring.split();
+            for _ in 0..3 {
+                assert!(producer.push(DropCounter(&drops)).is_ok());
+            }
+
+            drop(consumer.pop());
+            assert_eq!(drops.get(), 1);
+        }
+
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn spsc_threads() {
+        let mut ring = SpscRingBuffer::<usize, 8>::new();
+        let (mut producer, mut consumer) = ring.split();
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for i in 0..COUNT {
+                    while producer.push(i).is_err() {
+                        thread::yield_now();
+                    }
+                }
+            });
+
+            scope.spawn(move || {
+                for i in 0..COUNT {
+                    loop {
+                        match consumer.pop() {
+                            Some(value) => {
+                                assert_eq!(value, i);
+                                break;
+                            }
+                            None => thread::yield_now(),
+                        }
+                    }
+                }
+
+                assert!(consumer.is_empty());
+            });
+        });
+    }
+
+    #[test]
+    fn spsc_static() {
+        static RING: SpscRingBuffer<usize, 8> = SpscRingBuffer::new();
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                // safety: this is the only producer
+                let mut producer = unsafe { RING.producer() };
+                for i in 0..COUNT {
+                    while producer.push(i).is_err() {
+                        thread::yield_now();
+                    }
+                }
+            });
+
+            scope.spawn(|| {
+                // safety: this is the only consumer
+                let mut consumer = unsafe { RING.consumer() };
+                for i in 0..COUNT {
+                    loop {
+                        match consumer.pop() {
+                            Some(value) => {
+                                assert_eq!(value, i);
+                                break;
+                            }
+                            None => thread::yield_now(),
+                        }
+                    }
+                }
+            });
+        });
+
+        assert!(RING.is_empty());
+    }
+
+    #[test]
+    fn mpsc_fill_and_wrap() {
+        let ring = MpscRingBuffer::<u32, 2>::new();
+
+        for lap in 0..3 {
+            assert_eq!(ring.push(lap * 2), Ok(()));
+            assert_eq!(ring.push(lap * 2 + 1), Ok(()));
+            assert_eq!(ring.len(), 2);
+            assert_eq!(ring.push(99), Err(99));
+
+            assert_eq!(ring.pop(), Some(lap * 2));
+            assert_eq!(ring.pop(), Some(lap * 2 + 1));
+            assert!(ring.is_empty());
+            assert_eq!(ring.pop(), None);
+        }
+    }
+
+    #[test]
+    fn mpsc_drops_remaining() {
+        let drops = Cell::new(0);
+
+        {
+            let ring = MpscRingBuffer::<DropCounter, 4>::new();
+            for _ in 0..3 {
+                assert!(ring.push(DropCounter(&drops)).is_ok());
+            }
+
+            drop(ring.pop());
+            assert_eq!(drops.get(), 1);
+        }
+
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn mpsc_threads_keep_per_producer_order() {
+        const PRODUCERS: usize = 4;
+        let ring = MpscRingBuffer::<(usize, usize), 8>::new();
+
+        thread::scope(|scope| {
+            for id in 0..PRODUCERS {
+                let ring = &ring;
+                scope.spawn(move || {
+                    for i in 0..COUNT {
+                        while ring.push((id, i)).is_err() {
+                            thread::yield_now();
+                        }
+                    }
+                });
+            }
+
+            let mut next = [0; PRODUCERS];
+            for _ in 0..PRODUCERS * COUNT {
+                let (id, i) = loop {
+                    match ring.pop() {
+                        Some(value) => break value,
+                        None => thread::yield_now(),
+                    }
+                };
+
+                assert_eq!(i, next[id], "producer {id} out of order");
+                next[id] += 1;
+            }
+
+            assert_eq!(next, [COUNT; PRODUCERS]);
+        });
+
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn mpsc_threads_multiple_consumers() {
+        const THREADS: usize = 2;
+        let ring = MpscRingBuffer::<usize, 4>::new();
+        let seen = [const { AtomicBool::new(false) }; THREADS * COUNT];
+
+        thread::scope(|scope| {
+            for id in 0..THREADS {
+                let ring = &ring;
+                scope.spawn(move || {
+                    for i in 0..COUNT {
+                        while ring.push(id * COUNT + i).is_err() {
+                            thread::yield_now();
+                        }
+                    }
+                });
+            }
+
+            for _ in 0..THREADS {
+                let (ring, seen) = (&ring, &seen);
+                scope.spawn(move || {
+                    for _ in 0..COUNT {
+                        let value = loop {
+                            match ring.pop() {
+                                Some(value) => break value,
+                                None => thread::yield_now(),
+                            }
+                        };
+
+                        assert!(
+                            !seen[value].swap(true, Ordering::Relaxed),
+                            "{value} popped twice"
+                        );
+                    }
+                });
+            }
+        });
+
+        assert!(seen.iter().all(|seen| seen.load(Ordering::Relaxed)));
+        assert!(ring.is_empty());
+    }
+}
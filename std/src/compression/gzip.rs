@@ -0,0 +1,172 @@
+//! Decoder for the gzip file format, as described in RFC 1952
+
+use crate::compression::{DecompressError, inflate::inflate};
+
+/// Magic bytes at the start of every gzip file
+const MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// Compression method for DEFLATE, the only one defined
+const METHOD_DEFLATE: u8 = 8;
+
+/// Header flag: CRC16 of header present
+const FLAG_HEADER_CRC: u8 = 1 << 1;
+
+/// Header flag: extra field present
+const FLAG_EXTRA: u8 = 1 << 2;
+
+/// Header flag: original file name present
+const FLAG_NAME: u8 = 1 << 3;
+
+/// Header flag: comment present
+const FLAG_COMMENT: u8 = 1 << 4;
+
+/// Size of the fixed part of the header in bytes
+const HEADER_SIZE: usize = 10;
+
+/// Size of the trailer (CRC32 + ISIZE) in bytes
+const TRAILER_SIZE: usize = 8;
+
+/// Lookup table for the CRC32 used by gzip, computed at compile time
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut value = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            value = if value & 1 != 0 {
+                0xEDB88320 ^ (value >> 1)
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+
+        table[i] = value;
+        i += 1;
+    }
+
+    table
+};
+
+/// Computes the CRC32 checksum of the given data
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Continues a CRC32 checksum over more data, for data which isn't all available at once. `crc` is the checksum of
+/// everything before `data`, or 0 to start.
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+
+    for &byte in data {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+
+    !crc
+}
+
+/// Returns whether the data starts with the gzip magic bytes
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// Returns the size of the decompressed data, as stored in the gzip trailer.
+///
+/// This is only stored modulo 2^32, but that is plenty for our use.
+pub fn decompressed_size(data: &[u8]) -> Option<usize> {
+    if !is_gzip(data) || data.len() < HEADER_SIZE + TRAILER_SIZE {
+        return None;
+    }
+
+    let size = data[data.len() - 4..].try_into().ok()?;
+
+    Some(u32::from_le_bytes(size) as usize)
+}
+
+/// Skips over a null-terminated string starting at `offset`, returning the offset after it
+fn skip_string(data: &[u8], offset: usize) -> Result<usize, DecompressError> {
+    let length = data
+        .get(offset..)
+        .and_then(|data| data.iter().position(|&byte| byte == 0))
+        .ok_or(DecompressError::UnexpectedEnd)?;
+
+    Ok(offset + length + 1)
+}
+
+/// Decompresses a gzip file into `output`, returning the number of bytes written.
+///
+/// The CRC32 and length stored in the trailer are verified against the decompressed data.
+pub fn decompress(data: &[u8], output: &mut [u8]) -> Result<usize, DecompressError> {
+    if data.len() < HEADER_SIZE + TRAILER_SIZE || !is_gzip(data) {
+        return Err(DecompressError::BadHeader);
+    }
+
+    if data[2] != METHOD_DEFLATE {
+        return Err(DecompressError::UnsupportedMethod);
+    }
+
+    // skip over the optional header fields
+    let flags = data[3];
+    let mut offset = HEADER_SIZE;
+
+    if flags & FLAG_EXTRA != 0 {
+        let length = data
+            .get(offset..offset + 2)
+            .ok_or(DecompressError::UnexpectedEnd)?;
+
+        offset += 2 + u16::from_le_bytes([length[0], length[1]]) as usize;
+    }
+    if flags & FLAG_NAME != 0 {
+        offset = skip_string(data, offset)?;
+    }
+    if flags & FLAG_COMMENT != 0 {
+        offset = skip_string(data, offset)?;
+    }
+    if flags & FLAG_HEADER_CRC != 0 {
+        offset += 2;
+    }
+
+    let compressed = data
+        .get(offset..data.len() - TRAILER_SIZE)
+        .ok_or(DecompressError::UnexpectedEnd)?;
+    let (_, written) = inflate(compressed, output)?;
+
+    // finally check trailer matches what we decompressed
+    let trailer = &data[data.len() - TRAILER_SIZE..];
+    let expected_crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let expected_size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+
+    if expected_size != written as u32 {
+        return Err(DecompressError::SizeMismatch);
+    }
+
+    if expected_crc != crc32(&output[..written]) {
+        return Err(DecompressError::ChecksumMismatch);
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn crc32_in_parts() {
+        let data = b"The quick brown fox jumps over the lazy dog";
+
+        for split in 0..=data.len() {
+            let (first, second) = data.split_at(split);
+            assert_eq!(crc32_update(crc32(first), second), crc32(data));
+        }
+    }
+}
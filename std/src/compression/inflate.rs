@@ -0,0 +1,371 @@
+//! Decoder for raw DEFLATE streams, as described in RFC 1951.
+//!
+//! This favours simplicity over speed, decoding huffman codes a bit at a time.
+
+use crate::compression::DecompressError;
+
+/// Base lengths for length symbols 257-285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+
+/// Extra bits for length symbols 257-285
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base offsets for distance symbols 0-29
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// Extra bits for distance symbols 0-29
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which code length code lengths are stored in dynamic blocks
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Maximum number of bits in a huffman code
+const MAX_BITS: usize = 15;
+
+/// Maximum number of literal/length codes
+const MAX_LIT_CODES: usize = 288;
+
+/// Maximum number of distance codes
+const MAX_DIST_CODES: usize = 30;
+
+/// Reads the input a bit at a time, least significant bit first
+struct BitReader<'a> {
+    /// Compressed input
+    input: &'a [u8],
+    /// Offset of next unread byte in input
+    position: usize,
+    /// Bits read from input but not yet consumed
+    bit_buffer: u32,
+    /// Number of valid bits in `bit_buffer`
+    bit_count: u32,
+}
+
+impl BitReader<'_> {
+    /// Reads `count` bits (at most 16), returning them as an integer
+    fn bits(&mut self, count: u32) -> Result<u32, DecompressError> {
+        let mut value = self.bit_buffer;
+
+        while self.bit_count < count {
+            let byte = *self
+                .input
+                .get(self.position)
+                .ok_or(DecompressError::UnexpectedEnd)?;
+            self.position += 1;
+
+            value |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+
+        self.bit_buffer = value >> count;
+        self.bit_count -= count;
+
+        Ok(value & ((1 << count) - 1))
+    }
+
+    /// Discards any bits left in the current byte
+    fn align_to_byte(&mut self) {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+
+    /// Reads `count` whole bytes, which must start on a byte boundary
+    fn bytes(&mut self, count: usize) -> Result<&[u8], DecompressError> {
+        let bytes = self
+            .input
+            .get(self.position..self.position + count)
+            .ok_or(DecompressError::UnexpectedEnd)?;
+        self.position += count;
+
+        Ok(bytes)
+    }
+}
+
+/// Writes decompressed data into the output buffer
+struct Output<'a> {
+    /// Buffer to write into
+    buffer: &'a mut [u8],
+    /// Number of bytes written so far
+    position: usize,
+}
+
+impl Output<'_> {
+    /// Writes a single byte
+    fn push(&mut self, byte: u8) -> Result<(), DecompressError> {
+        let slot = self
+            .buffer
+            .get_mut(self.position)
+            .ok_or(DecompressError::OutputFull)?;
+
+        *slot = byte;
+        self.position += 1;
+
+        Ok(())
+    }
+
+    /// Copies `length` bytes starting `distance` bytes back into the output
+    fn copy_back(&mut self, distance: usize, length: usize) -> Result<(), DecompressError> {
+        if distance > self.position {
+            return Err(DecompressError::InvalidDistance);
+        }
+
+        if self.position + length > self.buffer.len() {
+            return Err(DecompressError::OutputFull);
+        }
+
+        // ranges may overlap (in which case bytes get repeated), so copy byte by byte
+        for _ in 0..length {
+            self.buffer[self.position] = self.buffer[self.position - distance];
+            self.position += 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// A canonical huffman code
+struct Huffman {
+    /// Number of symbols of each code length
+    counts: [u16; MAX_BITS + 1],
+    /// Symbols ordered by code
+    symbols: [u16; MAX_LIT_CODES],
+}
+
+impl Huffman {
+    /// Constructs a huffman code from the code length of each symbol, where 0 means the symbol is unused
+    fn new(lengths: &[u8]) -> Result<Self, DecompressError> {
+        let mut huffman = Self {
+            counts: [0; MAX_BITS + 1],
+            symbols: [0; MAX_LIT_CODES],
+        };
+
+        for &length in lengths {
+            huffman.counts[length as usize] += 1;
+        }
+
+        // make sure the code isn't over-subscribed
+        let mut left: i32 = 1;
+        for length in 1..=MAX_BITS {
+            left <<= 1;
+            left -= huffman.counts[length] as i32;
+
+            if left < 0 {
+                return Err(DecompressError::InvalidCodeLengths);
+            }
+        }
+
+        // find offset of the first symbol of each length within symbols table
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + huffman.counts[length];
+        }
+
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                huffman.symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(huffman)
+    }
+
+    /// Decodes a single symbol from the input
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, DecompressError> {
+        // first code of the current length
+        let mut first: i32 = 0;
+        // index of first symbol of the current length
+        let mut index: i32 = 0;
+        // code read so far
+        let mut code: i32 = 0;
+
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+
+        Err(DecompressError::InvalidSymbol)
+    }
+}
+
+/// Decompresses a raw DEFLATE stream into `output`, returning the number of bytes consumed from `input`
+/// and the number of bytes written to `output`.
+pub fn inflate(input: &[u8], output: &mut [u8]) -> Result<(usize, usize), DecompressError> {
+    let mut reader = BitReader {
+        input,
+        position: 0,
+        bit_buffer: 0,
+        bit_count: 0,
+    };
+    let mut output = Output {
+        buffer: output,
+        position: 0,
+    };
+
+    loop {
+        let last_block = reader.bits(1)? == 1;
+
+        match reader.bits(2)? {
+            0 => stored_block(&mut reader, &mut output)?,
+            1 => fixed_block(&mut reader, &mut output)?,
+            2 => dynamic_block(&mut reader, &mut output)?,
+            _ => return Err(DecompressError::InvalidBlockType),
+        }
+
+        if last_block {
+            break;
+        }
+    }
+
+    Ok((reader.position, output.position))
+}
+
+/// Copies an uncompressed block to the output
+fn stored_block(reader: &mut BitReader, output: &mut Output) -> Result<(), DecompressError> {
+    reader.align_to_byte();
+
+    let header = reader.bytes(4)?;
+    let length = u16::from_le_bytes([header[0], header[1]]);
+    let complement = u16::from_le_bytes([header[2], header[3]]);
+
+    if length != !complement {
+        return Err(DecompressError::StoredLengthMismatch);
+    }
+
+    for &byte in reader.bytes(length as usize)? {
+        output.push(byte)?;
+    }
+
+    Ok(())
+}
+
+/// Decodes a block compressed with the fixed huffman codes
+fn fixed_block(reader: &mut BitReader, output: &mut Output) -> Result<(), DecompressError> {
+    let mut lengths = [0u8; MAX_LIT_CODES];
+
+    lengths[0..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..288].fill(8);
+    let literal_code = Huffman::new(&lengths)?;
+
+    let distance_code = Huffman::new(&[5; MAX_DIST_CODES])?;
+
+    codes(reader, output, &literal_code, &distance_code)
+}
+
+/// Decodes a block compressed with huffman codes described in the block header
+fn dynamic_block(reader: &mut BitReader, output: &mut Output) -> Result<(), DecompressError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    if literal_count > 286 || distance_count > MAX_DIST_CODES {
+        return Err(DecompressError::InvalidCodeLengths);
+    }
+
+    // first read the code used to compress the code lengths
+    let mut lengths = [0u8; MAX_LIT_CODES + MAX_DIST_CODES];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        lengths[index] = reader.bits(3)? as u8;
+    }
+    let length_code = Huffman::new(&lengths[..19])?;
+
+    // then the code lengths for both literal/length and distance codes
+    lengths.fill(0);
+    let total = literal_count + distance_count;
+    let mut index = 0;
+
+    while index < total {
+        let symbol = length_code.decode(reader)?;
+
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                // repeat previous length 3-6 times
+                let previous = *index
+                    .checked_sub(1)
+                    .and_then(|previous| lengths.get(previous))
+                    .ok_or(DecompressError::InvalidCodeLengths)?;
+
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+
+        if index + repeat > total {
+            return Err(DecompressError::InvalidCodeLengths);
+        }
+
+        lengths[index..index + repeat].fill(length);
+        index += repeat;
+    }
+
+    // a block without an end-of-block code can never finish
+    if lengths[256] == 0 {
+        return Err(DecompressError::InvalidCodeLengths);
+    }
+
+    let literal_code = Huffman::new(&lengths[..literal_count])?;
+    let distance_code = Huffman::new(&lengths[literal_count..total])?;
+
+    codes(reader, output, &literal_code, &distance_code)
+}
+
+/// Decodes literals and back-references until the end of block code
+fn codes(
+    reader: &mut BitReader,
+    output: &mut Output,
+    literal_code: &Huffman,
+    distance_code: &Huffman,
+) -> Result<(), DecompressError> {
+    loop {
+        let symbol = literal_code.decode(reader)? as usize;
+
+        match symbol {
+            0..=255 => output.push(symbol as u8)?,
+            256 => return Ok(()),
+            _ => {
+                let symbol = symbol - 257;
+                if symbol >= LENGTH_BASE.len() {
+                    return Err(DecompressError::InvalidSymbol);
+                }
+
+                let length = LENGTH_BASE[symbol] as usize
+                    + reader.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+                let symbol = distance_code.decode(reader)? as usize;
+                if symbol >= DIST_BASE.len() {
+                    return Err(DecompressError::InvalidSymbol);
+                }
+
+                let distance =
+                    DIST_BASE[symbol] as usize + reader.bits(DIST_EXTRA[symbol] as u32)? as usize;
+
+                output.copy_back(distance, length)?;
+            }
+        }
+    }
+}
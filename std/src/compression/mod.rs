@@ -0,0 +1,51 @@
+//! Decompression of compressed data formats
+
+use core::fmt::{Display, Formatter};
+
+pub mod gzip;
+pub mod inflate;
+
+/// An error encountered while decompressing data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecompressError {
+    /// Header of the compressed data is invalid
+    BadHeader,
+    /// Data uses a compression method other than DEFLATE
+    UnsupportedMethod,
+    /// Input ended before decompression was complete
+    UnexpectedEnd,
+    /// Output buffer is too small to hold decompressed data
+    OutputFull,
+    /// DEFLATE block has reserved block type
+    InvalidBlockType,
+    /// Stored block length does not match its complement
+    StoredLengthMismatch,
+    /// Huffman code lengths do not describe a valid code
+    InvalidCodeLengths,
+    /// Encountered a symbol which does not correspond to any code
+    InvalidSymbol,
+    /// Back-reference distance points before the start of output
+    InvalidDistance,
+    /// Checksum of decompressed data does not match stored checksum
+    ChecksumMismatch,
+    /// Length of decompressed data does not match stored length
+    SizeMismatch,
+}
+
+impl Display for DecompressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::BadHeader => "invalid header",
+            Self::UnsupportedMethod => "unsupported compression method",
+            Self::UnexpectedEnd => "unexpected end of input",
+            Self::OutputFull => "output buffer too small",
+            Self::InvalidBlockType => "invalid block type",
+            Self::StoredLengthMismatch => "stored block length mismatch",
+            Self::InvalidCodeLengths => "invalid huffman code lengths",
+            Self::InvalidSymbol => "invalid huffman symbol",
+            Self::InvalidDistance => "back-reference distance too far",
+            Self::ChecksumMismatch => "checksum mismatch",
+            Self::SizeMismatch => "decompressed size mismatch",
+        })
+    }
+}
@@ -3,13 +3,13 @@
 use core::ffi::CStr;
 
 /// A header for an individual ELF section
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct SectionHeader {
     /// Offset in bytes to the section name in string table
     pub section_name: u32,
-    /// Section type
-    pub section_type: SectionType,
+    /// Raw section type, see [`SectionHeader::section_type`]
+    section_type: u32,
     /// Section flags
     pub flags: u64,
     /// Virtual address of the beginning of section, 0 if should not be allocated
@@ -29,15 +29,32 @@ pub struct SectionHeader {
 }
 
 impl SectionHeader {
-    /// Returns the name of the header using the provided string table
-    pub fn name(&self, string_header: &Self, start_addr: usize) -> &'static CStr {
-        let location = if start_addr == 0 {
-            string_header.addr as usize
-        } else {
-            start_addr + string_header.offset as usize
-        } as *const i8;
+    /// Returns the name of the section, reading the string table from the address it was loaded at. Only valid
+    /// once the string table section has been loaded, such as for the image the bootloader loaded.
+    ///
+    /// # Safety
+    /// The string table must be mapped at `string_header.addr`, and contain a string at this section's name offset.
+    pub unsafe fn name_loaded(&self, string_header: &Self) -> &'static CStr {
+        unsafe { Self::name_at(string_header.addr as usize, self.section_name) }
+    }
+
+    /// Returns the name of the section, reading the string table from an ELF file sitting in memory starting at
+    /// `file_base`.
+    ///
+    /// # Safety
+    /// The whole file must be readable at `file_base`, and contain a string at this section's name offset.
+    pub unsafe fn name_in_file(&self, string_header: &Self, file_base: usize) -> &'static CStr {
+        unsafe { Self::name_at(file_base + string_header.offset as usize, self.section_name) }
+    }
+
+    /// Reads the string at `offset` within a string table starting at `string_table`
+    unsafe fn name_at(string_table: usize, offset: u32) -> &'static CStr {
+        unsafe { CStr::from_ptr((string_table as *const i8).add(offset as usize)) }
+    }
 
-        unsafe { CStr::from_ptr(location.add(self.section_name as usize)) }
+    /// Returns the section type, which may be a value not known to this parser
+    pub const fn section_type(&self) -> SectionType {
+        SectionType::from_u32(self.section_type)
     }
 
     /// Whether section is allocated
@@ -57,11 +74,10 @@ impl SectionHeader {
 }
 
 /// Type of the section
-#[repr(u32)]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SectionType {
     /// Unused section header
-    Null = 0,
+    Null,
     /// Information defined by program
     Progbits,
     /// Linker symbol table
@@ -84,12 +100,42 @@ pub enum SectionType {
     Shlib,
     /// Dynamic loader symbol table
     Dynsym,
-    /// Environment-specific use
-    LoOs = 0x60000000,
-    /// Environment-specific use
-    HiOs = 0x6FFFFFFF,
-    /// Processor-specific use
-    LoProc = 0x70000000,
-    /// Processor-specific use
-    HiProc = 0x7FFFFFFF,
+    /// Array of pointers to initialisation functions
+    InitArray,
+    /// Array of pointers to termination functions
+    FiniArray,
+    /// Array of pointers to functions run before other initialisation functions
+    PreinitArray,
+    /// Section group
+    Group,
+    /// Extended section indices for a symbol table
+    SymtabShndx,
+    /// Any other type, including environment and processor specific ones such as GNU extensions
+    Unknown(u32),
+}
+
+impl SectionType {
+    /// Decodes a raw section type
+    pub const fn from_u32(value: u32) -> Self {
+        match value {
+            0 => Self::Null,
+            1 => Self::Progbits,
+            2 => Self::Symtab,
+            3 => Self::Strtab,
+            4 => Self::Rela,
+            5 => Self::Hash,
+            6 => Self::Dynamic,
+            7 => Self::Note,
+            8 => Self::Nobits,
+            9 => Self::Rel,
+            10 => Self::Shlib,
+            11 => Self::Dynsym,
+            14 => Self::InitArray,
+            15 => Self::FiniArray,
+            16 => Self::PreinitArray,
+            17 => Self::Group,
+            18 => Self::SymtabShndx,
+            value => Self::Unknown(value),
+        }
+    }
 }
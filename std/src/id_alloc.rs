@@ -0,0 +1,121 @@
+//! Allocator for small integer IDs, such as PIDs, file descriptors and interrupt vectors
+
+use core::ops::Range;
+
+use crate::bitmap::Bitmap;
+
+/// Hands out IDs from a fixed range, always returning the lowest free ID.
+///
+/// Capacity is `WORDS * usize::BITS` IDs, and is fixed at compile time so no heap is needed.
+pub struct IdAllocator<const WORDS: usize> {
+    /// One bit per ID, set if allocated. Bits past the end of the range are always set.
+    words: [usize; WORDS],
+    /// First ID in the range
+    start: usize,
+    /// One past the last ID in the range
+    end: usize,
+}
+
+impl<const WORDS: usize> IdAllocator<WORDS> {
+    /// Constructs an allocator handing out IDs within `range`.
+    ///
+    /// Panics if the range holds more IDs than the allocator has capacity for.
+    pub const fn new(range: Range<usize>) -> Self {
+        assert!(range.start <= range.end, "invalid id range");
+
+        let len = range.end - range.start;
+        assert!(
+            len <= WORDS * usize::BITS as usize,
+            "id range larger than allocator capacity"
+        );
+
+        // block out everything past the end of the range, so it never gets handed out
+        let mut words = [0; WORDS];
+        let mut i = len;
+        while i < WORDS * usize::BITS as usize {
+            words[i / usize::BITS as usize] |= 1 << (i % usize::BITS as usize);
+            i += 1;
+        }
+
+        Self {
+            words,
+            start: range.start,
+            end: range.end,
+        }
+    }
+
+    /// Returns the range of IDs this allocator hands out
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Allocates the lowest free ID, returning None if all are in use
+    pub fn allocate(&mut self) -> Option<usize> {
+        let bitmap = Bitmap::from_slice_mut(&mut self.words);
+
+        let index = bitmap.find_first_zero()?;
+        bitmap.set(index);
+
+        Some(self.start + index)
+    }
+
+    /// Frees a previously allocated ID.
+    ///
+    /// Panics if the ID is outside the range or isn't allocated.
+    pub fn free(&mut self, id: usize) {
+        let index = self.index(id).expect("freeing id outside of range");
+
+        let bitmap = Bitmap::from_slice_mut(&mut self.words);
+        assert!(bitmap.get(index), "freeing id {id} which isn't allocated");
+
+        bitmap.clear(index);
+    }
+
+    /// Marks a specific ID as allocated, returning false if it was already in use or is outside the range
+    pub fn reserve(&mut self, id: usize) -> bool {
+        let Some(index) = self.index(id) else {
+            return false;
+        };
+
+        let bitmap = Bitmap::from_slice_mut(&mut self.words);
+        if bitmap.get(index) {
+            return false;
+        }
+
+        bitmap.set(index);
+        true
+    }
+
+    /// Marks every ID within `range` as allocated, regardless of whether some already were.
+    ///
+    /// Panics if `range` is not within the allocator's range.
+    pub fn reserve_range(&mut self, range: Range<usize>) {
+        assert!(
+            self.start <= range.start && range.end <= self.end,
+            "reserving ids outside of range"
+        );
+
+        Bitmap::from_slice_mut(&mut self.words)
+            .set_range(range.start - self.start..range.end - self.start);
+    }
+
+    /// Returns true if `id` is currently allocated
+    pub fn is_allocated(&self, id: usize) -> bool {
+        self.index(id)
+            .is_some_and(|index| Bitmap::from_slice(&self.words).get(index))
+    }
+
+    /// Returns the number of IDs currently allocated
+    pub fn allocated(&self) -> usize {
+        let padding = self.words.len() * usize::BITS as usize - (self.end - self.start);
+
+        Bitmap::from_slice(&self.words).count_ones() - padding
+    }
+
+    /// Converts an ID to its bit index, if it lies within the range
+    fn index(&self, id: usize) -> Option<usize> {
+        (self.start..self.end)
+            .contains(&id)
+            .then(|| id - self.start)
+    }
+}
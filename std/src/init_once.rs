@@ -0,0 +1,64 @@
+//! Guard for initialisation which must only happen once per boot, such as loading the IDT or setting the logger
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// [`InitOnce::call_once`] has not been called yet
+const UNINITIALISED: u8 = 0;
+/// The first call to [`InitOnce::call_once`] is still running
+const RUNNING: u8 = 1;
+/// The first call to [`InitOnce::call_once`] has returned
+const INITIALISED: u8 = 2;
+
+/// Runs an initialisation function at most once, no matter how many times it is called.
+///
+/// Unlike a `OnceCell`, this holds no value and never waits: only the first caller runs the function, and every
+/// other caller is told straight away that it has already been claimed. This suits kernel init code, where a second
+/// call is a bug to report rather than something to wait for.
+pub struct InitOnce {
+    /// One of [`UNINITIALISED`], [`RUNNING`] or [`INITIALISED`]
+    state: AtomicU8,
+}
+
+/// Proof that an [`InitOnce`] has finished initialising, which can be required by code that depends on it
+#[derive(Debug, Clone, Copy)]
+pub struct InitToken(());
+
+impl InitOnce {
+    /// Constructs a guard which has not been called
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(UNINITIALISED),
+        }
+    }
+
+    /// Runs `init` if this is the first call, returning its result along with a token proving it has run.
+    ///
+    /// Returns `None` without running `init` if it has already been called, even if that call is still running or
+    /// returned an error.
+    pub fn call_once<T>(&self, init: impl FnOnce() -> T) -> Option<(T, InitToken)> {
+        self.state
+            .compare_exchange(UNINITIALISED, RUNNING, Ordering::Acquire, Ordering::Acquire)
+            .ok()?;
+
+        let result = init();
+        self.state.store(INITIALISED, Ordering::Release);
+
+        Some((result, InitToken(())))
+    }
+
+    /// Returns whether the first call to [`Self::call_once`] has finished
+    pub fn is_initialised(&self) -> bool {
+        self.state.load(Ordering::Acquire) == INITIALISED
+    }
+
+    /// Returns a token if the first call to [`Self::call_once`] has finished
+    pub fn token(&self) -> Option<InitToken> {
+        self.is_initialised().then_some(InitToken(()))
+    }
+}
+
+impl Default for InitOnce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,370 @@
+//! Minimal JSON writer for structured diagnostics, writing straight into any [`core::fmt::Write`] so no heap is
+//! needed.
+//!
+//! ```ignore
+//! let mut json = JsonWriter::new(&mut out);
+//! json.begin_object()?;
+//! json.field("used", &used)?;
+//! json.field("level", "warn")?;
+//! json.end_object()?;
+//! ```
+
+use core::fmt::{Display, Write};
+
+/// Deepest nesting of objects and arrays supported
+pub const MAX_DEPTH: usize = 64;
+
+/// Writes JSON tokens, inserting commas and colons between them
+pub struct JsonWriter<W: Write> {
+    /// Output being written to
+    out: W,
+    /// Current nesting depth of objects and arrays
+    depth: usize,
+    /// One bit per depth, set once a value has been written at that depth so the next needs a comma
+    has_values: u64,
+    /// Whether a key was just written, so the next value follows it directly
+    after_key: bool,
+}
+
+impl<W: Write> JsonWriter<W> {
+    /// Constructs a writer outputting to `out`
+    pub const fn new(out: W) -> Self {
+        Self {
+            out,
+            depth: 0,
+            has_values: 0,
+            after_key: false,
+        }
+    }
+
+    /// Returns the output being written to
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Writes a separator if needed before the next key or value
+    fn separate(&mut self) -> core::fmt::Result {
+        if self.after_key {
+            self.after_key = false;
+            return Ok(());
+        }
+
+        let bit = 1 << self.depth;
+        if self.has_values & bit != 0 {
+            self.out.write_char(',')?;
+        }
+        self.has_values |= bit;
+
+        Ok(())
+    }
+
+    /// Opens an object or array
+    fn open(&mut self, bracket: char) -> core::fmt::Result {
+        if self.depth + 1 >= MAX_DEPTH {
+            return Err(core::fmt::Error);
+        }
+
+        self.separate()?;
+        self.out.write_char(bracket)?;
+
+        self.depth += 1;
+        self.has_values &= !(1 << self.depth);
+        Ok(())
+    }
+
+    /// Closes an object or array, failing if nothing is open or a key is still waiting for its value
+    fn close(&mut self, bracket: char) -> core::fmt::Result {
+        if self.depth == 0 || self.after_key {
+            return Err(core::fmt::Error);
+        }
+
+        self.depth -= 1;
+        self.out.write_char(bracket)
+    }
+
+    /// Starts an object
+    pub fn begin_object(&mut self) -> core::fmt::Result {
+        self.open('{')
+    }
+
+    /// Ends the current object
+    pub fn end_object(&mut self) -> core::fmt::Result {
+        self.close('}')
+    }
+
+    /// Starts an array
+    pub fn begin_array(&mut self) -> core::fmt::Result {
+        self.open('[')
+    }
+
+    /// Ends the current array
+    pub fn end_array(&mut self) -> core::fmt::Result {
+        self.close(']')
+    }
+
+    /// Writes an object key, which must be followed by a value
+    pub fn key(&mut self, key: &str) -> core::fmt::Result {
+        if self.after_key {
+            return Err(core::fmt::Error);
+        }
+
+        self.separate()?;
+        self.string_unseparated(key)?;
+        self.out.write_char(':')?;
+
+        self.after_key = true;
+        Ok(())
+    }
+
+    /// Writes a value
+    pub fn value<T: ToJson + ?Sized>(&mut self, value: &T) -> core::fmt::Result {
+        value.write_json(self)
+    }
+
+    /// Writes a key and its value
+    pub fn field<T: ToJson + ?Sized>(&mut self, key: &str, value: &T) -> core::fmt::Result {
+        self.key(key)?;
+        self.value(value)
+    }
+
+    /// Writes anything implementing [`Display`] as an escaped string, e.g. addresses or enums
+    pub fn display<T: Display + ?Sized>(&mut self, value: &T) -> core::fmt::Result {
+        self.separate()?;
+        self.out.write_char('"')?;
+        write!(Escaper(&mut self.out), "{value}")?;
+        self.out.write_char('"')
+    }
+
+    /// Writes a string value
+    pub fn string(&mut self, value: &str) -> core::fmt::Result {
+        self.separate()?;
+        self.string_unseparated(value)
+    }
+
+    /// Writes an escaped string without a preceding separator
+    fn string_unseparated(&mut self, value: &str) -> core::fmt::Result {
+        self.out.write_char('"')?;
+        Escaper(&mut self.out).write_str(value)?;
+        self.out.write_char('"')
+    }
+
+    /// Writes a value without quoting, for numbers and literals
+    fn raw<T: Display + ?Sized>(&mut self, value: &T) -> core::fmt::Result {
+        self.separate()?;
+        write!(self.out, "{value}")
+    }
+
+    /// Writes `null`
+    pub fn null(&mut self) -> core::fmt::Result {
+        self.raw("null")
+    }
+}
+
+/// Escapes everything written through it for use inside a JSON string
+struct Escaper<'a, W: Write>(&'a mut W);
+
+impl<W: Write> Write for Escaper<'_, W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        // write unescaped runs in one go, only breaking them up for characters which need escaping
+        let mut start = 0;
+
+        for (index, c) in s.char_indices() {
+            let escaped = match c {
+                '"' => "\\\"",
+                '\\' => "\\\\",
+                '\n' => "\\n",
+                '\r' => "\\r",
+                '\t' => "\\t",
+                c if (c as u32) < 0x20 => "",
+                _ => continue,
+            };
+
+            self.0.write_str(&s[start..index])?;
+            if escaped.is_empty() {
+                write!(self.0, "\\u{:04x}", c as u32)?;
+            } else {
+                self.0.write_str(escaped)?;
+            }
+            start = index + c.len_utf8();
+        }
+
+        self.0.write_str(&s[start..])
+    }
+}
+
+/// Types which can be written as a single JSON value
+pub trait ToJson {
+    /// Writes self to the writer
+    fn write_json<W: Write>(&self, writer: &mut JsonWriter<W>) -> core::fmt::Result;
+}
+
+impl ToJson for str {
+    fn write_json<W: Write>(&self, writer: &mut JsonWriter<W>) -> core::fmt::Result {
+        writer.string(self)
+    }
+}
+
+impl ToJson for bool {
+    fn write_json<W: Write>(&self, writer: &mut JsonWriter<W>) -> core::fmt::Result {
+        writer.raw(self)
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn write_json<W: Write>(&self, writer: &mut JsonWriter<W>) -> core::fmt::Result {
+        match self {
+            Some(value) => value.write_json(writer),
+            None => writer.null(),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn write_json<W: Write>(&self, writer: &mut JsonWriter<W>) -> core::fmt::Result {
+        writer.begin_array()?;
+        self.iter().try_for_each(|value| value.write_json(writer))?;
+        writer.end_array()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn write_json<W: Write>(&self, writer: &mut JsonWriter<W>) -> core::fmt::Result {
+        (**self).write_json(writer)
+    }
+}
+
+/// Implements [`ToJson`] for integer types, which are written as plain numbers
+macro_rules! impl_to_json_integer {
+    ($($type:ty),*) => {
+        $(
+            impl ToJson for $type {
+                fn write_json<W: Write>(&self, writer: &mut JsonWriter<W>) -> core::fmt::Result {
+                    writer.raw(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_to_json_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::{self, Write};
+
+    use super::{JsonWriter, MAX_DEPTH};
+
+    /// Fixed-size string to write JSON into
+    struct Buffer {
+        /// Bytes written so far
+        bytes: [u8; 256],
+        /// Number of bytes written
+        len: usize,
+    }
+
+    impl Buffer {
+        /// Constructs an empty buffer
+        const fn new() -> Self {
+            Self {
+                bytes: [0; 256],
+                len: 0,
+            }
+        }
+
+        /// Returns what has been written
+        fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.bytes[..self.len]).unwrap()
+        }
+    }
+
+    impl Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.len + s.len();
+            self.bytes
+                .get_mut(self.len..end)
+                .ok_or(fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn nested_values() {
+        let mut buffer = Buffer::new();
+        let mut json = JsonWriter::new(&mut buffer);
+
+        json.begin_object().unwrap();
+        json.field("used", &12u32).unwrap();
+        json.field("ok", &true).unwrap();
+        json.field("missing", &None::<u8>).unwrap();
+        json.field("list", &[1i8, -2, 3][..]).unwrap();
+        json.key("nested").unwrap();
+        json.begin_object().unwrap();
+        json.key("address").unwrap();
+        json.display(&format_args!("{:#x}", 0xb8000)).unwrap();
+        json.end_object().unwrap();
+        json.key("empty").unwrap();
+        json.begin_array().unwrap();
+        json.end_array().unwrap();
+        json.end_object().unwrap();
+
+        assert_eq!(
+            buffer.as_str(),
+            r#"{"used":12,"ok":true,"missing":null,"list":[1,-2,3],"nested":{"address":"0xb8000"},"empty":[]}"#
+        );
+    }
+
+    #[test]
+    fn escapes_strings() {
+        let mut buffer = Buffer::new();
+        let mut json = JsonWriter::new(&mut buffer);
+
+        json.begin_array().unwrap();
+        json.string("quote \" slash \\ tab \t line\r\n bell \u{7} é")
+            .unwrap();
+        json.display(&format_args!("{}", "a\"b")).unwrap();
+        json.end_array().unwrap();
+
+        assert_eq!(
+            buffer.as_str(),
+            r#"["quote \" slash \\ tab \t line\r\n bell \u0007 é","a\"b"]"#
+        );
+    }
+
+    #[test]
+    fn rejects_dangling_key() {
+        let mut buffer = Buffer::new();
+        let mut json = JsonWriter::new(&mut buffer);
+
+        json.begin_object().unwrap();
+        json.key("value").unwrap();
+        assert!(json.end_object().is_err());
+        assert!(json.key("other").is_err());
+
+        // giving the key its value makes the object valid again
+        json.value(&1u8).unwrap();
+        json.end_object().unwrap();
+        assert_eq!(buffer.as_str(), r#"{"value":1}"#);
+    }
+
+    #[test]
+    fn rejects_unbalanced_close() {
+        let mut buffer = Buffer::new();
+        let mut json = JsonWriter::new(&mut buffer);
+
+        assert!(json.end_object().is_err());
+        assert!(json.end_array().is_err());
+    }
+
+    #[test]
+    fn limits_depth() {
+        let mut buffer = Buffer::new();
+        let mut json = JsonWriter::new(&mut buffer);
+
+        for _ in 0..MAX_DEPTH - 1 {
+            json.begin_array().unwrap();
+        }
+        assert!(json.begin_array().is_err());
+    }
+}
@@ -3,30 +3,117 @@
 #![no_std]
 #![warn(missing_docs, clippy::missing_docs_in_private_items)]
 
+pub mod bitmap;
+pub mod collections;
+pub mod compression;
 pub mod cursor;
 pub mod duration;
 pub mod elf;
+pub mod id_alloc;
+pub mod init_once;
+pub mod json;
+pub mod lock_stats;
 pub mod mutex;
+pub mod sha256;
+pub mod static_assert;
 
 /// Align downwards - returns the greatest _x_ with alignment `align`
-/// such that _x_ <= addr. `align` must be power of 2
+/// such that _x_ <= addr.
+///
+/// An `align` of 0 leaves `addr` unchanged. Panics if `align` is otherwise not a power of 2, see
+/// [`checked_align_down`] for a non-panicking version.
 pub const fn align_down(addr: usize, align: usize) -> usize {
-    if align.is_power_of_two() {
-        addr & !(align - 1)
-    } else if align == 0 {
-        addr
-    } else {
-        panic!("`align` must be power of two")
+    if align == 0 {
+        return addr;
+    }
+
+    match checked_align_down(addr, align) {
+        Some(aligned) => aligned,
+        None => panic!("`align` must be power of two"),
     }
 }
 
 /// Align upwards - returns the smallest _x_ with alignment `align`
-/// such that _x_ >= addr. `align` must be power of 2
+/// such that _x_ >= addr.
+///
+/// Panics if `align` is not a power of 2 (which includes 0), or if _x_ would overflow. Shorthand for
+/// [`align_up_pow2_or_panic`], see [`checked_align_up`] for a non-panicking version.
 pub const fn align_up(addr: usize, align: usize) -> usize {
-    align_down(addr + align - 1, align)
+    align_up_pow2_or_panic(addr, align)
+}
+
+/// Align upwards - returns the smallest _x_ with alignment `align`
+/// such that _x_ >= addr.
+///
+/// Panics with a message saying which went wrong if `align` is not a power of 2 (which includes 0), or if _x_ would
+/// overflow. Use this where either is a bug, and [`checked_align_up`] where the address may come from outside.
+pub const fn align_up_pow2_or_panic(addr: usize, align: usize) -> usize {
+    if !align.is_power_of_two() {
+        panic!("`align` must be power of two")
+    }
+
+    match checked_align_up(addr, align) {
+        Some(aligned) => aligned,
+        None => panic!("aligning upwards overflowed"),
+    }
+}
+
+/// Align downwards - returns the greatest _x_ with alignment `align`
+/// such that _x_ <= addr, or None if `align` is not a power of 2 (which includes 0)
+pub const fn checked_align_down(addr: usize, align: usize) -> Option<usize> {
+    if !align.is_power_of_two() {
+        return None;
+    }
+
+    Some(addr & !(align - 1))
 }
 
-/// Checks if an address is aligned to a given boundary
+/// Align upwards - returns the smallest _x_ with alignment `align`
+/// such that _x_ >= addr, or None if `align` is not a power of 2 (which includes 0) or _x_ would overflow
+pub const fn checked_align_up(addr: usize, align: usize) -> Option<usize> {
+    if !align.is_power_of_two() {
+        return None;
+    }
+
+    match addr.checked_add(align - 1) {
+        Some(addr) => Some(addr & !(align - 1)),
+        None => None,
+    }
+}
+
+/// Checks if an address is aligned to a given boundary, which must be a power of 2 or 0. Nothing is aligned to 0.
+///
+/// Unlike comparing against [`align_up`], this can't overflow for addresses near `usize::MAX`.
 pub const fn is_aligned(addr: usize, alignment: usize) -> bool {
-    align_up(addr, alignment) == addr
+    if alignment == 0 {
+        return false;
+    }
+
+    if !alignment.is_power_of_two() {
+        panic!("`alignment` must be power of two")
+    }
+
+    addr & (alignment - 1) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_zero() {
+        assert_eq!(align_down(0x1234, 0), 0x1234);
+        assert_eq!(checked_align_down(0x1234, 0), None);
+        assert_eq!(checked_align_up(0x1234, 0), None);
+        assert!(!is_aligned(0, 0));
+        assert!(!is_aligned(0x1000, 0));
+    }
+
+    #[test]
+    fn align_power_of_two() {
+        assert_eq!(align_down(0x1234, 0x1000), 0x1000);
+        assert_eq!(align_up(0x1234, 0x1000), 0x2000);
+        assert!(is_aligned(usize::MAX - 0xFFF, 0x1000));
+        assert!(!is_aligned(0x1234, 0x1000));
+    }
 }
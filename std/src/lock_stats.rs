@@ -0,0 +1,180 @@
+//! Per call site lock statistics, recorded by [`Mutex`](crate::mutex::Mutex) when the `LOCK_STATS` feature is
+//! enabled.
+//!
+//! Sites are identified by the location `lock` or `try_lock` was called from, and kept in a fixed size table so no
+//! heap is needed. Hold times are measured in timestamp counter cycles.
+
+// recording is compiled out of the mutex without the feature, leaving only the empty table to report on
+#![cfg_attr(not(feature = "LOCK_STATS"), allow(dead_code))]
+
+use core::{
+    panic::Location,
+    ptr::null_mut,
+    sync::atomic::{AtomicPtr, AtomicU64, Ordering},
+};
+
+/// Whether lock statistics are being recorded
+pub const ENABLED: bool = cfg!(feature = "LOCK_STATS");
+
+/// Maximum number of call sites tracked, locks taken from any others are counted in [`untracked`]
+pub const MAX_SITES: usize = 128;
+
+/// Statistics for every call site, filled in as sites are first seen
+static SITES: [LockSite; MAX_SITES] = [const { LockSite::new() }; MAX_SITES];
+
+/// Acquisitions which couldn't be recorded because the table was full
+static UNTRACKED: AtomicU64 = AtomicU64::new(0);
+
+/// Counters for a single call site
+pub(crate) struct LockSite {
+    /// Location of the call site, null if this entry is unused
+    location: AtomicPtr<Location<'static>>,
+    /// Times the lock was acquired
+    acquisitions: AtomicU64,
+    /// Times the lock was already held when acquiring
+    contended: AtomicU64,
+    /// Total spins waiting for the lock
+    spins: AtomicU64,
+    /// Longest time the lock was held for, in cycles
+    max_hold_cycles: AtomicU64,
+}
+
+impl LockSite {
+    /// Constructs an unused entry
+    const fn new() -> Self {
+        Self {
+            location: AtomicPtr::new(null_mut()),
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+            spins: AtomicU64::new(0),
+            max_hold_cycles: AtomicU64::new(0),
+        }
+    }
+}
+
+/// A held lock being timed, released when the guard holding it is dropped
+pub(crate) struct Held {
+    /// Call site the lock was acquired from, [`None`] if the table was full
+    site: Option<&'static LockSite>,
+    /// Timestamp the lock was acquired at
+    acquired_at: u64,
+}
+
+impl Held {
+    /// Records the lock being released
+    pub(crate) fn release(&self) {
+        if let Some(site) = self.site {
+            let held = timestamp().wrapping_sub(self.acquired_at);
+            site.max_hold_cycles.fetch_max(held, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Records a lock being acquired after spinning `spins` times
+pub(crate) fn acquired(location: &'static Location<'static>, spins: u64) -> Held {
+    let site = find_site(location);
+
+    match site {
+        Some(site) => {
+            site.acquisitions.fetch_add(1, Ordering::Relaxed);
+            if spins > 0 {
+                site.contended.fetch_add(1, Ordering::Relaxed);
+                site.spins.fetch_add(spins, Ordering::Relaxed);
+            }
+        }
+        None => {
+            UNTRACKED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    Held {
+        site,
+        acquired_at: timestamp(),
+    }
+}
+
+/// Finds the entry for a call site, claiming an unused one if it hasn't been seen before
+fn find_site(location: &'static Location<'static>) -> Option<&'static LockSite> {
+    let pointer = location as *const Location as *mut Location;
+
+    // the same call site can have more than one Location if it was inlined into different codegen units, so hash
+    // and compare by value rather than address
+    let start = (location.line() as usize * 31 + location.column() as usize) % MAX_SITES;
+
+    for offset in 0..MAX_SITES {
+        let site = &SITES[(start + offset) % MAX_SITES];
+
+        match site.location.compare_exchange(
+            null_mut(),
+            pointer,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => return Some(site),
+            Err(existing) if unsafe { *existing == *location } => return Some(site),
+            Err(_) => continue,
+        }
+    }
+
+    None
+}
+
+/// Reads the timestamp counter
+fn timestamp() -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        core::arch::x86_64::_rdtsc()
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    0
+}
+
+/// Snapshot of the statistics for one call site
+#[derive(Debug, Clone, Copy)]
+pub struct LockSiteStats {
+    /// Location the lock was taken from
+    pub location: &'static Location<'static>,
+    /// Times the lock was acquired
+    pub acquisitions: u64,
+    /// Times the lock was already held when acquiring
+    pub contended: u64,
+    /// Total spins waiting for the lock
+    pub spins: u64,
+    /// Longest time the lock was held for, in cycles
+    pub max_hold_cycles: u64,
+}
+
+/// Returns statistics for every call site seen so far
+pub fn sites() -> impl Iterator<Item = LockSiteStats> {
+    SITES.iter().filter_map(|site| {
+        let location = site.location.load(Ordering::Acquire);
+
+        // SAFETY: only ever set from a &'static Location
+        let location = unsafe { location.as_ref() }?;
+
+        Some(LockSiteStats {
+            location,
+            acquisitions: site.acquisitions.load(Ordering::Relaxed),
+            contended: site.contended.load(Ordering::Relaxed),
+            spins: site.spins.load(Ordering::Relaxed),
+            max_hold_cycles: site.max_hold_cycles.load(Ordering::Relaxed),
+        })
+    })
+}
+
+/// Returns how many acquisitions weren't recorded because every entry was in use
+pub fn untracked() -> u64 {
+    UNTRACKED.load(Ordering::Relaxed)
+}
+
+/// Zeroes every counter, keeping the call sites seen so far
+pub fn reset() {
+    for site in &SITES {
+        site.acquisitions.store(0, Ordering::Relaxed);
+        site.contended.store(0, Ordering::Relaxed);
+        site.spins.store(0, Ordering::Relaxed);
+        site.max_hold_cycles.store(0, Ordering::Relaxed);
+    }
+    UNTRACKED.store(0, Ordering::Relaxed);
+}
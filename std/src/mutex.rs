@@ -1,5 +1,7 @@
 //! Module for a simple spin-lock mutex
 
+#[cfg(feature = "LOCK_STATS")]
+use core::panic::Location;
 use core::{
     cell::UnsafeCell,
     fmt,
@@ -7,6 +9,9 @@ use core::{
     sync::atomic::{AtomicBool, Ordering},
 };
 
+#[cfg(feature = "LOCK_STATS")]
+use crate::lock_stats::{self, Held};
+
 /// Spin-lock mutex, allowing shared access to a common resource.
 /// This should only be used when locks are not going to be held for a long time.
 pub struct Mutex<T: ?Sized> {
@@ -31,22 +36,34 @@ impl<T> Mutex<T> {
     }
 
     /// Locks the mutex, returning a guard which can be used to access the underlying data.
+    #[cfg_attr(feature = "LOCK_STATS", track_caller)]
     pub fn lock(&self) -> MutexGuard<T> {
+        let mut spins = 0;
+
         // spin loop until lock released
         while self
             .lock
             .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
             .is_err()
         {
+            spins += 1;
             while self.is_locked() {
+                spins += 1;
                 core::hint::spin_loop();
             }
         }
 
-        MutexGuard {
-            lock: &self.lock,
-            data: unsafe { &mut *self.data.get() },
-        }
+        MutexGuard::new(&self.lock, self.data.get(), spins)
+    }
+
+    /// Attempts to lock the mutex without spinning, returning `None` if it is already locked.
+    #[cfg_attr(feature = "LOCK_STATS", track_caller)]
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.lock
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+
+        Some(MutexGuard::new(&self.lock, self.data.get(), 0))
     }
 }
 
@@ -56,6 +73,23 @@ pub struct MutexGuard<'a, T: ?Sized + 'a> {
     lock: &'a AtomicBool,
     /// Stored data
     data: *mut T,
+    /// Statistics for the call site which acquired the lock
+    #[cfg(feature = "LOCK_STATS")]
+    held: Held,
+}
+
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    /// Constructs a guard for a lock which has just been acquired after spinning `spins` times
+    #[cfg_attr(feature = "LOCK_STATS", track_caller)]
+    #[cfg_attr(not(feature = "LOCK_STATS"), allow(unused_variables))]
+    fn new(lock: &'a AtomicBool, data: *mut T, spins: u64) -> Self {
+        Self {
+            lock,
+            data,
+            #[cfg(feature = "LOCK_STATS")]
+            held: lock_stats::acquired(Location::caller(), spins),
+        }
+    }
 }
 
 impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'a, T> {
@@ -88,6 +122,9 @@ impl<'a, T: ?Sized> DerefMut for MutexGuard<'a, T> {
 impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
     /// The dropping of the MutexGuard will release the lock it was created from.
     fn drop(&mut self) {
+        #[cfg(feature = "LOCK_STATS")]
+        self.held.release();
+
         self.lock.store(false, Ordering::Release);
     }
 }
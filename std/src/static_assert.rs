@@ -0,0 +1,53 @@
+//! Assertions checked at compile time, for pinning down the layout of structures shared with hardware or firmware.
+//!
+//! Each macro expands to an anonymous constant, so can be used anywhere an item can. A failed assertion is a compile
+//! error naming the condition, rather than a silent change in layout.
+
+/// Asserts a constant condition holds at compile time, with an optional message
+#[macro_export]
+macro_rules! static_assert {
+    ($cond:expr $(,)?) => {
+        const _: () = assert!(
+            $cond,
+            concat!("static assertion failed: ", stringify!($cond))
+        );
+    };
+    ($cond:expr, $message:expr $(,)?) => {
+        const _: () = assert!($cond, $message);
+    };
+}
+
+/// Asserts a type is the given number of bytes at compile time
+#[macro_export]
+macro_rules! assert_eq_size {
+    ($ty:ty, $size:expr $(,)?) => {
+        $crate::static_assert!(
+            ::core::mem::size_of::<$ty>() == $size,
+            concat!(
+                "`",
+                stringify!($ty),
+                "` is not ",
+                stringify!($size),
+                " bytes"
+            )
+        );
+    };
+}
+
+/// Asserts a field is at the given byte offset within a type at compile time
+#[macro_export]
+macro_rules! assert_offset {
+    ($ty:ty, $field:ident, $offset:expr $(,)?) => {
+        $crate::static_assert!(
+            ::core::mem::offset_of!($ty, $field) == $offset,
+            concat!(
+                "`",
+                stringify!($ty),
+                "::",
+                stringify!($field),
+                "` is not at offset ",
+                stringify!($offset)
+            )
+        );
+    };
+}